@@ -0,0 +1,97 @@
+use std::path::PathBuf;
+
+use json_parser::lexer::tokenise;
+use json_parser::parser::{parse, Node};
+use proc_macro::TokenStream;
+
+/// Read the path out of a string literal, like `"fixtures/config.json"` or `r"C:\config.json"`,
+/// with any escapes in it worked out the way the compiler would.
+fn string_literal(input: TokenStream) -> Option<String> {
+    syn::parse::<syn::LitStr>(input).ok().map(|literal| literal.value())
+}
+
+/// Write out Rust code that builds `node`.
+fn write_node(node: &Node, out: &mut String) {
+    match node {
+        Node::Null | Node::Empty => out.push_str("::json_parser::parser::Node::Null"),
+        Node::Bool(b) => out.push_str(&format!("::json_parser::parser::Node::Bool({})", b)),
+        Node::Integer(n) => out.push_str(&format!("::json_parser::parser::Node::Integer({}i64)", n)),
+        Node::Unsigned(n) => out.push_str(&format!("::json_parser::parser::Node::Unsigned({}u64)", n)),
+
+        // The bits give back exactly the same float, whatever it is.
+        Node::Float(f) => out.push_str(&format!(
+            "::json_parser::parser::Node::Float(f64::from_bits({:#x}u64))",
+            f.to_bits()
+        )),
+
+        Node::String(s) => out.push_str(&format!(
+            "::json_parser::parser::Node::String(::std::string::String::from({:?}))",
+            s
+        )),
+
+        Node::Array(arr) => {
+            out.push_str("::json_parser::parser::Node::Array(::std::vec![");
+
+            for item in arr {
+                write_node(item, out);
+                out.push(',');
+            }
+
+            out.push_str("])");
+        },
+
+        Node::Object(map) => {
+            // Sorted, so the same file always expands to the same code.
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+
+            out.push_str("::json_parser::parser::Node::Object(::std::iter::FromIterator::from_iter([");
+
+            for key in keys {
+                out.push_str(&format!("(::std::string::String::from({:?}), ", key));
+                write_node(&map[key], out);
+                out.push_str("),");
+            }
+
+            out.push_str("]))");
+        }
+    }
+}
+
+fn compile_error(message: &str) -> TokenStream {
+    format!("::std::compile_error!({:?})", message).parse().unwrap()
+}
+
+/// Parse a JSON file at compile time, expanding to code that builds its `Node`
+/// directly, so nothing is parsed when the program runs.
+///
+/// The path is relative to the directory of the crate's `Cargo.toml`.
+/// Invalid JSON, or a file that can't be read, fails the build.
+#[proc_macro]
+pub fn include_json(input: TokenStream) -> TokenStream {
+    let Some(relative) = string_literal(input) else {
+        return compile_error("include_json! takes a single string literal path");
+    };
+
+    let mut path = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default());
+    path.push(&relative);
+
+    let text = match std::fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) => return compile_error(&format!("Could not read {}: {}", path.display(), e))
+    };
+
+    let node = match tokenise(&text).and_then(|tokens| parse(&tokens)) {
+        Ok(node) => node,
+        Err(e) => return compile_error(&format!("Could not parse {}: {}", path.display(), e))
+    };
+
+    let mut code = String::from("{");
+
+    // Including the bytes makes Cargo rebuild when the file changes.
+    code.push_str(&format!("const _: &[u8] = ::std::include_bytes!({:?});", path.display().to_string()));
+    write_node(&node, &mut code);
+    code.push('}');
+
+    code.parse().unwrap()
+}
@@ -0,0 +1,170 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::parser::Node;
+use crate::serializer::to_string;
+
+/// When `ArrayFileAppender` asks the OS to make writes durable.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SyncPolicy {
+    /// Leave it to the OS, which is fastest, but recent appends can be lost in a power cut.
+    Never,
+
+    /// Call `sync_data` after every append, so it's on disk once `append` returns.
+    EveryAppend,
+
+    /// Call `sync_all` after every append, which also writes metadata like the modified time.
+    EveryAppendWithMetadata
+}
+
+/// Keeps a file holding a JSON array valid across appends, for logs that
+/// any JSON tool can read without knowing about NDJSON.
+///
+/// Each append seeks back over the closing `]`, writes a comma and the new item,
+/// then closes the array again, so the file is laid out like `split_array` writes:
+///
+/// ```text
+/// [
+/// {"event":"start"},
+/// {"event":"stop"}
+/// ]
+/// ```
+///
+/// The new item and the closing `]` go out in one write, but the OS can still
+/// stop part way through one, like in a crash or power cut. That leaves the file
+/// without its `]`, which `open` then refuses rather than guessing, and which can be
+/// fixed by hand or read with `ArrayItemReader` up to the broken item. Nothing
+/// stops two appenders writing to the same file at once, so only have one.
+pub struct ArrayFileAppender {
+    file: File,
+
+    /// Where the last item ends, or just after the `[` if there are none,
+    /// which is where new items are written from.
+    end: u64,
+    empty: bool,
+    sync: SyncPolicy
+}
+
+/// Find the last byte before `before` that isn't whitespace, reading backwards a piece at a time.
+fn last_non_whitespace(file: &mut File, mut before: u64) -> io::Result<Option<(u64, u8)>> {
+    let mut buffer = [0; 4096];
+
+    while before > 0 {
+        let start = before.saturating_sub(buffer.len() as u64);
+        let chunk = &mut buffer[..(before - start) as usize];
+
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(chunk)?;
+
+        if let Some(i) = chunk.iter().rposition(|b| !b" \t\r\n".contains(b)) {
+            return Ok(Some((start + i as u64, chunk[i])));
+        }
+
+        before = start;
+    }
+
+    Ok(None)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+impl ArrayFileAppender {
+    /// Open a file to append to, making it with an empty array if it doesn't exist.
+    ///
+    /// The file has to end with a `]`, and only the end of it is read,
+    /// so the rest isn't checked to be a valid array.
+    pub fn open(path: impl AsRef<Path>, sync: SyncPolicy) -> io::Result<ArrayFileAppender> {
+        let file = OpenOptions::new().read(true).write(true).create(true).truncate(false).open(path)?;
+        let len = file.metadata()?.len();
+
+        let mut appender = ArrayFileAppender {
+            file,
+            end: 0,
+            empty: true,
+            sync
+        };
+
+        if len == 0 {
+            appender.file.write_all(b"[]\n")?;
+            appender.sync()?;
+            appender.end = 1;
+
+            return Ok(appender);
+        }
+
+        let Some((end, b']')) = last_non_whitespace(&mut appender.file, len)? else {
+            return Err(invalid("file doesn't end with a ']', so it isn't a complete array"));
+        };
+
+        let (last, empty) = match last_non_whitespace(&mut appender.file, end)? {
+            Some((last, byte)) => (last, byte == b'['),
+            None => return Err(invalid("file has a ']' with no '[' before it"))
+        };
+
+        appender.end = last + 1;
+        appender.empty = empty;
+
+        Ok(appender)
+    }
+
+    /// Add an item to the end of the array, written compactly on its own line.
+    pub fn append(&mut self, node: &Node) -> io::Result<()> {
+        let separator = if self.empty { "\n" } else { ",\n" };
+        let text = format!("{}{}", separator, to_string(node));
+
+        self.write_at_end(text.as_bytes())?;
+        self.empty = false;
+
+        Ok(())
+    }
+
+    /// Add items to the end of the array, with one write and one sync for all of them.
+    pub fn append_all<'a>(&mut self, nodes: impl IntoIterator<Item = &'a Node>) -> io::Result<()> {
+        let mut text = String::new();
+
+        for node in nodes {
+            text.push_str(if self.empty && text.is_empty() { "\n" } else { ",\n" });
+            text.push_str(&to_string(node));
+        }
+
+        if !text.is_empty() {
+            self.write_at_end(text.as_bytes())?;
+            self.empty = false;
+        }
+
+        Ok(())
+    }
+
+    fn sync(&self) -> io::Result<()> {
+        match self.sync {
+            SyncPolicy::Never => Ok(()),
+            SyncPolicy::EveryAppend => self.file.sync_data(),
+            SyncPolicy::EveryAppendWithMetadata => self.file.sync_all()
+        }
+    }
+
+    /// Write `text` after the last item, over the closing `]`, then close the array again.
+    fn write_at_end(&mut self, text: &[u8]) -> io::Result<()> {
+        let mut buffer = Vec::with_capacity(text.len() + 3);
+        buffer.extend_from_slice(text);
+        buffer.extend_from_slice(b"\n]\n");
+
+        self.file.seek(SeekFrom::Start(self.end))?;
+        self.file.write_all(&buffer)?;
+
+        let len = self.end + buffer.len() as u64;
+
+        // Anything after the old `]`, like trailing whitespace, would be left behind otherwise.
+        self.file.set_len(len)?;
+
+        self.sync()?;
+
+        // Next time, write over the `\n]\n`.
+        self.end = len - 3;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock, Weak};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::error::JsonError;
+use crate::lexer::tokenise;
+use crate::parser::{parse, Node};
+
+/// Why a document couldn't be loaded into the cache.
+#[derive(Debug)]
+pub enum CacheError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, Box<JsonError>)
+}
+
+impl Display for CacheError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(path, e) => write!(f, "Could not read {}: {}", path.display(), e),
+            Self::Parse(path, e) => write!(f, "Could not parse {}: {}", path.display(), e)
+        }
+    }
+}
+
+impl std::error::Error for CacheError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(_, e) => Some(e),
+            Self::Parse(_, e) => Some(e.as_ref())
+        }
+    }
+}
+
+/// What a file looked like when it was read, to tell if it's changed since.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) struct Stamp {
+    modified: SystemTime,
+    len: u64
+}
+
+pub(crate) fn stamp(path: &Path) -> Result<Stamp, CacheError> {
+    match fs::metadata(path) {
+        Ok(meta) => Ok(Stamp {
+            // Some platforms don't record when a file was modified, so the size is all there is.
+            modified: meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            len: meta.len()
+        }),
+        Err(e) => Err(CacheError::Io(path.to_path_buf(), e))
+    }
+}
+
+pub(crate) fn load(path: &Path) -> Result<(Stamp, Node), CacheError> {
+    // Stamp it first, so a write partway through reading gets picked up next time.
+    let stamp = stamp(path)?;
+
+    let text = match fs::read_to_string(path) {
+        Ok(x) => x,
+        Err(e) => return Err(CacheError::Io(path.to_path_buf(), e))
+    };
+
+    match tokenise(&text).and_then(|tokens| parse(&tokens)) {
+        Ok(node) => Ok((stamp, node)),
+        Err(e) => Err(CacheError::Parse(path.to_path_buf(), Box::new(e)))
+    }
+}
+
+struct Entry {
+    stamp: Stamp,
+    node: Arc<Node>
+}
+
+struct Inner {
+    entries: Mutex<HashMap<PathBuf, Entry>>,
+
+    /// Whether `get` should check the file itself, which it doesn't need to
+    /// when a background thread is doing it.
+    check_on_get: bool
+}
+
+impl Inner {
+    /// Reload every cached file that has changed, and forget any that can't be loaded any more.
+    fn revalidate(&self) {
+        let cached: Vec<(PathBuf, Stamp)> = self.entries.lock().unwrap().iter().map(
+            |(path, entry)| (path.clone(), entry.stamp)
+        ).collect();
+
+        // Files are read without holding the lock, so `get` isn't kept waiting.
+        for (path, old) in cached {
+            let reloaded = match stamp(&path) {
+                Ok(new) if new == old => continue,
+                Ok(_) => load(&path).ok(),
+                Err(_) => None
+            };
+
+            let mut entries = self.entries.lock().unwrap();
+
+            match reloaded {
+                Some((stamp, node)) => {
+                    entries.insert(path, Entry { stamp, node: Arc::new(node) });
+                },
+                None => {
+                    entries.remove(&path);
+                }
+            }
+        }
+    }
+}
+
+/// Parsed JSON files, kept in memory until they change on disk.
+///
+/// Files are keyed by the path they were asked for with, and count as changed when their
+/// modification time or size is different. Clones share the same cache, so one
+/// can be handed to each thread that needs it.
+#[derive(Clone)]
+pub struct DocumentCache {
+    inner: Arc<Inner>
+}
+
+impl Default for DocumentCache {
+    fn default() -> DocumentCache {
+        DocumentCache::new()
+    }
+}
+
+impl DocumentCache {
+    /// A cache that checks whether a file has changed every time it's asked for.
+    pub fn new() -> DocumentCache {
+        DocumentCache {
+            inner: Arc::new(Inner {
+                entries: Mutex::new(HashMap::new()),
+                check_on_get: true
+            })
+        }
+    }
+
+    /// A cache that checks its files for changes on a background thread every `interval`,
+    /// so `get` can give back a cached document without touching the disk.
+    ///
+    /// Changes are picked up on the next check rather than straight away.
+    /// The thread stops once every clone of the cache has been dropped.
+    pub fn with_revalidation(interval: Duration) -> DocumentCache {
+        let cache = DocumentCache {
+            inner: Arc::new(Inner {
+                entries: Mutex::new(HashMap::new()),
+                check_on_get: false
+            })
+        };
+
+        let weak: Weak<Inner> = Arc::downgrade(&cache.inner);
+
+        thread::spawn(move || loop {
+            thread::sleep(interval);
+
+            match weak.upgrade() {
+                Some(inner) => inner.revalidate(),
+                None => break
+            }
+        });
+
+        cache
+    }
+
+    /// A cache shared by the whole program, made the first time it's used.
+    pub fn global() -> &'static DocumentCache {
+        static GLOBAL: OnceLock<DocumentCache> = OnceLock::new();
+
+        GLOBAL.get_or_init(DocumentCache::new)
+    }
+
+    /// Get the parsed contents of a file, reading it only if it isn't cached or has changed.
+    ///
+    /// Files that fail to load are never cached, so the error comes back every time until they're fixed.
+    pub fn get(&self, path: impl AsRef<Path>) -> Result<Arc<Node>, CacheError> {
+        let path = path.as_ref();
+
+        let current = match self.inner.check_on_get {
+            true => Some(stamp(path)?),
+            false => None
+        };
+
+        if let Some(entry) = self.inner.entries.lock().unwrap().get(path) {
+            match current {
+                Some(stamp) if stamp != entry.stamp => {},
+                _ => return Ok(entry.node.clone())
+            }
+        }
+
+        let (stamp, node) = load(path)?;
+        let node = Arc::new(node);
+
+        self.inner.entries.lock().unwrap().insert(path.to_path_buf(), Entry {
+            stamp,
+            node: node.clone()
+        });
+
+        Ok(node)
+    }
+
+    /// Forget a file, so it's read again the next time it's asked for.
+    pub fn invalidate(&self, path: impl AsRef<Path>) {
+        self.inner.entries.lock().unwrap().remove(path.as_ref());
+    }
+
+    pub fn clear(&self) {
+        self.inner.entries.lock().unwrap().clear();
+    }
+
+    /// How many files are cached.
+    pub fn len(&self) -> usize {
+        self.inner.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh file for one test. Every change in these tests changes the size,
+    /// so they don't depend on how precise modification times are.
+    fn file(test: &str, text: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("json-rs-cache-{}-{}.json", std::process::id(), test));
+        fs::write(&path, text).unwrap();
+
+        path
+    }
+
+    #[test]
+    fn documents_are_reused_until_they_change() {
+        let path = file("reuse", "[1]");
+        let cache = DocumentCache::new();
+
+        let first = cache.get(&path).unwrap();
+        assert!(Arc::ptr_eq(&first, &cache.get(&path).unwrap()));
+        assert_eq!(cache.len(), 1);
+
+        fs::write(&path, "[1, 2]").unwrap();
+        let changed = cache.get(&path).unwrap();
+        assert!(matches!(&*changed, Node::Array(arr) if arr.len() == 2));
+
+        cache.invalidate(&path);
+        assert!(cache.is_empty());
+        assert!(!Arc::ptr_eq(&changed, &cache.get(&path).unwrap()));
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn failed_loads_arent_cached() {
+        let path = file("failed", "[1,");
+        let cache = DocumentCache::new();
+
+        assert!(matches!(cache.get(&path), Err(CacheError::Parse(..))));
+        assert!(cache.is_empty());
+
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(cache.get(&path), Err(CacheError::Io(..))));
+    }
+
+    #[test]
+    fn background_revalidation_picks_up_changes() {
+        let path = file("background", "1");
+        let cache = DocumentCache::with_revalidation(Duration::from_millis(10));
+
+        assert!(matches!(*cache.get(&path).unwrap(), Node::Integer(1)));
+        fs::write(&path, "22").unwrap();
+
+        let reloaded = (0..200).any(|_| {
+            thread::sleep(Duration::from_millis(10));
+            matches!(*cache.get(&path).unwrap(), Node::Integer(22))
+        });
+        assert!(reloaded);
+
+        // Files that disappear are forgotten.
+        fs::remove_file(&path).unwrap();
+        assert!((0..200).any(|_| {
+            thread::sleep(Duration::from_millis(10));
+            cache.is_empty()
+        }));
+    }
+}
@@ -0,0 +1,189 @@
+use std::fs::{read_to_string, File};
+use std::io::{stderr, stdin, stdout, BufRead, BufReader, BufWriter, ErrorKind, IsTerminal, Read, StdoutLock, Write};
+
+use json_parser::error::JsonError;
+use json_parser::framing::ConcatenatedReader;
+use json_parser::lexer::tokenise;
+use json_parser::parser::{parse, Node};
+use json_parser::serializer::to_writer;
+
+mod bench;
+mod codegen;
+mod convert;
+mod describe;
+mod diff;
+mod filter;
+mod get;
+mod join;
+mod lint;
+mod merge;
+mod normalize;
+mod patch;
+mod rewrite;
+mod sample;
+mod split;
+mod stats;
+mod validate;
+mod view;
+#[cfg(feature = "watch")]
+mod watch;
+
+const USAGE: &str = "\
+Usage: json-rs <command> [arguments]
+
+Commands:
+    bench                       Time how long it takes to parse massive-test.json
+    codegen [options] [file]    Generate Rust types that fit a sample document
+    convert --from json --to <format> [options] [file]
+                                Convert a file, or stdin, to msgpack, cbor, yaml, csv or toml
+    describe [file]             Write a JSON Schema for a config file, from its /// comments
+    diff [options] <file> <file>
+                                Compare two files, failing if they differ
+    filter [options] <program> [file]
+                                Run a jq-style filter over a file, or stdin
+    get <pointer> [file]        Print the value at a JSON Pointer in each value of NDJSON, or stdin
+    join [options] <file>...    Join files of top-level arrays into one array
+    lint [options] [file]       Check a file, or stdin, against style rules
+    merge [options] <base> <overlay>...
+                                Deep merge layered config files, later ones winning
+    normalize [options] [file]  Rewrite a file, or stdin, in a canonical form
+    patch --patch <file> [options] <file>
+                                Apply a JSON Patch to a file, showing what changed
+    rewrite [options] <old> [new]
+                                Write a regenerated file with the old one's key order and layout
+    sample [options] [file]     Pick items at random from a big array or NDJSON
+    split [options] [file]      Split a top-level array into files of smaller arrays
+    stats [options] [file]      Summarise what a file, or stdin, is made of
+    validate --schema <file> [options] <file>...
+                                Check files against a JSON Schema, failing if any don't match
+    view [file]                 Browse a file, or stdin, as a tree in the terminal
+    watch [options] <dir>       Check files in a directory again each time they change";
+
+/// Read the contents of a file, or all of stdin if no path (or `-`) is given.
+pub fn read_input(path: Option<&String>) -> Result<String, String> {
+    match path.map(|p| p.as_str()) {
+        None | Some("-") => {
+            let mut text = String::new();
+
+            match stdin().read_to_string(&mut text) {
+                Ok(_) => Ok(text),
+                Err(e) => Err(format!("Could not read stdin: {}", e))
+            }
+        },
+        Some(p) => read_to_string(p).map_err(|e| format!("Could not read {}: {}", p, e))
+    }
+}
+
+/// Show a parse error under the line it happened on, in color if it's going to a terminal.
+pub fn render_error(e: &JsonError, text: &str) -> String {
+    let rendered = match stderr().is_terminal() {
+        true => e.render_ansi(text),
+        false => e.render_plain(text)
+    };
+
+    // The caller prints this, so leave the final newline to them.
+    rendered.trim_end().to_string()
+}
+
+/// Write to stdout through a buffer, stopping quietly if whatever's reading
+/// the output goes away part way through, like `head`.
+pub fn write_stdout(write: impl FnOnce(&mut BufWriter<StdoutLock>) -> std::io::Result<()>) -> Result<(), String> {
+    let mut out = BufWriter::new(stdout().lock());
+
+    match write(&mut out).and_then(|_| out.flush()) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::BrokenPipe => Ok(()),
+        Err(e) => Err(format!("Could not write to stdout: {}", e))
+    }
+}
+
+/// Run `each` on every value of NDJSON or concatenated JSON from a file, or stdin,
+/// writing what it gives back to stdout as NDJSON, for use in pipelines like `tail -f`.
+///
+/// Output is buffered while input keeps coming, and flushed whenever reading would
+/// have to wait. Values that can't be parsed, or that `each` fails on, are reported
+/// on stderr and skipped, so one bad line doesn't stop a long-running stream.
+pub fn stream_values(path: Option<&String>, mut each: impl FnMut(&Node) -> Result<Vec<Node>, String>) -> Result<(), String> {
+    let reader: Box<dyn BufRead> = match path.map(|p| p.as_str()) {
+        None | Some("-") => Box::new(stdin().lock()),
+        Some(p) => Box::new(BufReader::new(File::open(p).map_err(|e| format!("Could not read {}: {}", p, e))?))
+    };
+
+    let mut values = ConcatenatedReader::new(reader);
+    let mut out = BufWriter::new(stdout().lock());
+    let mut errors = 0;
+
+    while let Some(text) = values.next() {
+        let text = text.map_err(|e| e.to_string())?;
+
+        let outputs = tokenise(&text)
+            .and_then(|tokens| parse(&tokens))
+            .map_err(|e| render_error(&e, &text))
+            .and_then(|node| each(&node));
+
+        let written = match outputs {
+            Ok(outputs) => outputs.iter().try_for_each(|node| to_writer(node, &mut out).and_then(|_| writeln!(out))),
+            Err(e) => {
+                eprintln!("{}", e);
+                errors += 1;
+                Ok(())
+            }
+        };
+
+        let written = written.and_then(|_| match values.has_buffered_input() {
+            true => Ok(()),
+            false => out.flush()
+        });
+
+        match written {
+            Ok(()) => {},
+
+            // Whatever's reading the output has stopped, like `head`, so there's nothing left to do.
+            Err(e) if e.kind() == ErrorKind::BrokenPipe => return Ok(()),
+            Err(e) => return Err(format!("Could not write to stdout: {}", e))
+        }
+    }
+
+    match errors {
+        0 => Ok(()),
+        1 => Err("Found 1 value that couldn't be used".to_string()),
+        n => Err(format!("Found {} values that couldn't be used", n))
+    }
+}
+
+pub fn run(args: &[String]) -> Result<(), String> {
+    // Running with no arguments keeps the old behaviour of benchmarking the parser.
+    let Some(command) = args.first() else {
+        return bench::run();
+    };
+
+    match command.as_str() {
+        "bench" => bench::run(),
+        "codegen" => codegen::run(&args[1..]),
+        "convert" => convert::run(&args[1..]),
+        "describe" => describe::run(&args[1..]),
+        "diff" => diff::run(&args[1..]),
+        "filter" => filter::run(&args[1..]),
+        "get" => get::run(&args[1..]),
+        "join" => join::run(&args[1..]),
+        "lint" => lint::run(&args[1..]),
+        "merge" => merge::run(&args[1..]),
+        "normalize" => normalize::run(&args[1..]),
+        "patch" => patch::run(&args[1..]),
+        "rewrite" => rewrite::run(&args[1..]),
+        "sample" => sample::run(&args[1..]),
+        "split" => split::run(&args[1..]),
+        "stats" => stats::run(&args[1..]),
+        "validate" => validate::run(&args[1..]),
+        "view" => view::run(&args[1..]),
+        #[cfg(feature = "watch")]
+        "watch" => watch::run(&args[1..]),
+        #[cfg(not(feature = "watch"))]
+        "watch" => Err("json-rs was built without the watch feature".to_string()),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            Ok(())
+        },
+        _ => Err(format!("Unknown command {:?}\n\n{}", command, USAGE))
+    }
+}
@@ -0,0 +1,57 @@
+use std::fs::read_to_string;
+use std::io::Write;
+use std::time::Instant;
+
+use json_parser::lexer::{tokenise, tokenise_with_capacity};
+use json_parser::parser::{parse, parse_str};
+
+use super::write_stdout;
+
+pub fn run() -> Result<(), String> {
+    let input = match read_to_string("massive-test.json") {
+        Ok(x) => x,
+        Err(e) => return Err(format!("Could not read file: {}", e))
+    };
+
+    // Tokenise once into a vector that has to grow from nothing,
+    // to compare with the presized one below.
+    let start = Instant::now();
+
+    if let Err(e) = tokenise_with_capacity(input.as_str(), 0) {
+        return Err(format!("Could not tokenise file: {}", e));
+    }
+
+    let unsized_tokens = start.elapsed();
+
+    let start = Instant::now();
+
+    let tokens = match tokenise(input.as_str()) {
+        Ok(x) => x,
+        Err(e) => return Err(format!("Could not tokenise file: {}", e))
+    };
+
+    let after_tokens = start.elapsed();
+
+    if let Err(e) = parse(&tokens) {
+        return Err(format!("Could not parse file: {}", e));
+    }
+
+    let after_parse = start.elapsed() - after_tokens;
+
+    // Then both at once, without the vector of tokens in between.
+    let start = Instant::now();
+
+    if let Err(e) = parse_str(input.as_str()) {
+        return Err(format!("Could not parse file: {}", e));
+    }
+
+    let one_pass = start.elapsed();
+
+    // Nothing is written until every run is done, so writing doesn't get timed.
+    write_stdout(|out| {
+        writeln!(out, "Time taken to tokenise without presizing: {:?}", unsized_tokens)?;
+        writeln!(out, "Time taken to tokenise: {:?}", after_tokens)?;
+        writeln!(out, "Time taken to parse tokens: {:?}", after_parse)?;
+        writeln!(out, "Time taken to tokenise and parse in one pass: {:?}", one_pass)
+    })
+}
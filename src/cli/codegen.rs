@@ -0,0 +1,38 @@
+use std::io::Write;
+
+use json_parser::codegen::{generate_rust, CodegenOptions};
+use json_parser::lexer::tokenise;
+use json_parser::parser::parse;
+
+use super::{read_input, render_error, write_stdout};
+
+const USAGE: &str = "\
+Usage: json-rs codegen [options] [file]
+
+Options:
+    --name <name>   The name of the top-level type (default: Root)
+    --serde         Derive serde's Serialize and Deserialize";
+
+/// `json-rs codegen [options] [file]`, printing Rust types that fit the sample.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut options = CodegenOptions::default();
+    let mut path = None;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--name" => match args.next() {
+                Some(name) => options.root_name = name.clone(),
+                None => return Err(format!("--name needs a type name\n\n{}", USAGE))
+            },
+            "--serde" => options.serde = true,
+            _ if path.is_none() && (!arg.starts_with("--") || arg == "-") => path = Some(arg),
+            _ => return Err(format!("Unexpected argument {:?}\n\n{}", arg, USAGE))
+        }
+    }
+
+    let text = read_input(path)?;
+    let sample = tokenise(&text).and_then(|tokens| parse(&tokens)).map_err(|e| render_error(&e, &text))?;
+
+    write_stdout(|out| write!(out, "{}", generate_rust(&sample, &options)))
+}
@@ -0,0 +1,207 @@
+use std::fs::File;
+use std::io::{stdin, stdout, BufRead, BufReader, BufWriter, IsTerminal, Read, Write};
+
+#[cfg(feature = "yaml")]
+use json_parser::convert::to_yaml;
+use json_parser::convert::{to_cbor, to_csv, to_msgpack, to_toml, CborArrayWriter, CsvWriter};
+use json_parser::framing::ArrayItemReader;
+use json_parser::lexer::tokenise;
+use json_parser::parser::{parse, Node};
+
+use super::render_error;
+
+const USAGE: &str = "\
+Usage: json-rs convert --from json --to <format> [options] [file]
+
+Options:
+    --from <format>   The format of the input, which can only be json for now
+    --to <format>     The format to write: msgpack, cbor, yaml, csv or toml
+    --output <file>   Write to a file instead of stdout
+
+A top-level array is converted one item at a time for cbor, yaml and csv,
+so it doesn't have to fit in memory. For csv, that means the columns
+come from the keys of the first item.";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Msgpack,
+    Cbor,
+    #[cfg(feature = "yaml")]
+    Yaml,
+    Csv,
+    Toml
+}
+
+impl Format {
+    fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "msgpack" => Some(Format::Msgpack),
+            "cbor" => Some(Format::Cbor),
+            #[cfg(feature = "yaml")]
+            "yaml" => Some(Format::Yaml),
+            "csv" => Some(Format::Csv),
+            "toml" => Some(Format::Toml),
+            _ => None
+        }
+    }
+
+    fn is_binary(self) -> bool {
+        matches!(self, Format::Msgpack | Format::Cbor)
+    }
+
+    /// Whether a top-level array can be written an item at a time.
+    fn streams(self) -> bool {
+        match self {
+            Format::Cbor | Format::Csv => true,
+            #[cfg(feature = "yaml")]
+            Format::Yaml => true,
+            Format::Msgpack | Format::Toml => false
+        }
+    }
+}
+
+/// Check whether the input is a top-level array, looking past whitespace without reading anything else.
+fn starts_with_array(reader: &mut dyn BufRead) -> Result<bool, String> {
+    loop {
+        let buffer = reader.fill_buf().map_err(|e| format!("Could not read the input: {}", e))?;
+
+        if buffer.is_empty() {
+            return Ok(false);
+        }
+
+        match buffer.iter().position(|b| !b" \t\r\n".contains(b)) {
+            Some(i) => return Ok(buffer[i] == b'['),
+            None => {
+                let len = buffer.len();
+                reader.consume(len);
+            }
+        }
+    }
+}
+
+fn parse_text(text: &str) -> Result<Node, String> {
+    tokenise(text).and_then(|tokens| parse(&tokens)).map_err(|e| render_error(&e, text))
+}
+
+/// Convert the items of a top-level array as they're read.
+fn convert_items(reader: Box<dyn BufRead>, to: Format, out: &mut dyn Write) -> Result<(), String> {
+    let items = ArrayItemReader::new(reader).enumerate().map(|(i, item)| {
+        let text = item.map_err(|e| e.to_string())?;
+        parse_text(&text).map_err(|e| format!("In item {}:\n{}", i, e))
+    });
+
+    let write_error = |e: std::io::Error| format!("Could not write the output: {}", e);
+
+    match to {
+        Format::Cbor => {
+            let mut writer = CborArrayWriter::new(out).map_err(write_error)?;
+
+            for item in items {
+                writer.write(&item?).map_err(write_error)?;
+            }
+
+            writer.finish().map_err(write_error)?;
+        },
+        #[cfg(feature = "yaml")]
+        Format::Yaml => {
+            let mut empty = true;
+
+            // Each item is written as an array of one, which is a `- ` line and what's under it.
+            for item in items {
+                out.write_all(to_yaml(&Node::Array(vec![item?])).as_bytes()).map_err(write_error)?;
+                empty = false;
+            }
+
+            if empty {
+                out.write_all(b"[]\n").map_err(write_error)?;
+            }
+        },
+        Format::Csv => {
+            let mut writer = CsvWriter::new(out);
+
+            for (i, item) in items.enumerate() {
+                writer.write_row(&item?).map_err(|e| format!("In item {}: {}", i, e))?;
+            }
+
+            writer.finish().map_err(|e| e.to_string())?;
+        },
+        Format::Msgpack | Format::Toml => unreachable!("only formats that stream convert items")
+    }
+
+    Ok(())
+}
+
+fn convert_document(mut reader: Box<dyn BufRead>, to: Format, out: &mut dyn Write) -> Result<(), String> {
+    let mut text = String::new();
+    reader.read_to_string(&mut text).map_err(|e| format!("Could not read the input: {}", e))?;
+
+    let doc = parse_text(&text)?;
+
+    let bytes = match to {
+        Format::Msgpack => to_msgpack(&doc).map_err(|e| e.to_string())?,
+        Format::Cbor => to_cbor(&doc),
+        #[cfg(feature = "yaml")]
+        Format::Yaml => to_yaml(&doc).into_bytes(),
+        Format::Csv => to_csv(&doc).map_err(|e| e.to_string())?.into_bytes(),
+        Format::Toml => to_toml(&doc).map_err(|e| e.to_string())?.into_bytes()
+    };
+
+    out.write_all(&bytes).map_err(|e| format!("Could not write the output: {}", e))
+}
+
+/// `json-rs convert --from json --to <format> [options] [file]`, converting a file, or stdin, to another format.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut to = None;
+    let mut output = None;
+    let mut path = None;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--from" => match args.next().map(|f| f.as_str()) {
+                Some("json") => {},
+                Some(format) => return Err(format!("Only json can be converted from, not {:?}\n\n{}", format, USAGE)),
+                None => return Err(format!("--from needs a format\n\n{}", USAGE))
+            },
+            "--to" => match args.next().map(|f| f.as_str()) {
+                #[cfg(not(feature = "yaml"))]
+                Some("yaml") => return Err("json-rs was built without the yaml feature".to_string()),
+                name => match name.and_then(Format::from_name) {
+                    Some(format) => to = Some(format),
+                    None => return Err(format!("--to must be msgpack, cbor, yaml, csv or toml\n\n{}", USAGE))
+                }
+            },
+            "--output" => match args.next() {
+                Some(p) => output = Some(p),
+                None => return Err(format!("--output needs a file\n\n{}", USAGE))
+            },
+            _ if path.is_none() && (!arg.starts_with("--") || arg == "-") => path = Some(arg),
+            _ => return Err(format!("Unexpected argument {:?}\n\n{}", arg, USAGE))
+        }
+    }
+
+    let Some(to) = to else {
+        return Err(format!("A format to convert to is needed with --to\n\n{}", USAGE));
+    };
+
+    if to.is_binary() && output.is_none() && stdout().is_terminal() {
+        return Err("Refusing to write binary output to a terminal, so use --output or a pipe".to_string());
+    }
+
+    let mut reader: Box<dyn BufRead> = match path.map(|p| p.as_str()) {
+        None | Some("-") => Box::new(stdin().lock()),
+        Some(p) => Box::new(BufReader::new(File::open(p).map_err(|e| format!("Could not read {}: {}", p, e))?))
+    };
+
+    let mut out: Box<dyn Write> = match output {
+        Some(p) => Box::new(BufWriter::new(File::create(p).map_err(|e| format!("Could not write {}: {}", p, e))?)),
+        None => Box::new(BufWriter::new(stdout().lock()))
+    };
+
+    match to.streams() && starts_with_array(&mut reader)? {
+        true => convert_items(reader, to, &mut out)?,
+        false => convert_document(reader, to, &mut out)?
+    }
+
+    out.flush().map_err(|e| format!("Could not write the output: {}", e))
+}
@@ -0,0 +1,39 @@
+use std::io::Write;
+
+use json_parser::comments::parse_with_comments;
+use json_parser::profile::Profile;
+use json_parser::schema::describe;
+use json_parser::serializer::{to_string_with, FormatOptions};
+
+use super::{read_input, render_error, write_stdout};
+
+const USAGE: &str = "\
+Usage: json-rs describe [file]
+
+Writes a JSON Schema for a commented config file, or stdin, with the /// comments
+before or beside each value as its description. The file is read as JSON5, so
+comments, trailing commas and the like are all allowed.";
+
+/// `json-rs describe [file]`, printing a schema documenting a config file from its comments.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut path = None;
+
+    for arg in args {
+        match arg.as_str() {
+            _ if path.is_none() && (!arg.starts_with("--") || arg == "-") => path = Some(arg),
+            _ => return Err(format!("Unexpected argument {:?}\n\n{}", arg, USAGE))
+        }
+    }
+
+    let text = read_input(path)?;
+    let (doc, comments) = parse_with_comments(&text, &Profile::Json5.options()).map_err(|e| render_error(&e, &text))?;
+
+    let options = FormatOptions {
+        sort_keys: true,
+        ..FormatOptions::pretty(2)
+    };
+
+    let schema = to_string_with(&describe(&doc, &comments), &options).map_err(|e| e.to_string())?;
+
+    write_stdout(|out| writeln!(out, "{}", schema))
+}
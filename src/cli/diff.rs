@@ -0,0 +1,63 @@
+use std::io::Write;
+
+use json_parser::compare::Tolerance;
+use json_parser::glob::PathGlob;
+use json_parser::lexer::tokenise;
+use json_parser::parser::{parse, Node};
+
+use super::{read_input, render_error, write_stdout};
+
+const USAGE: &str = "\
+Usage: json-rs diff [options] <file> <file>
+
+Options:
+    --ignore-path <glob>   Skip values at paths like `$.meta.*` or `**.updated_at` (can be repeated)
+    --ignore-order         Treat arrays as equal if they hold the same items in any order";
+
+fn read_document(path: &String) -> Result<Node, String> {
+    let text = read_input(Some(path))?;
+
+    tokenise(&text)
+        .and_then(|tokens| parse(&tokens))
+        .map_err(|e| format!("In {}:\n{}", path, render_error(&e, &text)))
+}
+
+/// `json-rs diff [options] <file> <file>`, printing every difference and failing if there are any.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut tolerance = Tolerance::default();
+    let mut ignore = Vec::new();
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--ignore-path" => match args.next() {
+                Some(glob) => ignore.push(PathGlob::new(glob)),
+                None => return Err(format!("--ignore-path needs a glob\n\n{}", USAGE))
+            },
+            "--ignore-order" => tolerance.ignore_array_order = true,
+            _ if paths.len() < 2 && (!arg.starts_with("--") || arg == "-") => paths.push(arg),
+            _ => return Err(format!("Unexpected argument {:?}\n\n{}", arg, USAGE))
+        }
+    }
+
+    let [left, right] = paths[..] else {
+        return Err(format!("Two files are needed to compare\n\n{}", USAGE));
+    };
+
+    let differences = read_document(left)?.differences(&read_document(right)?, &tolerance, &ignore);
+
+    write_stdout(|out| {
+        for difference in &differences {
+            writeln!(out, "{}", difference)?;
+        }
+
+        Ok(())
+    })?;
+
+    match differences.len() {
+        0 => Ok(()),
+        1 => Err("Found 1 difference".to_string()),
+        n => Err(format!("Found {} differences", n))
+    }
+}
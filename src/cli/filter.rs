@@ -0,0 +1,73 @@
+use std::io::Write;
+
+use json_parser::filter::{FilterError, Program};
+use json_parser::render::{color_enabled, to_string_colored};
+use json_parser::serializer::to_string;
+
+use super::{read_input, render_error, stream_values, write_stdout};
+
+const USAGE: &str = "\
+Usage: json-rs filter [options] <program> [file]
+
+Options:
+    --color <auto|always|never>   Color the output (default: auto, when printing to a terminal)
+    --stream                      Run the program on each value of NDJSON, or JSON values one after
+                                  another, printing the outputs as NDJSON as they're found";
+
+/// `json-rs filter [options] <program> [file]`, printing each output on its own line.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut color = color_enabled();
+    let mut stream = false;
+    let mut positional = Vec::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--color" => color = match args.next().map(|s| s.as_str()) {
+                Some("auto") => color_enabled(),
+                Some("always") => true,
+                Some("never") => false,
+                _ => return Err(format!("--color needs one of auto, always or never\n\n{}", USAGE))
+            },
+            "--stream" => stream = true,
+            _ if positional.len() < 2 => positional.push(arg),
+            _ => return Err(format!("Unexpected argument {:?}\n\n{}", arg, USAGE))
+        }
+    }
+
+    let Some(source) = positional.first() else {
+        return Err(USAGE.to_string());
+    };
+
+    let program = Program::compile(source).map_err(|e| e.to_string())?;
+
+    if stream {
+        return stream_values(positional.get(1).copied(), |node| program.run(node).map_err(|e| e.to_string()));
+    }
+
+    let text = read_input(positional.get(1).copied())?;
+
+    let mut failed = None;
+
+    // Whatever was output before a failure is still written.
+    write_stdout(|out| {
+        for output in program.run_stream(&text) {
+            match output {
+                Ok(node) if color => writeln!(out, "{}", to_string_colored(&node))?,
+                Ok(node) => writeln!(out, "{}", to_string(&node))?,
+                Err(FilterError::Json(e)) => {
+                    failed = Some(render_error(&e, &text));
+                    break;
+                },
+                Err(e) => {
+                    failed = Some(e.to_string());
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    })?;
+
+    failed.map_or(Ok(()), Err)
+}
@@ -0,0 +1,32 @@
+use json_parser::pointer::split_pointer;
+
+use super::stream_values;
+
+const USAGE: &str = "\
+Usage: json-rs get <pointer> [file]
+
+Reads NDJSON, or JSON values one after another, from a file or stdin, and prints
+the value at a JSON Pointer like /level in each, one per line. Values that
+don't have anything at the pointer are skipped.";
+
+/// `json-rs get <pointer> [file]`, picking one value out of each value of a stream.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut positional = Vec::new();
+
+    for arg in args {
+        match arg.as_str() {
+            _ if positional.len() < 2 && (!arg.starts_with("--") || arg == "-") => positional.push(arg),
+            _ => return Err(format!("Unexpected argument {:?}\n\n{}", arg, USAGE))
+        }
+    }
+
+    let Some(pointer) = positional.first() else {
+        return Err(USAGE.to_string());
+    };
+
+    if split_pointer(pointer).is_none() {
+        return Err(format!("{:?} isn't a JSON Pointer, since it doesn't start with a `/`", pointer));
+    }
+
+    stream_values(positional.get(1).copied(), |node| Ok(node.pointer(pointer).cloned().into_iter().collect()))
+}
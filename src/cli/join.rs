@@ -0,0 +1,47 @@
+use std::fs::File;
+use std::io::{stdout, BufReader, BufWriter};
+
+use json_parser::shard::join_arrays;
+
+const USAGE: &str = "\
+Usage: json-rs join [options] <file>...
+
+Options:
+    --output <file>   Write the joined array to a file instead of stdout";
+
+/// `json-rs join [options] <file>...`, joining top-level arrays back into one, in order.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut output = None;
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--output" => match args.next() {
+                Some(p) => output = Some(p),
+                None => return Err(format!("--output needs a file\n\n{}", USAGE))
+            },
+            _ if !arg.starts_with("--") => paths.push(arg),
+            _ => return Err(format!("Unexpected argument {:?}\n\n{}", arg, USAGE))
+        }
+    }
+
+    if paths.is_empty() {
+        return Err(format!("No files to join\n\n{}", USAGE));
+    }
+
+    // Open everything first, so a missing file doesn't leave half an array behind.
+    let readers = paths.iter()
+        .map(|p| File::open(p).map(BufReader::new).map_err(|e| format!("Could not read {}: {}", p, e)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let joined = match output {
+        Some(p) => {
+            let file = File::create(p).map_err(|e| format!("Could not write {}: {}", p, e))?;
+            join_arrays(readers, BufWriter::new(file))
+        },
+        None => join_arrays(readers, BufWriter::new(stdout().lock()))
+    };
+
+    joined.map(|_| ()).map_err(|e| e.to_string())
+}
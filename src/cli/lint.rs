@@ -0,0 +1,68 @@
+use std::io::Write;
+
+use json_parser::lint::{lint, KeyCase, LintConfig};
+
+use super::{read_input, render_error, write_stdout};
+
+const USAGE: &str = "\
+Usage: json-rs lint [options] [file]
+
+Options:
+    --case <snake|camel|pascal|kebab>   Require keys to follow a naming convention
+    --max-depth <n>                     Limit how deeply values can be nested
+    --max-digits <n>                    Limit how many significant digits numbers can have
+    --no-nulls                          Forbid null values
+    --sorted-keys                       Require object keys to be sorted
+    --allow-duplicates                  Allow objects to repeat keys";
+
+fn parse_number(value: Option<&String>, flag: &str) -> Result<usize, String> {
+    match value.map(|v| v.parse()) {
+        Some(Ok(n)) => Ok(n),
+        _ => Err(format!("{} needs a number\n\n{}", flag, USAGE))
+    }
+}
+
+/// `json-rs lint [options] [file]`, printing each warning and failing if there are any.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut config = LintConfig::default();
+    let mut path = None;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--case" => config.key_case = Some(match args.next().map(|s| s.as_str()) {
+                Some("snake") => KeyCase::Snake,
+                Some("camel") => KeyCase::Camel,
+                Some("pascal") => KeyCase::Pascal,
+                Some("kebab") => KeyCase::Kebab,
+                _ => return Err(format!("--case needs one of snake, camel, pascal or kebab\n\n{}", USAGE))
+            }),
+            "--max-depth" => config.max_depth = Some(parse_number(args.next(), arg)?),
+            "--max-digits" => config.max_significant_digits = Some(parse_number(args.next(), arg)?),
+            "--no-nulls" => config.forbid_nulls = true,
+            "--sorted-keys" => config.sorted_keys = true,
+            "--allow-duplicates" => config.no_duplicate_keys = false,
+            _ if path.is_none() && (!arg.starts_with("--") || arg == "-") => path = Some(arg),
+            _ => return Err(format!("Unexpected argument {:?}\n\n{}", arg, USAGE))
+        }
+    }
+
+    let text = read_input(path)?;
+    let warnings = lint(&text, &config).map_err(|e| render_error(&e, &text))?;
+
+    let name = path.map_or("<stdin>", |p| p.as_str());
+
+    write_stdout(|out| {
+        for warning in &warnings {
+            writeln!(out, "{}:{}", name, warning)?;
+        }
+
+        Ok(())
+    })?;
+
+    match warnings.len() {
+        0 => Ok(()),
+        1 => Err("Found 1 problem".to_string()),
+        n => Err(format!("Found {} problems", n))
+    }
+}
@@ -0,0 +1,83 @@
+use std::io::Write;
+
+use json_parser::lexer::tokenise;
+use json_parser::merge::{ArrayMerge, MergeOptions, NullMerge, Provenance};
+use json_parser::parser::{parse, Node};
+use json_parser::serializer::to_writer_pretty;
+
+use super::{read_input, render_error, write_stdout};
+
+const USAGE: &str = "\
+Usage: json-rs merge [options] <base> <overlay>...
+
+Options:
+    --arrays <concat|replace>   Add overlay array items after the base's, or replace them (the default)
+    --nulls <remove|keep>       Take out keys set to null in an overlay, or keep the nulls (the default)
+    --why <pointer>             Print which file the value at a JSON Pointer came from, instead of the result
+
+Overlays are merged on top of the base in order, so later files win.";
+
+fn read_document(path: &String) -> Result<Node, String> {
+    let text = read_input(Some(path))?;
+
+    tokenise(&text)
+        .and_then(|tokens| parse(&tokens))
+        .map_err(|e| format!("In {}:\n{}", path, render_error(&e, &text)))
+}
+
+/// `json-rs merge [options] <base> <overlay>...`, deep merging layered documents and printing the result.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut options = MergeOptions::default();
+    let mut why = None;
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        // Options can be given as `--arrays concat` or `--arrays=concat`.
+        let (flag, value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value)),
+            _ => (arg.as_str(), None)
+        };
+
+        let mut value = || value.or_else(|| args.next().map(|v| v.as_str()));
+
+        match flag {
+            "--arrays" => options.arrays = match value() {
+                Some("concat") => ArrayMerge::Concat,
+                Some("replace") => ArrayMerge::Replace,
+                _ => return Err(format!("--arrays must be concat or replace\n\n{}", USAGE))
+            },
+            "--nulls" => options.nulls = match value() {
+                Some("remove") => NullMerge::Remove,
+                Some("keep") => NullMerge::Keep,
+                _ => return Err(format!("--nulls must be remove or keep\n\n{}", USAGE))
+            },
+            "--why" => match value() {
+                Some(pointer) => why = Some(pointer.to_string()),
+                None => return Err(format!("--why needs a JSON Pointer\n\n{}", USAGE))
+            },
+            _ if !arg.starts_with("--") || arg == "-" => paths.push(arg),
+            _ => return Err(format!("Unexpected argument {:?}\n\n{}", arg, USAGE))
+        }
+    }
+
+    let Some((base, overlays)) = paths.split_first() else {
+        return Err(format!("No files to merge\n\n{}", USAGE));
+    };
+
+    let mut merged = read_document(base)?;
+    let mut provenance = Provenance::new(&merged, base.as_str());
+
+    for overlay in overlays {
+        merged.deep_merge_traced(read_document(overlay)?, &options, overlay.as_str(), &mut provenance);
+    }
+
+    if let Some(pointer) = why {
+        return match provenance.provenance(&pointer) {
+            Some(file) => write_stdout(|out| writeln!(out, "{}", file)),
+            None => Err(format!("Nothing is at {:?} in the merged document", pointer))
+        };
+    }
+
+    write_stdout(|out| to_writer_pretty(&merged, &mut *out).and_then(|_| writeln!(out)))
+}
@@ -0,0 +1,77 @@
+use std::io::Write;
+
+use json_parser::hash::dedup_by_key;
+use json_parser::lexer::tokenise;
+use json_parser::parser::{parse, Node};
+use json_parser::serializer::{to_writer_with, FloatFormat, FormatOptions};
+
+use super::{read_input, render_error, write_stdout};
+
+const USAGE: &str = "\
+Usage: json-rs normalize [options] [file]
+
+Options:
+    --sort-keys                          Write object keys in sorted order
+    --dedupe-array-of-objects-by <key>   In every array, drop objects with the same value under <key> as an earlier one
+    --number-format <format>             Write floats as shortest, scientific, or fixed:<digits>, like fixed:2
+    --max-width <cols>                   Keep arrays and objects that fit in <cols> columns on one line, like Prettier";
+
+/// Drop repeated objects from every array in the tree, innermost arrays first.
+fn dedupe_arrays(node: &mut Node, key: &str) {
+    match node {
+        Node::Array(arr) => {
+            arr.iter_mut().for_each(|item| dedupe_arrays(item, key));
+            *arr = dedup_by_key(std::mem::take(arr), key);
+        },
+        Node::Object(map) => map.values_mut().for_each(|value| dedupe_arrays(value, key)),
+        _ => {}
+    }
+}
+
+fn parse_number_format(format: &str) -> Option<FloatFormat> {
+    match format {
+        "shortest" => Some(FloatFormat::Shortest),
+        "scientific" => Some(FloatFormat::Scientific),
+        _ => format.strip_prefix("fixed:")?.parse().ok().map(FloatFormat::Fixed)
+    }
+}
+
+/// `json-rs normalize [options] [file]`, rewriting a file, or stdin, in a canonical form.
+///
+/// Steps run in a fixed order, deduplicating before writing, so the output only depends
+/// on which options are given and not the order they're given in.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut options = FormatOptions::pretty(2);
+    let mut dedupe_by = None;
+    let mut path = None;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--sort-keys" => options.sort_keys = true,
+            "--dedupe-array-of-objects-by" => match args.next() {
+                Some(key) => dedupe_by = Some(key),
+                None => return Err(format!("--dedupe-array-of-objects-by needs a key\n\n{}", USAGE))
+            },
+            "--number-format" => match args.next().and_then(|f| parse_number_format(f)) {
+                Some(format) => options.float_format = format,
+                None => return Err(format!("--number-format must be shortest, scientific or fixed:<digits>\n\n{}", USAGE))
+            },
+            "--max-width" => match args.next().and_then(|cols| cols.parse().ok()) {
+                Some(cols) => options.max_width = Some(cols),
+                None => return Err(format!("--max-width needs a number of columns\n\n{}", USAGE))
+            },
+            _ if path.is_none() && (!arg.starts_with("--") || arg == "-") => path = Some(arg),
+            _ => return Err(format!("Unexpected argument {:?}\n\n{}", arg, USAGE))
+        }
+    }
+
+    let text = read_input(path)?;
+    let mut doc = tokenise(&text).and_then(|tokens| parse(&tokens)).map_err(|e| render_error(&e, &text))?;
+
+    if let Some(key) = dedupe_by {
+        dedupe_arrays(&mut doc, key);
+    }
+
+    write_stdout(|out| to_writer_with(&doc, &options, &mut *out).and_then(|_| writeln!(out)))
+}
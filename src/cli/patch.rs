@@ -0,0 +1,165 @@
+use std::fs::File;
+use std::io::{stderr, stdout, BufWriter, IsTerminal, Write};
+
+use json_parser::compare::Tolerance;
+use json_parser::lexer::tokenise;
+use json_parser::parser::{parse, Node};
+use json_parser::patch::Patch;
+use json_parser::pointer::escape_token;
+use json_parser::serializer::{to_string, to_writer_pretty};
+
+use super::{read_input, render_error, write_stdout};
+
+const USAGE: &str = "\
+Usage: json-rs patch --patch <file> <file>
+
+Options:
+    --patch <file>   The JSON Patch (RFC 6902) to apply, as an array of operations
+    --dry-run        Only show what would change, without writing anything
+    --in-place       Write the patched document back to the file, instead of to stdout";
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+/// A value that's different after patching, found by `changes`.
+enum Change<'a> {
+    Added(String, &'a Node),
+    Removed(String, &'a Node),
+    Changed(String, &'a Node, &'a Node)
+}
+
+/// Walk two trees side by side, listing where they differ, with object keys sorted.
+fn changes<'a>(before: &'a Node, after: &'a Node, pointer: &str, found: &mut Vec<Change<'a>>) {
+    match (before, after) {
+        (Node::Object(old), Node::Object(new)) => {
+            let mut keys: Vec<&String> = old.keys().chain(new.keys().filter(|key| !old.contains_key(*key))).collect();
+            keys.sort();
+
+            for key in keys {
+                let pointer = format!("{}/{}", pointer, escape_token(key));
+
+                match (old.get(key), new.get(key)) {
+                    (Some(old), Some(new)) => changes(old, new, &pointer, found),
+                    (Some(old), None) => found.push(Change::Removed(pointer, old)),
+                    (None, Some(new)) => found.push(Change::Added(pointer, new)),
+                    (None, None) => {}
+                }
+            }
+        },
+
+        (Node::Array(old), Node::Array(new)) => {
+            for i in 0..old.len().max(new.len()) {
+                let pointer = format!("{}/{}", pointer, i);
+
+                match (old.get(i), new.get(i)) {
+                    (Some(old), Some(new)) => changes(old, new, &pointer, found),
+                    (Some(old), None) => found.push(Change::Removed(pointer, old)),
+                    (None, Some(new)) => found.push(Change::Added(pointer, new)),
+                    (None, None) => {}
+                }
+            }
+        },
+
+        _ => if !before.approx_eq(after, &Tolerance::exact()) {
+            found.push(Change::Changed(pointer.to_string(), before, after));
+        }
+    }
+}
+
+/// Show a change on one line, like `~ /name: "Ada" -> "Grace"`, in color if asked to.
+fn describe(change: &Change, color: bool) -> String {
+    let shown = |pointer: &str| match pointer {
+        "" => "(the whole document)".to_string(),
+        _ => pointer.to_string()
+    };
+
+    let (sign, tint, text) = match change {
+        Change::Added(pointer, new) => ('+', GREEN, format!("{}: {}", shown(pointer), to_string(new))),
+        Change::Removed(pointer, old) => ('-', RED, format!("{}: {}", shown(pointer), to_string(old))),
+        Change::Changed(pointer, old, new) => ('~', YELLOW, format!("{}: {} -> {}", shown(pointer), to_string(old), to_string(new)))
+    };
+
+    match color {
+        true => format!("{}{} {}{}", tint, sign, text, RESET),
+        false => format!("{} {}", sign, text)
+    }
+}
+
+fn read_document(path: &String) -> Result<Node, String> {
+    let text = read_input(Some(path))?;
+
+    tokenise(&text)
+        .and_then(|tokens| parse(&tokens))
+        .map_err(|e| format!("In {}:\n{}", path, render_error(&e, &text)))
+}
+
+/// `json-rs patch --patch <file> <file>`, applying a JSON Patch and showing what it changed.
+///
+/// The patched document goes to stdout with the changes on stderr, unless it's
+/// written back with `--in-place` or not at all with `--dry-run`, when the changes go to stdout.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut patch_path = None;
+    let mut path = None;
+    let mut dry_run = false;
+    let mut in_place = false;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--patch" => match args.next() {
+                Some(p) => patch_path = Some(p),
+                None => return Err(format!("--patch needs a file\n\n{}", USAGE))
+            },
+            "--dry-run" => dry_run = true,
+            "--in-place" => in_place = true,
+            _ if path.is_none() && (!arg.starts_with("--") || arg == "-") => path = Some(arg),
+            _ => return Err(format!("Unexpected argument {:?}\n\n{}", arg, USAGE))
+        }
+    }
+
+    let Some(patch_path) = patch_path else {
+        return Err(format!("A patch is needed with --patch\n\n{}", USAGE));
+    };
+
+    let Some(path) = path else {
+        return Err(format!("A file to patch is needed\n\n{}", USAGE));
+    };
+
+    if in_place && path == "-" {
+        return Err("stdin can't be patched in place".to_string());
+    }
+
+    let patch = Patch::from_node(&read_document(patch_path)?).map_err(|e| format!("In {}: {}", patch_path, e))?;
+
+    let before = read_document(path)?;
+    let mut after = before.clone();
+
+    patch.apply(&mut after).map_err(|e| format!("Could not apply {}: {}", patch_path, e))?;
+
+    let mut found = Vec::new();
+    changes(&before, &after, "", &mut found);
+
+    let to_stdout = dry_run || in_place;
+    let color = match to_stdout {
+        true => stdout().is_terminal(),
+        false => stderr().is_terminal()
+    };
+
+    match to_stdout {
+        true => write_stdout(|out| found.iter().try_for_each(|change| writeln!(out, "{}", describe(change, color))))?,
+        false => found.iter().for_each(|change| eprintln!("{}", describe(change, color)))
+    }
+
+    if dry_run {
+        return Ok(());
+    }
+
+    let write = |out: &mut dyn Write| to_writer_pretty(&after, &mut *out).and_then(|_| writeln!(out)).and_then(|_| out.flush());
+
+    match in_place {
+        true => File::create(path).and_then(|file| write(&mut BufWriter::new(file))).map_err(|e| format!("Could not write {}: {}", path, e)),
+        false => write_stdout(|out| write(out))
+    }
+}
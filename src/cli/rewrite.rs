@@ -0,0 +1,61 @@
+use std::fs::write;
+use std::io::{stdout, Write};
+
+use json_parser::lexer::tokenise;
+use json_parser::parser::parse;
+use json_parser::rewrite::{rewrite, RewriteError};
+use json_parser::serializer::FormatOptions;
+
+use super::{read_input, render_error};
+
+const USAGE: &str = "\
+Usage: json-rs rewrite [options] <old> [new]
+
+Options:
+    --indent <n>   How far to indent anything that wasn't in the old file (default: 2)
+    --in-place     Write the result over the old file, instead of to stdout
+
+The new document, from a file or stdin, is written with the old file's key order
+and layout wherever they hold the same values, so only what changed shows up in a diff.";
+
+/// `json-rs rewrite [options] <old> [new]`, writing a regenerated file laid out like the one it replaces.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut indent = 2;
+    let mut in_place = false;
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--indent" => match args.next().and_then(|n| n.parse().ok()) {
+                Some(n) => indent = n,
+                None => return Err(format!("--indent needs a number of spaces\n\n{}", USAGE))
+            },
+            "--in-place" => in_place = true,
+            _ if paths.len() < 2 && (!arg.starts_with("--") || arg == "-") => paths.push(arg),
+            _ => return Err(format!("Unexpected argument {:?}\n\n{}", arg, USAGE))
+        }
+    }
+
+    let Some(old_path) = paths.first() else {
+        return Err(format!("The old file is needed\n\n{}", USAGE));
+    };
+
+    if in_place && *old_path == "-" {
+        return Err("stdin can't be rewritten in place".to_string());
+    }
+
+    let old = read_input(Some(old_path))?;
+    let text = read_input(paths.get(1).copied())?;
+    let new = tokenise(&text).and_then(|tokens| parse(&tokens)).map_err(|e| render_error(&e, &text))?;
+
+    let rewritten = rewrite(&old, &new, &FormatOptions::pretty(indent)).map_err(|e| match e {
+        RewriteError::Parse(e) => format!("In {}:\n{}", old_path, render_error(&e, &old)),
+        RewriteError::Serialize(e) => e.to_string()
+    })?;
+
+    match in_place {
+        true => write(old_path, rewritten).map_err(|e| format!("Could not write {}: {}", old_path, e)),
+        false => stdout().lock().write_all(rewritten.as_bytes()).map_err(|e| format!("Could not write to stdout: {}", e))
+    }
+}
@@ -0,0 +1,69 @@
+use std::fs::File;
+use std::io::{stdin, BufRead, BufReader, Write};
+
+use json_parser::framing::{ArrayItemReader, NdjsonReader};
+use json_parser::shard::sample;
+
+use super::write_stdout;
+
+const USAGE: &str = "\
+Usage: json-rs sample [options] [file]
+
+Options:
+    -n, --count <n>   How many items to pick (default: 1000)
+    --seed <n>        Pick the same items every time for the same seed (default: random)
+    --ndjson          Read one value per line instead of a top-level array";
+
+fn parse_number<T: std::str::FromStr>(value: Option<&String>, flag: &str) -> Result<T, String> {
+    match value.map(|v| v.parse()) {
+        Some(Ok(n)) => Ok(n),
+        _ => Err(format!("{} needs a number\n\n{}", flag, USAGE))
+    }
+}
+
+/// A seed that's different every run, for when none is given.
+fn random_seed() -> u64 {
+    use std::hash::{BuildHasher, RandomState};
+
+    RandomState::new().hash_one(std::time::SystemTime::now())
+}
+
+/// `json-rs sample [options] [file]`, picking items at random from a top-level array
+/// or NDJSON without reading it all into memory.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut count = 1000;
+    let mut seed = None;
+    let mut ndjson = false;
+    let mut path = None;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-n" | "--count" => count = parse_number(args.next(), arg)?,
+            "--seed" => seed = Some(parse_number(args.next(), arg)?),
+            "--ndjson" => ndjson = true,
+            _ if path.is_none() && (!arg.starts_with("--") || arg == "-") => path = Some(arg),
+            _ => return Err(format!("Unexpected argument {:?}\n\n{}", arg, USAGE))
+        }
+    }
+
+    let reader: Box<dyn BufRead> = match path.map(|p| p.as_str()) {
+        None | Some("-") => Box::new(stdin().lock()),
+        Some(p) => Box::new(BufReader::new(File::open(p).map_err(|e| format!("Could not read {}: {}", p, e))?))
+    };
+
+    let seed = seed.unwrap_or_else(random_seed);
+
+    let items = match ndjson {
+        true => sample(NdjsonReader::new(reader), count, seed),
+        false => sample(ArrayItemReader::new(reader), count, seed)
+    };
+
+    let items = items.map_err(|e| e.to_string())?;
+
+    write_stdout(|out| match ndjson {
+        true => items.iter().try_for_each(|item| writeln!(out, "{}", item)),
+        false if items.is_empty() => writeln!(out, "[]"),
+        false => writeln!(out, "[\n{}\n]", items.join(",\n"))
+    })
+}
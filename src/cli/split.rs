@@ -0,0 +1,57 @@
+use std::fs::File;
+use std::io::{stdin, BufReader, BufWriter};
+use std::path::Path;
+
+use json_parser::shard::split_array;
+
+const USAGE: &str = "\
+Usage: json-rs split [options] [file]
+
+Options:
+    --items-per-file <n>   How many items to put in each file (default: 1000)
+    --prefix <prefix>      Start of each file's name, before `-0001.json` (default: the input's name)";
+
+/// `json-rs split [options] [file]`, writing a top-level array out as numbered files of smaller arrays.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut items_per_file = 1000;
+    let mut prefix = None;
+    let mut path = None;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--items-per-file" => items_per_file = match args.next().map(|v| v.parse()) {
+                Some(Ok(n)) if n > 0 => n,
+                _ => return Err(format!("--items-per-file needs a number above 0\n\n{}", USAGE))
+            },
+            "--prefix" => match args.next() {
+                Some(p) => prefix = Some(p.clone()),
+                None => return Err(format!("--prefix needs a value\n\n{}", USAGE))
+            },
+            _ if path.is_none() && (!arg.starts_with("--") || arg == "-") => path = Some(arg),
+            _ => return Err(format!("Unexpected argument {:?}\n\n{}", arg, USAGE))
+        }
+    }
+
+    // Name the files after the input, like `big-0001.json` for `big.json`.
+    let prefix = prefix.unwrap_or_else(|| match path.map(|p| p.as_str()) {
+        None | Some("-") => "shard".to_string(),
+        Some(p) => Path::new(p).with_extension("").to_string_lossy().into_owned()
+    });
+
+    let open_shard = |i: usize| File::create(format!("{}-{:04}.json", prefix, i + 1)).map(BufWriter::new);
+
+    let shards = match path.map(|p| p.as_str()) {
+        None | Some("-") => split_array(stdin().lock(), items_per_file, open_shard),
+        Some(p) => {
+            let file = File::open(p).map_err(|e| format!("Could not read {}: {}", p, e))?;
+            split_array(BufReader::new(file), items_per_file, open_shard)
+        }
+    };
+
+    let shards = shards.map_err(|e| e.to_string())?;
+
+    eprintln!("Wrote {} file{}", shards, if shards == 1 { "" } else { "s" });
+
+    Ok(())
+}
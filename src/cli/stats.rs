@@ -0,0 +1,97 @@
+use std::io::Write;
+
+use json_parser::stats::stats;
+
+use super::{read_input, render_error, write_stdout};
+
+const USAGE: &str = "\
+Usage: json-rs stats [options] [file]
+
+Options:
+    --top <n>   How many of the most used keys and biggest values to list (default: 10)";
+
+/// `json-rs stats [options] [file]`, printing a summary of what the document is made of.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut top = 10;
+    let mut path = None;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--top" => top = match args.next().map(|v| v.parse()) {
+                Some(Ok(n)) => n,
+                _ => return Err(format!("--top needs a number\n\n{}", USAGE))
+            },
+            _ if path.is_none() && (!arg.starts_with("--") || arg == "-") => path = Some(arg),
+            _ => return Err(format!("Unexpected argument {:?}\n\n{}", arg, USAGE))
+        }
+    }
+
+    let text = read_input(path)?;
+    let stats = stats(&text, top).map_err(|e| render_error(&e, &text))?;
+    let types = &stats.types;
+
+    write_stdout(|out| {
+        writeln!(out, "Values: {}", types.total())?;
+
+        for (name, count) in [
+            ("objects", types.objects),
+            ("arrays", types.arrays),
+            ("strings", types.strings),
+            ("integers", types.integers),
+            ("floats", types.floats),
+            ("booleans", types.booleans),
+            ("nulls", types.nulls)
+        ] {
+            writeln!(out, "    {:<10}{}", name, count)?;
+        }
+
+        writeln!(out, "\nMemory once parsed: about {} bytes", stats.estimated_tree_bytes)?;
+
+        writeln!(out, "\nValues at each depth:")?;
+
+        for (depth, count) in stats.depths.iter().enumerate() {
+            writeln!(out, "    {:<10}{}", depth, count)?;
+        }
+
+        // Most used first, then alphabetically so the order is always the same.
+        let mut keys: Vec<(&String, &usize)> = stats.key_counts.iter().collect();
+        keys.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+
+        if !keys.is_empty() {
+            writeln!(out, "\nMost used keys ({} different):", keys.len())?;
+
+            let width = keys.iter().take(top).map(|(key, _)| key.chars().count()).max().unwrap_or(0);
+
+            for (key, count) in keys.iter().take(top) {
+                writeln!(out, "    {:<width$}  {}", key, count, width = width)?;
+            }
+        }
+
+        if !stats.largest_values.is_empty() {
+            writeln!(out, "\nLargest values:")?;
+
+            for item in &stats.largest_values {
+                writeln!(out, "    {} bytes  {}", item.size, item.path)?;
+            }
+        }
+
+        if !stats.largest_arrays.is_empty() {
+            writeln!(out, "\nLargest arrays:")?;
+
+            for item in &stats.largest_arrays {
+                writeln!(out, "    {} items  {}", item.size, item.path)?;
+            }
+        }
+
+        if !stats.duplicate_keys.is_empty() {
+            writeln!(out, "\nDuplicate keys:")?;
+
+            for duplicate in &stats.duplicate_keys {
+                writeln!(out, "    {}:{}  {}", duplicate.line, duplicate.column, duplicate.path)?;
+            }
+        }
+
+        Ok(())
+    })
+}
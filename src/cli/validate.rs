@@ -0,0 +1,146 @@
+use std::io::Write;
+
+use json_parser::json;
+use json_parser::lexer::tokenise;
+use json_parser::parser::{parse, Node};
+use json_parser::schema::from_schema;
+use json_parser::serializer::{to_string_with, FormatOptions};
+use json_parser::validate::Violation;
+
+use super::{read_input, render_error, write_stdout};
+
+const USAGE: &str = "\
+Usage: json-rs validate --schema <file> [options] <file>...
+
+Options:
+    --schema <file>              The JSON Schema to check each file against
+    --format <text|json|junit>   How to print the results (default: text)";
+
+#[derive(Clone, Copy)]
+enum Format {
+    Text,
+    Json,
+    Junit
+}
+
+/// What was found in one file, where a file that can't be parsed has one violation at `$`.
+struct Report<'a> {
+    file: &'a str,
+    violations: Vec<Violation>
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn plural(n: usize) -> &'static str {
+    if n == 1 { "" } else { "s" }
+}
+
+fn to_json(reports: &[Report]) -> String {
+    let files = reports.iter().map(|report| json!({
+        "file": report.file,
+        "violations": (Node::Array(report.violations.iter().map(|violation| json!({
+            "path": (violation.path.as_str()),
+            "message": (violation.message.as_str())
+        })).collect()))
+    })).collect();
+
+    let options = FormatOptions {
+        sort_keys: true,
+        ..FormatOptions::pretty(2)
+    };
+
+    to_string_with(&Node::Array(files), &options).unwrap()
+}
+
+fn to_junit(reports: &[Report]) -> String {
+    let failures = reports.iter().filter(|report| !report.violations.is_empty()).count();
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str(&format!("<testsuite name=\"json-rs validate\" tests=\"{}\" failures=\"{}\">\n", reports.len(), failures));
+
+    for report in reports {
+        let name = escape_xml(report.file);
+        let count = report.violations.len();
+
+        if count == 0 {
+            out.push_str(&format!("  <testcase name=\"{}\"/>\n", name));
+            continue;
+        }
+
+        let details: Vec<String> = report.violations.iter().map(|violation| escape_xml(&violation.to_string())).collect();
+
+        out.push_str(&format!("  <testcase name=\"{}\">\n", name));
+        out.push_str(&format!("    <failure message=\"{} violation{}\">{}</failure>\n", count, plural(count), details.join("\n")));
+        out.push_str("  </testcase>\n");
+    }
+
+    out.push_str("</testsuite>");
+    out
+}
+
+/// `json-rs validate --schema <file> [options] <file>...`, checking each file against a schema
+/// and failing if any of them don't match it.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut schema_path = None;
+    let mut format = Format::Text;
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--schema" => match args.next() {
+                Some(p) => schema_path = Some(p),
+                None => return Err(format!("--schema needs a file\n\n{}", USAGE))
+            },
+            "--format" => format = match args.next().map(|f| f.as_str()) {
+                Some("text") => Format::Text,
+                Some("json") => Format::Json,
+                Some("junit") => Format::Junit,
+                _ => return Err(format!("--format must be text, json or junit\n\n{}", USAGE))
+            },
+            _ if !arg.starts_with("--") || arg == "-" => paths.push(arg),
+            _ => return Err(format!("Unexpected argument {:?}\n\n{}", arg, USAGE))
+        }
+    }
+
+    let Some(schema_path) = schema_path else {
+        return Err(format!("A schema is needed with --schema\n\n{}", USAGE));
+    };
+
+    if paths.is_empty() {
+        return Err(format!("No files to validate\n\n{}", USAGE));
+    }
+
+    let text = read_input(Some(schema_path))?;
+    let schema = tokenise(&text).and_then(|tokens| parse(&tokens)).map_err(|e| format!("In {}:\n{}", schema_path, render_error(&e, &text)))?;
+    let validator = from_schema(&schema).map_err(|e| format!("In {}: {}", schema_path, e))?;
+
+    let mut reports = Vec::new();
+
+    for path in paths {
+        let text = read_input(Some(path))?;
+
+        let violations = match tokenise(&text).and_then(|tokens| parse(&tokens)) {
+            Ok(doc) => validator.validate(&doc),
+            Err(e) => vec![Violation { path: "$".to_string(), message: e.to_string() }]
+        };
+
+        reports.push(Report { file: path, violations });
+    }
+
+    write_stdout(|out| match format {
+        Format::Text => reports.iter().try_for_each(|report| {
+            report.violations.iter().try_for_each(|violation| writeln!(out, "{}: {}", report.file, violation))
+        }),
+        Format::Json => writeln!(out, "{}", to_json(&reports)),
+        Format::Junit => writeln!(out, "{}", to_junit(&reports))
+    })?;
+
+    match reports.iter().map(|report| report.violations.len()).sum() {
+        0 => Ok(()),
+        1 => Err("Found 1 violation".to_string()),
+        n => Err(format!("Found {} violations", n))
+    }
+}
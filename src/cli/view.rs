@@ -0,0 +1,622 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{stdout, IsTerminal, Read, Write};
+use std::process::{Command, Stdio};
+
+use json_parser::lexer::{scan_string, LexOptions};
+use json_parser::lines::LineIndex;
+use json_parser::parser::parse_str;
+use json_parser::pointer::{escape_token, split_pointer};
+use json_parser::serializer::to_string;
+use json_parser::utils::base64_encode;
+
+use super::read_input;
+
+const HELP: &str = "q quit  j/k move  l/enter open  h close  / search  n next  y copy pointer";
+
+/// Run `stty` on the terminal itself, since stdin might be the document.
+fn stty(args: &[&str]) -> Result<String, String> {
+    let tty = File::open("/dev/tty").map_err(|e| format!("Could not open the terminal: {}", e))?;
+
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::from(tty))
+        .output()
+        .map_err(|e| format!("Could not run stty: {}", e))?;
+
+    match output.status.success() {
+        true => Ok(String::from_utf8_lossy(&output.stdout).trim().to_string()),
+        false => Err(format!("stty failed: {}", String::from_utf8_lossy(&output.stderr).trim()))
+    }
+}
+
+/// The terminal in raw mode on the alternate screen, put back how it was when dropped.
+struct Terminal {
+    saved: String,
+    input: File,
+    rows: usize,
+    columns: usize
+}
+
+impl Terminal {
+    fn open() -> Result<Terminal, String> {
+        let saved = stty(&["-g"])?;
+
+        let size = stty(&["size"])?;
+        let (rows, columns) = size.split_once(' ').unwrap_or(("24", "80"));
+
+        let input = File::open("/dev/tty").map_err(|e| format!("Could not open the terminal: {}", e))?;
+
+        stty(&["raw", "-echo"])?;
+        print!("\x1b[?1049h\x1b[?25l");
+
+        Ok(Terminal {
+            saved,
+            input,
+            // Some terminals report a size of 0 when they don't know it.
+            rows: rows.parse().ok().filter(|&n| n > 0).unwrap_or(24),
+            columns: columns.parse().ok().filter(|&n| n > 0).unwrap_or(80)
+        })
+    }
+
+    fn read_byte(&mut self) -> Option<u8> {
+        let mut byte = [0];
+
+        match self.input.read(&mut byte) {
+            Ok(1) => Some(byte[0]),
+            _ => None
+        }
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        print!("\x1b[?25h\x1b[?1049l");
+        let _ = stdout().flush();
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+/// Whether a byte is whitespace between JSON tokens.
+fn is_space(byte: u8) -> bool {
+    matches!(byte, b' ' | b'\t' | b'\n' | b'\r')
+}
+
+fn skip_spaces(bytes: &[u8], mut i: usize) -> usize {
+    while i < bytes.len() && is_space(bytes[i]) {
+        i += 1;
+    }
+
+    i
+}
+
+/// The offset just past the string whose opening quote is at `start`.
+fn string_end(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut i = start + 1;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return Some(i + 1),
+            _ => i += 1
+        }
+    }
+
+    None
+}
+
+/// Find the end of the value starting at `start` without parsing it, by matching up
+/// brackets outside of strings, along with how many items or keys it has if it's
+/// an array or object. Anything wrong inside it is left for when it's opened.
+fn skip_value(bytes: &[u8], start: usize) -> Option<(usize, Option<usize>)> {
+    match bytes.get(start)? {
+        b'"' => string_end(bytes, start).map(|end| (end, None)),
+
+        b'[' | b'{' => {
+            let mut depth = 0usize;
+            let mut commas = 0;
+            let mut empty = true;
+            let mut i = start;
+
+            while i < bytes.len() {
+                match bytes[i] {
+                    b'"' => {
+                        empty = false;
+                        i = string_end(bytes, i)?;
+                        continue;
+                    },
+                    b'[' | b'{' => {
+                        empty &= depth == 0;
+                        depth += 1;
+                    },
+                    b']' | b'}' => {
+                        depth -= 1;
+
+                        if depth == 0 {
+                            return Some((i + 1, Some(if empty { 0 } else { commas + 1 })));
+                        }
+                    },
+                    b',' if depth == 1 => commas += 1,
+                    byte if is_space(byte) => {},
+                    _ => empty = false
+                }
+
+                i += 1;
+            }
+
+            None
+        },
+
+        _ => {
+            let len = bytes[start..].iter().position(|&b| matches!(b, b',' | b']' | b'}') || is_space(b)).unwrap_or(bytes.len() - start);
+            (len > 0).then_some((start + len, None))
+        }
+    }
+}
+
+/// A value in the document, known by where it is in the text.
+struct Value {
+    start: usize,
+    end: usize,
+
+    /// How many items or keys it has, if it's an array or object.
+    count: Option<usize>,
+
+    /// Its items or entries as labels, reference tokens and values, once it's been opened.
+    children: Option<Vec<(String, String, usize)>>
+}
+
+/// The document, parsed only as far as it's been looked at, so even a huge file
+/// opens as soon as its top level has been scanned. An array or object's children
+/// are found the first time it's opened, and values are parsed when they're shown.
+struct Document<'a> {
+    text: &'a str,
+    values: Vec<Value>
+}
+
+impl<'a> Document<'a> {
+    fn new(text: &'a str) -> Result<Document<'a>, String> {
+        let bytes = text.as_bytes();
+        let start = skip_spaces(bytes, 0);
+
+        let mut document = Document {
+            text,
+            values: Vec::new()
+        };
+
+        let Some((end, count)) = skip_value(bytes, start) else {
+            return Err(document.error(start, "Expected a value"));
+        };
+
+        if skip_spaces(bytes, end) < bytes.len() {
+            return Err(document.error(skip_spaces(bytes, end), "Expected the end of the document"));
+        }
+
+        document.values.push(Value { start, end, count, children: None });
+
+        Ok(document)
+    }
+
+    fn error(&self, offset: usize, message: &str) -> String {
+        let (line, column) = LineIndex::new(self.text).offset_to_line_col(offset);
+        format!("{} at line {}, column {}", message, line, column)
+    }
+
+    fn is_container(&self, id: usize) -> bool {
+        self.values[id].count.is_some()
+    }
+
+    /// The items or entries of an array or object, finding them if it hasn't been opened before.
+    /// Keys are sorted, so the tree looks the same every time.
+    fn children(&mut self, id: usize) -> Result<Vec<(String, String, usize)>, String> {
+        if let Some(children) = &self.values[id].children {
+            return Ok(children.clone());
+        }
+
+        let bytes = self.text.as_bytes();
+        let start = self.values[id].start;
+        let (is_object, close) = match bytes[start] {
+            b'{' => (true, b'}'),
+            b'[' => (false, b']'),
+            _ => return Ok(Vec::new())
+        };
+
+        let mut children: Vec<(String, String, usize)> = Vec::new();
+        let mut i = skip_spaces(bytes, start + 1);
+
+        while bytes.get(i) != Some(&close) {
+            let (label, token) = match is_object {
+                true => {
+                    if bytes.get(i) != Some(&b'"') {
+                        return Err(self.error(i, "Expected a key"));
+                    }
+
+                    let (key, span) = scan_string(self.text, i, &LexOptions::default()).map_err(|e| e.to_string())?;
+                    i = skip_spaces(bytes, span.end);
+
+                    if bytes.get(i) != Some(&b':') {
+                        return Err(self.error(i, "Expected a ':'"));
+                    }
+
+                    i = skip_spaces(bytes, i + 1);
+
+                    let token = escape_token(&key);
+                    (key, token)
+                },
+                false => (children.len().to_string(), children.len().to_string())
+            };
+
+            let Some((end, count)) = skip_value(bytes, i) else {
+                return Err(self.error(i, "Expected a value"));
+            };
+
+            self.values.push(Value { start: i, end, count, children: None });
+            let value = self.values.len() - 1;
+
+            // The last of a key given twice wins, like when parsing.
+            match children.iter_mut().find(|(existing, _, _)| is_object && *existing == label) {
+                Some(child) => child.2 = value,
+                None => children.push((label, token, value))
+            }
+
+            i = skip_spaces(bytes, end);
+
+            match bytes.get(i) {
+                Some(b',') => i = skip_spaces(bytes, i + 1),
+                Some(&b) if b == close => {},
+                _ => return Err(self.error(i, "Expected a ',' or the end of the list"))
+            }
+        }
+
+        if is_object {
+            children.sort_by(|a, b| a.0.cmp(&b.0));
+        }
+
+        self.values[id].children = Some(children.clone());
+
+        Ok(children)
+    }
+
+    fn describe(&self, id: usize) -> String {
+        let value = &self.values[id];
+        let raw = &self.text[value.start..value.end];
+
+        match (raw.as_bytes()[0], value.count) {
+            (b'[', Some(1)) => "[…] 1 item".to_string(),
+            (b'[', Some(n)) => format!("[…] {} items", n),
+            (_, Some(1)) => "{…} 1 key".to_string(),
+            (_, Some(n)) => format!("{{…}} {} keys", n),
+            _ => match parse_str(raw) {
+                Ok(node) => to_string(&node),
+                Err(_) => format!("{} (not valid JSON)", raw)
+            }
+        }
+    }
+}
+
+/// A line of the tree on screen.
+struct Row {
+    pointer: String,
+    depth: usize,
+    label: Option<String>,
+    value: usize
+}
+
+struct Viewer<'a> {
+    document: Document<'a>,
+    expanded: HashSet<String>,
+    rows: Vec<Row>,
+    cursor: usize,
+    scroll: usize,
+    status: String
+}
+
+impl<'a> Viewer<'a> {
+    fn new(document: Document<'a>) -> Viewer<'a> {
+        let mut viewer = Viewer {
+            document,
+            expanded: HashSet::new(),
+            rows: Vec::new(),
+            cursor: 0,
+            scroll: 0,
+            status: HELP.to_string()
+        };
+
+        viewer.rebuild();
+        viewer.open();
+        viewer
+    }
+
+    /// Work out the rows again after something has been opened or closed.
+    ///
+    /// Only open arrays and objects are walked, so a huge document costs
+    /// no more than the part of it that's showing.
+    fn rebuild(&mut self) {
+        fn walk(document: &mut Document, value: usize, pointer: String, depth: usize, label: Option<String>, expanded: &HashSet<String>, rows: &mut Vec<Row>) {
+            let open = expanded.contains(&pointer);
+
+            rows.push(Row { pointer: pointer.clone(), depth, label, value });
+
+            // Anything open has been opened before, so its children have already been found.
+            if open && let Ok(children) = document.children(value) {
+                for (label, token, child) in children {
+                    walk(document, child, format!("{}/{}", pointer, token), depth + 1, Some(label), expanded, rows);
+                }
+            }
+        }
+
+        let current = self.rows.get(self.cursor).map(|row| row.pointer.clone());
+
+        self.rows.clear();
+        walk(&mut self.document, 0, String::new(), 0, None, &self.expanded, &mut self.rows);
+
+        // Stay on the same value, if it's still showing.
+        if let Some(current) = current {
+            self.select(&current);
+        }
+
+        self.cursor = self.cursor.min(self.rows.len() - 1);
+    }
+
+    fn select(&mut self, pointer: &str) {
+        if let Some(i) = self.rows.iter().position(|row| row.pointer == pointer) {
+            self.cursor = i;
+        }
+    }
+
+    fn current(&self) -> &Row {
+        &self.rows[self.cursor]
+    }
+
+    fn open(&mut self) {
+        let value = self.current().value;
+
+        if !self.document.is_container(value) {
+            return;
+        }
+
+        match self.document.children(value) {
+            Ok(_) => {
+                self.expanded.insert(self.current().pointer.clone());
+                self.rebuild();
+            },
+            Err(e) => self.status = e
+        }
+    }
+
+    /// Close the current value, or go up to its parent if it's already closed.
+    fn close(&mut self) {
+        let pointer = self.current().pointer.clone();
+
+        if self.expanded.remove(&pointer) {
+            self.rebuild();
+        }
+        else if let Some((parent, _)) = pointer.rsplit_once('/') {
+            self.select(parent);
+        }
+    }
+
+    fn toggle(&mut self) {
+        match self.expanded.contains(&self.current().pointer) {
+            true => self.close(),
+            false => self.open()
+        }
+    }
+
+    /// Go to the next key containing `query`, after the current value, opening
+    /// everything around it. The whole document is searched, not only what's showing,
+    /// so searching a huge document finds the children of everything in it.
+    fn search(&mut self, query: &str) {
+        /// Every value under `value` in the order of the rows, and whether its key matches.
+        fn walk(document: &mut Document, value: usize, pointer: String, query: &str, order: &mut Vec<(String, bool)>) -> Result<(), String> {
+            let is_object = document.text.as_bytes()[document.values[value].start] == b'{';
+
+            for (label, token, child) in document.children(value)? {
+                let path = format!("{}/{}", pointer, token);
+                let matched = is_object && label.to_lowercase().contains(query);
+
+                order.push((path.clone(), matched));
+                walk(document, child, path, query, order)?;
+            }
+
+            Ok(())
+        }
+
+        let mut order = Vec::new();
+
+        if let Err(e) = walk(&mut self.document, 0, String::new(), &query.to_lowercase(), &mut order) {
+            self.status = e;
+            return;
+        }
+
+        // Look from just after the current value, wrapping round to the top.
+        let start = order.iter().position(|(pointer, _)| *pointer == self.current().pointer).map_or(0, |i| i + 1);
+
+        let next = order[start..].iter().chain(&order[..start]).find(|(_, matched)| *matched);
+
+        let Some((next, _)) = next.cloned() else {
+            self.status = format!("No keys match {:?}", query);
+            return;
+        };
+
+        // Open every array and object on the way down.
+        let mut parent = String::new();
+
+        for token in split_pointer(&next).unwrap_or_default() {
+            self.expanded.insert(parent.clone());
+            parent = format!("{}/{}", parent, escape_token(&token));
+        }
+
+        self.rebuild();
+        self.select(&next);
+        self.status = format!("Found {}", next);
+    }
+
+    /// Copy the current pointer with an OSC 52 escape, which most terminals
+    /// pass on to the clipboard, even over SSH.
+    fn copy_pointer(&mut self) {
+        let pointer = self.current().pointer.clone();
+
+        print!("\x1b]52;c;{}\x07", base64_encode(pointer.as_bytes(), false));
+        self.status = format!("Copied {:?}", pointer);
+    }
+
+    fn draw(&mut self, terminal: &Terminal, prompt: Option<&str>) {
+        let height = terminal.rows.saturating_sub(1).max(1);
+
+        // Scroll just enough to keep the cursor on screen.
+        if self.cursor < self.scroll {
+            self.scroll = self.cursor;
+        }
+        else if self.cursor >= self.scroll + height {
+            self.scroll = self.cursor + 1 - height;
+        }
+
+        let mut screen = String::from("\x1b[H\x1b[2J");
+
+        for (i, row) in self.rows.iter().enumerate().skip(self.scroll).take(height) {
+            let marker = match (self.document.is_container(row.value), self.expanded.contains(&row.pointer)) {
+                (true, true) => "▾ ",
+                (true, false) => "▸ ",
+                _ => "  "
+            };
+
+            let label = match &row.label {
+                Some(label) => format!("{}: ", label),
+                None => String::new()
+            };
+
+            let line = format!("{}{}{}{}", "  ".repeat(row.depth), marker, label, self.document.describe(row.value));
+            let line: String = line.chars().take(terminal.columns).collect();
+
+            match i == self.cursor {
+                true => screen.push_str(&format!("\x1b[7m{}\x1b[0m\r\n", line)),
+                false => screen.push_str(&format!("{}\r\n", line))
+            }
+        }
+
+        let status = match prompt {
+            Some(query) => format!("/{}", query),
+            None => format!("{}  {}", self.current().pointer, self.status)
+        };
+
+        let status: String = status.chars().take(terminal.columns).collect();
+
+        screen.push_str(&format!("\x1b[{};1H\x1b[1m{}\x1b[0m", terminal.rows, status));
+
+        print!("{}", screen);
+        let _ = stdout().flush();
+    }
+}
+
+/// Read a search query from the keyboard, or `None` if it's cancelled with escape.
+fn read_query(viewer: &mut Viewer, terminal: &mut Terminal) -> Option<String> {
+    let mut query = String::new();
+
+    loop {
+        viewer.draw(terminal, Some(&query));
+
+        match terminal.read_byte()? {
+            b'\r' | b'\n' => return Some(query),
+            0x1b => return None,
+            0x7f | 0x08 => {
+                query.pop();
+            },
+            b if b.is_ascii_graphic() || b == b' ' => query.push(b as char),
+            _ => {}
+        }
+    }
+}
+
+/// `json-rs view [file]`, browsing a document as a tree that can be opened and closed.
+pub fn run(args: &[String]) -> Result<(), String> {
+    if !stdout().is_terminal() {
+        return Err("json-rs view needs to be run in a terminal".to_string());
+    }
+
+    let text = read_input(args.first())?;
+    let document = Document::new(&text)?;
+
+    let mut terminal = Terminal::open()?;
+    let mut viewer = Viewer::new(document);
+    let mut last_query = None;
+
+    loop {
+        viewer.draw(&terminal, None);
+
+        let Some(key) = terminal.read_byte() else {
+            break;
+        };
+
+        match key {
+            b'q' | 0x03 => break,
+            b'j' => viewer.cursor = (viewer.cursor + 1).min(viewer.rows.len() - 1),
+            b'k' => viewer.cursor = viewer.cursor.saturating_sub(1),
+            b'g' => viewer.cursor = 0,
+            b'G' => viewer.cursor = viewer.rows.len() - 1,
+            b'l' => viewer.open(),
+            b'h' => viewer.close(),
+            b'\r' | b' ' => viewer.toggle(),
+            b'y' => viewer.copy_pointer(),
+
+            b'/' => if let Some(query) = read_query(&mut viewer, &mut terminal) {
+                viewer.search(&query);
+                last_query = Some(query);
+            },
+
+            b'n' => if let Some(query) = &last_query {
+                viewer.search(query);
+            },
+
+            // Arrow keys, which come as `ESC [ A` and so on.
+            0x1b => match (terminal.read_byte(), terminal.read_byte()) {
+                (Some(b'['), Some(b'A')) => viewer.cursor = viewer.cursor.saturating_sub(1),
+                (Some(b'['), Some(b'B')) => viewer.cursor = (viewer.cursor + 1).min(viewer.rows.len() - 1),
+                (Some(b'['), Some(b'C')) => viewer.open(),
+                (Some(b'['), Some(b'D')) => viewer.close(),
+                _ => {}
+            },
+
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_are_skipped_without_parsing() {
+        let text = r#"{"a": [1, "]", {"b": null}], "c": {}, "d": "x\"y"}"#;
+        let document = Document::new(text).unwrap();
+
+        assert_eq!(document.values[0].count, Some(3));
+        assert_eq!(skip_value(b"[[], []]", 0), Some((8, Some(2))));
+        assert_eq!(skip_value(b"12, 3", 0), Some((2, None)));
+        assert_eq!(skip_value(b"[1", 0), None);
+    }
+
+    #[test]
+    fn children_are_found_when_opened() {
+        let mut document = Document::new(r#"{"b": [1, 2], "a": "x", "a": 1}"#).unwrap();
+        assert_eq!(document.values.len(), 1);
+
+        let children = document.children(0).unwrap();
+        let labels: Vec<_> = children.iter().map(|(label, _, _)| label.as_str()).collect();
+
+        assert_eq!(labels, ["a", "b"]);
+        assert_eq!(document.describe(children[0].2), "1");
+        assert_eq!(document.describe(children[1].2), "[…] 2 items");
+    }
+
+    #[test]
+    fn errors_are_found_when_opened() {
+        assert!(Document::new("[1] 2").is_err());
+
+        let mut document = Document::new(r#"{"a" 1}"#).unwrap();
+        assert!(document.children(0).unwrap_err().contains("line 1"));
+    }
+}
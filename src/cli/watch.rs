@@ -0,0 +1,124 @@
+use std::io::Write;
+use std::path::Path;
+use std::sync::mpsc::channel;
+use std::time::Duration;
+
+use json_parser::cache::CacheError;
+use json_parser::json;
+use json_parser::lexer::tokenise;
+use json_parser::parser::{parse, Node};
+use json_parser::schema::from_schema;
+use json_parser::serializer::{to_string_with, FormatOptions};
+use json_parser::validate::{Validator, Violation};
+use json_parser::watch::{watch_dir, WatchOptions};
+
+use super::{read_input, render_error, write_stdout};
+
+const USAGE: &str = "\
+Usage: json-rs watch [options] <dir>
+
+Options:
+    --glob <pattern>        Which files to watch, by name (default: *.json)
+    --schema <file>         Check each file against a JSON Schema, as well as that it parses
+    --format <text|json>    Print a line of text per problem, or one JSON object per check
+    --interval <ms>         How often to look for changes (default: 250)
+
+Files are checked when they're first found and each time they change, until interrupted.";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Text,
+    Json
+}
+
+/// Print what was found in a file, with a file that can't be read or parsed having one violation at `$`.
+fn report(path: &Path, result: Result<Node, CacheError>, validator: Option<&dyn Validator>, format: Format) -> Result<(), String> {
+    let violations = match result {
+        Ok(doc) => validator.map(|validator| validator.validate(&doc)).unwrap_or_default(),
+        Err(e) => vec![Violation { path: "$".to_string(), message: e.to_string() }]
+    };
+
+    let file = path.display().to_string();
+
+    write_stdout(|out| match format {
+        Format::Text if violations.is_empty() => writeln!(out, "{}: ok", file),
+        Format::Text => violations.iter().try_for_each(|violation| writeln!(out, "{}: {}", file, violation)),
+        Format::Json => {
+            let line = json!({
+                "file": (file.as_str()),
+                "violations": (Node::Array(violations.iter().map(|violation| json!({
+                    "path": (violation.path.as_str()),
+                    "message": (violation.message.as_str())
+                })).collect()))
+            });
+
+            let options = FormatOptions {
+                sort_keys: true,
+                ..FormatOptions::compact()
+            };
+
+            writeln!(out, "{}", to_string_with(&line, &options).unwrap())
+        }
+    })
+}
+
+/// `json-rs watch [options] <dir>`, checking files in a directory again each time they change.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let mut pattern = "*.json".to_string();
+    let mut schema_path = None;
+    let mut format = Format::Text;
+    let mut options = WatchOptions::default();
+    let mut dir = None;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--glob" => match args.next() {
+                Some(glob) => pattern = glob.clone(),
+                None => return Err(format!("--glob needs a pattern\n\n{}", USAGE))
+            },
+            "--schema" => match args.next() {
+                Some(p) => schema_path = Some(p),
+                None => return Err(format!("--schema needs a file\n\n{}", USAGE))
+            },
+            "--format" => format = match args.next().map(|f| f.as_str()) {
+                Some("text") => Format::Text,
+                Some("json") => Format::Json,
+                _ => return Err(format!("--format must be text or json\n\n{}", USAGE))
+            },
+            "--interval" => match args.next().and_then(|ms| ms.parse().ok()) {
+                Some(ms) => options.poll_interval = Duration::from_millis(ms),
+                None => return Err(format!("--interval needs a number of milliseconds\n\n{}", USAGE))
+            },
+            _ if dir.is_none() && !arg.starts_with("--") => dir = Some(arg),
+            _ => return Err(format!("Unexpected argument {:?}\n\n{}", arg, USAGE))
+        }
+    }
+
+    let Some(dir) = dir else {
+        return Err(format!("A directory to watch is needed\n\n{}", USAGE));
+    };
+
+    let validator = match schema_path {
+        Some(path) => {
+            let text = read_input(Some(path))?;
+            let schema = tokenise(&text).and_then(|tokens| parse(&tokens)).map_err(|e| format!("In {}:\n{}", path, render_error(&e, &text)))?;
+
+            Some(from_schema(&schema).map_err(|e| format!("In {}: {}", path, e))?)
+        },
+        None => None
+    };
+
+    // Validators don't have to be thread-safe, so results are checked here rather than on the watcher threads.
+    let (sender, receiver) = channel();
+
+    let _watcher = watch_dir(dir, &pattern, options, move |path, result| {
+        let _ = sender.send((path.to_path_buf(), result));
+    }).map_err(|e| format!("Could not watch {}: {}", dir, e))?;
+
+    for (path, result) in receiver {
+        report(&path, result, validator.as_deref(), format)?;
+    }
+
+    Ok(())
+}
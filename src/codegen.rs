@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+
+use crate::parser::Node;
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "type", "unsafe",
+    "use", "where", "while", "abstract", "become", "box", "do", "final", "gen", "macro", "override",
+    "priv", "try", "typeof", "unsized", "virtual", "yield"
+];
+
+pub struct CodegenOptions {
+    /// The name of the type generated for the whole document.
+    pub root_name: String,
+
+    /// Whether to derive serde's `Serialize` and `Deserialize`, renaming fields
+    /// whose names had to change and using `serde_json::Value` for values of mixed types.
+    pub serde: bool
+}
+
+impl Default for CodegenOptions {
+    fn default() -> CodegenOptions {
+        CodegenOptions {
+            root_name: "Root".to_string(),
+            serde: false
+        }
+    }
+}
+
+/// The type inferred for a value, from every sample seen of it.
+#[derive(Clone)]
+enum Shape {
+    /// Only ever seen as `null`, or never seen at all, like the items of an empty array.
+    Unknown,
+
+    Bool,
+    Integer,
+
+    /// Seen with numbers too big for an `i64`.
+    Unsigned,
+    Float,
+    String,
+    Array(Box<Field>),
+    Object(HashMap<String, Field>),
+
+    /// Seen with types that don't fit together.
+    Mixed
+}
+
+#[derive(Clone)]
+struct Field {
+    shape: Shape,
+
+    /// Whether the value was sometimes `null` or missing.
+    optional: bool
+}
+
+fn merge_shapes(a: Shape, b: Shape) -> Shape {
+    match (a, b) {
+        (Shape::Unknown, x) | (x, Shape::Unknown) => x,
+
+        (Shape::Bool, Shape::Bool) => Shape::Bool,
+        (Shape::Integer, Shape::Integer) => Shape::Integer,
+
+        // Numbers past `i64::MAX` only fit in a `u64`, though any negative ones won't.
+        (Shape::Integer | Shape::Unsigned, Shape::Integer | Shape::Unsigned) => Shape::Unsigned,
+        (Shape::Integer | Shape::Unsigned | Shape::Float, Shape::Integer | Shape::Unsigned | Shape::Float) => Shape::Float,
+        (Shape::String, Shape::String) => Shape::String,
+
+        (Shape::Array(a), Shape::Array(b)) => Shape::Array(Box::new(merge_fields(*a, *b))),
+
+        (Shape::Object(mut a), Shape::Object(mut b)) => {
+            let mut merged = HashMap::new();
+
+            for (key, field) in a.drain() {
+                let field = match b.remove(&key) {
+                    Some(other) => merge_fields(field, other),
+
+                    // Missing from one sample means it's optional.
+                    None => Field {
+                        optional: true,
+                        ..field
+                    }
+                };
+
+                merged.insert(key, field);
+            }
+
+            for (key, field) in b {
+                merged.insert(key, Field {
+                    optional: true,
+                    ..field
+                });
+            }
+
+            Shape::Object(merged)
+        },
+
+        _ => Shape::Mixed
+    }
+}
+
+fn merge_fields(a: Field, b: Field) -> Field {
+    Field {
+        shape: merge_shapes(a.shape, b.shape),
+        optional: a.optional || b.optional
+    }
+}
+
+fn infer(node: &Node) -> Field {
+    let shape = match node {
+        Node::Null | Node::Empty => return Field {
+            shape: Shape::Unknown,
+            optional: true
+        },
+
+        Node::Bool(_) => Shape::Bool,
+        Node::Integer(_) => Shape::Integer,
+        Node::Unsigned(_) => Shape::Unsigned,
+        Node::Float(_) => Shape::Float,
+        Node::String(_) => Shape::String,
+
+        Node::Array(arr) => {
+            let item = arr.iter().map(infer).reduce(merge_fields).unwrap_or(Field {
+                shape: Shape::Unknown,
+                optional: false
+            });
+
+            Shape::Array(Box::new(item))
+        },
+
+        Node::Object(map) => Shape::Object(map.iter().map(
+            |(key, value)| (key.clone(), infer(value))
+        ).collect())
+    };
+
+    Field {
+        shape,
+        optional: false
+    }
+}
+
+/// Split a key into lowercase words, at underscores, dashes, spaces and camelCase humps.
+fn words(key: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut previous_lower = false;
+
+    for ch in key.chars() {
+        if !ch.is_alphanumeric() {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+
+            previous_lower = false;
+            continue;
+        }
+
+        if ch.is_uppercase() && previous_lower && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+
+        previous_lower = ch.is_lowercase() || ch.is_ascii_digit();
+        current.extend(ch.to_lowercase());
+    }
+
+    if !current.is_empty() {
+        words.push(current);
+    }
+
+    words
+}
+
+fn field_name(key: &str) -> String {
+    let name = words(key).join("_");
+
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        return format!("field_{}", name);
+    }
+
+    if KEYWORDS.contains(&name.as_str()) {
+        // These can't be raw identifiers, so they get a suffix instead.
+        if matches!(name.as_str(), "self" | "super" | "crate") {
+            return format!("{}_", name);
+        }
+
+        return format!("r#{}", name);
+    }
+
+    name
+}
+
+fn type_name(key: &str) -> String {
+    let name: String = words(key).iter().map(|word| {
+        let mut chars = word.chars();
+
+        match chars.next() {
+            Some(first) => first.to_uppercase().chain(chars).collect(),
+            None => String::new()
+        }
+    }).collect();
+
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        return format!("Type{}", name);
+    }
+
+    name
+}
+
+/// A rough singular for naming the items of an array, like `Users` to `User`.
+fn singular(name: &str) -> String {
+    if let Some(stem) = name.strip_suffix("ies") {
+        return format!("{}y", stem);
+    }
+
+    match name.strip_suffix('s') {
+        Some(stem) if !stem.is_empty() && !stem.ends_with('s') => stem.to_string(),
+        _ => format!("{}Item", name)
+    }
+}
+
+struct Generator<'a> {
+    options: &'a CodegenOptions,
+
+    /// Generated structs, in the order they should be written.
+    structs: Vec<String>,
+    used_names: Vec<String>
+}
+
+impl<'a> Generator<'a> {
+    fn unique_name(&mut self, name: String) -> String {
+        let mut candidate = name.clone();
+        let mut n = 2;
+
+        while self.used_names.contains(&candidate) {
+            candidate = format!("{}{}", name, n);
+            n += 1;
+        }
+
+        self.used_names.push(candidate.clone());
+        candidate
+    }
+
+    fn any_type(&self) -> &'static str {
+        if self.options.serde { "serde_json::Value" } else { "json_parser::parser::Node" }
+    }
+
+    /// Work out the Rust type for a field, generating structs for any objects in it.
+    fn rust_type(&mut self, field: &Field, name: &str) -> String {
+        let ty = match &field.shape {
+            Shape::Unknown | Shape::Mixed => self.any_type().to_string(),
+            Shape::Bool => "bool".to_string(),
+            Shape::Integer => "i64".to_string(),
+            Shape::Unsigned => "u64".to_string(),
+            Shape::Float => "f64".to_string(),
+            Shape::String => "String".to_string(),
+            Shape::Array(item) => format!("Vec<{}>", self.rust_type(item, &singular(name))),
+            Shape::Object(fields) => self.write_struct(fields, name)
+        };
+
+        if field.optional && !matches!(field.shape, Shape::Unknown) {
+            format!("Option<{}>", ty)
+        }
+        else {
+            ty
+        }
+    }
+
+    fn write_struct(&mut self, fields: &HashMap<String, Field>, name: &str) -> String {
+        let name = self.unique_name(name.to_string());
+
+        // Reserve this struct's place so it comes before the structs inside it.
+        let index = self.structs.len();
+        self.structs.push(String::new());
+
+        let mut keys: Vec<&String> = fields.keys().collect();
+        keys.sort();
+
+        let mut body = String::new();
+
+        for key in keys {
+            let field = field_name(key);
+            let ty = self.rust_type(&fields[key], &type_name(key));
+
+            if self.options.serde && field.trim_start_matches("r#") != key.as_str() {
+                body.push_str(&format!("    #[serde(rename = {:?})]\n", key));
+            }
+
+            body.push_str(&format!("    pub {}: {},\n", field, ty));
+        }
+
+        let derives = if self.options.serde {
+            "Debug, Clone, serde::Serialize, serde::Deserialize"
+        }
+        else {
+            "Debug, Clone"
+        };
+
+        self.structs[index] = match body.is_empty() {
+            true => format!("#[derive({})]\npub struct {} {{}}\n", derives, name),
+            false => format!("#[derive({})]\npub struct {} {{\n{}}}\n", derives, name, body)
+        };
+
+        name
+    }
+}
+
+/// Generate Rust type definitions that fit an example document.
+///
+/// If the document is an array of objects, each item is treated as another
+/// sample of the same struct, so fields missing from some items become `Option`s,
+/// as do fields that are sometimes `null`. Any other document that isn't an object
+/// gets a type alias.
+pub fn generate_rust(sample: &Node, options: &CodegenOptions) -> String {
+    let root = match sample {
+        Node::Array(arr) if arr.iter().all(|item| matches!(item, Node::Object(_))) && !arr.is_empty() => {
+            arr.iter().map(infer).reduce(merge_fields).unwrap()
+        },
+        _ => infer(sample)
+    };
+
+    let mut generator = Generator {
+        options,
+        structs: Vec::new(),
+        used_names: Vec::new()
+    };
+
+    match &root.shape {
+        Shape::Object(fields) if !root.optional => {
+            generator.write_struct(fields, &options.root_name);
+        },
+        _ => {
+            let name = generator.unique_name(options.root_name.clone());
+            let ty = generator.rust_type(&root, &name);
+
+            generator.structs.insert(0, format!("pub type {} = {};\n", name, ty));
+        }
+    }
+
+    generator.structs.join("\n")
+}
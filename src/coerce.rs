@@ -0,0 +1,214 @@
+use crate::parser::Node;
+use crate::serializer::to_string;
+
+/// A type that values can be coerced to with `Node::coerce_to`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ValueType {
+    String,
+    Integer,
+    Float,
+    Bool
+}
+
+impl ValueType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::String => "string",
+            Self::Integer => "integer",
+            Self::Float => "float",
+            Self::Bool => "bool"
+        }
+    }
+
+    /// The type with the given name, as given by `name`.
+    pub fn from_name(name: &str) -> Option<ValueType> {
+        match name {
+            "string" => Some(Self::String),
+            "integer" => Some(Self::Integer),
+            "float" => Some(Self::Float),
+            "bool" => Some(Self::Bool),
+            _ => None
+        }
+    }
+}
+
+/// Floats at or past this size don't fit in an `i64`.
+const I64_LIMIT: f64 = 9_223_372_036_854_775_808.0;
+
+fn whole_float_to_i64(f: f64) -> Option<i64> {
+    (f.fract() == 0.0 && f.abs() < I64_LIMIT).then_some(f as i64)
+}
+
+fn parse_bool(s: &str) -> Option<bool> {
+    match s.trim().to_ascii_lowercase().as_str() {
+        "true" | "1" => Some(true),
+        "false" | "0" => Some(false),
+        _ => None
+    }
+}
+
+impl Node {
+    /// Convert a value to another type, for APIs that send numbers as strings and the like.
+    ///
+    /// | From                | To string | To integer         | To float         | To bool                           |
+    /// |---------------------|-----------|--------------------|------------------|-----------------------------------|
+    /// | string              | itself    | `"42"`, `"42.0"`   | `"4.2"`, `"1e3"` | `"true"`, `"false"`, `"1"`, `"0"` |
+    /// | integer             | `"42"`    | itself             | `42.0`           | `1` and `0` only                  |
+    /// | float               | `"4.2"`   | whole numbers only | itself           | no                                |
+    /// | bool                | `"true"`  | `1` or `0`         | no               | itself                            |
+    /// | null, array, object | no        | no                 | no               | no                                |
+    ///
+    /// Strings have whitespace trimmed from both ends first, and `"true"` and `"false"`
+    /// can be in any case. Strings that are numbers but not finite, like `"inf"`, don't convert.
+    /// Floats are written as they would be in JSON text, like `"2.0"` or `"1e+300"`.
+    /// Anything that can't be converted gives back `None`.
+    pub fn coerce_to(&self, to: ValueType) -> Option<Node> {
+        let coerced = match (to, self) {
+            (ValueType::String, Node::String(s)) => Node::String(s.clone()),
+            (ValueType::String, Node::Integer(n)) => Node::String(n.to_string()),
+            (ValueType::String, Node::Unsigned(n)) => Node::String(n.to_string()),
+            (ValueType::String, Node::Float(f)) if f.is_finite() => Node::String(to_string(self)),
+            (ValueType::String, Node::Bool(b)) => Node::String(b.to_string()),
+
+            (ValueType::Integer, Node::Integer(n)) => Node::Integer(*n),
+            (ValueType::Integer, Node::Unsigned(n)) => Node::Unsigned(*n),
+            (ValueType::Integer, Node::Float(f)) => Node::Integer(whole_float_to_i64(*f)?),
+            (ValueType::Integer, Node::Bool(b)) => Node::Integer(*b as i64),
+            (ValueType::Integer, Node::String(s)) => {
+                let s = s.trim();
+
+                match (s.parse(), s.parse()) {
+                    (Ok(n), _) => Node::Integer(n),
+                    (Err(_), Ok(n)) => Node::Unsigned(n),
+                    _ => Node::Integer(whole_float_to_i64(s.parse().ok()?)?)
+                }
+            },
+
+            (ValueType::Float, Node::Float(f)) => Node::Float(*f),
+            (ValueType::Float, Node::Integer(n)) => Node::Float(*n as f64),
+            (ValueType::Float, Node::Unsigned(n)) => Node::Float(*n as f64),
+            (ValueType::Float, Node::String(s)) => Node::Float(s.trim().parse().ok().filter(|f: &f64| f.is_finite())?),
+
+            (ValueType::Bool, Node::Bool(b)) => Node::Bool(*b),
+            (ValueType::Bool, Node::Integer(0)) => Node::Bool(false),
+            (ValueType::Bool, Node::Integer(1)) => Node::Bool(true),
+            (ValueType::Bool, Node::String(s)) => Node::Bool(parse_bool(s)?),
+
+            _ => return None
+        };
+
+        Some(coerced)
+    }
+
+    /// The value as an integer, also taking strings like `"42"` and whole floats like `42.0`.
+    /// See `coerce_to` for everything that's accepted.
+    pub fn as_i64_lossy(&self) -> Option<i64> {
+        match self.coerce_to(ValueType::Integer)? {
+            Node::Integer(n) => Some(n),
+            _ => None
+        }
+    }
+
+    /// The value as a float, also taking integers and strings like `"4.2"`.
+    /// See `coerce_to` for everything that's accepted.
+    pub fn as_f64_lossy(&self) -> Option<f64> {
+        match self.coerce_to(ValueType::Float)? {
+            Node::Float(f) => Some(f),
+            _ => None
+        }
+    }
+
+    /// The value as a bool, also taking `1` and `0` and strings like `"true"`.
+    /// See `coerce_to` for everything that's accepted.
+    pub fn as_bool_lossy(&self) -> Option<bool> {
+        match self.coerce_to(ValueType::Bool)? {
+            Node::Bool(b) => Some(b),
+            _ => None
+        }
+    }
+
+    /// The value as a string, also taking numbers and bools, like `"42"` for `42`.
+    /// See `coerce_to` for everything that's accepted.
+    pub fn as_string_lossy(&self) -> Option<String> {
+        match self.coerce_to(ValueType::String)? {
+            Node::String(s) => Some(s),
+            _ => None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compare::Tolerance;
+    use crate::parser::parse_str;
+
+    fn coerce(text: &str, to: ValueType) -> Option<Node> {
+        parse_str(text).unwrap().coerce_to(to)
+    }
+
+    #[test]
+    fn coercions_follow_the_table() {
+        let cases = [
+            (r#"" 42 ""#, ValueType::Integer, Some("42")),
+            (r#""42.0""#, ValueType::Integer, Some("42")),
+            (r#""18446744073709551615""#, ValueType::Integer, Some("18446744073709551615")),
+            (r#""4.2""#, ValueType::Integer, None),
+            ("42.0", ValueType::Integer, Some("42")),
+            ("1e19", ValueType::Integer, None),
+            ("true", ValueType::Integer, Some("1")),
+
+            (r#""1e3""#, ValueType::Float, Some("1000.0")),
+            (r#""inf""#, ValueType::Float, None),
+            ("42", ValueType::Float, Some("42.0")),
+            ("false", ValueType::Float, None),
+
+            (r#""TRUE""#, ValueType::Bool, Some("true")),
+            (r#""0""#, ValueType::Bool, Some("false")),
+            (r#""yes""#, ValueType::Bool, None),
+            ("1", ValueType::Bool, Some("true")),
+            ("2", ValueType::Bool, None),
+            ("1.0", ValueType::Bool, None),
+
+            ("2.0", ValueType::String, Some(r#""2.0""#)),
+            ("1e300", ValueType::String, Some(r#""1e+300""#)),
+            ("-7", ValueType::String, Some(r#""-7""#)),
+            ("true", ValueType::String, Some(r#""true""#))
+        ];
+
+        for (text, to, expected) in cases {
+            match (coerce(text, to), expected) {
+                (Some(node), Some(expected)) => assert!(node.approx_eq(&parse_str(expected).unwrap(), &Tolerance::exact()), "{} to {}", text, to.name()),
+                (None, None) => {},
+                (node, _) => panic!("{} to {} gave {:?}", text, to.name(), node)
+            }
+        }
+
+        for text in ["null", "[]", "{}"] {
+            for to in [ValueType::String, ValueType::Integer, ValueType::Float, ValueType::Bool] {
+                assert!(coerce(text, to).is_none(), "{} to {}", text, to.name());
+            }
+        }
+    }
+
+    #[test]
+    fn lossy_accessors_use_coerce_to() {
+        let node = Node::String("12".to_string());
+
+        assert_eq!(node.as_i64_lossy(), Some(12));
+        assert_eq!(node.as_f64_lossy(), Some(12.0));
+        assert_eq!(node.as_bool_lossy(), None);
+        assert_eq!(Node::Integer(0).as_bool_lossy(), Some(false));
+        assert_eq!(Node::Float(0.5).as_string_lossy().as_deref(), Some("0.5"));
+        assert_eq!(Node::Unsigned(u64::MAX).as_i64_lossy(), None);
+    }
+
+    #[test]
+    fn type_names_round_trip() {
+        for to in [ValueType::String, ValueType::Integer, ValueType::Float, ValueType::Bool] {
+            assert_eq!(ValueType::from_name(to.name()), Some(to));
+        }
+
+        assert_eq!(ValueType::from_name("number"), None);
+    }
+}
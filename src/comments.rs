@@ -0,0 +1,187 @@
+use crate::error::Result;
+use crate::lexer::{Lexer, Span};
+use crate::lines::LineIndex;
+use crate::parser::{parse_with_options, Node, ParseOptions};
+use crate::token::{Token, TokenType};
+use crate::utils::{index_path, key_path};
+
+/// Whether a comment was written `// like this` or `/* like this */`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CommentKind {
+    Line,
+    Block
+}
+
+/// How a comment relates to the value it's attached to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Attachment {
+    /// The comment comes before the value, like on the line above a key.
+    Leading,
+
+    /// The comment comes after the value on the same line, like `"port": 80, // the default`.
+    Trailing,
+
+    /// The comment is inside an array or object with nothing after it to go with,
+    /// like at the end of an object or in an empty one.
+    Inner
+}
+
+/// A comment from a document, along with the value it's about.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Comment {
+    /// The text of the comment, without the `//` or `/*` and `*/` around it.
+    pub text: String,
+    pub kind: CommentKind,
+
+    /// Where the comment is in the text, markers and all.
+    pub span: Span,
+    pub line: usize,
+    pub column: usize,
+
+    /// The path of the value the comment is attached to, like `$.server.port`.
+    pub path: String,
+    pub attachment: Attachment
+}
+
+/// Where a value was in the text, and where its items or entries were.
+struct Slot {
+    path: String,
+
+    /// Where the entry starts, which is at its key for an object's entries.
+    start: usize,
+    value_start: usize,
+    end: usize,
+    children: Vec<Slot>
+}
+
+/// Find where the value starting at `tokens[*i]` is, leaving `i` just after it.
+///
+/// The tokens have already been parsed, so they're known to make up a whole value.
+/// `ends` has the offset just past each token.
+fn slot(tokens: &[Token], ends: &[usize], i: &mut usize, path: String, start: usize) -> Slot {
+    let value_start = tokens[*i].offset();
+    let is_object = tokens[*i].tok_type == TokenType::LBrace;
+    let mut children = Vec::new();
+
+    if !matches!(tokens[*i].tok_type, TokenType::LBrace | TokenType::LSqBrac) {
+        *i += 1;
+        return Slot { path, start, value_start, end: ends[*i - 1], children };
+    }
+
+    *i += 1;
+
+    while !matches!(tokens[*i].tok_type, TokenType::RBrace | TokenType::RSqBrac) {
+        if tokens[*i].tok_type == TokenType::Comma {
+            *i += 1;
+            continue;
+        }
+
+        let child_start = tokens[*i].offset();
+
+        let child_path = match is_object {
+            true => {
+                // Skip the key, quoted or not, and the colon after it.
+                let key = key_path(&path, tokens[*i].text());
+                *i += 2;
+                key
+            },
+            false => index_path(&path, children.len())
+        };
+
+        children.push(slot(tokens, ends, i, child_path, child_start));
+    }
+
+    *i += 1;
+
+    Slot { path, start, value_start, end: ends[*i - 1], children }
+}
+
+/// Work out which value inside the array or object `slot` a comment in it is about.
+fn attach(slot: &Slot, comment: Span, text: &str) -> (String, Attachment) {
+    let children = &slot.children;
+
+    // The first item or entry that doesn't end before the comment.
+    let i = children.partition_point(|child| child.end <= comment.start);
+
+    if let Some(child) = children.get(i) && child.start < comment.start {
+        return match comment.start < child.value_start {
+            // Between a key and its value, like `"a": /* here */ 1`.
+            true => (child.path.clone(), Attachment::Leading),
+            false => attach(child, comment, text)
+        };
+    }
+
+    let on_own_line = |from: usize, to: usize| text[from..to].contains('\n');
+
+    if let Some(before) = i.checked_sub(1).map(|i| &children[i])
+        && !on_own_line(before.end, comment.start)
+        && children.get(i).is_none_or(|after| on_own_line(comment.end, after.start))
+    {
+        return (before.path.clone(), Attachment::Trailing);
+    }
+
+    match children.get(i) {
+        Some(after) => (after.path.clone(), Attachment::Leading),
+        None => (slot.path.clone(), Attachment::Inner)
+    }
+}
+
+/// Parse `text` like `parse_with_options`, also giving back its comments,
+/// each attached to the value it's about, for tools that read notes from
+/// JSONC files, like documentation generators.
+///
+/// Comments are only allowed with `allow_comments`, like in the lenient and
+/// json5 profiles, so there are none to give back without it.
+///
+/// A comment is attached to the value after it, unless it's on the same line
+/// as the value before it and nothing comes after it on that line, in which case
+/// it's attached to that one. Comments with nothing after them in an array or
+/// object are attached to that array or object, and ones outside the document
+/// to the whole document, at `$`.
+pub fn parse_with_comments(text: &str, options: &ParseOptions) -> Result<(Node, Vec<Comment>)> {
+    let (node, end) = parse_with_options(text, options)?;
+
+    // Anything after the value is only read when it's meant to be part of the document.
+    let text = match options.allow_trailing_data {
+        true => &text[..end],
+        false => text
+    };
+
+    // The text parsed, so lexing it again can't fail.
+    let mut lexer = Lexer::with_options(text, options.lex).keep_comments();
+    let mut tokens = Vec::new();
+    let mut ends = Vec::new();
+
+    while let Some(token) = lexer.next_token()? {
+        tokens.push(token);
+        ends.push(lexer.offset());
+    }
+
+    let root = slot(&tokens, &ends, &mut 0, "$".to_string(), tokens[0].offset());
+    let lines = LineIndex::new(text);
+
+    let comments = lexer.take_comments().into_iter().map(|span| {
+        let (path, attachment) = if span.end <= root.start {
+            ("$".to_string(), Attachment::Leading)
+        }
+        else if span.start >= root.end {
+            ("$".to_string(), Attachment::Trailing)
+        }
+        else {
+            attach(&root, span, text)
+        };
+
+        let raw = &text[span.start..span.end];
+
+        let (text, kind) = match raw.strip_prefix("//") {
+            Some(rest) => (rest.strip_suffix('\r').unwrap_or(rest), CommentKind::Line),
+            None => (&raw[2..raw.len() - 2], CommentKind::Block)
+        };
+
+        let (line, column) = lines.offset_to_line_col(span.start);
+
+        Comment { text: text.to_string(), kind, span, line, column, path, attachment }
+    }).collect();
+
+    Ok((node, comments))
+}
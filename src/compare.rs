@@ -0,0 +1,348 @@
+use std::fmt::Display;
+
+use crate::glob::PathGlob;
+use crate::parser::Node;
+use crate::utils::{index_path, key_path};
+
+/// How loosely two trees are allowed to differ while still being considered equal.
+///
+/// Object keys are always compared regardless of order.
+pub struct Tolerance {
+    /// Floats are equal if they differ by at most this much,
+    /// scaled by their magnitude once it's larger than one.
+    pub float_epsilon: f64,
+
+    /// Arrays are equal if they hold the same elements in any order.
+    pub ignore_array_order: bool,
+
+    /// Integers and floats with the same value are equal, like `2` and `2.0`.
+    pub int_float_equal: bool
+}
+
+impl Tolerance {
+    /// No tolerance at all: values must match exactly, including their number types.
+    pub fn exact() -> Tolerance {
+        Tolerance {
+            float_epsilon: 0.0,
+            ignore_array_order: false,
+            int_float_equal: false
+        }
+    }
+}
+
+impl Default for Tolerance {
+    fn default() -> Tolerance {
+        Tolerance {
+            float_epsilon: 1e-9,
+            ignore_array_order: false,
+            int_float_equal: true
+        }
+    }
+}
+
+/// A place where two trees were found to differ.
+#[derive(Debug)]
+pub struct Mismatch {
+    /// Where the difference is, like `$.orders[17].price`.
+    pub path: String,
+    pub message: String
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "at {}: {}", self.path, self.message)
+    }
+}
+
+fn floats_eq(a: f64, b: f64, tolerance: &Tolerance) -> bool {
+    if a == b {
+        return true;
+    }
+
+    let scale = a.abs().max(b.abs()).max(1.0);
+
+    (a - b).abs() <= tolerance.float_epsilon * scale
+}
+
+/// Check a wildcard string like `"<ANY>"` against a value, if it is one.
+fn wildcard_matches(pattern: &str, value: &Node) -> Option<bool> {
+    let matches = match pattern {
+        "<ANY>"    => true,
+        "<STRING>" => matches!(value, Node::String(_)),
+        "<NUMBER>" => matches!(value, Node::Integer(_) | Node::Unsigned(_) | Node::Float(_)),
+        "<BOOL>"   => matches!(value, Node::Bool(_)),
+        "<ARRAY>"  => matches!(value, Node::Array(_)),
+        "<OBJECT>" => matches!(value, Node::Object(_)),
+
+        _ => return None
+    };
+
+    Some(matches)
+}
+
+/// Walks two trees side by side, collecting the places where they differ.
+struct Differ<'a> {
+    tolerance: &'a Tolerance,
+    wildcards: bool,
+    ignore: &'a [PathGlob],
+
+    /// Stop after finding this many differences.
+    limit: usize,
+    found: Vec<Mismatch>
+}
+
+impl<'a> Differ<'a> {
+    fn new(tolerance: &'a Tolerance, wildcards: bool, ignore: &'a [PathGlob], limit: usize) -> Differ<'a> {
+        Differ { tolerance, wildcards, ignore, limit, found: Vec::new() }
+    }
+
+    fn done(&self) -> bool {
+        self.found.len() >= self.limit
+    }
+
+    fn push(&mut self, path: String, message: String) {
+        if !self.done() {
+            self.found.push(Mismatch { path, message });
+        }
+    }
+
+    /// Check whether two values are equal, ignoring the same paths.
+    fn equal(&self, left: &Node, right: &Node, path: &str, segments: &mut Vec<String>) -> bool {
+        let mut differ = Differ::new(self.tolerance, self.wildcards, self.ignore, 1);
+        differ.walk(left, right, path, segments);
+
+        differ.found.is_empty()
+    }
+
+    /// `segments` holds the keys and indexes on the way to the values, for matching `ignore`.
+    fn walk(&mut self, left: &Node, right: &Node, path: &str, segments: &mut Vec<String>) {
+        if self.done() || self.ignored(segments) {
+            return;
+        }
+
+        let tolerance = self.tolerance;
+
+        if self.wildcards
+            && let Node::String(pattern) = left
+            && let Some(matches) = wildcard_matches(pattern, right)
+        {
+            if !matches {
+                self.push(path.to_string(), format!("expected {}, found {:?}", pattern, right));
+            }
+
+            return;
+        }
+
+        match (left, right) {
+            (Node::Integer(a), Node::Integer(b)) if a == b => {},
+            (Node::Unsigned(a), Node::Unsigned(b)) if a == b => {},
+            (Node::String(a), Node::String(b)) if a == b => {},
+            (Node::Bool(a), Node::Bool(b)) if a == b => {},
+            (Node::Null, Node::Null) | (Node::Empty, Node::Empty) => {},
+
+            (Node::Float(a), Node::Float(b)) if floats_eq(*a, *b, tolerance) => {},
+
+            (Node::Integer(a), Node::Float(b)) | (Node::Float(b), Node::Integer(a))
+                if tolerance.int_float_equal && floats_eq(*a as f64, *b, tolerance) => {},
+            (Node::Unsigned(a), Node::Float(b)) | (Node::Float(b), Node::Unsigned(a))
+                if tolerance.int_float_equal && floats_eq(*a as f64, *b, tolerance) => {},
+
+            (Node::Array(a), Node::Array(b)) => {
+                if a.len() != b.len() {
+                    self.push(path.to_string(), format!("expected an array of {} items, found {} items", a.len(), b.len()));
+                }
+
+                if !tolerance.ignore_array_order {
+                    for (i, (x, y)) in a.iter().zip(b).enumerate() {
+                        segments.push(i.to_string());
+                        self.walk(x, y, &index_path(path, i), segments);
+                        segments.pop();
+                    }
+
+                    return;
+                }
+
+                // Pair each item on the left with the first unused equal item on the right.
+                let mut used = vec![false; b.len()];
+
+                for (i, x) in a.iter().enumerate() {
+                    let item_path = index_path(path, i);
+                    segments.push(i.to_string());
+
+                    let found = (0..b.len()).find(
+                        |&j| !used[j] && self.equal(x, &b[j], &item_path, segments)
+                    );
+
+                    segments.pop();
+
+                    match found {
+                        Some(j) => used[j] = true,
+                        None => self.push(item_path, format!("no matching item for {:?} in the other array", x))
+                    }
+                }
+
+                for (j, y) in b.iter().enumerate().filter(|(j, _)| !used[*j]) {
+                    segments.push(j.to_string());
+
+                    if !self.ignored(segments) {
+                        self.push(index_path(path, j), format!("item {:?} was not expected", y));
+                    }
+
+                    segments.pop();
+                }
+            },
+
+            (Node::Object(a), Node::Object(b)) => {
+                // Sort the keys so the reported mismatches don't depend on hashing order.
+                let mut keys: Vec<&String> = a.keys().chain(b.keys()).collect();
+                keys.sort();
+                keys.dedup();
+
+                for key in keys {
+                    segments.push(key.clone());
+
+                    match (a.get(key), b.get(key)) {
+                        (Some(x), Some(y)) => self.walk(x, y, &key_path(path, key), segments),
+                        (Some(_), None) if !self.ignored(segments) => {
+                            self.push(key_path(path, key), "key is missing from the found value".to_string())
+                        },
+                        (None, Some(_)) if !self.ignored(segments) => {
+                            self.push(key_path(path, key), "key was not expected".to_string())
+                        },
+                        _ => {}
+                    }
+
+                    segments.pop();
+                }
+            },
+
+            _ => self.push(path.to_string(), format!("expected {:?}, found {:?}", left, right))
+        }
+    }
+
+    fn ignored(&self, segments: &[String]) -> bool {
+        self.ignore.iter().any(|glob| glob.matches(segments))
+    }
+}
+
+fn find_mismatch(left: &Node, right: &Node, tolerance: &Tolerance, wildcards: bool) -> Option<Mismatch> {
+    let mut differ = Differ::new(tolerance, wildcards, &[], 1);
+    differ.walk(left, right, "$", &mut Vec::new());
+
+    differ.found.pop()
+}
+
+impl Node {
+    /// Check whether two trees are equal within the given tolerance.
+    pub fn approx_eq(&self, other: &Node, tolerance: &Tolerance) -> bool {
+        self.first_mismatch(other, tolerance).is_none()
+    }
+
+    /// Find the first place where two trees differ beyond the given tolerance.
+    pub fn first_mismatch(&self, other: &Node, tolerance: &Tolerance) -> Option<Mismatch> {
+        find_mismatch(self, other, tolerance, false)
+    }
+
+    /// Treat this tree as a pattern and find the first place where `value` doesn't match it.
+    ///
+    /// Strings in the pattern like `"<ANY>"`, `"<STRING>"`, `"<NUMBER>"`, `"<BOOL>"`,
+    /// `"<ARRAY>"` and `"<OBJECT>"` are wildcards that match any value of that kind.
+    pub fn first_pattern_mismatch(&self, value: &Node, tolerance: &Tolerance) -> Option<Mismatch> {
+        find_mismatch(self, value, tolerance, true)
+    }
+
+    /// Find every place where two trees differ beyond the given tolerance,
+    /// skipping any value whose path matches one of the `ignore` globs.
+    ///
+    /// Differences are listed in a stable order: object keys are visited sorted,
+    /// and array items by index.
+    pub fn differences(&self, other: &Node, tolerance: &Tolerance, ignore: &[PathGlob]) -> Vec<Mismatch> {
+        let mut differ = Differ::new(tolerance, false, ignore, usize::MAX);
+        differ.walk(self, other, "$", &mut Vec::new());
+
+        differ.found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_str;
+
+    fn doc(text: &str) -> Node {
+        parse_str(text).unwrap()
+    }
+
+    #[test]
+    fn floats_are_equal_within_the_epsilon() {
+        let tolerance = Tolerance::default();
+
+        assert!(doc("1.0").approx_eq(&doc("1.0000000000001"), &tolerance));
+        assert!(!doc("1.0").approx_eq(&doc("1.001"), &tolerance));
+        assert!(!doc("1.0").approx_eq(&doc("1.0000000000001"), &Tolerance::exact()));
+
+        // The epsilon scales with the size of the numbers once they're past one.
+        assert!(doc("1e12").approx_eq(&doc("1000000000000.5"), &tolerance));
+    }
+
+    #[test]
+    fn int_float_equal_decides_on_mixed_numbers() {
+        assert!(doc("[2, 2.0]").approx_eq(&doc("[2.0, 2]"), &Tolerance::default()));
+        assert!(!doc("2").approx_eq(&doc("2.0"), &Tolerance::exact()));
+        assert!(!doc("2").approx_eq(&doc("2.5"), &Tolerance::default()));
+    }
+
+    #[test]
+    fn ignore_array_order_pairs_up_items() {
+        let tolerance = Tolerance { ignore_array_order: true, ..Tolerance::exact() };
+
+        assert!(doc("[1, 2, 2]").approx_eq(&doc("[2, 1, 2]"), &tolerance));
+        assert!(!doc("[1, 2, 2]").approx_eq(&doc("[2, 1, 2]"), &Tolerance::exact()));
+
+        let mismatch = doc("[1, 2]").first_mismatch(&doc("[2, 2]"), &tolerance).unwrap();
+        assert_eq!(mismatch.path, "$[0]");
+    }
+
+    #[test]
+    fn mismatches_know_their_path() {
+        let expected = doc(r#"{"orders": [{"price": 1}, {"price": 2}], "a b": true}"#);
+        let tolerance = Tolerance::exact();
+
+        let mismatch = expected.first_mismatch(&doc(r#"{"orders": [{"price": 1}, {"price": 3}], "a b": true}"#), &tolerance).unwrap();
+        assert_eq!(mismatch.path, "$.orders[1].price");
+
+        let mismatch = expected.first_mismatch(&doc(r#"{"orders": [{"price": 1}, {"price": 2}]}"#), &tolerance).unwrap();
+        assert_eq!(mismatch.to_string(), r#"at $["a b"]: key is missing from the found value"#);
+
+        let mismatch = expected.first_mismatch(&doc(r#"{"orders": [], "a b": true}"#), &tolerance).unwrap();
+        assert_eq!(mismatch.message, "expected an array of 2 items, found 0 items");
+    }
+
+    #[test]
+    fn patterns_match_wildcards() {
+        let pattern = doc(r#"{"id": "<NUMBER>", "name": "<STRING>", "tags": "<ANY>"}"#);
+        let tolerance = Tolerance::default();
+
+        assert!(pattern.first_pattern_mismatch(&doc(r#"{"id": 5, "name": "x", "tags": null}"#), &tolerance).is_none());
+
+        let mismatch = pattern.first_pattern_mismatch(&doc(r#"{"id": "5", "name": "x", "tags": []}"#), &tolerance).unwrap();
+        assert_eq!(mismatch.path, "$.id");
+
+        // Only patterns treat these strings as wildcards.
+        assert!(doc(r#""<ANY>""#).first_mismatch(&doc("1"), &tolerance).is_some());
+    }
+
+    #[test]
+    fn differences_lists_every_mismatch_in_order() {
+        let left = doc(r#"{"b": [1, 2], "a": 1, "updated_at": 5, "nested": {"updated_at": 6}}"#);
+        let right = doc(r#"{"b": [1, 3], "a": 2, "updated_at": 7, "nested": {"updated_at": 8}, "c": null}"#);
+        let tolerance = Tolerance::exact();
+
+        let paths: Vec<String> = left.differences(&right, &tolerance, &[]).into_iter().map(|m| m.path).collect();
+        assert_eq!(paths, ["$.a", "$.b[1]", "$.c", "$.nested.updated_at", "$.updated_at"]);
+
+        let ignore = [PathGlob::new("**.updated_at"), PathGlob::new("c")];
+        let paths: Vec<String> = left.differences(&right, &tolerance, &ignore).into_iter().map(|m| m.path).collect();
+        assert_eq!(paths, ["$.a", "$.b[1]"]);
+    }
+}
@@ -0,0 +1,196 @@
+use std::fmt::Display;
+use std::path::Path;
+
+use crate::include::{load_with_includes, IncludeError};
+use crate::lexer::tokenise;
+use crate::parser::{parse, Node, ObjectMap};
+
+/// Why a layer of configuration couldn't be added.
+#[derive(Debug)]
+pub enum ConfigError {
+    File(IncludeError),
+
+    /// A CLI override that isn't in the form `key.path=value`.
+    InvalidOverride(String)
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::File(e) => write!(f, "{}", e),
+            Self::InvalidOverride(s) => write!(f, "Invalid override {:?}, expected 'key.path=value'", s)
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::File(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+/// Read a value from the environment or the command line as JSON if we can,
+/// so `8080` becomes a number, and treat it as a plain string otherwise.
+fn parse_value(text: &str) -> Node {
+    match tokenise(text).and_then(|tokens| parse(&tokens)) {
+        Ok(Node::Empty) | Err(_) => Node::String(text.to_string()),
+        Ok(node) => node
+    }
+}
+
+/// Build a node that holds `value` under the nested keys of `path`.
+fn nest(path: &[&str], value: Node) -> Node {
+    path.iter().rev().fold(value, |inner, key| {
+        Node::Object(ObjectMap::from_iter([(key.to_string(), inner)]))
+    })
+}
+
+/// Merges layers of configuration into a single `Node`, where each layer
+/// is deep merged on top of the ones added before it.
+pub struct ConfigBuilder {
+    root: Node
+}
+
+impl ConfigBuilder {
+    pub fn new() -> ConfigBuilder {
+        ConfigBuilder {
+            root: Node::Object(ObjectMap::default())
+        }
+    }
+
+    /// Add a document that has already been parsed.
+    pub fn add_node(mut self, node: Node) -> ConfigBuilder {
+        self.root.deep_merge(node);
+        self
+    }
+
+    /// Add a JSON file, following any `$include`s inside it.
+    pub fn add_file(self, path: impl AsRef<Path>) -> Result<ConfigBuilder, ConfigError> {
+        match load_with_includes(path) {
+            Ok(node) => Ok(self.add_node(node)),
+            Err(e) => Err(ConfigError::File(e))
+        }
+    }
+
+    /// Add every environment variable that starts with `prefix`.
+    ///
+    /// The rest of the name is lowercased and split on `__` into nested keys,
+    /// so `APP_SERVER__PORT=8080` with the prefix `APP_` sets `server.port`.
+    /// Variables whose name or value isn't valid Unicode are skipped.
+    pub fn add_env(self, prefix: &str) -> ConfigBuilder {
+        let vars = std::env::vars_os().filter_map(|(name, value)| Some((name.into_string().ok()?, value.into_string().ok()?)));
+
+        self.add_env_from(prefix, vars)
+    }
+
+    /// Like `add_env`, but reads variables from the given pairs instead of the environment.
+    pub fn add_env_from(mut self, prefix: &str, vars: impl IntoIterator<Item = (String, String)>) -> ConfigBuilder {
+        // Sort the variables so overlapping names always merge in the same order.
+        let mut matching: Vec<(String, String)> = vars.into_iter().filter_map(
+            |(name, value)| Some((name.strip_prefix(prefix)?.to_lowercase(), value))
+        ).collect();
+
+        matching.sort();
+
+        for (name, value) in matching {
+            if name.is_empty() {
+                continue;
+            }
+
+            self.root.deep_merge(nest(&name.split("__").collect::<Vec<_>>(), parse_value(&value)));
+        }
+
+        self
+    }
+
+    /// Add an override from the command line, like `server.port=8080`.
+    pub fn add_override(mut self, arg: &str) -> Result<ConfigBuilder, ConfigError> {
+        let (path, value) = match arg.split_once('=') {
+            Some(x) => x,
+            None => return Err(ConfigError::InvalidOverride(arg.to_string()))
+        };
+
+        if path.is_empty() || path.split('.').any(|key| key.is_empty()) {
+            return Err(ConfigError::InvalidOverride(arg.to_string()));
+        }
+
+        self.root.deep_merge(nest(&path.split('.').collect::<Vec<_>>(), parse_value(value)));
+
+        Ok(self)
+    }
+
+    /// Add several overrides from the command line, in order.
+    pub fn add_overrides<'a>(mut self, args: impl IntoIterator<Item = &'a str>) -> Result<ConfigBuilder, ConfigError> {
+        for arg in args {
+            self = self.add_override(arg)?;
+        }
+
+        Ok(self)
+    }
+
+    pub fn build(self) -> Node {
+        self.root
+    }
+}
+
+impl Default for ConfigBuilder {
+    fn default() -> ConfigBuilder {
+        ConfigBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compare::Tolerance;
+    use crate::parser::parse_str;
+
+    fn doc(text: &str) -> Node {
+        parse_str(text).unwrap()
+    }
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn layers_are_merged_in_order() {
+        let config = ConfigBuilder::new()
+            .add_node(doc(r#"{"server": {"host": "localhost", "port": 80}, "debug": false}"#))
+            .add_env_from("APP_", vars(&[
+                ("APP_SERVER__PORT", "8080"),
+                ("APP_SERVER__NAME", "web 1"),
+                ("APP_DEBUG", "true"),
+                ("APP_", "ignored"),
+                ("OTHER_DEBUG", "ignored")
+            ]))
+            .add_overrides(["server.port=9000", "server.tags=[\"a\"]"])
+            .unwrap()
+            .build();
+
+        let expected = doc(r#"{"server": {"host": "localhost", "port": 9000, "name": "web 1", "tags": ["a"]}, "debug": true}"#);
+        assert!(config.approx_eq(&expected, &Tolerance::exact()));
+    }
+
+    #[test]
+    fn overlapping_env_vars_merge_the_same_way_every_time() {
+        let config = ConfigBuilder::new()
+            .add_env_from("APP_", vars(&[("APP_DB__USER", "ada"), ("APP_DB", "{\"port\": 1}")]))
+            .build();
+
+        assert!(config.approx_eq(&doc(r#"{"db": {"port": 1, "user": "ada"}}"#), &Tolerance::exact()));
+    }
+
+    #[test]
+    fn bad_layers_are_errors() {
+        for arg in ["a", "=1", "a..b=1", ".a=1"] {
+            assert!(matches!(ConfigBuilder::new().add_override(arg), Err(ConfigError::InvalidOverride(_))), "{}", arg);
+        }
+
+        let missing = std::env::temp_dir().join("json-rs-config-missing.json");
+        assert!(matches!(ConfigBuilder::new().add_file(missing), Err(ConfigError::File(IncludeError::Io(..)))));
+    }
+}
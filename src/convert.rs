@@ -0,0 +1,45 @@
+use std::fmt::Display;
+
+pub mod protobuf;
+
+mod cbor;
+mod csv;
+mod msgpack;
+mod query;
+mod toml;
+#[cfg(feature = "serde_json")]
+mod value;
+mod xml;
+#[cfg(feature = "yaml")]
+mod yaml;
+
+pub use cbor::{to_cbor, CborArrayWriter};
+pub use csv::{to_csv, CsvWriter};
+pub use msgpack::to_msgpack;
+pub use query::{from_query_string, to_query_string};
+pub use toml::to_toml;
+pub use xml::{from_xml, to_xml, XmlOptions};
+#[cfg(feature = "yaml")]
+pub use yaml::to_yaml;
+
+/// A value that can't be represented in the format being converted to or from.
+#[derive(Debug)]
+pub struct ConvertError {
+    pub message: String
+}
+
+impl ConvertError {
+    pub fn new(message: impl Into<String>) -> ConvertError {
+        ConvertError {
+            message: message.into()
+        }
+    }
+}
+
+impl Display for ConvertError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for ConvertError {}
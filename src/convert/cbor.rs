@@ -0,0 +1,134 @@
+use std::io::{self, Write};
+
+use crate::parser::Node;
+
+/// Write the first bytes of a data item: its major type and a number in the smallest form.
+fn write_head(major: u8, n: u64, out: &mut Vec<u8>) {
+    let major = major << 5;
+
+    match n {
+        0..=23 => out.push(major | n as u8),
+        24..=0xff => out.extend_from_slice(&[major | 24, n as u8]),
+        0x100..=0xffff => {
+            out.push(major | 25);
+            out.extend_from_slice(&(n as u16).to_be_bytes());
+        },
+        0x1_0000..=0xffff_ffff => {
+            out.push(major | 26);
+            out.extend_from_slice(&(n as u32).to_be_bytes());
+        },
+        _ => {
+            out.push(major | 27);
+            out.extend_from_slice(&n.to_be_bytes());
+        }
+    }
+}
+
+fn write_node(node: &Node, out: &mut Vec<u8>) {
+    match node {
+        Node::Integer(n) if *n >= 0 => write_head(0, *n as u64, out),
+
+        // Negative integers are stored as `-1 - n`, which is the same as flipping every bit.
+        Node::Integer(n) => write_head(1, !*n as u64, out),
+        Node::Unsigned(n) => write_head(0, *n, out),
+
+        Node::Float(f) => {
+            out.push(0xfb);
+            out.extend_from_slice(&f.to_be_bytes());
+        },
+        Node::String(s) => {
+            write_head(3, s.len() as u64, out);
+            out.extend_from_slice(s.as_bytes());
+        },
+        Node::Array(arr) => {
+            write_head(4, arr.len() as u64, out);
+            arr.iter().for_each(|item| write_node(item, out));
+        },
+        Node::Object(map) => {
+            write_head(5, map.len() as u64, out);
+
+            // Sort the keys so the same document always gives the same bytes.
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+
+            for key in keys {
+                write_head(3, key.len() as u64, out);
+                out.extend_from_slice(key.as_bytes());
+                write_node(&map[key], out);
+            }
+        },
+        Node::Bool(false) => out.push(0xf4),
+        Node::Bool(true) => out.push(0xf5),
+
+        // An empty document has no value of its own, so it's written as null.
+        Node::Null | Node::Empty => out.push(0xf6)
+    }
+}
+
+/// Convert a document to CBOR (RFC 8949), with integers and lengths in their
+/// smallest forms and floats always as 64-bit.
+pub fn to_cbor(node: &Node) -> Vec<u8> {
+    let mut out = Vec::new();
+    write_node(node, &mut out);
+
+    out
+}
+
+/// Writes a CBOR array one item at a time, without knowing how many there are up front,
+/// for converting arrays too big to hold in memory, like from an `ArrayItemReader`.
+///
+/// The array is written with an indefinite length, so it has to be ended with `finish`.
+pub struct CborArrayWriter<W: Write> {
+    writer: W
+}
+
+impl<W: Write> CborArrayWriter<W> {
+    /// Start the array.
+    pub fn new(mut writer: W) -> io::Result<CborArrayWriter<W>> {
+        writer.write_all(&[0x9f])?;
+
+        Ok(CborArrayWriter { writer })
+    }
+
+    pub fn write(&mut self, item: &Node) -> io::Result<()> {
+        self.writer.write_all(&to_cbor(item))
+    }
+
+    /// End the array, giving back the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.write_all(&[0xff])?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_str;
+
+    fn doc(text: &str) -> Node {
+        parse_str(text).unwrap()
+    }
+
+    #[test]
+    fn values_are_written_in_their_smallest_forms() {
+        assert_eq!(to_cbor(&doc(r#"{"a": [1, -1, 24, 256, true, false, null]}"#)), [
+            0xa1, 0x61, b'a', 0x87, 0x01, 0x20, 0x18, 0x18, 0x19, 0x01, 0x00, 0xf5, 0xf4, 0xf6
+        ]);
+
+        assert_eq!(to_cbor(&doc("1.5")), [0xfb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(to_cbor(&doc("-4294967297")), [0x3b, 0, 0, 0, 1, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn array_writer_writes_an_indefinite_array() {
+        let mut writer = CborArrayWriter::new(Vec::new()).unwrap();
+
+        writer.write(&doc("1")).unwrap();
+        writer.write(&doc(r#""a""#)).unwrap();
+
+        assert_eq!(writer.finish().unwrap(), [0x9f, 0x01, 0x61, b'a', 0xff]);
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::BTreeSet;
+use std::io::Write;
+
+use crate::convert::ConvertError;
+use crate::parser::Node;
+use crate::serializer::to_string;
+
+/// Quote a field if it has anything in it that would be read as CSV syntax, doubling any quotes.
+fn escape_field(text: &str) -> String {
+    match text.contains([',', '"', '\r', '\n']) || text.trim() != text {
+        true => format!("\"{}\"", text.replace('"', "\"\"")),
+        false => text.to_string()
+    }
+}
+
+/// The text of a value in a cell, with arrays and objects written as compact JSON.
+fn cell(node: &Node) -> String {
+    match node {
+        Node::String(s) => s.clone(),
+        Node::Null | Node::Empty => String::new(),
+        other => to_string(other)
+    }
+}
+
+fn write_line<W: Write>(writer: &mut W, fields: impl Iterator<Item = String>) -> Result<(), ConvertError> {
+    let line = fields.map(|field| escape_field(&field)).collect::<Vec<_>>().join(",");
+
+    writeln!(writer, "{}", line).map_err(|e| ConvertError::new(e.to_string()))
+}
+
+/// Writes objects as the rows of a CSV file one at a time, with a header
+/// line of their keys, for converting arrays too big to hold in memory.
+///
+/// Values that are `null` or missing are written as empty cells, and arrays
+/// and objects are written as compact JSON.
+pub struct CsvWriter<W: Write> {
+    writer: W,
+    columns: Option<Vec<String>>,
+    started: bool
+}
+
+impl<W: Write> CsvWriter<W> {
+    /// Start a CSV file whose columns are the sorted keys of the first row.
+    /// Since the header is written first, later rows can't have any other keys.
+    pub fn new(writer: W) -> CsvWriter<W> {
+        CsvWriter {
+            writer,
+            columns: None,
+            started: false
+        }
+    }
+
+    /// Start a CSV file with the columns given, in that order.
+    pub fn with_columns(writer: W, columns: Vec<String>) -> CsvWriter<W> {
+        CsvWriter {
+            writer,
+            columns: Some(columns),
+            started: false
+        }
+    }
+
+    fn write_header(&mut self) -> Result<(), ConvertError> {
+        self.started = true;
+
+        match &self.columns {
+            Some(columns) if !columns.is_empty() => write_line(&mut self.writer, columns.iter().cloned()),
+            _ => Ok(())
+        }
+    }
+
+    pub fn write_row(&mut self, row: &Node) -> Result<(), ConvertError> {
+        let Node::Object(map) = row else {
+            return Err(ConvertError::new(format!("Only objects can be written as CSV rows, not {}", to_string(row))));
+        };
+
+        if self.columns.is_none() {
+            let mut keys: Vec<String> = map.keys().cloned().collect();
+            keys.sort();
+
+            self.columns = Some(keys);
+        }
+
+        if !self.started {
+            self.write_header()?;
+        }
+
+        let columns = self.columns.as_ref().unwrap();
+
+        if let Some(key) = map.keys().find(|key| !columns.contains(key)) {
+            return Err(ConvertError::new(format!("Key {:?} isn't one of the columns, which come from the first row", key)));
+        }
+
+        write_line(&mut self.writer, columns.iter().map(|column| map.get(column).map(cell).unwrap_or_default()))
+    }
+
+    /// Give back the writer, after writing the header if no rows were written.
+    pub fn finish(mut self) -> Result<W, ConvertError> {
+        if !self.started {
+            self.write_header()?;
+        }
+
+        self.writer.flush().map_err(|e| ConvertError::new(e.to_string()))?;
+
+        Ok(self.writer)
+    }
+}
+
+/// Convert an array of objects to CSV, with a column for every key of every row,
+/// in sorted order. See `CsvWriter` for how values are written.
+pub fn to_csv(rows: &Node) -> Result<String, ConvertError> {
+    let Node::Array(rows) = rows else {
+        return Err(ConvertError::new("Only an array of objects can be written as CSV"));
+    };
+
+    let columns: BTreeSet<&String> = rows.iter().flat_map(|row| match row {
+        Node::Object(map) => map.keys().collect(),
+        _ => Vec::new()
+    }).collect();
+
+    let mut writer = CsvWriter::with_columns(Vec::new(), columns.into_iter().cloned().collect());
+    rows.iter().try_for_each(|row| writer.write_row(row))?;
+
+    Ok(String::from_utf8(writer.finish()?).unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_str;
+
+    fn doc(text: &str) -> Node {
+        parse_str(text).unwrap()
+    }
+
+    #[test]
+    fn to_csv_has_a_column_for_every_key() {
+        let csv = to_csv(&doc(r#"[{"b": 1, "a": "x,y"}, {"c": [1, 2], "a": null}, {"a": "say \"hi\"", "b": " pad"}]"#)).unwrap();
+
+        assert_eq!(csv, "a,b,c\n\"x,y\",1,\n,,\"[1,2]\"\n\"say \"\"hi\"\"\",\" pad\",\n");
+        assert!(to_csv(&doc(r#"{"a": 1}"#)).is_err());
+    }
+
+    #[test]
+    fn csv_writer_takes_its_columns_from_the_first_row() {
+        let mut writer = CsvWriter::new(Vec::new());
+
+        writer.write_row(&doc(r#"{"b": true, "a": 1}"#)).unwrap();
+        writer.write_row(&doc(r#"{"a": 2}"#)).unwrap();
+        assert!(writer.write_row(&doc(r#"{"c": 3}"#)).is_err());
+        assert!(writer.write_row(&doc("[1]")).is_err());
+
+        assert_eq!(String::from_utf8(writer.finish().unwrap()).unwrap(), "a,b\n1,true\n2,\n");
+    }
+
+    #[test]
+    fn csv_writer_writes_the_header_without_rows() {
+        let writer = CsvWriter::with_columns(Vec::new(), vec!["id".to_string(), "name".to_string()]);
+
+        assert_eq!(String::from_utf8(writer.finish().unwrap()).unwrap(), "id,name\n");
+    }
+}
@@ -0,0 +1,129 @@
+use crate::convert::ConvertError;
+use crate::parser::Node;
+
+/// Write a length in the smallest form, using `fix` when it fits in `fix_bits`, or else
+/// the marker for an 8-bit length (if the type has one), a 16-bit or a 32-bit length.
+fn write_len(len: usize, fix: u8, fix_bits: u32, (one, two, four): (Option<u8>, u8, u8), out: &mut Vec<u8>) -> Result<(), ConvertError> {
+    match (len, one) {
+        _ if len < 1 << fix_bits => out.push(fix | len as u8),
+        (0..=0xff, Some(marker)) => out.extend_from_slice(&[marker, len as u8]),
+        (0..=0xffff, _) => {
+            out.push(two);
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+        },
+        _ if len <= u32::MAX as usize => {
+            out.push(four);
+            out.extend_from_slice(&(len as u32).to_be_bytes());
+        },
+        _ => return Err(ConvertError::new(format!("A length of {} is too long to write in MessagePack", len)))
+    }
+
+    Ok(())
+}
+
+fn write_integer(n: i64, out: &mut Vec<u8>) {
+    match n {
+        0..=0x7f => out.push(n as u8),
+        -0x20..=-1 => out.push(n as i8 as u8),
+        0x80..=0xff => out.extend_from_slice(&[0xcc, n as u8]),
+        0x100..=0xffff => {
+            out.push(0xcd);
+            out.extend_from_slice(&(n as u16).to_be_bytes());
+        },
+        0x1_0000..=0xffff_ffff => {
+            out.push(0xce);
+            out.extend_from_slice(&(n as u32).to_be_bytes());
+        },
+        -0x80..=-0x21 => out.extend_from_slice(&[0xd0, n as i8 as u8]),
+        -0x8000..=-0x81 => {
+            out.push(0xd1);
+            out.extend_from_slice(&(n as i16).to_be_bytes());
+        },
+        -0x8000_0000..=-0x8001 => {
+            out.push(0xd2);
+            out.extend_from_slice(&(n as i32).to_be_bytes());
+        },
+        _ => {
+            out.push(0xd3);
+            out.extend_from_slice(&n.to_be_bytes());
+        }
+    }
+}
+
+fn write_node(node: &Node, out: &mut Vec<u8>) -> Result<(), ConvertError> {
+    match node {
+        // An empty document has no value of its own, so it's written as nil.
+        Node::Null | Node::Empty => out.push(0xc0),
+        Node::Bool(b) => out.push(if *b { 0xc3 } else { 0xc2 }),
+        Node::Integer(n) => write_integer(*n, out),
+        Node::Unsigned(n) => {
+            out.push(0xcf);
+            out.extend_from_slice(&n.to_be_bytes());
+        },
+        Node::Float(f) => {
+            out.push(0xcb);
+            out.extend_from_slice(&f.to_be_bytes());
+        },
+        Node::String(s) => {
+            write_len(s.len(), 0xa0, 5, (Some(0xd9), 0xda, 0xdb), out)?;
+            out.extend_from_slice(s.as_bytes());
+        },
+        Node::Array(arr) => {
+            write_len(arr.len(), 0x90, 4, (None, 0xdc, 0xdd), out)?;
+            arr.iter().try_for_each(|item| write_node(item, out))?;
+        },
+        Node::Object(map) => {
+            write_len(map.len(), 0x80, 4, (None, 0xde, 0xdf), out)?;
+
+            // Sort the keys so the same document always gives the same bytes.
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+
+            for key in keys {
+                write_node(&Node::String(key.clone()), out)?;
+                write_node(&map[key], out)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Convert a document to MessagePack, with integers and lengths in their smallest forms
+/// and floats always as 64-bit. MessagePack needs the length of an array up front,
+/// so the whole document has to be in memory.
+pub fn to_msgpack(node: &Node) -> Result<Vec<u8>, ConvertError> {
+    let mut out = Vec::new();
+    write_node(node, &mut out)?;
+
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_str;
+
+    fn doc(text: &str) -> Node {
+        parse_str(text).unwrap()
+    }
+
+    #[test]
+    fn values_are_written_in_their_smallest_forms() {
+        assert_eq!(to_msgpack(&doc(r#"{"a": [1, -1, -33, 200, 300, null, true]}"#)).unwrap(), [
+            0x81, 0xa1, b'a', 0x97, 0x01, 0xff, 0xd0, 0xdf, 0xcc, 0xc8, 0xcd, 0x01, 0x2c, 0xc0, 0xc3
+        ]);
+
+        assert_eq!(to_msgpack(&doc("1.5")).unwrap(), [0xcb, 0x3f, 0xf8, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(to_msgpack(&doc("18446744073709551615")).unwrap(), [0xcf, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn lengths_grow_past_their_fixed_forms() {
+        let string = to_msgpack(&Node::String("x".repeat(32))).unwrap();
+        assert_eq!(string[..2], [0xd9, 0x20]);
+
+        let array = to_msgpack(&Node::Array(vec![Node::Null; 16])).unwrap();
+        assert_eq!(array[..3], [0xdc, 0x00, 0x10]);
+    }
+}
@@ -0,0 +1,463 @@
+use crate::convert::ConvertError;
+use crate::parser::{Node, ObjectMap};
+use crate::utils::{base64_decode, base64_encode};
+
+const MIN_TIMESTAMP_SECONDS: i64 = -62_135_596_800; // 0001-01-01T00:00:00Z
+const MAX_TIMESTAMP_SECONDS: i64 = 253_402_300_799; // 9999-12-31T23:59:59Z
+const MAX_DURATION_SECONDS: i64 = 315_576_000_000;
+
+/// Types from `google.protobuf` whose JSON isn't an object of their fields,
+/// so they need to be wrapped in a `value` key inside an `Any`.
+const SPECIAL_TYPES: [&str; 16] = [
+    "google.protobuf.Timestamp",
+    "google.protobuf.Duration",
+    "google.protobuf.FieldMask",
+    "google.protobuf.Struct",
+    "google.protobuf.Value",
+    "google.protobuf.ListValue",
+    "google.protobuf.Empty",
+    "google.protobuf.DoubleValue",
+    "google.protobuf.FloatValue",
+    "google.protobuf.Int64Value",
+    "google.protobuf.UInt64Value",
+    "google.protobuf.Int32Value",
+    "google.protobuf.UInt32Value",
+    "google.protobuf.BoolValue",
+    "google.protobuf.StringValue",
+    "google.protobuf.BytesValue"
+];
+
+/// 64-bit integers are written as strings, since JavaScript can't hold them exactly.
+pub fn int64_to_node(n: i64) -> Node {
+    Node::String(n.to_string())
+}
+
+pub fn uint64_to_node(n: u64) -> Node {
+    Node::String(n.to_string())
+}
+
+/// Read a 64-bit integer, which can be written as a number or a string.
+pub fn node_to_int64(node: &Node) -> Result<i64, ConvertError> {
+    match node {
+        Node::Integer(n) => Ok(*n),
+        Node::Float(f) if f.fract() == 0.0 && f.abs() < 9.2e18 => Ok(*f as i64),
+        Node::String(s) => match s.parse() {
+            Ok(x) => Ok(x),
+            Err(_) => Err(ConvertError::new(format!("{:?} is not a valid int64", s)))
+        },
+        _ => Err(ConvertError::new(format!("Expected an int64, found {:?}", node)))
+    }
+}
+
+pub fn node_to_uint64(node: &Node) -> Result<u64, ConvertError> {
+    match node {
+        Node::Integer(n) if *n >= 0 => Ok(*n as u64),
+        Node::Unsigned(n) => Ok(*n),
+        Node::Float(f) if f.fract() == 0.0 && *f >= 0.0 && *f < 1.8e19 => Ok(*f as u64),
+        Node::String(s) => match s.parse() {
+            Ok(x) => Ok(x),
+            Err(_) => Err(ConvertError::new(format!("{:?} is not a valid uint64", s)))
+        },
+        _ => Err(ConvertError::new(format!("Expected a uint64, found {:?}", node)))
+    }
+}
+
+/// Doubles are numbers, apart from `"NaN"`, `"Infinity"` and `"-Infinity"`.
+pub fn double_to_node(f: f64) -> Node {
+    if f.is_nan() {
+        Node::String("NaN".to_string())
+    }
+    else if f.is_infinite() {
+        Node::String(if f > 0.0 { "Infinity" } else { "-Infinity" }.to_string())
+    }
+    else {
+        Node::Float(f)
+    }
+}
+
+pub fn node_to_double(node: &Node) -> Result<f64, ConvertError> {
+    match node {
+        Node::Float(f) => Ok(*f),
+        Node::Integer(n) => Ok(*n as f64),
+        Node::Unsigned(n) => Ok(*n as f64),
+        Node::String(s) => match s.as_str() {
+            "NaN" => Ok(f64::NAN),
+            "Infinity" => Ok(f64::INFINITY),
+            "-Infinity" => Ok(f64::NEG_INFINITY),
+            _ => match s.parse() {
+                Ok(x) => Ok(x),
+                Err(_) => Err(ConvertError::new(format!("{:?} is not a valid double", s)))
+            }
+        },
+        _ => Err(ConvertError::new(format!("Expected a double, found {:?}", node)))
+    }
+}
+
+/// Bytes are written as standard base64.
+pub fn bytes_to_node(bytes: &[u8]) -> Node {
+    Node::String(base64_encode(bytes, false))
+}
+
+/// Read bytes written as base64, in either the standard or URL-safe alphabet.
+pub fn node_to_bytes(node: &Node) -> Result<Vec<u8>, ConvertError> {
+    match node {
+        Node::String(s) => match base64_decode(s) {
+            Some(x) => Ok(x),
+            None => Err(ConvertError::new(format!("{:?} is not valid base64", s)))
+        },
+        _ => Err(ConvertError::new(format!("Expected base64 bytes, found {:?}", node)))
+    }
+}
+
+/// Write nanoseconds as 3, 6 or 9 fractional digits, whichever is shortest without losing precision.
+fn format_nanos(nanos: u32) -> String {
+    if nanos == 0 {
+        String::new()
+    }
+    else if nanos.is_multiple_of(1_000_000) {
+        format!(".{:03}", nanos / 1_000_000)
+    }
+    else if nanos.is_multiple_of(1_000) {
+        format!(".{:06}", nanos / 1_000)
+    }
+    else {
+        format!(".{:09}", nanos)
+    }
+}
+
+/// Read up to 9 fractional digits as nanoseconds.
+fn parse_nanos(digits: &str) -> Option<u32> {
+    if digits.is_empty() || digits.len() > 9 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    Some(digits.parse::<u32>().ok()? * 10u32.pow(9 - digits.len() as u32))
+}
+
+/// Count the days from 1970-01-01 to a date in the proleptic Gregorian calendar.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The inverse of `days_from_civil`, giving back the year, month and day.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+
+    (year_of_era + era * 400 + if month <= 2 { 1 } else { 0 }, month, day)
+}
+
+/// A `google.protobuf.Timestamp`, written as an RFC 3339 string in UTC.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timestamp {
+    pub seconds: i64,
+    pub nanos: i32
+}
+
+impl Timestamp {
+    pub fn to_node(&self) -> Result<Node, ConvertError> {
+        if !(MIN_TIMESTAMP_SECONDS..=MAX_TIMESTAMP_SECONDS).contains(&self.seconds)
+            || !(0..1_000_000_000).contains(&self.nanos)
+        {
+            return Err(ConvertError::new(format!("Timestamp {:?} is out of range", self)));
+        }
+
+        let (year, month, day) = civil_from_days(self.seconds.div_euclid(86_400));
+        let time = self.seconds.rem_euclid(86_400);
+
+        Ok(Node::String(format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}Z",
+            year,
+            month,
+            day,
+            time / 3600,
+            time / 60 % 60,
+            time % 60,
+            format_nanos(self.nanos as u32)
+        )))
+    }
+
+    /// Read a timestamp like `1972-01-01T10:00:20.021Z` or `1972-01-01T10:00:20+01:00`.
+    pub fn from_node(node: &Node) -> Result<Timestamp, ConvertError> {
+        let Node::String(s) = node else {
+            return Err(ConvertError::new(format!("Expected a timestamp string, found {:?}", node)));
+        };
+
+        let invalid = || ConvertError::new(format!("{:?} is not a valid RFC 3339 timestamp", s));
+
+        let number = |range: std::ops::Range<usize>| -> Result<i64, ConvertError> {
+            let digits = s.get(range).ok_or_else(invalid)?;
+
+            match digits.chars().all(|c| c.is_ascii_digit()) {
+                true => digits.parse().map_err(|_| invalid()),
+                false => Err(invalid())
+            }
+        };
+
+        if s.get(4..5) != Some("-") || s.get(7..8) != Some("-") || !matches!(s.get(10..11), Some("T" | "t"))
+            || s.get(13..14) != Some(":") || s.get(16..17) != Some(":")
+        {
+            return Err(invalid());
+        }
+
+        let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+        let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+
+        if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 59 {
+            return Err(invalid());
+        }
+
+        let mut rest = s.get(19..).ok_or_else(invalid)?;
+        let mut nanos = 0;
+
+        if let Some(fraction) = rest.strip_prefix('.') {
+            let end = fraction.find(|c: char| !c.is_ascii_digit()).unwrap_or(fraction.len());
+
+            nanos = parse_nanos(&fraction[..end]).ok_or_else(invalid)?;
+            rest = &fraction[end..];
+        }
+
+        let offset = match rest {
+            "Z" | "z" => 0,
+            _ => {
+                let sign = match rest.get(0..1) {
+                    Some("+") => 1,
+                    Some("-") => -1,
+                    _ => return Err(invalid())
+                };
+
+                if rest.len() != 6 || !rest.is_ascii() || &rest[3..4] != ":" {
+                    return Err(invalid());
+                }
+
+                let hours: i64 = rest[1..3].parse().map_err(|_| invalid())?;
+                let minutes: i64 = rest[4..6].parse().map_err(|_| invalid())?;
+
+                sign * (hours * 3600 + minutes * 60)
+            }
+        };
+
+        let seconds = days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second - offset;
+
+        if !(MIN_TIMESTAMP_SECONDS..=MAX_TIMESTAMP_SECONDS).contains(&seconds) {
+            return Err(invalid());
+        }
+
+        Ok(Timestamp {
+            seconds,
+            nanos: nanos as i32
+        })
+    }
+}
+
+/// A `google.protobuf.Duration`, written as seconds with an `s` suffix, like `1.5s`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Duration {
+    pub seconds: i64,
+    pub nanos: i32
+}
+
+impl Duration {
+    pub fn to_node(&self) -> Result<Node, ConvertError> {
+        let same_sign = self.seconds == 0 || self.nanos == 0 || (self.seconds < 0) == (self.nanos < 0);
+
+        if self.seconds.abs() > MAX_DURATION_SECONDS || self.nanos.abs() >= 1_000_000_000 || !same_sign {
+            return Err(ConvertError::new(format!("Duration {:?} is out of range", self)));
+        }
+
+        let sign = if self.seconds < 0 || self.nanos < 0 { "-" } else { "" };
+
+        Ok(Node::String(format!(
+            "{}{}{}s",
+            sign,
+            self.seconds.abs(),
+            format_nanos(self.nanos.unsigned_abs())
+        )))
+    }
+
+    pub fn from_node(node: &Node) -> Result<Duration, ConvertError> {
+        let Node::String(s) = node else {
+            return Err(ConvertError::new(format!("Expected a duration string, found {:?}", node)));
+        };
+
+        let invalid = || ConvertError::new(format!("{:?} is not a valid duration", s));
+
+        let body = s.strip_suffix('s').ok_or_else(invalid)?;
+        let (negative, body) = match body.strip_prefix('-') {
+            Some(x) => (true, x),
+            None => (false, body)
+        };
+
+        let (whole, fraction) = match body.split_once('.') {
+            Some((whole, fraction)) => (whole, parse_nanos(fraction).ok_or_else(invalid)?),
+            None => (body, 0)
+        };
+
+        if whole.is_empty() || !whole.chars().all(|c| c.is_ascii_digit()) {
+            return Err(invalid());
+        }
+
+        let seconds: i64 = whole.parse().map_err(|_| invalid())?;
+
+        if seconds > MAX_DURATION_SECONDS {
+            return Err(invalid());
+        }
+
+        let sign = if negative { -1 } else { 1 };
+
+        Ok(Duration {
+            seconds: sign * seconds,
+            nanos: sign as i32 * fraction as i32
+        })
+    }
+}
+
+/// Wrap a message's JSON in a `google.protobuf.Any`, tagged with its `@type`.
+///
+/// Ordinary messages get an `@type` key next to their fields, while well-known
+/// types with special JSON forms are put under a `value` key instead.
+pub fn pack_any(type_url: &str, message: Node) -> Result<Node, ConvertError> {
+    let type_name = type_url.rsplit('/').next().unwrap_or(type_url);
+    let mut map = ObjectMap::default();
+
+    if SPECIAL_TYPES.contains(&type_name) {
+        map.insert("value".to_string(), message);
+    }
+    else {
+        match message {
+            Node::Object(fields) => map = fields,
+            other => return Err(ConvertError::new(format!("Expected a message object for {}, found {:?}", type_url, other)))
+        }
+    }
+
+    map.insert("@type".to_string(), Node::String(type_url.to_string()));
+
+    Ok(Node::Object(map))
+}
+
+/// Unwrap a `google.protobuf.Any`, giving back its type URL and the message's JSON.
+pub fn unpack_any(node: &Node) -> Result<(String, Node), ConvertError> {
+    let Node::Object(map) = node else {
+        return Err(ConvertError::new(format!("Expected an Any object, found {:?}", node)));
+    };
+
+    let type_url = match map.get("@type") {
+        Some(Node::String(x)) => x.clone(),
+        _ => return Err(ConvertError::new("An Any must have an \"@type\" string"))
+    };
+
+    let type_name = type_url.rsplit('/').next().unwrap_or(&type_url);
+
+    if SPECIAL_TYPES.contains(&type_name) {
+        return match map.get("value") {
+            Some(value) => Ok((type_url, value.clone())),
+            None => Err(ConvertError::new(format!("An Any holding {} must have a \"value\"", type_name)))
+        };
+    }
+
+    let mut fields = map.clone();
+    fields.remove("@type");
+
+    Ok((type_url, Node::Object(fields)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compare::Tolerance;
+    use crate::parser::parse_str;
+
+    fn doc(text: &str) -> Node {
+        parse_str(text).unwrap()
+    }
+
+    fn string(s: &str) -> Node {
+        Node::String(s.to_string())
+    }
+
+    #[test]
+    fn int64s_are_strings_but_read_either_way() {
+        assert!(matches!(int64_to_node(i64::MIN), Node::String(s) if s == "-9223372036854775808"));
+
+        assert_eq!(node_to_int64(&doc("-5")).unwrap(), -5);
+        assert_eq!(node_to_int64(&string("-5")).unwrap(), -5);
+        assert_eq!(node_to_uint64(&doc("18446744073709551615")).unwrap(), u64::MAX);
+        assert!(node_to_int64(&string("1.5")).is_err());
+        assert!(node_to_uint64(&doc("-1")).is_err());
+    }
+
+    #[test]
+    fn doubles_and_bytes_have_special_forms() {
+        assert!(matches!(double_to_node(f64::NEG_INFINITY), Node::String(s) if s == "-Infinity"));
+        assert!(node_to_double(&string("NaN")).unwrap().is_nan());
+        assert_eq!(node_to_double(&string("2.5")).unwrap(), 2.5);
+
+        let bytes = [0xfb, 0xff, 0x00];
+        assert!(matches!(bytes_to_node(&bytes), Node::String(s) if s == "+/8A"));
+        assert_eq!(node_to_bytes(&string("-_8A")).unwrap(), bytes);
+        assert!(node_to_bytes(&string("!")).is_err());
+    }
+
+    #[test]
+    fn timestamps_are_rfc3339_strings() {
+        let timestamp = Timestamp::from_node(&string("1972-01-01T10:00:20.021Z")).unwrap();
+        assert_eq!(timestamp, Timestamp { seconds: 63_108_020, nanos: 21_000_000 });
+        assert!(matches!(timestamp.to_node().unwrap(), Node::String(s) if s == "1972-01-01T10:00:20.021Z"));
+
+        let offset = Timestamp::from_node(&string("1972-01-01T10:00:20+01:00")).unwrap();
+        assert!(matches!(offset.to_node().unwrap(), Node::String(s) if s == "1972-01-01T09:00:20Z"));
+
+        let oldest = Timestamp { seconds: MIN_TIMESTAMP_SECONDS, nanos: 1 };
+        assert!(matches!(oldest.to_node().unwrap(), Node::String(s) if s == "0001-01-01T00:00:00.000000001Z"));
+
+        for text in ["1972-01-01 10:00:20Z", "1972-13-01T10:00:20Z", "1972-01-01T10:00:20", "1972-01-01T10:00:20.Z"] {
+            assert!(Timestamp::from_node(&string(text)).is_err(), "{}", text);
+        }
+
+        assert!(Timestamp { seconds: MAX_TIMESTAMP_SECONDS + 1, nanos: 0 }.to_node().is_err());
+    }
+
+    #[test]
+    fn durations_are_seconds_with_a_suffix() {
+        assert_eq!(Duration::from_node(&string("1.5s")).unwrap(), Duration { seconds: 1, nanos: 500_000_000 });
+
+        let negative = Duration::from_node(&string("-0.000001s")).unwrap();
+        assert_eq!(negative, Duration { seconds: 0, nanos: -1_000 });
+        assert!(matches!(negative.to_node().unwrap(), Node::String(s) if s == "-0.000001s"));
+
+        assert!(Duration { seconds: 1, nanos: -1 }.to_node().is_err());
+
+        for text in ["1.5", "s", "1.1234567890s", "+1s"] {
+            assert!(Duration::from_node(&string(text)).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn any_wraps_special_types_in_a_value() {
+        let packed = pack_any("type.googleapis.com/example.User", doc(r#"{"name": "Ada"}"#)).unwrap();
+        assert!(packed.approx_eq(&doc(r#"{"@type": "type.googleapis.com/example.User", "name": "Ada"}"#), &Tolerance::exact()));
+
+        let (type_url, message) = unpack_any(&packed).unwrap();
+        assert_eq!(type_url, "type.googleapis.com/example.User");
+        assert!(message.approx_eq(&doc(r#"{"name": "Ada"}"#), &Tolerance::exact()));
+
+        let packed = pack_any("type.googleapis.com/google.protobuf.Duration", string("1s")).unwrap();
+        assert!(packed.approx_eq(&doc(r#"{"@type": "type.googleapis.com/google.protobuf.Duration", "value": "1s"}"#), &Tolerance::exact()));
+        assert!(matches!(unpack_any(&packed).unwrap().1, Node::String(s) if s == "1s"));
+
+        assert!(pack_any("example.User", string("x")).is_err());
+        assert!(unpack_any(&doc(r#"{"name": "Ada"}"#)).is_err());
+    }
+}
@@ -0,0 +1,245 @@
+use crate::convert::ConvertError;
+use crate::parser::{Node, ObjectMap};
+
+/// Percent-encode text for `application/x-www-form-urlencoded`, with spaces as `+`.
+fn encode(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+
+    for byte in text.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => result.push(byte as char),
+            b' ' => result.push('+'),
+            _ => result.push_str(&format!("%{:02X}", byte))
+        }
+    }
+
+    result
+}
+
+fn decode(text: &str) -> Result<String, ConvertError> {
+    let bytes = text.as_bytes();
+    let mut result = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => result.push(b' '),
+            b'%' => {
+                let byte = text.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+                match byte {
+                    Some(x) => result.push(x),
+                    None => return Err(ConvertError::new(format!("Invalid percent-encoding in {:?}", text)))
+                }
+
+                i += 2;
+            },
+            b => result.push(b)
+        }
+
+        i += 1;
+    }
+
+    match String::from_utf8(result) {
+        Ok(x) => Ok(x),
+        Err(_) => Err(ConvertError::new(format!("Percent-encoding in {:?} is not valid UTF-8", text)))
+    }
+}
+
+fn flatten(node: &Node, key: &str, pairs: &mut Vec<String>) -> Result<(), ConvertError> {
+    match node {
+        Node::Object(map) => {
+            // Sort the keys so the same document always gives the same query string.
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+
+            for name in keys {
+                flatten(&map[name], &format!("{}[{}]", key, encode(name)), pairs)?;
+            }
+        },
+
+        Node::Array(arr) => {
+            for (i, item) in arr.iter().enumerate() {
+                flatten(item, &format!("{}[{}]", key, i), pairs)?;
+            }
+        },
+
+        Node::String(s) => pairs.push(format!("{}={}", key, encode(s))),
+        Node::Integer(n) => pairs.push(format!("{}={}", key, n)),
+        Node::Unsigned(n) => pairs.push(format!("{}={}", key, n)),
+        Node::Float(f) => pairs.push(format!("{}={}", key, f)),
+        Node::Bool(b) => pairs.push(format!("{}={}", key, b)),
+
+        // A key without a value stands for null, so it isn't mixed up with an empty string.
+        Node::Null => pairs.push(key.to_string()),
+
+        Node::Empty => return Err(ConvertError::new("Cannot convert an empty document to a query string"))
+    }
+
+    Ok(())
+}
+
+/// Convert an object to a query string like `a[b][0]=x&c=1`.
+///
+/// Nested objects and arrays use bracketed keys, and `null` becomes a key with no `=`.
+/// Empty arrays and objects have nothing to write, so they are left out.
+pub fn to_query_string(node: &Node) -> Result<String, ConvertError> {
+    let Node::Object(map) = node else {
+        return Err(ConvertError::new(format!("Only objects can be converted to query strings, found {:?}", node)));
+    };
+
+    let mut keys: Vec<&String> = map.keys().collect();
+    keys.sort();
+
+    let mut pairs = Vec::new();
+
+    for name in keys {
+        flatten(&map[name], &encode(name), &mut pairs)?;
+    }
+
+    Ok(pairs.join("&"))
+}
+
+/// Split a key like `a[b][0]` into `["a", "b", "0"]`.
+fn split_key(key: &str) -> Result<Vec<String>, ConvertError> {
+    let invalid = || ConvertError::new(format!("Invalid bracketed key {:?}", key));
+
+    let (name, mut rest) = match key.find('[') {
+        Some(i) => (&key[..i], &key[i..]),
+        None => (key, "")
+    };
+
+    if name.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut parts = vec![decode(name)?];
+
+    while !rest.is_empty() {
+        let inner = rest.strip_prefix('[').ok_or_else(invalid)?;
+        let end = inner.find(']').ok_or_else(invalid)?;
+
+        parts.push(decode(&inner[..end])?);
+        rest = &inner[end + 1..];
+    }
+
+    Ok(parts)
+}
+
+fn insert(target: &mut Node, parts: &[String], value: Node, key: &str) -> Result<(), ConvertError> {
+    let conflict = || ConvertError::new(format!("Key {:?} conflicts with an earlier key", key));
+
+    let Some((part, rest)) = parts.split_first() else {
+        return match target {
+            // Only a placeholder from a container we just created can be replaced.
+            Node::Empty => {
+                *target = value;
+                Ok(())
+            },
+            _ => Err(conflict())
+        };
+    };
+
+    // Numeric and empty segments index into arrays, everything else into objects.
+    let is_index = part.is_empty() || part.chars().all(|c| c.is_ascii_digit());
+
+    if let Node::Empty = target {
+        *target = match is_index {
+            true => Node::Array(Vec::new()),
+            false => Node::Object(ObjectMap::default())
+        };
+    }
+
+    match target {
+        Node::Array(arr) if is_index => {
+            let index = match part.is_empty() {
+                true => arr.len(),
+                false => match part.parse::<usize>() {
+                    Ok(x) => x,
+                    Err(_) => return Err(conflict())
+                }
+            };
+
+            if index > arr.len() {
+                return Err(ConvertError::new(format!("Key {:?} skips over array indexes", key)));
+            }
+
+            if index == arr.len() {
+                arr.push(Node::Empty);
+            }
+
+            insert(&mut arr[index], rest, value, key)
+        },
+
+        Node::Object(map) => insert(map.entry(part.clone()).or_insert(Node::Empty), rest, value, key),
+
+        _ => Err(conflict())
+    }
+}
+
+/// Parse a query string like `a[b][0]=x&c=1` into an object.
+///
+/// Bracketed keys build nested objects, and numeric or empty brackets build arrays.
+/// Values are always strings, since query strings don't carry types, apart from
+/// keys without an `=`, which become `null`.
+pub fn from_query_string(query: &str) -> Result<Node, ConvertError> {
+    let mut root = Node::Object(ObjectMap::default());
+
+    for pair in query.trim_start_matches('?').split('&') {
+        if pair.is_empty() {
+            continue;
+        }
+
+        let (key, value) = match pair.split_once('=') {
+            Some((key, value)) => (key, Node::String(decode(value)?)),
+            None => (pair, Node::Null)
+        };
+
+        let parts = split_key(key)?;
+
+        insert(&mut root, &parts, value, key)?;
+    }
+
+    Ok(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compare::Tolerance;
+    use crate::parser::parse_str;
+
+    fn doc(text: &str) -> Node {
+        parse_str(text).unwrap()
+    }
+
+    #[test]
+    fn nested_values_get_bracketed_keys() {
+        let query = to_query_string(&doc(r#"{"a": {"b": ["x y", "é"]}, "c": 1, "d": null, "e": [], "f&g": "="}"#)).unwrap();
+
+        assert_eq!(query, "a[b][0]=x+y&a[b][1]=%C3%A9&c=1&d&f%26g=%3D");
+        assert!(to_query_string(&doc("[1]")).is_err());
+    }
+
+    #[test]
+    fn query_strings_survive_a_round_trip() {
+        let node = doc(r#"{"a": {"b": ["x y", "é"], "c": {"d": null}}, "e": "", "f&g": "="}"#);
+        let query = to_query_string(&node).unwrap();
+
+        assert!(from_query_string(&query).unwrap().approx_eq(&node, &Tolerance::exact()));
+    }
+
+    #[test]
+    fn empty_brackets_append_to_arrays() {
+        let node = from_query_string("?a[]=1&a[]=2&&b[0][c]=3").unwrap();
+
+        assert!(node.approx_eq(&doc(r#"{"a": ["1", "2"], "b": [{"c": "3"}]}"#), &Tolerance::exact()));
+    }
+
+    #[test]
+    fn bad_query_strings_are_errors() {
+        for query in ["a=1&a=2", "a[b]=1&a=2", "a[1]=x", "a[b=1", "a]b[=1", "%zz=1", "a=%ff", "=1", "a[]=1&a[b]=2"] {
+            assert!(from_query_string(query).is_err(), "{}", query);
+        }
+    }
+}
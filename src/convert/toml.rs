@@ -0,0 +1,163 @@
+use crate::convert::ConvertError;
+use crate::parser::{Node, ObjectMap};
+use crate::serializer::{to_string, write_escaped};
+use crate::utils::{index_path, key_path};
+
+fn write_key(key: &str, out: &mut String) {
+    match !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        true => out.push_str(key),
+
+        // JSON's escapes all mean the same in TOML's basic strings.
+        false => write_escaped(key, out)
+    }
+}
+
+fn sorted_keys(map: &ObjectMap) -> Vec<&String> {
+    let mut keys: Vec<&String> = map.keys().collect();
+    keys.sort();
+
+    keys
+}
+
+/// An array that's written as `[[name]]` tables, rather than inline.
+fn is_table_array(node: &Node) -> bool {
+    matches!(node, Node::Array(arr) if !arr.is_empty() && arr.iter().all(|item| matches!(item, Node::Object(_))))
+}
+
+/// Write a value on one line, using inline tables for any objects inside arrays.
+fn write_inline(node: &Node, path: &str, out: &mut String) -> Result<(), ConvertError> {
+    match node {
+        Node::String(s) => write_escaped(s, out),
+        Node::Float(f) if f.is_nan() => out.push_str("nan"),
+        Node::Float(f) if f.is_infinite() => out.push_str(if *f > 0.0 { "inf" } else { "-inf" }),
+        Node::Integer(_) | Node::Float(_) | Node::Bool(_) => out.push_str(&to_string(node)),
+        Node::Unsigned(n) => return Err(ConvertError::new(format!("TOML integers are signed 64-bit, so {} at {} can't be written", n, path))),
+        Node::Array(arr) => {
+            out.push('[');
+
+            for (i, item) in arr.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+
+                write_inline(item, &index_path(path, i), out)?;
+            }
+
+            out.push(']');
+        },
+        Node::Object(map) => {
+            out.push('{');
+
+            for (i, key) in sorted_keys(map).into_iter().enumerate() {
+                out.push_str(if i > 0 { ", " } else { " " });
+                write_key(key, out);
+                out.push_str(" = ");
+                write_inline(&map[key], &key_path(path, key), out)?;
+            }
+
+            out.push_str(if map.is_empty() { "}" } else { " }" });
+        },
+        Node::Null | Node::Empty => return Err(ConvertError::new(format!("TOML has no null, so the one at {} can't be written", path)))
+    }
+
+    Ok(())
+}
+
+/// Write the entries of a table, with plain values first and then any tables
+/// inside it under their own headers, like `[server.tls]`.
+fn write_table(map: &ObjectMap, header: &str, path: &str, out: &mut String) -> Result<(), ConvertError> {
+    let keys = sorted_keys(map);
+
+    for key in &keys {
+        let value = &map[*key];
+
+        if matches!(value, Node::Object(_)) || is_table_array(value) {
+            continue;
+        }
+
+        write_key(key, out);
+        out.push_str(" = ");
+        write_inline(value, &key_path(path, key), out)?;
+        out.push('\n');
+    }
+
+    for key in keys {
+        let mut name = String::new();
+        write_key(key, &mut name);
+
+        let header = match header {
+            "" => name,
+            _ => format!("{}.{}", header, name)
+        };
+
+        let path = key_path(path, key);
+
+        match &map[key] {
+            Node::Object(inner) => {
+                out.push_str(&format!("\n[{}]\n", header));
+                write_table(inner, &header, &path, out)?;
+            },
+            Node::Array(items) if is_table_array(&map[key]) => {
+                for (i, item) in items.iter().enumerate() {
+                    let Node::Object(inner) = item else { unreachable!() };
+
+                    out.push_str(&format!("\n[[{}]]\n", header));
+                    write_table(inner, &header, &index_path(&path, i), out)?;
+                }
+            },
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Convert an object to TOML, with nested objects as tables and arrays of
+/// objects as arrays of tables. Keys are written in sorted order.
+///
+/// TOML has no null, so documents with nulls in them can't be converted.
+pub fn to_toml(node: &Node) -> Result<String, ConvertError> {
+    let Node::Object(map) = node else {
+        return Err(ConvertError::new("Only an object can be written as TOML"));
+    };
+
+    let mut out = String::new();
+    write_table(map, "", "$", &mut out)?;
+
+    // Tables after the top-level values start with a blank line, which isn't needed at the very start.
+    Ok(out.trim_start_matches('\n').to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_str;
+
+    fn doc(text: &str) -> Node {
+        parse_str(text).unwrap()
+    }
+
+    #[test]
+    fn objects_become_tables() {
+        let toml = to_toml(&doc(r#"{
+            "title": "x",
+            "server": {"port": 80, "tls": {"on": true}},
+            "users": [{"name": "a"}, {"name": "b"}],
+            "mixed": [1, {"k": "v"}],
+            "odd key": 1.5
+        }"#)).unwrap();
+
+        assert_eq!(toml, concat!(
+            "mixed = [1, { k = \"v\" }]\n\"odd key\" = 1.5\ntitle = \"x\"\n",
+            "\n[server]\nport = 80\n\n[server.tls]\non = true\n",
+            "\n[[users]]\nname = \"a\"\n\n[[users]]\nname = \"b\"\n"
+        ));
+    }
+
+    #[test]
+    fn values_toml_cant_hold_are_errors() {
+        assert_eq!(to_toml(&doc(r#"{"a": [1, null]}"#)).unwrap_err().message, "TOML has no null, so the one at $.a[1] can't be written");
+        assert!(to_toml(&doc(r#"{"a": 18446744073709551615}"#)).is_err());
+        assert!(to_toml(&doc("[1]")).is_err());
+    }
+}
@@ -0,0 +1,88 @@
+use serde_json::{Map, Number, Value};
+
+use crate::parser::{Node, ObjectMap};
+
+impl From<Value> for Node {
+    /// Convert a `serde_json::Value`.
+    fn from(value: Value) -> Node {
+        match value {
+            Value::Null => Node::Null,
+            Value::Bool(b) => Node::Bool(b),
+            Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+                (Some(i), _) => Node::Integer(i),
+                (None, Some(u)) => Node::Unsigned(u),
+                (None, None) => Node::Float(n.as_f64().unwrap_or(f64::NAN))
+            },
+            Value::String(s) => Node::String(s),
+            Value::Array(arr) => Node::Array(arr.into_iter().map(Node::from).collect()),
+            Value::Object(map) => Node::Object(map.into_iter().map(
+                |(key, value)| (key, Node::from(value))
+            ).collect::<ObjectMap>())
+        }
+    }
+}
+
+impl From<&Node> for Value {
+    /// Convert to a `serde_json::Value`. Like serde_json itself,
+    /// `NaN` and infinite floats become `null`, as does `Node::Empty`.
+    fn from(node: &Node) -> Value {
+        match node {
+            Node::Null | Node::Empty => Value::Null,
+            Node::Bool(b) => Value::Bool(*b),
+            Node::Integer(n) => Value::Number(Number::from(*n)),
+            Node::Unsigned(n) => Value::Number(Number::from(*n)),
+            Node::Float(f) => Number::from_f64(*f).map_or(Value::Null, Value::Number),
+            Node::String(s) => Value::String(s.clone()),
+            Node::Array(arr) => Value::Array(arr.iter().map(Value::from).collect()),
+            Node::Object(map) => Value::Object(map.iter().map(
+                |(key, value)| (key.clone(), Value::from(value))
+            ).collect::<Map<String, Value>>())
+        }
+    }
+}
+
+impl From<Node> for Value {
+    fn from(node: Node) -> Value {
+        match node {
+            Node::String(s) => Value::String(s),
+            Node::Array(arr) => Value::Array(arr.into_iter().map(Value::from).collect()),
+            Node::Object(map) => Value::Object(map.into_iter().map(
+                |(key, value)| (key, Value::from(value))
+            ).collect::<Map<String, Value>>()),
+            other => Value::from(&other)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compare::Tolerance;
+    use crate::parser::parse_str;
+
+    const TEXT: &str = r#"{"a": [1, -2, 18446744073709551615, 2.5, "x", true, null], "b": {"c": {}}}"#;
+
+    #[test]
+    fn values_survive_a_round_trip() {
+        let node = parse_str(TEXT).unwrap();
+        let value: Value = serde_json::from_str(TEXT).unwrap();
+
+        assert_eq!(Value::from(&node), value);
+        assert_eq!(Value::from(node.clone()), value);
+        assert!(Node::from(value).approx_eq(&node, &Tolerance::exact()));
+    }
+
+    #[test]
+    fn numbers_keep_their_kind() {
+        assert!(matches!(Node::from(serde_json::json!(-1)), Node::Integer(-1)));
+        assert!(matches!(Node::from(serde_json::json!(u64::MAX)), Node::Unsigned(u64::MAX)));
+        assert!(matches!(Node::from(serde_json::json!(1.0)), Node::Float(f) if f == 1.0));
+    }
+
+    #[test]
+    fn values_serde_json_cant_hold_become_null() {
+        assert_eq!(Value::from(&Node::Float(f64::NAN)), Value::Null);
+        assert_eq!(Value::from(Node::Float(f64::INFINITY)), Value::Null);
+        assert_eq!(Value::from(&Node::Empty), Value::Null);
+    }
+}
@@ -0,0 +1,523 @@
+use std::collections::HashSet;
+
+use crate::convert::ConvertError;
+use crate::parser::{Node, ObjectMap};
+
+pub struct XmlOptions {
+    /// The name of the element wrapping the whole document.
+    pub root_name: String,
+
+    /// The name of elements holding the items of arrays that aren't under a key,
+    /// like the top-level document or an array inside another array.
+    pub item_name: String,
+
+    /// Keys starting with this prefix are written as attributes, and attributes
+    /// are read back as keys with it. With `None`, everything is an element
+    /// and attributes are read as plain keys.
+    pub attribute_prefix: Option<String>,
+
+    /// The key holding the text of an element that also has attributes or children.
+    pub text_key: String
+}
+
+impl Default for XmlOptions {
+    fn default() -> XmlOptions {
+        XmlOptions {
+            root_name: "root".to_string(),
+            item_name: "item".to_string(),
+            attribute_prefix: Some("@".to_string()),
+            text_key: "#text".to_string()
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+
+    for ch in text.chars() {
+        match ch {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&apos;"),
+            c => result.push(c)
+        }
+    }
+
+    result
+}
+
+fn check_name(name: &str) -> Result<(), ConvertError> {
+    let valid_start = name.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_');
+    let valid_rest = name.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ':'));
+
+    if valid_start && valid_rest && !name.to_ascii_lowercase().starts_with("xml") {
+        Ok(())
+    }
+    else {
+        Err(ConvertError::new(format!("{:?} cannot be used as an XML name", name)))
+    }
+}
+
+/// The text of a scalar, or `None` if it's an array or object.
+fn scalar_text(node: &Node) -> Option<String> {
+    match node {
+        Node::String(s) => Some(s.clone()),
+        Node::Integer(n) => Some(n.to_string()),
+        Node::Unsigned(n) => Some(n.to_string()),
+        Node::Float(f) => Some(f.to_string()),
+        Node::Bool(b) => Some(b.to_string()),
+        Node::Null | Node::Empty => Some(String::new()),
+        Node::Array(_) | Node::Object(_) => None
+    }
+}
+
+fn write_element(name: &str, node: &Node, options: &XmlOptions, out: &mut String) -> Result<(), ConvertError> {
+    check_name(name)?;
+
+    match node {
+        Node::Array(arr) => {
+            out.push_str(&format!("<{}>", name));
+
+            for item in arr {
+                write_element(&options.item_name, item, options, out)?;
+            }
+
+            out.push_str(&format!("</{}>", name));
+        },
+
+        Node::Object(map) => {
+            // Sort the keys so the same document always gives the same XML.
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+
+            let mut attributes = String::new();
+            let mut children = String::new();
+
+            for key in keys {
+                let value = &map[key];
+
+                if *key == options.text_key {
+                    match scalar_text(value) {
+                        Some(text) => children.push_str(&escape(&text)),
+                        None => return Err(ConvertError::new(format!("{:?} must hold text", key)))
+                    }
+
+                    continue;
+                }
+
+                if let Some(prefix) = &options.attribute_prefix
+                    && let Some(attribute) = key.strip_prefix(prefix.as_str())
+                {
+                    check_name(attribute)?;
+
+                    match scalar_text(value) {
+                        Some(text) => attributes.push_str(&format!(" {}=\"{}\"", attribute, escape(&text))),
+                        None => return Err(ConvertError::new(format!("Attribute {:?} must hold text", key)))
+                    }
+
+                    continue;
+                }
+
+                match value {
+                    // Arrays under a key become one element per item, all with that key's name.
+                    Node::Array(arr) => {
+                        for item in arr {
+                            write_element(key, item, options, &mut children)?;
+                        }
+                    },
+                    _ => write_element(key, value, options, &mut children)?
+                }
+            }
+
+            match children.is_empty() {
+                true => out.push_str(&format!("<{}{}/>", name, attributes)),
+                false => out.push_str(&format!("<{}{}>{}</{}>", name, attributes, children, name))
+            }
+        },
+
+        Node::Null | Node::Empty => out.push_str(&format!("<{}/>", name)),
+
+        scalar => {
+            let text = scalar_text(scalar).unwrap();
+            out.push_str(&format!("<{}>{}</{}>", name, escape(&text), name));
+        }
+    }
+
+    Ok(())
+}
+
+/// Convert a document to XML, wrapped in an element named by `options.root_name`.
+pub fn to_xml(node: &Node, options: &XmlOptions) -> Result<String, ConvertError> {
+    let mut out = String::new();
+
+    write_element(&options.root_name, node, options, &mut out)?;
+
+    Ok(out)
+}
+
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Element>,
+    text: String
+}
+
+struct XmlReader<'a> {
+    text: &'a str,
+    offset: usize
+}
+
+impl<'a> XmlReader<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.offset..]
+    }
+
+    fn error(&self, message: &str) -> ConvertError {
+        ConvertError::new(format!("{} at byte {}", message, self.offset))
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.offset += rest.len() - rest.trim_start().len();
+    }
+
+    /// Skip past the next occurrence of `end`.
+    fn skip_past(&mut self, end: &str) -> Result<(), ConvertError> {
+        match self.rest().find(end) {
+            Some(i) => {
+                self.offset += i + end.len();
+                Ok(())
+            },
+            None => Err(self.error(&format!("Expected {:?}", end)))
+        }
+    }
+
+    /// Skip comments, processing instructions and doctypes.
+    fn skip_misc(&mut self) -> Result<(), ConvertError> {
+        loop {
+            self.skip_whitespace();
+
+            let rest = self.rest();
+
+            if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            }
+            else if rest.starts_with("<?") {
+                self.skip_past("?>")?;
+            }
+            else if rest.starts_with("<!DOCTYPE") {
+                self.skip_past(">")?;
+            }
+            else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn read_name(&mut self) -> Result<String, ConvertError> {
+        let rest = self.rest();
+        let end = rest.find(|c: char| c.is_whitespace() || matches!(c, '/' | '>' | '=')).unwrap_or(rest.len());
+
+        if end == 0 {
+            return Err(self.error("Expected a name"));
+        }
+
+        self.offset += end;
+
+        Ok(rest[..end].to_string())
+    }
+
+    fn read_element(&mut self) -> Result<Element, ConvertError> {
+        if !self.rest().starts_with('<') {
+            return Err(self.error("Expected an element"));
+        }
+
+        self.offset += 1;
+
+        let mut element = Element {
+            name: self.read_name()?,
+            attributes: Vec::new(),
+            children: Vec::new(),
+            text: String::new()
+        };
+
+        // Read attributes until the start tag ends.
+        loop {
+            self.skip_whitespace();
+
+            if self.rest().starts_with("/>") {
+                self.offset += 2;
+                return Ok(element);
+            }
+
+            if self.rest().starts_with('>') {
+                self.offset += 1;
+                break;
+            }
+
+            let name = self.read_name()?;
+
+            self.skip_whitespace();
+
+            if !self.rest().starts_with('=') {
+                return Err(self.error("Expected '=' after attribute name"));
+            }
+
+            self.offset += 1;
+            self.skip_whitespace();
+
+            let quote = match self.rest().chars().next() {
+                Some(q @ ('"' | '\'')) => q,
+                _ => return Err(self.error("Expected a quoted attribute value"))
+            };
+
+            self.offset += 1;
+
+            let end = match self.rest().find(quote) {
+                Some(x) => x,
+                None => return Err(self.error("Unterminated attribute value"))
+            };
+
+            let value = unescape(&self.rest()[..end]).map_err(|e| self.error(&e))?;
+            self.offset += end + 1;
+
+            element.attributes.push((name, value));
+        }
+
+        // Read the content until the matching end tag.
+        loop {
+            let rest = self.rest();
+
+            if rest.starts_with("</") {
+                self.offset += 2;
+
+                let name = self.read_name()?;
+
+                if name != element.name {
+                    return Err(self.error(&format!("Expected </{}>, found </{}>", element.name, name)));
+                }
+
+                self.skip_whitespace();
+                self.skip_past(">")?;
+
+                return Ok(element);
+            }
+
+            if rest.starts_with("<!--") || rest.starts_with("<?") {
+                self.skip_past(if rest.starts_with("<?") { "?>" } else { "-->" })?;
+            }
+            else if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+                let end = match cdata.find("]]>") {
+                    Some(x) => x,
+                    None => return Err(self.error("Unterminated CDATA section"))
+                };
+
+                element.text.push_str(&cdata[..end]);
+                self.offset += "<![CDATA[".len() + end + 3;
+            }
+            else if rest.starts_with('<') {
+                element.children.push(self.read_element()?);
+            }
+            else if rest.is_empty() {
+                return Err(self.error(&format!("Expected </{}>", element.name)));
+            }
+            else {
+                let end = rest.find('<').unwrap_or(rest.len());
+                let text = unescape(&rest[..end]).map_err(|e| self.error(&e))?;
+
+                element.text.push_str(&text);
+                self.offset += end;
+            }
+        }
+    }
+}
+
+fn unescape(text: &str) -> Result<String, String> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+
+        let end = match rest[start..].find(';') {
+            Some(x) => start + x,
+            None => return Err(format!("Unterminated entity in {:?}", text))
+        };
+
+        let entity = &rest[start + 1..end];
+
+        let ch = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => match entity.strip_prefix("#x").or_else(|| entity.strip_prefix("#X")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok().and_then(char::from_u32),
+                None => entity.strip_prefix('#').and_then(|n| n.parse().ok()).and_then(char::from_u32)
+            }
+        };
+
+        match ch {
+            Some(c) => result.push(c),
+            None => return Err(format!("Unknown entity &{};", entity))
+        }
+
+        rest = &rest[end + 1..];
+    }
+
+    result.push_str(rest);
+
+    Ok(result)
+}
+
+fn element_to_node(element: Element, options: &XmlOptions) -> Node {
+    let text = element.text.trim();
+
+    if element.attributes.is_empty() && element.children.is_empty() {
+        return match text.is_empty() {
+            true => Node::Null,
+            false => Node::String(text.to_string())
+        };
+    }
+
+    // Elements holding nothing but items came from an array.
+    if element.attributes.is_empty()
+        && text.is_empty()
+        && element.children.iter().all(|child| child.name == options.item_name)
+    {
+        return Node::Array(element.children.into_iter().map(
+            |child| element_to_node(child, options)
+        ).collect());
+    }
+
+    let mut map = ObjectMap::default();
+
+    for (name, value) in element.attributes {
+        let key = match &options.attribute_prefix {
+            Some(prefix) => format!("{}{}", prefix, name),
+            None => name
+        };
+
+        map.insert(key, Node::String(value));
+    }
+
+    // The names already collected into an array, kept apart from the names of elements
+    // that became arrays themselves, so those aren't added to by the next sibling.
+    let mut repeated = HashSet::new();
+
+    for child in element.children {
+        let name = child.name.clone();
+        let value = element_to_node(child, options);
+
+        // Repeated elements with the same name are collected into an array.
+        match map.get_mut(&name) {
+            Some(Node::Array(arr)) if repeated.contains(&name) => arr.push(value),
+            Some(existing) => {
+                let first = std::mem::replace(existing, Node::Null);
+                *existing = Node::Array(vec![first, value]);
+                repeated.insert(name);
+            },
+            None => {
+                map.insert(name, value);
+            }
+        }
+    }
+
+    if !text.is_empty() {
+        map.insert(options.text_key.clone(), Node::String(text.to_string()));
+    }
+
+    Node::Object(map)
+}
+
+/// Convert XML into a document, on a best-effort basis.
+///
+/// The root element itself is dropped and its content becomes the document.
+/// Elements with only text become strings, empty elements become `null`,
+/// repeated elements become arrays, and so do elements holding only items. Text is never converted to other types.
+pub fn from_xml(text: &str, options: &XmlOptions) -> Result<Node, ConvertError> {
+    let mut reader = XmlReader {
+        text,
+        offset: 0
+    };
+
+    reader.skip_misc()?;
+
+    let root = reader.read_element()?;
+
+    reader.skip_misc()?;
+
+    if !reader.rest().is_empty() {
+        return Err(reader.error("Found trailing content after the root element"));
+    }
+
+    Ok(element_to_node(root, options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compare::Tolerance;
+    use crate::parser::parse_str;
+
+    fn doc(text: &str) -> Node {
+        parse_str(text).unwrap()
+    }
+
+    fn round_trip(node: &Node) -> Node {
+        let options = XmlOptions::default();
+        from_xml(&to_xml(node, &options).unwrap(), &options).unwrap()
+    }
+
+    #[test]
+    fn documents_survive_a_round_trip() {
+        let texts = [
+            r#"{"a": "1", "b": null, "c": ["x", "y"]}"#,
+            r#"["a", ["b", "c"], {"d": "e"}]"#,
+            r##"{"user": {"@id": "7", "#text": "Ann"}}"##,
+            r#"{"a": [["1", "2"], ["3", "4"]], "b": "5"}"#,
+            r#"{"a": [["1", "2"], "3", ["4"]]}"#,
+            r#"{"a": {"b": [{"c": "1"}, {"c": "2"}]}}"#
+        ];
+
+        for text in texts {
+            assert!(round_trip(&doc(text)).approx_eq(&doc(text), &Tolerance::exact()), "{}", text);
+        }
+    }
+
+    #[test]
+    fn array_children_dont_take_in_their_siblings() {
+        let options = XmlOptions::default();
+        let node = from_xml("<root><a><item>1</item><item>2</item></a><a><item>3</item></a><a>4</a></root>", &options).unwrap();
+
+        assert!(node.approx_eq(&doc(r#"{"a": [["1", "2"], ["3"], "4"]}"#), &Tolerance::exact()));
+    }
+
+    #[test]
+    fn to_xml_writes_attributes_and_items() {
+        let xml = to_xml(&doc(r#"{"@id": 1, "name": "<a&b>", "tags": [true, null]}"#), &XmlOptions::default()).unwrap();
+        assert_eq!(xml, r#"<root id="1"><name>&lt;a&amp;b&gt;</name><tags>true</tags><tags/></root>"#);
+
+        let options = XmlOptions { attribute_prefix: None, ..XmlOptions::default() };
+        assert_eq!(to_xml(&doc(r#"{"@id": 1}"#), &options).unwrap_err().message, r#""@id" cannot be used as an XML name"#);
+    }
+
+    #[test]
+    fn from_xml_reads_entities_and_attributes() {
+        let node = from_xml(r#"<?xml version="1.0"?><root a='1'><b>&#x41;&#66;&quot;</b><!-- c --></root>"#, &XmlOptions::default()).unwrap();
+
+        assert!(node.approx_eq(&doc(r#"{"@a": "1", "b": "AB\""}"#), &Tolerance::exact()));
+    }
+
+    #[test]
+    fn bad_xml_is_an_error() {
+        let options = XmlOptions::default();
+
+        for text in ["", "<root>", "<root></other>", "<root>&nope;</root>", "<root/><root/>"] {
+            assert!(from_xml(text, &options).is_err(), "{:?}", text);
+        }
+    }
+}
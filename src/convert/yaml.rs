@@ -0,0 +1,154 @@
+use crate::parser::Node;
+use crate::serializer::{to_string, write_escaped};
+
+/// Check whether a string can be written without quotes and still be read back
+/// as the same string, rather than as a number, bool, null or other YAML syntax.
+fn is_plain(s: &str) -> bool {
+    let Some(first) = s.chars().next() else {
+        return false;
+    };
+
+    let reserved = ["true", "false", "null", "yes", "no", "on", "off", "y", "n", "~"];
+
+    !reserved.contains(&s.to_ascii_lowercase().as_str())
+        && !first.is_ascii_digit()
+        && !"-+.?:,[]{}#&*!|>'\"%@`".contains(first)
+        && s.trim() == s
+        && !s.contains(": ")
+        && !s.contains(" #")
+        && !s.ends_with(':')
+        && !s.chars().any(|c| c.is_control())
+}
+
+/// Write a string, in double quotes if it needs them. JSON's escapes all mean
+/// the same in YAML's double-quoted strings, so the JSON form can be used as it is.
+fn write_string(s: &str, out: &mut String) {
+    match is_plain(s) {
+        true => out.push_str(s),
+        false => write_escaped(s, out)
+    }
+}
+
+/// Write anything that fits on one line, including empty arrays and objects.
+fn write_scalar(node: &Node, out: &mut String) {
+    match node {
+        Node::String(s) => write_string(s, out),
+        Node::Float(f) if f.is_nan() => out.push_str(".nan"),
+        Node::Float(f) if f.is_infinite() => out.push_str(if *f > 0.0 { ".inf" } else { "-.inf" }),
+        Node::Null | Node::Empty => out.push_str("null"),
+        other => out.push_str(&to_string(other))
+    }
+}
+
+fn is_block(node: &Node) -> bool {
+    match node {
+        Node::Array(arr) => !arr.is_empty(),
+        Node::Object(map) => !map.is_empty(),
+        _ => false
+    }
+}
+
+/// Write a value as lines indented by `indent`, each ending in a newline.
+fn write_node(node: &Node, indent: usize, out: &mut String) {
+    let pad = " ".repeat(indent);
+
+    match node {
+        Node::Array(arr) if !arr.is_empty() => {
+            for item in arr {
+                match is_block(item) {
+                    // Write the item as if it were indented further, then put the
+                    // `- ` over the start of its first line, like `- name: Ada`.
+                    true => {
+                        let mut nested = String::new();
+                        write_node(item, indent + 2, &mut nested);
+
+                        out.push_str(&pad);
+                        out.push_str("- ");
+                        out.push_str(&nested[indent + 2..]);
+                    },
+                    false => {
+                        out.push_str(&pad);
+                        out.push_str("- ");
+                        write_scalar(item, out);
+                        out.push('\n');
+                    }
+                }
+            }
+        },
+
+        Node::Object(map) if !map.is_empty() => {
+            // Sort the keys so the same document always gives the same YAML.
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+
+            for key in keys {
+                let value = &map[key];
+
+                out.push_str(&pad);
+                write_string(key, out);
+                out.push(':');
+
+                match is_block(value) {
+                    true => {
+                        out.push('\n');
+                        write_node(value, indent + 2, out);
+                    },
+                    false => {
+                        out.push(' ');
+                        write_scalar(value, out);
+                        out.push('\n');
+                    }
+                }
+            }
+        },
+
+        scalar => {
+            out.push_str(&pad);
+            write_scalar(scalar, out);
+            out.push('\n');
+        }
+    }
+}
+
+/// Convert a document to YAML in block style, like:
+///
+/// ```text
+/// users:
+///   - name: Ada
+///     tags:
+///       - admin
+/// ```
+///
+/// Strings are quoted when they'd otherwise read back as something else, like `"true"` or `"12"`.
+pub fn to_yaml(node: &Node) -> String {
+    let mut out = String::new();
+    write_node(node, 0, &mut out);
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_str;
+
+    fn doc(text: &str) -> Node {
+        parse_str(text).unwrap()
+    }
+
+    #[test]
+    fn documents_are_written_in_block_style() {
+        let yaml = to_yaml(&doc(r#"{"users": [{"name": "Ada", "tags": ["admin"]}], "count": "12", "empty": [], "ok": "true", "x": null}"#));
+
+        assert_eq!(yaml, "count: \"12\"\nempty: []\nok: \"true\"\nusers:\n  - name: Ada\n    tags:\n      - admin\nx: null\n");
+    }
+
+    #[test]
+    fn strings_are_quoted_when_they_need_to_be() {
+        for text in ["", "a: b", " a", "-a", "No", "a #b", "a:", "a\nb"] {
+            assert_ne!(to_yaml(&Node::String(text.to_string())), format!("{}\n", text), "{:?}", text);
+        }
+
+        assert_eq!(to_yaml(&doc(r#""plain text""#)), "plain text\n");
+    }
+}
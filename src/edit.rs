@@ -0,0 +1,219 @@
+use std::fmt::Display;
+
+use crate::parser::Node;
+use crate::patch::{Patch, PatchOp};
+use crate::shared::SharedNode;
+
+/// An edit that couldn't be made.
+#[derive(Debug)]
+pub struct EditError {
+    /// The JSON Pointer that was being edited, like `/orders/17`.
+    pub path: String,
+    pub message: String
+}
+
+impl Display for EditError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "at {}: {}", self.path, self.message)
+    }
+}
+
+impl std::error::Error for EditError {}
+
+struct Step {
+    operation: PatchOp,
+    before: SharedNode,
+    after: SharedNode
+}
+
+/// A document being edited, keeping every version so edits can be undone and redone.
+///
+/// Each version shares everything but the changed parts with the one before it
+/// (see `SharedNode`), so keeping them all costs little more than the edits themselves.
+pub struct EditSession {
+    original: SharedNode,
+    current: SharedNode,
+    done: Vec<Step>,
+    undone: Vec<Step>
+}
+
+impl EditSession {
+    pub fn new(document: impl Into<SharedNode>) -> EditSession {
+        let document = document.into();
+
+        EditSession {
+            original: document.clone(),
+            current: document,
+            done: Vec::new(),
+            undone: Vec::new()
+        }
+    }
+
+    /// The document as it was when the session started.
+    pub fn original(&self) -> &SharedNode {
+        &self.original
+    }
+
+    /// The document with every edit that hasn't been undone.
+    pub fn current(&self) -> &SharedNode {
+        &self.current
+    }
+
+    fn record(&mut self, operation: PatchOp, after: SharedNode) {
+        let before = std::mem::replace(&mut self.current, after.clone());
+
+        self.done.push(Step { operation, before, after });
+
+        // A new edit starts a new branch of history, so the old one can't be redone.
+        self.undone.clear();
+    }
+
+    /// Set the value at a JSON Pointer, adding it if it isn't there yet.
+    /// See `SharedNode::set`.
+    pub fn set(&mut self, pointer: &str, value: impl Into<SharedNode>) -> Result<(), EditError> {
+        let value = value.into();
+        let existed = self.current.pointer(pointer).is_some();
+
+        let mut after = self.current.clone();
+
+        if !after.set(pointer, value.clone()) {
+            return Err(EditError {
+                path: pointer.to_string(),
+                message: "There's nowhere to put a value".to_string()
+            });
+        }
+
+        let path = pointer.to_string();
+        let value = Node::from(&value);
+
+        let operation = match existed {
+            true => PatchOp::Replace { path, value },
+            false => PatchOp::Add { path, value }
+        };
+
+        self.record(operation, after);
+
+        Ok(())
+    }
+
+    /// Insert a value at a JSON Pointer, moving later items of an array along.
+    /// See `SharedNode::insert`.
+    pub fn insert(&mut self, pointer: &str, value: impl Into<SharedNode>) -> Result<(), EditError> {
+        let value = value.into();
+        let mut after = self.current.clone();
+
+        if !after.insert(pointer, value.clone()) {
+            return Err(EditError {
+                path: pointer.to_string(),
+                message: "There's nowhere to insert a value".to_string()
+            });
+        }
+
+        self.record(PatchOp::Add { path: pointer.to_string(), value: Node::from(&value) }, after);
+
+        Ok(())
+    }
+
+    /// Remove the value at a JSON Pointer, giving it back.
+    pub fn remove(&mut self, pointer: &str) -> Result<SharedNode, EditError> {
+        let mut after = self.current.clone();
+
+        let Some(removed) = after.remove(pointer) else {
+            return Err(EditError {
+                path: pointer.to_string(),
+                message: "There's no value to remove".to_string()
+            });
+        };
+
+        self.record(PatchOp::Remove { path: pointer.to_string() }, after);
+
+        Ok(removed)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.done.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.undone.is_empty()
+    }
+
+    /// Undo the last edit, giving back `false` if there's nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let Some(step) = self.done.pop() else {
+            return false;
+        };
+
+        self.current = step.before.clone();
+        self.undone.push(step);
+
+        true
+    }
+
+    /// Redo the last edit that was undone, giving back `false` if there's nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        let Some(step) = self.undone.pop() else {
+            return false;
+        };
+
+        self.current = step.after.clone();
+        self.done.push(step);
+
+        true
+    }
+
+    /// The edits that make up the current document, oldest first.
+    pub fn operations(&self) -> impl Iterator<Item = &PatchOp> {
+        self.done.iter().map(|step| &step.operation)
+    }
+
+    /// The edits as a JSON Patch, which turns the original document into the current one.
+    pub fn to_patch(&self) -> Patch {
+        Patch {
+            ops: self.operations().cloned().collect()
+        }
+    }
+
+    /// Finish the session, giving back the current document as a tree of its own.
+    pub fn commit(self) -> Node {
+        Node::from(&self.current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compare::Tolerance;
+    use crate::parser::parse_str;
+
+    #[test]
+    fn edits_come_out_as_a_patch_that_replays_them() {
+        let original = parse_str(r#"{"a": 1, "list": [1, 2]}"#).unwrap();
+        let mut session = EditSession::new(original.clone());
+
+        session.set("/a", Node::from(2)).unwrap();
+        session.set("/b", Node::from("new")).unwrap();
+        session.insert("/list/0", Node::from(0)).unwrap();
+        session.remove("/list/2").unwrap();
+
+        let patch = session.to_patch();
+        assert!(matches!(&patch.ops[..], [PatchOp::Replace { .. }, PatchOp::Add { .. }, PatchOp::Add { .. }, PatchOp::Remove { .. }]));
+
+        let mut replayed = original.clone();
+        patch.apply(&mut replayed).unwrap();
+
+        assert!(replayed.approx_eq(&session.commit(), &Tolerance::exact()));
+    }
+
+    #[test]
+    fn undone_edits_leave_the_patch() {
+        let mut session = EditSession::new(parse_str(r#"{"a": 1}"#).unwrap());
+
+        session.set("/a", Node::from(2)).unwrap();
+        assert!(session.undo());
+
+        assert!(session.to_patch().ops.is_empty());
+        assert!(session.redo());
+        assert_eq!(session.to_patch().ops.len(), 1);
+    }
+}
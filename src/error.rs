@@ -0,0 +1,416 @@
+use std::fmt::{Debug, Display};
+
+use crate::lines::LineIndex;
+use crate::parser::{Node, ObjectMap};
+use crate::serializer::write_escaped;
+use crate::token::{Token, TokenType};
+use crate::utils::{index_path, key_path, Pos};
+
+pub type Result<T> = std::result::Result<T, JsonError>;
+
+/// What kind of problem an error is, with a code that stays the same between
+/// versions even when the message is reworded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    /// Anything that doesn't fit one of the other codes.
+    InvalidSyntax,
+
+    UnterminatedString,
+    NewlineInString,
+    InvalidEscape,
+    InvalidNumber,
+    NumberOutOfRange,
+    UnexpectedCharacter,
+    UnexpectedToken,
+    UnexpectedEof,
+
+    /// A name that isn't `true`, `false` or `null`.
+    InvalidLiteral,
+
+    /// Input that goes past one of the limits set in the options.
+    LimitExceeded,
+
+    /// A NUL character in a string, when the options don't allow them.
+    NulInString,
+
+    /// An object key given twice, when the options don't allow it.
+    DuplicateKey
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::InvalidSyntax => "E000_INVALID_SYNTAX",
+            ErrorCode::UnterminatedString => "E001_UNTERMINATED_STRING",
+            ErrorCode::NewlineInString => "E002_NEWLINE_IN_STRING",
+            ErrorCode::InvalidEscape => "E003_INVALID_ESCAPE",
+            ErrorCode::InvalidNumber => "E004_INVALID_NUMBER",
+            ErrorCode::NumberOutOfRange => "E005_NUMBER_OUT_OF_RANGE",
+            ErrorCode::UnexpectedCharacter => "E006_UNEXPECTED_CHARACTER",
+            ErrorCode::UnexpectedToken => "E007_UNEXPECTED_TOKEN",
+            ErrorCode::UnexpectedEof => "E008_UNEXPECTED_EOF",
+            ErrorCode::InvalidLiteral => "E009_INVALID_LITERAL",
+            ErrorCode::LimitExceeded => "E010_LIMIT_EXCEEDED",
+            ErrorCode::NulInString => "E011_NUL_IN_STRING",
+            ErrorCode::DuplicateKey => "E012_DUPLICATE_KEY"
+        }
+    }
+}
+
+impl Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// A step into an array or object, on the way to where an error happened.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize)
+}
+
+pub struct JsonError {
+    pub code: ErrorCode,
+    pub message: String,
+    pub line: u32,
+    pub column: u32,
+
+    /// The byte offset into the text where the error happened, if it's known.
+    ///
+    /// Unlike the line and column, this stays exact however long the lines are,
+    /// like in huge minified documents, so it's what `location` goes by.
+    pub offset: Option<usize>,
+
+    /// A short suggestion for fixing the problem, like `expected ',' or ']'`.
+    pub hint: Option<String>,
+
+    /// The token types that would have been accepted where the error happened,
+    /// or empty if the error wasn't about an unexpected token.
+    pub expected: Box<[TokenType]>,
+
+    /// Where in the document the error happened, outermost first,
+    /// or empty if it was at the top level.
+    pub path: Box<[PathSegment]>
+}
+
+fn describe_all(types: &[TokenType]) -> Vec<&'static str> {
+    let mut described: Vec<&str> = Vec::new();
+
+    // Numbers show up as both integers and floats, so drop repeats.
+    for t in types {
+        if !described.contains(&t.describe()) {
+            described.push(t.describe());
+        }
+    }
+
+    described
+}
+
+impl JsonError {
+    // Errors only happen once per parse, if at all, so keeping them
+    // out of the way lets the code around them be laid out for the normal case.
+    #[cold]
+    pub fn new(message: impl Into<String>, line: u32, column: u32) -> JsonError {
+        JsonError {
+            code: ErrorCode::InvalidSyntax,
+            message: message.into(),
+            line,
+            column,
+            offset: None,
+            hint: None,
+            expected: Box::new([]),
+            path: Box::new([])
+        }
+    }
+
+    pub fn with_offset(mut self, offset: usize) -> JsonError {
+        self.offset = Some(offset);
+        self
+    }
+
+    pub fn with_code(mut self, code: ErrorCode) -> JsonError {
+        self.code = code;
+        self
+    }
+
+    /// Note that the error happened inside the value of `key`.
+    ///
+    /// This is called as the error makes its way back out of the document,
+    /// so the path is built from the inside out.
+    pub fn in_key(self, key: &str) -> JsonError {
+        self.in_segment(PathSegment::Key(key.to_string()))
+    }
+
+    /// Note that the error happened inside the item at `index` of an array.
+    pub fn in_index(self, index: usize) -> JsonError {
+        self.in_segment(PathSegment::Index(index))
+    }
+
+    fn in_segment(mut self, segment: PathSegment) -> JsonError {
+        let mut path = Vec::with_capacity(self.path.len() + 1);
+        path.push(segment);
+        path.extend(self.path);
+
+        self.path = path.into();
+        self
+    }
+
+    /// The path to where the error happened, like `$.orders[17].items[2].price`.
+    pub fn path_string(&self) -> String {
+        self.path.iter().fold("$".to_string(), |path, segment| match segment {
+            PathSegment::Key(key) => key_path(&path, key),
+            PathSegment::Index(index) => index_path(&path, *index)
+        })
+    }
+
+    pub fn with_hint(mut self, hint: impl Into<String>) -> JsonError {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    /// Build an error located at the current position of the lexer.
+    #[cold]
+    pub fn at_pos(message: impl Into<String>, pos: &Pos) -> JsonError {
+        JsonError::new(message, pos.line, pos.column)
+    }
+
+    /// Build an error located at the start of the given token.
+    #[cold]
+    pub fn at_token(message: impl Into<String>, token: &Token) -> JsonError {
+        JsonError::new(message, token.line(), token.column()).with_offset(token.offset())
+    }
+
+    /// The line and column of the error in `source`, the text it came from.
+    ///
+    /// These are worked out from the byte offset when there is one, so they're
+    /// never cut short on lines too long for the `line` and `column` fields.
+    pub fn location(&self, source: &str) -> (usize, usize) {
+        self.location_in(&LineIndex::new(source))
+    }
+
+    /// The same as `location`, but using an index that's already been built for the text.
+    pub fn location_in(&self, index: &LineIndex) -> (usize, usize) {
+        match self.offset {
+            Some(offset) => index.offset_to_line_col(offset),
+            None => (self.line as usize, self.column as usize)
+        }
+    }
+
+    /// How each of the `expected` token types is written, like `','` or `a string`.
+    pub fn expected_descriptions(&self) -> Vec<&'static str> {
+        describe_all(&self.expected)
+    }
+
+    fn with_expected(code: ErrorCode, expected: &[TokenType], found: &str, token: &Token) -> JsonError {
+        let wanted = describe_all(expected);
+
+        let message = match wanted.as_slice() {
+            [] => format!("expected the end of the input; found {}", found),
+            [one] => format!("expected {}; found {}", one, found),
+            many => format!("expected one of: {}; found {}", many.join(", "), found)
+        };
+
+        let hint = match wanted.split_last() {
+            None => "remove this".to_string(),
+            Some((last, [])) => format!("expected {}", last),
+            Some((last, rest)) => format!("expected {} or {}", rest.join(", "), last)
+        };
+
+        let mut error = JsonError::at_token(message, token).with_code(code).with_hint(hint);
+        error.expected = expected.into();
+        error
+    }
+
+    /// Build an error for finding `found` where only the `expected` token types are allowed,
+    /// like `expected one of: ',', '}'; found ':'`.
+    ///
+    /// An empty `expected` means the input should have ended.
+    pub fn unexpected(expected: &[TokenType], found: &Token) -> JsonError {
+        let shown = match found.tok_type {
+            TokenType::String => format!("{:?}", found.text()),
+            _ => format!("'{}'", found.text())
+        };
+
+        JsonError::with_expected(ErrorCode::UnexpectedToken, expected, &shown, found)
+    }
+
+    /// Build an error for the input ending where the `expected` token types were needed,
+    /// located at the last token that was read.
+    pub fn unexpected_eof(expected: &[TokenType], last: &Token) -> JsonError {
+        JsonError::with_expected(ErrorCode::UnexpectedEof, expected, "the end of the input", last)
+    }
+}
+
+const RED: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[1;34m";
+const BOLD: &str = "\x1b[1m";
+const RESET: &str = "\x1b[0m";
+
+/// The most characters of a line `render_ansi` shows.
+const MAX_SHOWN: usize = 100;
+
+impl JsonError {
+    fn render(&self, source: &str, color: bool) -> String {
+        let paint = |code: &'static str| if color { code } else { "" };
+
+        let mut out = format!(
+            "{}error{}{}: {}{}\n",
+            paint(RED), paint(RESET), paint(BOLD), self.message, paint(RESET)
+        );
+
+        let index = LineIndex::new(source);
+        let (line_no, column) = self.location_in(&index);
+        let line_no = line_no.max(1);
+        let gutter = " ".repeat(line_no.to_string().len());
+
+        out.push_str(&format!("{}{}-->{} line {}, column {}", gutter, paint(BLUE), paint(RESET), line_no, column));
+
+        if !self.path.is_empty() {
+            out.push_str(&format!(", at {}", self.path_string()));
+        }
+
+        out.push('\n');
+
+        // Errors past the end of the text, like an unexpected EOF, have no line to show.
+        let Some(line) = index.line(line_no) else {
+            if let Some(hint) = &self.hint {
+                out.push_str(&format!("{} {}={} hint: {}\n", gutter, paint(BLUE), paint(RESET), hint));
+            }
+
+            return out;
+        };
+
+        // Only show part of very long lines, like in minified documents.
+        let chars: Vec<char> = line.chars().collect();
+        let column = column.clamp(1, chars.len() + 1);
+
+        let start = match chars.len() > MAX_SHOWN {
+            true => column.saturating_sub(MAX_SHOWN / 2).min(chars.len() - MAX_SHOWN),
+            false => 0
+        };
+
+        let end = (start + MAX_SHOWN).min(chars.len());
+
+        let mut shown: String = chars[start..end].iter().collect();
+        let mut skipped = 0;
+
+        if start > 0 {
+            shown.insert(0, '…');
+            skipped = 1;
+        }
+
+        if end < chars.len() {
+            shown.push('…');
+        }
+
+        let line = shown.as_str();
+
+        // Columns count characters, so tabs are kept to line the caret up.
+        let padding: String = line.chars().take(column - 1 - start + skipped).map(
+            |c| if c == '\t' { '\t' } else { ' ' }
+        ).collect();
+
+        out.push_str(&format!("{} {}|{}\n", gutter, paint(BLUE), paint(RESET)));
+        out.push_str(&format!("{}{} |{} {}\n", paint(BLUE), line_no, paint(RESET), line));
+        out.push_str(&format!(
+            "{} {}|{} {}{}^ {}{}\n",
+            gutter, paint(BLUE), paint(RESET), padding, paint(RED), self.hint.as_deref().unwrap_or(""), paint(RESET)
+        ));
+
+        out
+    }
+
+    /// Show the error under the line of `source` it happened on, with a caret
+    /// pointing at it and the hint next to it, like the Rust compiler does.
+    ///
+    /// The output is colored with ANSI escape codes, for printing to a terminal.
+    pub fn render_ansi(&self, source: &str) -> String {
+        self.render(source, true)
+    }
+
+    /// The same as `render_ansi`, but without any colors, for logs.
+    pub fn render_plain(&self, source: &str) -> String {
+        self.render(source, false)
+    }
+}
+
+impl JsonError {
+    /// The error as an object, with `code`, `message`, `path`, `line`, `column`,
+    /// `offset`, `hint` and `expected` keys, where `offset` and `hint` can be `null`.
+    pub fn to_node(&self) -> Node {
+        let mut map = ObjectMap::default();
+
+        map.insert("code".to_string(), Node::from(self.code.as_str()));
+        map.insert("message".to_string(), Node::from(self.message.as_str()));
+        map.insert("path".to_string(), Node::from(self.path_string()));
+        map.insert("line".to_string(), Node::from(self.line));
+        map.insert("column".to_string(), Node::from(self.column));
+        map.insert("offset".to_string(), self.offset.map_or(Node::Null, |offset| Node::Integer(offset as i64)));
+        map.insert("hint".to_string(), self.hint.as_deref().map_or(Node::Null, Node::from));
+        map.insert("expected".to_string(), Node::Array(
+            self.expected_descriptions().into_iter().map(Node::from).collect()
+        ));
+
+        Node::Object(map)
+    }
+
+    /// The error as compact JSON text, with the same keys as `to_node` always in the same order,
+    /// for returning from services or logging somewhere it can be aggregated.
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\"code\":\"");
+        out.push_str(self.code.as_str());
+
+        out.push_str("\",\"message\":");
+        write_escaped(&self.message, &mut out);
+
+        out.push_str(",\"path\":");
+        write_escaped(&self.path_string(), &mut out);
+
+        out.push_str(&format!(",\"line\":{},\"column\":{},\"offset\":", self.line, self.column));
+
+        match self.offset {
+            Some(offset) => out.push_str(&offset.to_string()),
+            None => out.push_str("null")
+        }
+
+        out.push_str(",\"hint\":");
+
+        match &self.hint {
+            Some(hint) => write_escaped(hint, &mut out),
+            None => out.push_str("null")
+        }
+
+        out.push_str(",\"expected\":[");
+
+        for (i, description) in self.expected_descriptions().into_iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+
+            write_escaped(description, &mut out);
+        }
+
+        out.push_str("]}");
+        out
+    }
+}
+
+impl Display for JsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.path.is_empty() {
+            write!(f, "{} at {} [Line: {}, Column: {}]", self.message, self.path_string(), self.line, self.column)
+        }
+        else {
+            write!(f, "{} [Line: {}, Column: {}]", self.message, self.line, self.column)
+        }
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+impl Debug for JsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "JsonError({}, {:?}, line = {}, column = {})", self.code, self.message, self.line, self.column)
+    }
+}
@@ -0,0 +1,804 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::fmt::Display;
+
+use crate::compare::Tolerance;
+use crate::error::JsonError;
+use crate::lexer::tokenise;
+use crate::parser::{parse, parse_array_iter, ArrayIter, Node, ObjectMap};
+
+/// Why a filter couldn't be compiled or run.
+#[derive(Debug)]
+pub enum FilterError {
+    /// The program itself is invalid, with the byte offset of the problem.
+    Syntax(String, usize),
+
+    /// The program couldn't be applied to a value, like indexing a number.
+    Eval(String),
+
+    /// The input isn't valid JSON.
+    Json(JsonError)
+}
+
+impl Display for FilterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Syntax(message, offset) => write!(f, "Invalid filter at offset {}: {}", offset, message),
+            Self::Eval(message) => write!(f, "{}", message),
+            Self::Json(e) => write!(f, "{}", e)
+        }
+    }
+}
+
+impl std::error::Error for FilterError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Json(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+impl From<JsonError> for FilterError {
+    fn from(e: JsonError) -> FilterError {
+        FilterError::Json(e)
+    }
+}
+
+enum Segment {
+    Key(String),
+    Index(i64),
+
+    /// `[]`, which gives back every item of an array or value of an object.
+    Iterate
+}
+
+#[derive(Clone, Copy)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge
+}
+
+enum Condition {
+    Value(Stage),
+    Compare(Stage, CompareOp, Stage),
+    Not(Box<Condition>),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>)
+}
+
+enum Stage {
+    Path(Vec<Segment>),
+    Literal(Node),
+    Select(Box<Condition>),
+    Map(Vec<Stage>),
+
+    /// `{a, b: .x.y}`, which builds an object from the input.
+    Pick(Vec<(String, Stage)>),
+
+    Flatten(Option<usize>),
+    Length,
+    Keys
+}
+
+/// A compiled filter program, in a small subset of `jq`.
+///
+/// Stages are separated by `|`, and each one is one of:
+///
+/// - a path like `.`, `.a.b`, `.a[0]`, `.["some key"]` or `.items[]`
+/// - `select(cond)`, where `cond` compares paths and literals with `==`, `!=`,
+///   `<`, `<=`, `>` or `>=`, combined with `and`, `or` and `not(...)`
+/// - `map(filter)`, applying another filter to every item of an array
+/// - `{a, b: .x.y}`, picking fields into a new object
+/// - `flatten` or `flatten(depth)`, `length` and `keys`
+pub struct Program {
+    stages: Vec<Stage>
+}
+
+struct Compiler<'a> {
+    source: &'a str,
+    offset: usize
+}
+
+impl<'a> Compiler<'a> {
+    fn rest(&self) -> &'a str {
+        &self.source[self.offset..]
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, FilterError> {
+        Err(FilterError::Syntax(message.into(), self.offset))
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.offset += rest.len() - rest.trim_start().len();
+    }
+
+    /// Consume `text` if it comes next, ignoring whitespace before it.
+    fn eat(&mut self, text: &str) -> bool {
+        self.skip_whitespace();
+
+        if self.rest().starts_with(text) {
+            self.offset += text.len();
+            return true;
+        }
+
+        false
+    }
+
+    fn expect(&mut self, text: &str) -> Result<(), FilterError> {
+        match self.eat(text) {
+            true => Ok(()),
+            false => self.error(format!("expected {:?}", text))
+        }
+    }
+
+    fn identifier(&mut self) -> Option<&'a str> {
+        self.skip_whitespace();
+
+        let rest = self.rest();
+        let end = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+
+        if end == 0 || rest.starts_with(|c: char| c.is_ascii_digit()) {
+            return None;
+        }
+
+        self.offset += end;
+
+        Some(&rest[..end])
+    }
+
+    /// Read a JSON literal, like a string, number, `true`, `false` or `null`.
+    fn literal(&mut self) -> Result<Node, FilterError> {
+        self.skip_whitespace();
+
+        let rest = self.rest();
+
+        let end = match rest.strip_prefix('"') {
+            Some(inner) => {
+                let mut escaped = false;
+                let mut end = None;
+
+                for (i, c) in inner.char_indices() {
+                    match c {
+                        '\\' if !escaped => escaped = true,
+                        '"' if !escaped => {
+                            end = Some(i + 2);
+                            break;
+                        },
+                        _ => escaped = false
+                    }
+                }
+
+                match end {
+                    Some(x) => x,
+                    None => return self.error("unterminated string")
+                }
+            },
+            None => rest.find(|c: char| !(c.is_alphanumeric() || matches!(c, '-' | '+' | '.'))).unwrap_or(rest.len())
+        };
+
+        let node = match tokenise(&rest[..end]).and_then(|tokens| parse(&tokens)) {
+            Ok(Node::Empty) | Err(_) => return self.error("expected a literal"),
+            Ok(node) => node
+        };
+
+        self.offset += end;
+
+        Ok(node)
+    }
+
+    fn path(&mut self) -> Result<Vec<Segment>, FilterError> {
+        self.expect(".")?;
+
+        let mut segments = Vec::new();
+
+        // A key can come straight after the leading dot, like `.a`.
+        if let Some(name) = self.identifier() {
+            segments.push(Segment::Key(name.to_string()));
+        }
+
+        loop {
+            if self.rest().starts_with("[") {
+                self.offset += 1;
+
+                if self.eat("]") {
+                    segments.push(Segment::Iterate);
+                    continue;
+                }
+
+                match self.literal()? {
+                    Node::String(key) => segments.push(Segment::Key(key)),
+                    Node::Integer(i) => segments.push(Segment::Index(i)),
+                    _ => return self.error("expected a key or index")
+                }
+
+                self.expect("]")?;
+            }
+            else if self.rest().starts_with('.') && !self.rest().starts_with("..") {
+                self.offset += 1;
+
+                match self.identifier() {
+                    Some(name) => segments.push(Segment::Key(name.to_string())),
+                    None => return self.error("expected a key after '.'")
+                }
+            }
+            else {
+                return Ok(segments);
+            }
+        }
+    }
+
+    fn operand(&mut self) -> Result<Stage, FilterError> {
+        self.skip_whitespace();
+
+        match self.rest().starts_with('.') {
+            true => Ok(Stage::Path(self.path()?)),
+            false => Ok(Stage::Literal(self.literal()?))
+        }
+    }
+
+    fn comparison(&mut self) -> Result<Condition, FilterError> {
+        if self.eat("not(") {
+            let inner = self.condition()?;
+            self.expect(")")?;
+
+            return Ok(Condition::Not(Box::new(inner)));
+        }
+
+        if self.eat("(") {
+            let inner = self.condition()?;
+            self.expect(")")?;
+
+            return Ok(inner);
+        }
+
+        let left = self.operand()?;
+
+        let ops = [
+            ("==", CompareOp::Eq),
+            ("!=", CompareOp::Ne),
+            ("<=", CompareOp::Le),
+            (">=", CompareOp::Ge),
+            ("<", CompareOp::Lt),
+            (">", CompareOp::Gt)
+        ];
+
+        for (text, op) in ops {
+            if self.eat(text) {
+                return Ok(Condition::Compare(left, op, self.operand()?));
+            }
+        }
+
+        Ok(Condition::Value(left))
+    }
+
+    fn keyword(&mut self, word: &str) -> bool {
+        let start = self.offset;
+
+        match self.identifier() {
+            Some(x) if x == word => true,
+            _ => {
+                self.offset = start;
+                false
+            }
+        }
+    }
+
+    fn conjunction(&mut self) -> Result<Condition, FilterError> {
+        let mut left = self.comparison()?;
+
+        while self.keyword("and") {
+            left = Condition::And(Box::new(left), Box::new(self.comparison()?));
+        }
+
+        Ok(left)
+    }
+
+    fn condition(&mut self) -> Result<Condition, FilterError> {
+        let mut left = self.conjunction()?;
+
+        while self.keyword("or") {
+            left = Condition::Or(Box::new(left), Box::new(self.conjunction()?));
+        }
+
+        Ok(left)
+    }
+
+    fn pick(&mut self) -> Result<Stage, FilterError> {
+        let mut fields = Vec::new();
+
+        if self.eat("}") {
+            return Ok(Stage::Pick(fields));
+        }
+
+        loop {
+            self.skip_whitespace();
+
+            let key = match self.rest().starts_with('"') {
+                true => match self.literal()? {
+                    Node::String(s) => s,
+                    _ => return self.error("expected a key")
+                },
+                false => match self.identifier() {
+                    Some(x) => x.to_string(),
+                    None => return self.error("expected a key")
+                }
+            };
+
+            // `{a}` is short for `{a: .a}`.
+            let value = match self.eat(":") {
+                true => self.operand()?,
+                false => Stage::Path(vec![Segment::Key(key.clone())])
+            };
+
+            fields.push((key, value));
+
+            if self.eat("}") {
+                return Ok(Stage::Pick(fields));
+            }
+
+            self.expect(",")?;
+        }
+    }
+
+    fn stage(&mut self) -> Result<Stage, FilterError> {
+        self.skip_whitespace();
+
+        if self.rest().starts_with('.') {
+            return Ok(Stage::Path(self.path()?));
+        }
+
+        if self.eat("{") {
+            return self.pick();
+        }
+
+        let start = self.offset;
+
+        let stage = match self.identifier() {
+            Some("select") => {
+                self.expect("(")?;
+                let condition = self.condition()?;
+                self.expect(")")?;
+
+                Stage::Select(Box::new(condition))
+            },
+            Some("map") => {
+                self.expect("(")?;
+                let inner = self.pipeline()?;
+                self.expect(")")?;
+
+                Stage::Map(inner)
+            },
+            Some("flatten") => match self.eat("(") {
+                true => {
+                    let depth = match self.literal()? {
+                        Node::Integer(n) if n >= 0 => n as usize,
+                        _ => return self.error("expected a depth that isn't negative")
+                    };
+
+                    self.expect(")")?;
+
+                    Stage::Flatten(Some(depth))
+                },
+                false => Stage::Flatten(None)
+            },
+            Some("length") => Stage::Length,
+            Some("keys") => Stage::Keys,
+
+            _ => {
+                self.offset = start;
+                Stage::Literal(self.literal()?)
+            }
+        };
+
+        Ok(stage)
+    }
+
+    fn pipeline(&mut self) -> Result<Vec<Stage>, FilterError> {
+        let mut stages = vec![self.stage()?];
+
+        while self.eat("|") {
+            stages.push(self.stage()?);
+        }
+
+        Ok(stages)
+    }
+}
+
+fn truthy(node: &Node) -> bool {
+    !matches!(node, Node::Null | Node::Bool(false))
+}
+
+fn compare(left: &Node, op: CompareOp, right: &Node) -> bool {
+    let tolerance = Tolerance {
+        float_epsilon: 0.0,
+        ..Tolerance::default()
+    };
+
+    let ordering = match (left, right) {
+        (Node::Integer(a), Node::Integer(b)) => Some(a.cmp(b)),
+
+        // Unsigned integers are all above `i64::MAX`, so both kinds fit in an `i128`.
+        (Node::Integer(_) | Node::Unsigned(_), Node::Integer(_) | Node::Unsigned(_)) => {
+            let as_wide = |n: &Node| match n {
+                Node::Integer(i) => *i as i128,
+                Node::Unsigned(u) => *u as i128,
+                _ => unreachable!()
+            };
+
+            Some(as_wide(left).cmp(&as_wide(right)))
+        },
+        (Node::Integer(_) | Node::Unsigned(_) | Node::Float(_), Node::Integer(_) | Node::Unsigned(_) | Node::Float(_)) => {
+            let as_float = |n: &Node| match n {
+                Node::Integer(i) => *i as f64,
+                Node::Unsigned(u) => *u as f64,
+                Node::Float(f) => *f,
+                _ => unreachable!()
+            };
+
+            as_float(left).partial_cmp(&as_float(right))
+        },
+        (Node::String(a), Node::String(b)) => Some(a.cmp(b)),
+        _ => None
+    };
+
+    match op {
+        CompareOp::Eq => left.approx_eq(right, &tolerance),
+        CompareOp::Ne => !left.approx_eq(right, &tolerance),
+        CompareOp::Lt => ordering == Some(Ordering::Less),
+        CompareOp::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+        CompareOp::Gt => ordering == Some(Ordering::Greater),
+        CompareOp::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal))
+    }
+}
+
+fn flatten_into(arr: &[Node], depth: Option<usize>, out: &mut Vec<Node>) {
+    for item in arr {
+        match (item, depth) {
+            (Node::Array(inner), None) => flatten_into(inner, None, out),
+            (Node::Array(inner), Some(d)) if d > 0 => flatten_into(inner, Some(d - 1), out),
+            _ => out.push(item.clone())
+        }
+    }
+}
+
+const NULL: &Node = &Node::Null;
+
+/// Walk a path through the input by reference, so nothing is copied but what it finds.
+fn walk_path<'a>(segments: &[Segment], input: &'a Node) -> Result<Vec<&'a Node>, FilterError> {
+    let mut values = vec![input];
+
+    for segment in segments {
+        let mut next = Vec::new();
+
+        for value in values {
+            match (segment, value) {
+                (Segment::Key(key), Node::Object(map)) => next.push(map.get(key).unwrap_or(NULL)),
+                (Segment::Index(i), Node::Array(arr)) => {
+                    // Negative indexes count back from the end.
+                    let index = if *i < 0 { arr.len() as i64 + i } else { *i };
+
+                    next.push(usize::try_from(index).ok().and_then(|i| arr.get(i)).unwrap_or(NULL));
+                },
+                (Segment::Iterate, Node::Array(arr)) => next.extend(arr),
+                (Segment::Iterate, Node::Object(map)) => next.extend(map.values()),
+                (Segment::Key(_) | Segment::Index(_), Node::Null) => next.push(NULL),
+
+                (Segment::Key(key), other) => return Err(FilterError::Eval(format!("Cannot get key {:?} of {:?}", key, other))),
+                (Segment::Index(i), other) => return Err(FilterError::Eval(format!("Cannot get index {} of {:?}", i, other))),
+                (Segment::Iterate, other) => return Err(FilterError::Eval(format!("Cannot iterate over {:?}", other)))
+            }
+        }
+
+        values = next;
+    }
+
+    Ok(values)
+}
+
+fn eval_path(segments: &[Segment], input: &Node) -> Result<Vec<Node>, FilterError> {
+    Ok(walk_path(segments, input)?.into_iter().cloned().collect())
+}
+
+/// Evaluate an operand of a condition, borrowing it from the input if it's a path.
+fn eval_operand<'a>(stage: &Stage, input: &'a Node) -> Result<Cow<'a, Node>, FilterError> {
+    match stage {
+        Stage::Path(segments) => Ok(Cow::Borrowed(walk_path(segments, input)?.into_iter().next().unwrap_or(NULL))),
+        _ => Ok(Cow::Owned(eval_one(stage, input)?))
+    }
+}
+
+/// Evaluate a stage that should give back a single value, like an operand.
+fn eval_one(stage: &Stage, input: &Node) -> Result<Node, FilterError> {
+    Ok(eval_stage(stage, input)?.into_iter().next().unwrap_or(Node::Null))
+}
+
+fn eval_condition(condition: &Condition, input: &Node) -> Result<bool, FilterError> {
+    let result = match condition {
+        Condition::Value(stage) => truthy(&*eval_operand(stage, input)?),
+        Condition::Compare(left, op, right) => compare(&*eval_operand(left, input)?, *op, &*eval_operand(right, input)?),
+        Condition::Not(inner) => !eval_condition(inner, input)?,
+        Condition::And(a, b) => eval_condition(a, input)? && eval_condition(b, input)?,
+        Condition::Or(a, b) => eval_condition(a, input)? || eval_condition(b, input)?
+    };
+
+    Ok(result)
+}
+
+fn eval_stage(stage: &Stage, input: &Node) -> Result<Vec<Node>, FilterError> {
+    let output = match stage {
+        Stage::Path(segments) => return eval_path(segments, input),
+        Stage::Literal(node) => vec![node.clone()],
+
+        Stage::Select(condition) => match eval_condition(condition, input)? {
+            true => vec![input.clone()],
+            false => vec![]
+        },
+
+        Stage::Map(stages) => match input {
+            Node::Array(arr) => {
+                let mut body = Vec::with_capacity(arr.len());
+
+                for item in arr {
+                    body.extend(eval_pipeline(stages, item)?);
+                }
+
+                vec![Node::Array(body)]
+            },
+            other => return Err(FilterError::Eval(format!("Cannot map over {:?}", other)))
+        },
+
+        Stage::Pick(fields) => {
+            let mut map = ObjectMap::with_capacity_and_hasher(fields.len(), Default::default());
+
+            for (key, value) in fields {
+                map.insert(key.clone(), eval_one(value, input)?);
+            }
+
+            vec![Node::Object(map)]
+        },
+
+        Stage::Flatten(depth) => match input {
+            Node::Array(arr) => {
+                let mut body = Vec::new();
+                flatten_into(arr, *depth, &mut body);
+
+                vec![Node::Array(body)]
+            },
+            other => return Err(FilterError::Eval(format!("Cannot flatten {:?}", other)))
+        },
+
+        Stage::Length => match input {
+            Node::Array(arr) => vec![Node::Integer(arr.len() as i64)],
+            Node::Object(map) => vec![Node::Integer(map.len() as i64)],
+            Node::String(s) => vec![Node::Integer(s.chars().count() as i64)],
+            Node::Null => vec![Node::Integer(0)],
+            other => return Err(FilterError::Eval(format!("{:?} has no length", other)))
+        },
+
+        Stage::Keys => match input {
+            Node::Object(map) => {
+                let mut keys: Vec<&String> = map.keys().collect();
+                keys.sort();
+
+                vec![Node::Array(keys.into_iter().map(|k| Node::String(k.clone())).collect())]
+            },
+            Node::Array(arr) => vec![Node::Array((0..arr.len() as i64).map(Node::Integer).collect())],
+            other => return Err(FilterError::Eval(format!("{:?} has no keys", other)))
+        }
+    };
+
+    Ok(output)
+}
+
+fn eval_pipeline(stages: &[Stage], input: &Node) -> Result<Vec<Node>, FilterError> {
+    let Some((first, rest)) = stages.split_first() else {
+        return Ok(vec![input.clone()]);
+    };
+
+    let mut values = eval_stage(first, input)?;
+
+    for stage in rest {
+        let mut next = Vec::new();
+
+        for value in &values {
+            next.extend(eval_stage(stage, value)?);
+        }
+
+        values = next;
+    }
+
+    Ok(values)
+}
+
+impl Program {
+    pub fn compile(source: &str) -> Result<Program, FilterError> {
+        let mut compiler = Compiler {
+            source,
+            offset: 0
+        };
+
+        let stages = compiler.pipeline()?;
+
+        compiler.skip_whitespace();
+
+        if !compiler.rest().is_empty() {
+            return compiler.error("unexpected trailing input");
+        }
+
+        Ok(Program {
+            stages
+        })
+    }
+
+    /// Run the program on a document that has already been parsed.
+    pub fn run(&self, input: &Node) -> Result<Vec<Node>, FilterError> {
+        eval_pipeline(&self.stages, input)
+    }
+
+    /// Run the program on JSON text, giving back its outputs one at a time.
+    ///
+    /// If the program starts by iterating over a top-level array, like `.[] | ...`,
+    /// the array's items are parsed and filtered one at a time, so the whole
+    /// document is never held in memory. Otherwise it is parsed all at once.
+    pub fn run_stream<'a>(&'a self, text: &'a str) -> FilterStream<'a> {
+        let streams = text.trim_start().starts_with('[')
+            && matches!(self.stages.first(), Some(Stage::Path(segments)) if matches!(segments.first(), Some(Segment::Iterate)));
+
+        FilterStream {
+            program: self,
+            text,
+            items: if streams { Some(parse_array_iter(text)) } else { None },
+            pending: VecDeque::new(),
+            started: false,
+            done: false
+        }
+    }
+}
+
+/// The outputs of a program run over JSON text, made by `Program::run_stream`.
+pub struct FilterStream<'a> {
+    program: &'a Program,
+    text: &'a str,
+    items: Option<ArrayIter<'a>>,
+    pending: VecDeque<Node>,
+    started: bool,
+    done: bool
+}
+
+impl<'a> FilterStream<'a> {
+    /// Fill up the pending outputs, or return `false` if there's nothing left to run.
+    fn fill(&mut self) -> Result<bool, FilterError> {
+        let stages = &self.program.stages;
+
+        let Some(items) = &mut self.items else {
+            if self.started {
+                return Ok(false);
+            }
+
+            self.started = true;
+
+            let document = parse(&tokenise(self.text)?)?;
+
+            self.pending.extend(eval_pipeline(stages, &document)?);
+
+            return Ok(true);
+        };
+
+        let item = match items.next() {
+            Some(x) => x?,
+            None => return Ok(false)
+        };
+
+        // Apply whatever follows the leading `[]` to this item on its own.
+        let Some(Stage::Path(segments)) = stages.first() else {
+            unreachable!()
+        };
+
+        for value in walk_path(&segments[1..], &item)? {
+            self.pending.extend(eval_pipeline(&stages[1..], value)?);
+        }
+
+        Ok(true)
+    }
+}
+
+impl<'a> Iterator for FilterStream<'a> {
+    type Item = Result<Node, FilterError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(node) = self.pending.pop_front() {
+                return Some(Ok(node));
+            }
+
+            if self.done {
+                return None;
+            }
+
+            match self.fill() {
+                Ok(true) => {},
+                Ok(false) => self.done = true,
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_str;
+
+    fn doc(text: &str) -> Node {
+        parse_str(text).unwrap()
+    }
+
+    fn run(program: &str, input: &str) -> Result<Node, FilterError> {
+        Program::compile(program)?.run(&doc(input)).map(Node::Array)
+    }
+
+    fn assert_outputs(program: &str, input: &str, expected: &str) {
+        let outputs = run(program, input).unwrap();
+        assert!(outputs.approx_eq(&doc(expected), &Tolerance::exact()), "{} gave {:?}", program, outputs);
+    }
+
+    #[test]
+    fn paths_walk_the_input() {
+        let input = r#"{"a": {"b": [10, 20, 30]}, "k e y": 1}"#;
+
+        assert_outputs(".a.b[0]", input, "[10]");
+        assert_outputs(".a.b[-1]", input, "[30]");
+        assert_outputs(".a.b[5]", input, "[null]");
+        assert_outputs(".missing.x", input, "[null]");
+        assert_outputs(r#".["k e y"]"#, input, "[1]");
+        assert_outputs(".a.b[]", input, "[10, 20, 30]");
+        assert!(run(".a.b.c", input).is_err());
+    }
+
+    #[test]
+    fn select_compares_numbers_by_value() {
+        let input = r#"[{"n": 1}, {"n": 2.0}, {"n": 3}, {"n": "2"}]"#;
+
+        assert_outputs(".[] | select(.n == 2)", input, r#"[{"n": 2.0}]"#);
+        assert_outputs(".[] | select(.n >= 2 and not(.n == 3)) | .n", input, "[2.0]");
+        assert_outputs(".[] | select(.n < 2 or .n == \"2\") | .n", input, r#"[1, "2"]"#);
+    }
+
+    #[test]
+    fn builtins_work_on_arrays_and_objects() {
+        assert_outputs("map(. | length)", r#"["ab", [1, 2, 3], {}]"#, "[[2, 3, 0]]");
+        assert_outputs("flatten", "[1, [2, [3]]]", "[[1, 2, 3]]");
+        assert_outputs("flatten(1)", "[1, [2, [3]]]", "[[1, 2, [3]]]");
+        assert_outputs("keys", r#"{"b": 1, "a": 2}"#, r#"[["a", "b"]]"#);
+        assert_outputs("{a, c: .b.x}", r#"{"a": 1, "b": {"x": 2}}"#, r#"[{"a": 1, "c": 2}]"#);
+        assert!(run("length", "true").is_err());
+    }
+
+    #[test]
+    fn bad_programs_say_where() {
+        assert!(matches!(Program::compile(".a |"), Err(FilterError::Syntax(_, 4))));
+        assert!(matches!(Program::compile("select(.a"), Err(FilterError::Syntax(..))));
+    }
+
+    #[test]
+    fn streaming_gives_the_same_outputs() {
+        let program = Program::compile(".[] | select(.a > 1) | .a").unwrap();
+        let text = r#"[{"a": 1}, {"a": 2}, {"a": 3}]"#;
+
+        let streamed: Vec<Node> = program.run_stream(text).collect::<Result<_, _>>().unwrap();
+        let whole = program.run(&doc(text)).unwrap();
+
+        assert!(Node::Array(streamed).approx_eq(&Node::Array(whole), &Tolerance::exact()));
+        assert!(matches!(program.run_stream("[@]").next(), Some(Err(FilterError::Json(_)))));
+    }
+}
@@ -0,0 +1,137 @@
+use std::fmt::Display;
+
+use crate::error::ErrorCode;
+
+/// What parsing a fixture with the default options should do.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Expected {
+    Valid,
+    Error(ErrorCode)
+}
+
+/// A snippet of JSON text from the conformance corpus, and what should happen to it.
+#[derive(Debug)]
+pub struct Fixture {
+    /// A short name for the fixture, like `trailing_comma_in_array`.
+    pub name: &'static str,
+    pub text: &'static str,
+    pub expected: Expected
+}
+
+impl Fixture {
+    /// Check what a parser did with the fixture, where `error_code` is the code
+    /// it failed with, as given by `ErrorCode::as_str`, or `None` if it parsed.
+    pub fn accepts(&self, error_code: Option<&str>) -> bool {
+        match (self.expected, error_code) {
+            (Expected::Valid, None) => true,
+            (Expected::Error(code), Some(found)) => code.as_str() == found,
+            _ => false
+        }
+    }
+}
+
+const fn valid(name: &'static str, text: &'static str) -> Fixture {
+    Fixture { name, text, expected: Expected::Valid }
+}
+
+const fn invalid(name: &'static str, text: &'static str, code: ErrorCode) -> Fixture {
+    Fixture { name, text, expected: Expected::Error(code) }
+}
+
+/// The grammar corpus, for checking that bindings to the parser, like over FFI or WASM,
+/// accept and reject exactly what the parser itself does with the default options.
+pub const FIXTURES: &[Fixture] = &[
+    valid("null", "null"),
+    valid("true", "true"),
+    valid("false", "false"),
+    valid("zero", "0"),
+    valid("negative_zero", "-0"),
+    valid("integer", "42"),
+    valid("negative_integer", "-42"),
+    valid("largest_integer", "9223372036854775807"),
+    valid("largest_unsigned_integer", "18446744073709551615"),
+    valid("float", "1.5"),
+    valid("exponent", "1e10"),
+    valid("exponent_with_sign", "-1.5E+10"),
+    valid("negative_exponent", "1e-10"),
+    valid("empty_string", r#""""#),
+    valid("short_escapes", r#""\"\\\/\b\f\n\r\t""#),
+    valid("unicode_escape", r#""\u00e9""#),
+    valid("surrogate_pair", r#""\ud83d\ude00""#),
+    valid("escaped_nul", r#""\u0000""#),
+    valid("raw_unicode", "\"caf\u{e9} \u{1f600}\""),
+    valid("empty_array", "[]"),
+    valid("empty_object", "{}"),
+    valid("nested", r#"{"a": [1, {"b": [true, null]}], "c": {}}"#),
+    valid("deep_nesting", "[[[[[[[[[[]]]]]]]]]]"),
+    valid("surrounding_whitespace", " \t\r\n [ 1 , 2 ] \n"),
+    valid("duplicate_keys", r#"{"a": 1, "a": 2}"#),
+
+    invalid("unterminated_string", r#""abc"#, ErrorCode::UnterminatedString),
+    invalid("newline_in_string", "\"a\nb\"", ErrorCode::NewlineInString),
+    invalid("unknown_escape", r#""\q""#, ErrorCode::InvalidEscape),
+    invalid("short_unicode_escape", r#""\u12""#, ErrorCode::InvalidEscape),
+    invalid("lone_high_surrogate", r#""\ud800""#, ErrorCode::InvalidEscape),
+    invalid("lone_low_surrogate", r#""\udc00""#, ErrorCode::InvalidEscape),
+    invalid("trailing_decimal_point", "1.", ErrorCode::InvalidNumber),
+    invalid("empty_exponent", "1e", ErrorCode::InvalidNumber),
+    invalid("lone_minus", "-", ErrorCode::InvalidNumber),
+    invalid("integer_too_large", "18446744073709551616", ErrorCode::NumberOutOfRange),
+    invalid("negative_integer_too_large", "-9223372036854775809", ErrorCode::NumberOutOfRange),
+    invalid("leading_decimal_point", ".5", ErrorCode::UnexpectedCharacter),
+    invalid("leading_plus", "+1", ErrorCode::UnexpectedCharacter),
+    invalid("single_quotes", "{'a': 1}", ErrorCode::UnexpectedCharacter),
+    invalid("line_comment", "// comment\n1", ErrorCode::UnexpectedCharacter),
+    invalid("block_comment", "/* comment */ 1", ErrorCode::UnexpectedCharacter),
+    invalid("cut_off_true", "tru", ErrorCode::InvalidLiteral),
+    invalid("capitalised_null", "Null", ErrorCode::InvalidLiteral),
+    invalid("nan", "NaN", ErrorCode::InvalidLiteral),
+    invalid("infinity", "Infinity", ErrorCode::InvalidLiteral),
+    invalid("trailing_comma_in_array", "[1,]", ErrorCode::UnexpectedToken),
+    invalid("trailing_comma_in_object", r#"{"a": 1,}"#, ErrorCode::UnexpectedToken),
+    invalid("missing_comma", "[1 2]", ErrorCode::UnexpectedToken),
+    invalid("missing_colon", r#"{"a" 1}"#, ErrorCode::UnexpectedToken),
+    invalid("unquoted_key", "{a: 1}", ErrorCode::UnexpectedToken),
+    invalid("number_key", "{1: 2}", ErrorCode::UnexpectedToken),
+    invalid("leading_comma", "[,1]", ErrorCode::UnexpectedToken),
+    invalid("closing_bracket", "]", ErrorCode::UnexpectedToken),
+    invalid("mismatched_brackets", "[}", ErrorCode::UnexpectedToken),
+    invalid("trailing_value", "[1] 2", ErrorCode::UnexpectedToken),
+    invalid("unclosed_array", "[1", ErrorCode::UnexpectedEof),
+    invalid("unclosed_object", r#"{"a": 1"#, ErrorCode::UnexpectedEof),
+    invalid("missing_value", r#"{"a":"#, ErrorCode::UnexpectedEof)
+];
+
+/// A fixture that a parser didn't handle as expected.
+#[derive(Debug)]
+pub struct Failure {
+    pub fixture: &'static Fixture,
+
+    /// The error code the parser gave, or `None` if it parsed the text.
+    pub found: Option<String>
+}
+
+impl Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let expected = match self.fixture.expected {
+            Expected::Valid => "to parse".to_string(),
+            Expected::Error(code) => code.as_str().to_string()
+        };
+
+        let found = self.found.as_deref().unwrap_or("it parsed");
+
+        write!(f, "{}: expected {}, found {}", self.fixture.name, expected, found)
+    }
+}
+
+/// Run every fixture through `parse`, giving back the ones it got wrong.
+///
+/// `parse` should give back `None` when the text parses, and the error code
+/// as given by `ErrorCode::as_str` when it doesn't, like `Some("E003_INVALID_ESCAPE")`.
+pub fn run(mut parse: impl FnMut(&str) -> Option<String>) -> Vec<Failure> {
+    FIXTURES.iter().filter_map(|fixture| {
+        let found = parse(fixture.text);
+
+        (!fixture.accepts(found.as_deref())).then_some(Failure { fixture, found })
+    }).collect()
+}
@@ -0,0 +1,756 @@
+use std::fmt::Display;
+use std::io::{self, BufRead, Read};
+
+use crate::error::JsonError;
+use crate::lexer::tokenise;
+use crate::parser::{parse, Node};
+
+/// Why a frame couldn't be read.
+#[derive(Debug)]
+pub enum FramingError {
+    Io(io::Error),
+
+    /// A frame's contents aren't valid UTF-8.
+    Utf8,
+
+    Json(JsonError),
+
+    /// A frame said it was longer than the reader allows.
+    TooLarge(usize),
+
+    /// The stream ended partway through a frame.
+    Truncated
+}
+
+impl Display for FramingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Could not read frame: {}", e),
+            Self::Utf8 => write!(f, "Frame is not valid UTF-8"),
+            Self::Json(e) => write!(f, "Frame is not valid JSON: {}", e),
+            Self::TooLarge(len) => write!(f, "Frame of {} bytes is larger than allowed", len),
+            Self::Truncated => write!(f, "Stream ended partway through a frame")
+        }
+    }
+}
+
+impl std::error::Error for FramingError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            Self::Json(e) => Some(e),
+            _ => None
+        }
+    }
+}
+
+fn parse_frame(bytes: Vec<u8>) -> Result<Node, FramingError> {
+    let text = String::from_utf8(bytes).map_err(|_| FramingError::Utf8)?;
+
+    tokenise(&text).and_then(|tokens| parse(&tokens)).map_err(FramingError::Json)
+}
+
+/// Reads frames made of a 4-byte big-endian length followed by that many bytes of JSON.
+pub struct LengthPrefixedReader<R: Read> {
+    reader: R,
+
+    /// The largest frame that will be read, so a corrupt length can't allocate gigabytes.
+    pub max_frame_len: usize,
+
+    done: bool
+}
+
+impl<R: Read> LengthPrefixedReader<R> {
+    pub fn new(reader: R) -> LengthPrefixedReader<R> {
+        LengthPrefixedReader {
+            reader,
+            max_frame_len: 16 * 1024 * 1024,
+            done: false
+        }
+    }
+
+    /// Read the next frame, or `None` if the stream ended cleanly between frames.
+    fn read_frame(&mut self) -> Result<Option<Node>, FramingError> {
+        let mut prefix = [0u8; 4];
+        let mut filled = 0;
+
+        while filled < prefix.len() {
+            match self.reader.read(&mut prefix[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err(FramingError::Truncated),
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(e) => return Err(FramingError::Io(e))
+            }
+        }
+
+        let len = u32::from_be_bytes(prefix) as usize;
+
+        if len > self.max_frame_len {
+            return Err(FramingError::TooLarge(len));
+        }
+
+        let mut body = vec![0u8; len];
+
+        match self.reader.read_exact(&mut body) {
+            Ok(()) => {},
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Err(FramingError::Truncated),
+            Err(e) => return Err(FramingError::Io(e))
+        }
+
+        parse_frame(body).map(Some)
+    }
+}
+
+impl<R: Read> Iterator for LengthPrefixedReader<R> {
+    type Item = Result<Node, FramingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let result = self.read_frame();
+
+        // Only bad JSON can be skipped over, since anything else
+        // means we no longer know where the next frame starts.
+        if !matches!(result, Ok(Some(_)) | Err(FramingError::Json(_) | FramingError::Utf8)) {
+            self.done = true;
+        }
+
+        result.transpose()
+    }
+}
+
+const RECORD_SEPARATOR: u8 = 0x1e;
+
+/// Reads JSON Text Sequences (RFC 7464), where each value starts with
+/// a `\x1e` record separator and ends with a newline.
+pub struct JsonSeqReader<R: BufRead> {
+    reader: R,
+    started: bool,
+    done: bool
+}
+
+impl<R: BufRead> JsonSeqReader<R> {
+    pub fn new(reader: R) -> JsonSeqReader<R> {
+        JsonSeqReader {
+            reader,
+            started: false,
+            done: false
+        }
+    }
+
+    /// Read the bytes up to the next record separator, or `None` at the end of the stream.
+    fn read_record(&mut self) -> Result<Option<Vec<u8>>, FramingError> {
+        let mut record = Vec::new();
+
+        // Skip anything before the first separator, as the RFC says to.
+        if !self.started {
+            self.started = true;
+            self.reader.read_until(RECORD_SEPARATOR, &mut record).map_err(FramingError::Io)?;
+
+            if record.last() != Some(&RECORD_SEPARATOR) {
+                return Ok(None);
+            }
+
+            record.clear();
+        }
+
+        let read = self.reader.read_until(RECORD_SEPARATOR, &mut record).map_err(FramingError::Io)?;
+
+        if read == 0 {
+            return Ok(None);
+        }
+
+        if record.last() == Some(&RECORD_SEPARATOR) {
+            record.pop();
+        }
+
+        Ok(Some(record))
+    }
+}
+
+impl<R: BufRead> Iterator for JsonSeqReader<R> {
+    type Item = Result<Node, FramingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let record = match self.read_record() {
+                Ok(Some(x)) => x,
+                Ok(None) => {
+                    self.done = true;
+                    return None;
+                },
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            };
+
+            // Empty records, like from two separators in a row, are skipped.
+            if record.iter().all(u8::is_ascii_whitespace) {
+                continue;
+            }
+
+            // A record that doesn't end in a newline was cut off while being written.
+            if record.last() != Some(&b'\n') {
+                return Some(Err(FramingError::Truncated));
+            }
+
+            return Some(parse_frame(record));
+        }
+
+        None
+    }
+}
+
+/// An event from a Server-Sent Events stream whose data was JSON.
+#[derive(Debug)]
+pub struct SseEvent {
+    /// The `event:` field, if there was one.
+    pub event: Option<String>,
+
+    /// The last `id:` seen in the stream so far, if any.
+    pub id: Option<String>,
+
+    /// All the event's `data:` lines joined with newlines, then parsed.
+    pub data: Node
+}
+
+/// Reads Server-Sent Events and parses each event's data as JSON.
+///
+/// Events with no data are skipped, and a `data: [DONE]` event ends the stream,
+/// as many streaming APIs send one at the end. Lines must end in `\n` or `\r\n`.
+pub struct SseReader<R: BufRead> {
+    reader: R,
+    last_id: Option<String>,
+    done: bool
+}
+
+impl<R: BufRead> SseReader<R> {
+    pub fn new(reader: R) -> SseReader<R> {
+        SseReader {
+            reader,
+            last_id: None,
+            done: false
+        }
+    }
+
+    /// Read the next line without its line ending, or `None` at the end of the stream.
+    fn read_line(&mut self) -> Result<Option<String>, FramingError> {
+        let mut line = Vec::new();
+
+        if self.reader.read_until(b'\n', &mut line).map_err(FramingError::Io)? == 0 {
+            return Ok(None);
+        }
+
+        if line.last() == Some(&b'\n') {
+            line.pop();
+        }
+
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+
+        String::from_utf8(line).map(Some).map_err(|_| FramingError::Utf8)
+    }
+
+    fn read_event(&mut self) -> Result<Option<SseEvent>, FramingError> {
+        let mut event = None;
+        let mut data: Option<String> = None;
+
+        loop {
+            let line = match self.read_line()? {
+                Some(x) => x,
+
+                // The last event still counts if the stream ends without a blank line after it.
+                None if data.is_some() => String::new(),
+                None => return Ok(None)
+            };
+
+            // A blank line sends the event, if it had any data.
+            if line.is_empty() {
+                let Some(text) = data.take() else {
+                    event = None;
+                    continue;
+                };
+
+                if text == "[DONE]" {
+                    return Ok(None);
+                }
+
+                let node = tokenise(&text).and_then(|tokens| parse(&tokens)).map_err(FramingError::Json)?;
+
+                return Ok(Some(SseEvent {
+                    event,
+                    id: self.last_id.clone(),
+                    data: node
+                }));
+            }
+
+            // Lines starting with a colon are comments.
+            if line.starts_with(':') {
+                continue;
+            }
+
+            let (field, value) = match line.split_once(':') {
+                Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+                None => (line.as_str(), "")
+            };
+
+            match field {
+                "data" => match &mut data {
+                    Some(text) => {
+                        text.push('\n');
+                        text.push_str(value);
+                    },
+                    None => data = Some(value.to_string())
+                },
+                "event" => event = Some(value.to_string()),
+                "id" if !value.contains('\0') => self.last_id = Some(value.to_string()),
+
+                // Anything else, like `retry`, doesn't matter here.
+                _ => {}
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for SseReader<R> {
+    type Item = Result<SseEvent, FramingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let result = self.read_event();
+
+        // Events are self-contained, so only a stream error stops reading.
+        if !matches!(result, Ok(Some(_)) | Err(FramingError::Json(_) | FramingError::Utf8)) {
+            self.done = true;
+        }
+
+        result.transpose()
+    }
+}
+
+/// Reads newline-delimited JSON (NDJSON), giving back the text of each line.
+///
+/// Blank lines are skipped, and lines may end in `\n` or `\r\n`. Like `ArrayItemReader`,
+/// lines aren't checked to be valid JSON until they're parsed.
+pub struct NdjsonReader<R: BufRead> {
+    reader: R,
+    done: bool
+}
+
+impl<R: BufRead> NdjsonReader<R> {
+    pub fn new(reader: R) -> NdjsonReader<R> {
+        NdjsonReader {
+            reader,
+            done: false
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for NdjsonReader<R> {
+    type Item = Result<String, FramingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let mut line = Vec::new();
+
+            match self.reader.read_until(b'\n', &mut line) {
+                Ok(0) => self.done = true,
+                Ok(_) if line.iter().all(u8::is_ascii_whitespace) => {},
+                Ok(_) => {
+                    while line.last().is_some_and(u8::is_ascii_whitespace) {
+                        line.pop();
+                    }
+
+                    return Some(String::from_utf8(line).map_err(|_| FramingError::Utf8));
+                },
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(FramingError::Io(e)));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Reads JSON values written one after another, like `{"a":1}{"a":2}` or `1 2 3`,
+/// giving back the text of each. NDJSON is read the same way, since its values
+/// are just separated by newlines.
+///
+/// A value is given back as soon as its last byte has been read, without waiting
+/// for any more input, so it works on streams that stay open like `tail -f`.
+/// Numbers, `true`, `false` and `null` can't be known to have ended until the byte
+/// after them, so they wait for the whitespace after them. Like `ArrayItemReader`,
+/// only brackets and strings are tracked, and values aren't checked to be valid
+/// JSON until they're parsed, so one broken value doesn't stop the ones after it being read.
+pub struct ConcatenatedReader<R: BufRead> {
+    reader: R,
+    done: bool,
+    buffered: bool
+}
+
+impl<R: BufRead> ConcatenatedReader<R> {
+    pub fn new(reader: R) -> ConcatenatedReader<R> {
+        ConcatenatedReader {
+            reader,
+            done: false,
+            buffered: false
+        }
+    }
+
+    /// Whether more input had already been read past the last value given back,
+    /// so reading the next one won't have to wait. Output can be flushed when this
+    /// is `false`, so it's written in batches while input is coming in fast but
+    /// straight away when it's about to wait.
+    pub fn has_buffered_input(&self) -> bool {
+        self.buffered
+    }
+
+    /// Look at the buffered input, reading more if there's none, or an empty slice at the end of the stream.
+    fn fill(&mut self) -> Result<&[u8], FramingError> {
+        loop {
+            match self.reader.fill_buf() {
+                Ok(_) => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(e) => return Err(FramingError::Io(e))
+            }
+        }
+
+        // Asking again just gives back what's buffered, which the borrow checker
+        // can't see would be fine to return from inside the loop.
+        self.reader.fill_buf().map_err(FramingError::Io)
+    }
+
+    fn read_value(&mut self) -> Result<Option<String>, FramingError> {
+        // Skip whitespace before the value.
+        let first = loop {
+            let buf = self.fill()?;
+            let Some(i) = buf.iter().position(|b| !b.is_ascii_whitespace()) else {
+                if buf.is_empty() {
+                    return Ok(None);
+                }
+
+                let len = buf.len();
+                self.reader.consume(len);
+                continue;
+            };
+
+            let first = buf[i];
+            self.reader.consume(i);
+            break first;
+        };
+
+        let scalar = !matches!(first, b'{' | b'[' | b'"');
+
+        let mut value = Vec::new();
+        let mut depth = 0usize;
+        let mut in_string = false;
+        let mut escaped = false;
+
+        loop {
+            let buf = self.fill()?;
+
+            if buf.is_empty() {
+                // A scalar can end at the end of the stream, but nothing else can.
+                return match scalar && !value.is_empty() {
+                    true => String::from_utf8(value).map(Some).map_err(|_| FramingError::Utf8),
+                    false => Err(FramingError::Truncated)
+                };
+            }
+
+            let mut end = None;
+
+            for (i, &byte) in buf.iter().enumerate() {
+                if scalar {
+                    if byte.is_ascii_whitespace() || matches!(byte, b'{' | b'[' | b'"') {
+                        end = Some(i);
+                        break;
+                    }
+
+                    continue;
+                }
+
+                if in_string {
+                    match (escaped, byte) {
+                        (true, _) => escaped = false,
+                        (false, b'\\') => escaped = true,
+                        (false, b'"') => in_string = false,
+                        _ => {}
+                    }
+
+                    if !in_string && depth == 0 {
+                        end = Some(i + 1);
+                        break;
+                    }
+
+                    continue;
+                }
+
+                match byte {
+                    b'"' => in_string = true,
+                    b'[' | b'{' => depth += 1,
+                    b']' | b'}' => {
+                        depth = depth.saturating_sub(1);
+
+                        if depth == 0 {
+                            end = Some(i + 1);
+                            break;
+                        }
+                    },
+                    _ => {}
+                }
+            }
+
+            let Some(end) = end else {
+                let len = buf.len();
+                value.extend_from_slice(buf);
+                self.reader.consume(len);
+                continue;
+            };
+
+            value.extend_from_slice(&buf[..end]);
+
+            // Take the whitespace after the value too, if it's already been read,
+            // so only another value counts as buffered input.
+            let rest = buf[end..].iter().take_while(|b| b.is_ascii_whitespace()).count();
+            self.buffered = end + rest < buf.len();
+            self.reader.consume(end + rest);
+
+            return String::from_utf8(value).map(Some).map_err(|_| FramingError::Utf8);
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for ConcatenatedReader<R> {
+    type Item = Result<String, FramingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let result = self.read_value();
+
+        // Only failing to read at all is the end, since a bad value still has an end to carry on from.
+        if matches!(result, Err(FramingError::Io(_) | FramingError::Truncated) | Ok(None)) {
+            self.done = true;
+        }
+
+        result.transpose()
+    }
+}
+
+/// Reads the items of one big top-level array one at a time, giving back the
+/// text of each, so arrays too big to fit in memory can still be worked through.
+///
+/// Only the nesting of brackets and strings is tracked to find where each item ends,
+/// so items aren't checked to be valid JSON until they're parsed.
+pub struct ArrayItemReader<R: BufRead> {
+    reader: R,
+    started: bool,
+    done: bool,
+
+    // Where the reader is, for errors.
+    line: u32,
+    column: u32,
+    offset: usize
+}
+
+impl<R: BufRead> ArrayItemReader<R> {
+    pub fn new(reader: R) -> ArrayItemReader<R> {
+        ArrayItemReader {
+            reader,
+            started: false,
+            done: false,
+            line: 1,
+            column: 1,
+            offset: 0
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> FramingError {
+        FramingError::Json(JsonError::new(message, self.line, self.column).with_offset(self.offset))
+    }
+
+    /// Look at the next byte without taking it, or `None` at the end of the stream.
+    fn peek(&mut self) -> Result<Option<u8>, FramingError> {
+        loop {
+            match self.reader.fill_buf() {
+                Ok(buf) => return Ok(buf.first().copied()),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+                Err(e) => return Err(FramingError::Io(e))
+            }
+        }
+    }
+
+    /// Move past bytes that have been looked at with `fill_buf`.
+    fn advance(&mut self, len: usize) {
+        let Ok(buf) = self.reader.fill_buf() else {
+            return;
+        };
+
+        for &byte in &buf[..len] {
+            match byte {
+                b'\n' => {
+                    self.line += 1;
+                    self.column = 1;
+                },
+
+                // Continuation bytes are part of the character before them.
+                0x80..=0xbf => {},
+                _ => self.column += 1
+            }
+        }
+
+        self.offset += len;
+        self.reader.consume(len);
+    }
+
+    /// Skip whitespace, giving back the byte after it.
+    fn skip_whitespace(&mut self) -> Result<Option<u8>, FramingError> {
+        while let Some(byte) = self.peek()? {
+            if !byte.is_ascii_whitespace() {
+                return Ok(Some(byte));
+            }
+
+            self.advance(1);
+        }
+
+        Ok(None)
+    }
+
+    /// Read the text of the next item, or `None` once the array is closed.
+    fn read_item(&mut self) -> Result<Option<String>, FramingError> {
+        if !self.started {
+            self.started = true;
+
+            match self.skip_whitespace()? {
+                Some(b'[') => self.advance(1),
+                Some(_) => return Err(self.error("Expected a top-level array")),
+                None => return Err(FramingError::Truncated)
+            }
+
+            if self.skip_whitespace()? == Some(b']') {
+                self.advance(1);
+                return self.finish().map(|_| None);
+            }
+        }
+
+        if self.skip_whitespace()?.is_none() {
+            return Err(FramingError::Truncated);
+        }
+
+        let mut item = Vec::new();
+        let mut depth = 0usize;
+        let mut in_string = false;
+        let mut escaped = false;
+
+        // Go through whatever's buffered at once, rather than a byte at a time.
+        loop {
+            let buf = loop {
+                match self.reader.fill_buf() {
+                    Ok(buf) => break buf,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+                    Err(e) => return Err(FramingError::Io(e))
+                }
+            };
+
+            if buf.is_empty() {
+                return Err(FramingError::Truncated);
+            }
+
+            let mut end = None;
+
+            for (i, &byte) in buf.iter().enumerate() {
+                if in_string {
+                    match (escaped, byte) {
+                        (true, _) => escaped = false,
+                        (false, b'\\') => escaped = true,
+                        (false, b'"') => in_string = false,
+                        _ => {}
+                    }
+
+                    continue;
+                }
+
+                match byte {
+                    b'"' => in_string = true,
+                    b'[' | b'{' => depth += 1,
+
+                    b',' | b']' if depth == 0 => {
+                        end = Some(i);
+                        break;
+                    },
+
+                    b']' | b'}' => depth = depth.saturating_sub(1),
+                    _ => {}
+                }
+            }
+
+            let Some(end) = end else {
+                let len = buf.len();
+                item.extend_from_slice(buf);
+                self.advance(len);
+                continue;
+            };
+
+            item.extend_from_slice(&buf[..end]);
+            let closing = buf[end] == b']';
+            self.advance(end);
+
+            while item.last().is_some_and(u8::is_ascii_whitespace) {
+                item.pop();
+            }
+
+            if item.is_empty() {
+                return Err(self.error("Expected an item"));
+            }
+
+            self.advance(1);
+
+            if closing {
+                self.finish()?;
+            }
+
+            return String::from_utf8(item).map(Some).map_err(|_| FramingError::Utf8);
+        }
+    }
+
+    /// Check that nothing but whitespace comes after the array.
+    fn finish(&mut self) -> Result<(), FramingError> {
+        self.done = true;
+
+        match self.skip_whitespace()? {
+            Some(_) => Err(self.error("Expected the end of the input after the array")),
+            None => Ok(())
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for ArrayItemReader<R> {
+    type Item = Result<String, FramingError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let result = self.read_item();
+
+        // A broken array can't be read any further, since where the next item starts is unknown.
+        if result.is_err() {
+            self.done = true;
+        }
+
+        result.transpose()
+    }
+}
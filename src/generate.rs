@@ -0,0 +1,282 @@
+use crate::parser::{Node, ObjectMap};
+
+/// A small, fast pseudo-random generator (SplitMix64), so the same seed
+/// always gives the same output on every platform.
+pub(crate) struct Rng(pub(crate) u64);
+
+impl Rng {
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+
+        z ^ (z >> 31)
+    }
+
+    /// A number from `0` up to and including `max`.
+    pub(crate) fn up_to(&mut self, max: usize) -> usize {
+        (self.next_u64() % (max as u64 + 1)) as usize
+    }
+
+    fn chance(&mut self, percent: u64) -> bool {
+        self.next_u64() % 100 < percent
+    }
+}
+
+pub struct GenOptions {
+    /// How deeply arrays and objects can be nested.
+    pub max_depth: usize,
+
+    /// The most keys an object can have.
+    pub max_keys: usize,
+
+    /// The most items an array can have.
+    pub max_array_len: usize,
+
+    /// The longest a string or key can be, in characters.
+    pub max_string_len: usize,
+
+    /// The characters strings and keys are made from.
+    pub string_alphabet: String
+}
+
+impl Default for GenOptions {
+    fn default() -> GenOptions {
+        GenOptions {
+            max_depth: 4,
+            max_keys: 8,
+            max_array_len: 8,
+            max_string_len: 16,
+            string_alphabet: "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789 _-".to_string()
+        }
+    }
+}
+
+struct Generator<'a> {
+    rng: Rng,
+    options: &'a GenOptions,
+    alphabet: Vec<char>
+}
+
+impl<'a> Generator<'a> {
+    fn string(&mut self, min_len: usize) -> String {
+        if self.alphabet.is_empty() {
+            return String::new();
+        }
+
+        let len = min_len.max(self.rng.up_to(self.options.max_string_len));
+
+        (0..len).map(|_| self.alphabet[self.rng.up_to(self.alphabet.len() - 1)]).collect()
+    }
+
+    fn scalar(&mut self) -> Node {
+        match self.rng.up_to(4) {
+            0 => Node::Null,
+            1 => Node::Bool(self.rng.chance(50)),
+
+            // Mostly small numbers, with the odd one from the whole range.
+            2 if self.rng.chance(90) => Node::Integer(self.rng.up_to(2000) as i64 - 1000),
+            2 => Node::Integer(self.rng.next_u64() as i64),
+
+            3 => Node::Float((self.rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64 * 2000.0 - 1000.0),
+            _ => Node::String(self.string(0))
+        }
+    }
+
+    fn container(&mut self, depth: usize) -> Node {
+        if self.rng.chance(50) {
+            let len = self.rng.up_to(self.options.max_array_len);
+
+            return Node::Array((0..len).map(|_| self.node(depth + 1)).collect());
+        }
+
+        let count = self.rng.up_to(self.options.max_keys);
+        let mut map = ObjectMap::with_capacity_and_hasher(count, Default::default());
+
+        for _ in 0..count {
+            let key = self.string(1);
+            let value = self.node(depth + 1);
+
+            map.insert(key, value);
+        }
+
+        Node::Object(map)
+    }
+
+    fn node(&mut self, depth: usize) -> Node {
+        // Containers get less likely the deeper we go.
+        match depth < self.options.max_depth && self.rng.chance(60 / (depth as u64 + 1) + 10) {
+            true => self.container(depth),
+            false => self.scalar()
+        }
+    }
+}
+
+/// Generate a random document, which is always the same value for the same seed and options.
+///
+/// The top level is always an array or object, unless `max_depth` is 0.
+/// Objects are `HashMap`s, so sort keys when serializing if the text itself
+/// needs to be the same between runs.
+pub fn random(seed: u64, options: &GenOptions) -> Node {
+    let mut generator = Generator {
+        rng: Rng(seed),
+        options,
+        alphabet: options.string_alphabet.chars().collect()
+    };
+
+    match options.max_depth {
+        0 => generator.scalar(),
+        _ => generator.container(0)
+    }
+}
+
+/// A way `mutate` broke a document.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mutation {
+    /// A comma between items was removed.
+    DeleteComma,
+
+    /// A double quote around a string was changed to a single quote.
+    FlipQuote,
+
+    /// The text was cut off partway through.
+    Truncate,
+
+    /// A key and its value were repeated in the same object.
+    DuplicateKey
+}
+
+/// Places in a document that can be mutated, as byte offsets.
+#[derive(Default)]
+struct Spots {
+    commas: Vec<usize>,
+    quotes: Vec<usize>,
+
+    /// The start of each key and the end of its value.
+    pairs: Vec<(usize, usize)>
+}
+
+fn find_spots(text: &str) -> Spots {
+    let mut spots = Spots::default();
+
+    // For each open object, where its current key started.
+    let mut stack: Vec<Option<usize>> = Vec::new();
+    let mut expect_key = false;
+
+    // The end of the last thing that wasn't whitespace.
+    let mut last_end = 0;
+
+    let mut chars = text.char_indices();
+
+    while let Some((i, ch)) = chars.next() {
+        match ch {
+            '"' => {
+                spots.quotes.push(i);
+
+                let mut escaped = false;
+
+                for (j, c) in chars.by_ref() {
+                    match c {
+                        '\\' if !escaped => escaped = true,
+                        '"' if !escaped => {
+                            spots.quotes.push(j);
+                            last_end = j + 1;
+                            break;
+                        },
+                        _ => escaped = false
+                    }
+                }
+
+                if expect_key && let Some(top) = stack.last_mut() {
+                    *top = Some(i);
+                    expect_key = false;
+                }
+
+                continue;
+            },
+            '{' => {
+                stack.push(None);
+                expect_key = true;
+            },
+            '[' => stack.push(None),
+            ',' | '}' | ']' => {
+                // The end of a pair, if we're in an object.
+                if let Some(Some(start)) = stack.last() {
+                    spots.pairs.push((*start, last_end));
+                }
+
+                if ch == ',' {
+                    spots.commas.push(i);
+
+                    if let Some(top) = stack.last_mut() {
+                        expect_key = top.is_some();
+                        *top = None;
+                    }
+                }
+                else {
+                    stack.pop();
+                }
+            },
+            c if c.is_whitespace() => continue,
+            _ => {}
+        }
+
+        last_end = i + ch.len_utf8();
+    }
+
+    spots
+}
+
+/// Break valid JSON text in a realistic way, for testing how errors are handled.
+///
+/// The same text and seed always give the same result. The mutation is picked
+/// from the ones the text has room for, falling back to truncating it.
+pub fn mutate(text: &str, seed: u64) -> (String, Mutation) {
+    let mut rng = Rng(seed);
+    let spots = find_spots(text);
+
+    let mut choices = vec![Mutation::Truncate];
+
+    if !spots.commas.is_empty() {
+        choices.push(Mutation::DeleteComma);
+    }
+
+    if !spots.quotes.is_empty() {
+        choices.push(Mutation::FlipQuote);
+    }
+
+    if !spots.pairs.is_empty() {
+        choices.push(Mutation::DuplicateKey);
+    }
+
+    let mutation = choices[rng.up_to(choices.len() - 1)];
+    let mut out = text.to_string();
+
+    match mutation {
+        Mutation::DeleteComma => {
+            out.remove(spots.commas[rng.up_to(spots.commas.len() - 1)]);
+        },
+        Mutation::FlipQuote => {
+            let i = spots.quotes[rng.up_to(spots.quotes.len() - 1)];
+            out.replace_range(i..i + 1, "'");
+        },
+        Mutation::Truncate => {
+            let boundaries: Vec<usize> = text.char_indices().map(|(i, _)| i).skip(1).collect();
+
+            let cut = match boundaries.is_empty() {
+                true => 0,
+                false => boundaries[rng.up_to(boundaries.len() - 1)]
+            };
+
+            out.truncate(cut);
+        },
+        Mutation::DuplicateKey => {
+            let (start, end) = spots.pairs[rng.up_to(spots.pairs.len() - 1)];
+            out.insert_str(end, &format!(",{}", &text[start..end]));
+        }
+    }
+
+    (out, mutation)
+}
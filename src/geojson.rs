@@ -0,0 +1,266 @@
+use std::fmt::Display;
+
+use crate::parser::{Node, ObjectMap};
+use crate::utils::{index_path, key_path};
+
+/// A GeoJSON document whose structure doesn't follow RFC 7946.
+#[derive(Debug)]
+pub struct GeoJsonError {
+    /// Where the problem is, like `$.features[3].geometry.coordinates[0]`.
+    pub path: String,
+    pub message: String
+}
+
+impl Display for GeoJsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "at {}: {}", self.path, self.message)
+    }
+}
+
+impl std::error::Error for GeoJsonError {}
+
+fn error<T>(path: &str, message: impl Into<String>) -> Result<T, GeoJsonError> {
+    Err(GeoJsonError {
+        path: path.to_string(),
+        message: message.into()
+    })
+}
+
+/// A longitude, latitude and optional altitude.
+pub type Position = Vec<f64>;
+
+#[derive(Debug)]
+pub enum Geometry {
+    Point(Position),
+    MultiPoint(Vec<Position>),
+    LineString(Vec<Position>),
+    MultiLineString(Vec<Vec<Position>>),
+    Polygon(Vec<Vec<Position>>),
+    MultiPolygon(Vec<Vec<Vec<Position>>>),
+    GeometryCollection(Vec<Geometry>)
+}
+
+#[derive(Debug)]
+pub struct Feature<'a> {
+    pub id: Option<&'a Node>,
+    pub geometry: Option<Geometry>,
+    pub properties: Option<&'a ObjectMap>,
+
+    /// The object this feature was read from, for any foreign members.
+    pub node: &'a Node
+}
+
+#[derive(Debug)]
+pub struct FeatureCollection<'a> {
+    pub features: Vec<Feature<'a>>,
+    pub node: &'a Node
+}
+
+#[derive(Debug)]
+pub enum GeoJson<'a> {
+    Geometry(Geometry),
+    Feature(Feature<'a>),
+    FeatureCollection(FeatureCollection<'a>)
+}
+
+fn get_object<'a>(node: &'a Node, path: &str) -> Result<&'a ObjectMap, GeoJsonError> {
+    match node {
+        Node::Object(map) => Ok(map),
+        _ => error(path, format!("expected an object, found {:?}", node))
+    }
+}
+
+fn get_type<'a>(map: &'a ObjectMap, path: &str) -> Result<&'a str, GeoJsonError> {
+    match map.get("type") {
+        Some(Node::String(s)) => Ok(s),
+        Some(other) => error(&key_path(path, "type"), format!("expected a string, found {:?}", other)),
+        None => error(path, "missing \"type\"")
+    }
+}
+
+fn get_array<'a>(node: &'a Node, path: &str) -> Result<&'a Vec<Node>, GeoJsonError> {
+    match node {
+        Node::Array(arr) => Ok(arr),
+        _ => error(path, format!("expected an array, found {:?}", node))
+    }
+}
+
+fn read_position(node: &Node, path: &str) -> Result<Position, GeoJsonError> {
+    let arr = get_array(node, path)?;
+
+    if arr.len() < 2 {
+        return error(path, format!("a position needs at least 2 numbers, found {}", arr.len()));
+    }
+
+    let mut position = Vec::with_capacity(arr.len());
+
+    for (i, item) in arr.iter().enumerate() {
+        match item {
+            Node::Integer(n) => position.push(*n as f64),
+            Node::Unsigned(n) => position.push(*n as f64),
+            Node::Float(f) => position.push(*f),
+            _ => return error(&index_path(path, i), format!("expected a number, found {:?}", item))
+        }
+    }
+
+    Ok(position)
+}
+
+fn read_positions(node: &Node, path: &str, min: usize) -> Result<Vec<Position>, GeoJsonError> {
+    let arr = get_array(node, path)?;
+
+    if arr.len() < min {
+        return error(path, format!("expected at least {} positions, found {}", min, arr.len()));
+    }
+
+    arr.iter().enumerate().map(
+        |(i, item)| read_position(item, &index_path(path, i))
+    ).collect()
+}
+
+fn read_line_strings(node: &Node, path: &str) -> Result<Vec<Vec<Position>>, GeoJsonError> {
+    get_array(node, path)?.iter().enumerate().map(
+        |(i, item)| read_positions(item, &index_path(path, i), 2)
+    ).collect()
+}
+
+/// Polygon rings need at least 4 positions, and must end where they start.
+fn read_polygon(node: &Node, path: &str) -> Result<Vec<Vec<Position>>, GeoJsonError> {
+    let arr = get_array(node, path)?;
+    let mut rings = Vec::with_capacity(arr.len());
+
+    for (i, item) in arr.iter().enumerate() {
+        let ring_path = index_path(path, i);
+        let ring = read_positions(item, &ring_path, 4)?;
+
+        if ring.first() != ring.last() {
+            return error(&ring_path, "a linear ring must end at the same position it starts");
+        }
+
+        rings.push(ring);
+    }
+
+    Ok(rings)
+}
+
+impl Geometry {
+    pub fn from_node(node: &Node) -> Result<Geometry, GeoJsonError> {
+        read_geometry(node, "$")
+    }
+}
+
+fn read_geometry(node: &Node, path: &str) -> Result<Geometry, GeoJsonError> {
+    let map = get_object(node, path)?;
+    let kind = get_type(map, path)?;
+
+    if kind == "GeometryCollection" {
+        let geometries_path = key_path(path, "geometries");
+
+        let geometries = match map.get("geometries") {
+            Some(x) => get_array(x, &geometries_path)?,
+            None => return error(path, "missing \"geometries\"")
+        };
+
+        return Ok(Geometry::GeometryCollection(geometries.iter().enumerate().map(
+            |(i, item)| read_geometry(item, &index_path(&geometries_path, i))
+        ).collect::<Result<_, _>>()?));
+    }
+
+    let coordinates_path = key_path(path, "coordinates");
+
+    let coordinates = match map.get("coordinates") {
+        Some(x) => x,
+        None => return error(path, "missing \"coordinates\"")
+    };
+
+    let geometry = match kind {
+        "Point" => Geometry::Point(read_position(coordinates, &coordinates_path)?),
+        "MultiPoint" => Geometry::MultiPoint(read_positions(coordinates, &coordinates_path, 0)?),
+        "LineString" => Geometry::LineString(read_positions(coordinates, &coordinates_path, 2)?),
+        "MultiLineString" => Geometry::MultiLineString(read_line_strings(coordinates, &coordinates_path)?),
+        "Polygon" => Geometry::Polygon(read_polygon(coordinates, &coordinates_path)?),
+        "MultiPolygon" => Geometry::MultiPolygon(
+            get_array(coordinates, &coordinates_path)?.iter().enumerate().map(
+                |(i, item)| read_polygon(item, &index_path(&coordinates_path, i))
+            ).collect::<Result<_, _>>()?
+        ),
+
+        _ => return error(&key_path(path, "type"), format!("unknown geometry type {:?}", kind))
+    };
+
+    Ok(geometry)
+}
+
+fn read_feature<'a>(node: &'a Node, path: &str) -> Result<Feature<'a>, GeoJsonError> {
+    let map = get_object(node, path)?;
+
+    if get_type(map, path)? != "Feature" {
+        return error(&key_path(path, "type"), "expected \"Feature\"");
+    }
+
+    let id = match map.get("id") {
+        Some(id @ (Node::String(_) | Node::Integer(_) | Node::Unsigned(_) | Node::Float(_))) => Some(id),
+        Some(other) => return error(&key_path(path, "id"), format!("expected a string or number, found {:?}", other)),
+        None => None
+    };
+
+    let geometry = match map.get("geometry") {
+        Some(Node::Null) | None => None,
+        Some(x) => Some(read_geometry(x, &key_path(path, "geometry"))?)
+    };
+
+    let properties = match map.get("properties") {
+        Some(Node::Null) | None => None,
+        Some(x) => Some(get_object(x, &key_path(path, "properties"))?)
+    };
+
+    Ok(Feature {
+        id,
+        geometry,
+        properties,
+        node
+    })
+}
+
+impl<'a> Feature<'a> {
+    pub fn from_node(node: &'a Node) -> Result<Feature<'a>, GeoJsonError> {
+        read_feature(node, "$")
+    }
+}
+
+impl<'a> FeatureCollection<'a> {
+    pub fn from_node(node: &'a Node) -> Result<FeatureCollection<'a>, GeoJsonError> {
+        let map = get_object(node, "$")?;
+
+        if get_type(map, "$")? != "FeatureCollection" {
+            return error("$.type", "expected \"FeatureCollection\"");
+        }
+
+        let features = match map.get("features") {
+            Some(x) => get_array(x, "$.features")?,
+            None => return error("$", "missing \"features\"")
+        };
+
+        Ok(FeatureCollection {
+            features: features.iter().enumerate().map(
+                |(i, item)| read_feature(item, &index_path("$.features", i))
+            ).collect::<Result<_, _>>()?,
+            node
+        })
+    }
+}
+
+impl<'a> GeoJson<'a> {
+    /// Read and validate any kind of GeoJSON object, depending on its `type`.
+    pub fn from_node(node: &'a Node) -> Result<GeoJson<'a>, GeoJsonError> {
+        let map = get_object(node, "$")?;
+
+        let geojson = match get_type(map, "$")? {
+            "FeatureCollection" => GeoJson::FeatureCollection(FeatureCollection::from_node(node)?),
+            "Feature" => GeoJson::Feature(Feature::from_node(node)?),
+            _ => GeoJson::Geometry(Geometry::from_node(node)?)
+        };
+
+        Ok(geojson)
+    }
+}
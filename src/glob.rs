@@ -0,0 +1,151 @@
+use crate::parser::Node;
+use crate::pointer::escape_token;
+
+/// A pattern over the keys and indexes on the way to a value, like `**.credentials.*`.
+///
+/// Patterns are split into segments on `.` and `[...]`, with an optional `$` at the start:
+/// - `*` matches any one key or index.
+/// - `**` matches any number of keys and indexes, including none.
+/// - Any other segment matches a key or index exactly, with `*` inside it standing for
+///   any run of characters, like `*_at`.
+///
+/// Keys with dots in them can be written in quoted brackets, like `$["a.b"].c`.
+#[derive(Clone, Debug)]
+pub struct PathGlob {
+    segments: Vec<String>
+}
+
+impl PathGlob {
+    pub fn new(pattern: &str) -> PathGlob {
+        let pattern = pattern.strip_prefix('$').unwrap_or(pattern);
+
+        let mut segments = Vec::new();
+        let mut current = String::new();
+        let mut chars = pattern.chars();
+
+        while let Some(ch) = chars.next() {
+            match ch {
+                '.' | '[' => {
+                    if !current.is_empty() {
+                        segments.push(std::mem::take(&mut current));
+                    }
+
+                    if ch == '[' {
+                        let inside: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                        let inside = inside.trim_matches(|c| c == '"' || c == '\'');
+
+                        segments.push(inside.to_string());
+                    }
+                },
+                _ => current.push(ch)
+            }
+        }
+
+        if !current.is_empty() {
+            segments.push(current);
+        }
+
+        PathGlob { segments }
+    }
+
+    /// Check whether the pattern matches the keys and indexes (as strings) on the way to a value.
+    pub fn matches<S: AsRef<str>>(&self, path: &[S]) -> bool {
+        matches_from(&self.segments, path)
+    }
+}
+
+/// Check whether some path starting with `path` could match the pattern,
+/// so a search can skip values that can't lead to a match.
+fn could_match_below(pattern: &[String], path: &[String]) -> bool {
+    match (pattern.split_first(), path.split_first()) {
+        (_, None) => true,
+        (None, Some(_)) => false,
+
+        // `**` can take in all of the rest of the path.
+        (Some((first, _)), Some(_)) if first == "**" => true,
+
+        (Some((first, rest)), Some((segment, path))) => wildcard_match(first, segment) && could_match_below(rest, path)
+    }
+}
+
+fn find<'a>(glob: &PathGlob, node: &'a Node, segments: &mut Vec<String>, pointer: &str, found: &mut Vec<(String, &'a Node)>) {
+    if !could_match_below(&glob.segments, segments) {
+        return;
+    }
+
+    if glob.matches(segments) {
+        found.push((pointer.to_string(), node));
+    }
+
+    let mut visit = |segment: String, child: &'a Node| {
+        let pointer = format!("{}/{}", pointer, escape_token(&segment));
+
+        segments.push(segment);
+        find(glob, child, segments, &pointer, found);
+        segments.pop();
+    };
+
+    match node {
+        Node::Array(arr) => arr.iter().enumerate().for_each(|(i, item)| visit(i.to_string(), item)),
+        Node::Object(map) => {
+            // Sort the keys so matches come back in the same order every time.
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+
+            keys.into_iter().for_each(|key| visit(key.clone(), &map[key]));
+        },
+        _ => {}
+    }
+}
+
+impl Node {
+    /// Find every value whose path matches a glob like `**.credentials.*`
+    /// (see `PathGlob`), giving back each with its JSON Pointer.
+    ///
+    /// Matches are in document order, with object keys sorted. A value and
+    /// values inside it can both match, like with `**`.
+    pub fn find_matching(&self, pattern: &str) -> Vec<(String, &Node)> {
+        let mut found = Vec::new();
+        find(&PathGlob::new(pattern), self, &mut Vec::new(), "", &mut found);
+
+        found
+    }
+}
+
+fn matches_from<S: AsRef<str>>(pattern: &[String], path: &[S]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+
+        Some((first, rest)) if first == "**" => {
+            (0..=path.len()).any(|skip| matches_from(rest, &path[skip..]))
+        },
+
+        Some((first, rest)) => match path.split_first() {
+            Some((segment, path)) => wildcard_match(first, segment.as_ref()) && matches_from(rest, path),
+            None => false
+        }
+    }
+}
+
+/// Match text against a pattern where `*` stands for any run of characters.
+pub(crate) fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let Some((start, rest)) = pattern.split_once('*') else {
+        return pattern == text;
+    };
+
+    let Some(mut text) = text.strip_prefix(start) else {
+        return false;
+    };
+
+    let mut parts: Vec<&str> = rest.split('*').collect();
+    let end = parts.pop().unwrap_or("");
+
+    for part in parts {
+        match text.find(part) {
+            Some(i) => text = &text[i + part.len()..],
+            None => return false
+        }
+    }
+
+    text.len() >= end.len() && text.ends_with(end)
+}
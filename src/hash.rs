@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+use std::hash::Hasher;
+
+use crate::compare::Tolerance;
+use crate::parser::Node;
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// A 64-bit FNV-1a hasher, used instead of `DefaultHasher`
+/// because its output is the same on every platform and Rust version.
+///
+/// This is also the hasher for objects with the `deterministic_hash` feature.
+pub struct FnvHasher(u64);
+
+impl Default for FnvHasher {
+    fn default() -> FnvHasher {
+        FnvHasher(FNV_OFFSET)
+    }
+}
+
+impl Hasher for FnvHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        FnvHasher::write(self, bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+impl FnvHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    /// Write a length before variable-sized data, so `["ab"]` and `["a", "b"]` differ.
+    fn write_len(&mut self, len: usize) {
+        self.write(&(len as u64).to_le_bytes());
+    }
+
+    fn write_str(&mut self, s: &str) {
+        self.write_len(s.len());
+        self.write(s.as_bytes());
+    }
+}
+
+fn hash_into(node: &Node, hasher: &mut FnvHasher) {
+    match node {
+        Node::Integer(n) => {
+            hasher.write(b"i");
+            hasher.write(&n.to_le_bytes());
+        },
+        Node::Unsigned(n) => {
+            hasher.write(b"u");
+            hasher.write(&n.to_le_bytes());
+        },
+        Node::Float(f) => {
+            hasher.write(b"f");
+
+            // Treat -0.0 like 0.0, since they compare equal.
+            let f = if *f == 0.0 { 0.0 } else { *f };
+            hasher.write(&f.to_bits().to_le_bytes());
+        },
+        Node::String(s) => {
+            hasher.write(b"s");
+            hasher.write_str(s);
+        },
+        Node::Bool(b) => hasher.write(if *b { b"t" } else { b"b" }),
+        Node::Null => hasher.write(b"n"),
+        Node::Array(arr) => {
+            hasher.write(b"a");
+            hasher.write_len(arr.len());
+
+            for item in arr {
+                hash_into(item, hasher);
+            }
+        },
+        Node::Object(map) => {
+            hasher.write(b"o");
+            hasher.write_len(map.len());
+
+            // Keys are hashed in sorted order so the hash doesn't depend on key order.
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+
+            for key in keys {
+                hasher.write_str(key);
+                hash_into(&map[key], hasher);
+            }
+        },
+        Node::Empty => hasher.write(b"e")
+    }
+}
+
+impl Node {
+    /// A stable 64-bit hash of this tree's structure and values.
+    ///
+    /// Objects with the same entries hash the same no matter their key order,
+    /// and the result doesn't change between runs, platforms or Rust versions,
+    /// so it can be stored. `1` and `1.0` hash differently.
+    pub fn structural_hash(&self) -> u64 {
+        let mut hasher = FnvHasher::default();
+        hash_into(self, &mut hasher);
+        hasher.0
+    }
+}
+
+/// Remove duplicate values from a list, keeping the first of each.
+///
+/// Values are grouped by `structural_hash`, then compared exactly,
+/// so a hash collision never drops a value that isn't a duplicate.
+pub fn dedup(items: Vec<Node>) -> Vec<Node> {
+    dedup_with(items, |item| Some(item))
+}
+
+/// Remove objects from a list that have the same value under `key` as an earlier one,
+/// like records with the same `"id"`, keeping the first of each.
+///
+/// Items that aren't objects, or don't have the key, are always kept.
+pub fn dedup_by_key(items: Vec<Node>, key: &str) -> Vec<Node> {
+    dedup_with(items, |item| match item {
+        Node::Object(map) => map.get(key),
+        _ => None
+    })
+}
+
+/// Keep the first item for each value `identify` picks out of it, and every item it picks nothing out of.
+fn dedup_with(items: Vec<Node>, identify: impl Fn(&Node) -> Option<&Node>) -> Vec<Node> {
+    let tolerance = Tolerance::exact();
+
+    let mut seen: HashMap<u64, Vec<usize>> = HashMap::new();
+    let mut unique: Vec<Node> = Vec::with_capacity(items.len());
+
+    for item in items {
+        let Some(identity) = identify(&item) else {
+            unique.push(item);
+            continue;
+        };
+
+        let indexes = seen.entry(identity.structural_hash()).or_default();
+
+        if indexes.iter().any(|i| identify(&unique[*i]).is_some_and(|other| other.approx_eq(identity, &tolerance))) {
+            continue;
+        }
+
+        indexes.push(unique.len());
+        unique.push(item);
+    }
+
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_str;
+
+    fn doc(text: &str) -> Node {
+        parse_str(text).unwrap()
+    }
+
+    fn hash(text: &str) -> u64 {
+        doc(text).structural_hash()
+    }
+
+    #[test]
+    fn key_order_doesnt_change_the_hash() {
+        assert_eq!(hash(r#"{"a": 1, "b": {"c": [1, 2], "d": null}}"#), hash(r#"{"b": {"d": null, "c": [1, 2]}, "a": 1}"#));
+        assert_ne!(hash(r#"{"a": 1, "b": 2}"#), hash(r#"{"a": 2, "b": 1}"#));
+    }
+
+    #[test]
+    fn hashes_tell_values_apart() {
+        assert_ne!(hash("1"), hash("1.0"));
+        assert_ne!(hash(r#"["ab"]"#), hash(r#"["a", "b"]"#));
+        assert_ne!(hash("[[]]"), hash("[]"));
+        assert_ne!(hash(r#"{"a": null}"#), hash(r#"{"an": []}"#));
+        assert_eq!(hash("0.0"), hash("-0.0"));
+    }
+
+    #[test]
+    fn hashes_are_stable() {
+        // FNV-1a of the single byte `n`, so this only changes if the encoding does.
+        assert_eq!(hash("null"), 0xaf63e34c8601f871);
+    }
+
+    #[test]
+    fn dedup_keeps_the_first_of_each() {
+        let items = doc(r#"[{"a": 1, "b": 2}, 1, {"b": 2, "a": 1}, 1.0, 1]"#);
+        let Node::Array(items) = items else { unreachable!() };
+
+        let unique = Node::Array(dedup(items));
+        assert!(unique.approx_eq(&doc(r#"[{"a": 1, "b": 2}, 1, 1.0]"#), &Tolerance::exact()));
+    }
+
+    #[test]
+    fn dedup_by_key_keeps_items_without_the_key() {
+        let items = doc(r#"[{"id": 1, "v": "a"}, {"id": 2}, {"id": 1, "v": "b"}, {"v": "c"}, {"v": "c"}, 5]"#);
+        let Node::Array(items) = items else { unreachable!() };
+
+        let unique = Node::Array(dedup_by_key(items, "id"));
+        assert!(unique.approx_eq(&doc(r#"[{"id": 1, "v": "a"}, {"id": 2}, {"v": "c"}, {"v": "c"}, 5]"#), &Tolerance::exact()));
+    }
+}
@@ -0,0 +1,271 @@
+use std::fmt::Display;
+use std::fs::read_to_string;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::error::JsonError;
+use crate::lexer::tokenise;
+use crate::parser::{parse, Node};
+
+/// Why a document and its includes couldn't be loaded.
+#[derive(Debug)]
+pub enum IncludeError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, Box<JsonError>),
+
+    /// A file ended up including itself.
+    Cycle(PathBuf),
+
+    /// Includes were nested more deeply than allowed.
+    TooDeep(PathBuf),
+
+    /// An `$include` whose value isn't a path, or that can't be overlaid.
+    Invalid(PathBuf, String)
+}
+
+impl Display for IncludeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io(path, e) => write!(f, "Could not read {}: {}", path.display(), e),
+            Self::Parse(path, e) => write!(f, "Could not parse {}: {}", path.display(), e),
+            Self::Cycle(path) => write!(f, "{} includes itself", path.display()),
+            Self::TooDeep(path) => write!(f, "Includes are nested too deeply at {}", path.display()),
+            Self::Invalid(path, message) => write!(f, "Invalid include in {}: {}", path.display(), message)
+        }
+    }
+}
+
+impl std::error::Error for IncludeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(_, e) => Some(e),
+            Self::Parse(_, e) => Some(e.as_ref()),
+            _ => None
+        }
+    }
+}
+
+pub struct IncludeOptions {
+    /// How many files deep includes can be nested.
+    pub max_depth: usize
+}
+
+impl Default for IncludeOptions {
+    fn default() -> IncludeOptions {
+        IncludeOptions {
+            max_depth: 32
+        }
+    }
+}
+
+struct Loader<'a> {
+    options: &'a IncludeOptions,
+
+    /// The files currently being loaded, to detect cycles.
+    stack: Vec<PathBuf>
+}
+
+impl<'a> Loader<'a> {
+    fn load(&mut self, path: &Path) -> Result<Node, IncludeError> {
+        let path = match path.canonicalize() {
+            Ok(x) => x,
+            Err(e) => return Err(IncludeError::Io(path.to_path_buf(), e))
+        };
+
+        if self.stack.contains(&path) {
+            return Err(IncludeError::Cycle(path));
+        }
+
+        if self.stack.len() >= self.options.max_depth {
+            return Err(IncludeError::TooDeep(path));
+        }
+
+        let text = match read_to_string(&path) {
+            Ok(x) => x,
+            Err(e) => return Err(IncludeError::Io(path, e))
+        };
+
+        let node = match tokenise(&text).and_then(|tokens| parse(&tokens)) {
+            Ok(x) => x,
+            Err(e) => return Err(IncludeError::Parse(path, Box::new(e)))
+        };
+
+        self.stack.push(path);
+        let result = self.splice(node);
+        self.stack.pop();
+
+        result
+    }
+
+    /// Replace every `$include` in this node with the contents of the file it names.
+    fn splice(&mut self, node: Node) -> Result<Node, IncludeError> {
+        let spliced = match node {
+            Node::Array(arr) => {
+                let mut body = Vec::with_capacity(arr.len());
+
+                for item in arr {
+                    body.push(self.splice(item)?);
+                }
+
+                Node::Array(body)
+            },
+
+            Node::Object(mut map) => {
+                let include = map.remove("$include");
+
+                for value in map.values_mut() {
+                    let taken = std::mem::replace(value, Node::Null);
+                    *value = self.splice(taken)?;
+                }
+
+                let Some(include) = include else {
+                    return Ok(Node::Object(map));
+                };
+
+                // The current file is always on the stack while we splice it.
+                let current = self.stack.last().unwrap().clone();
+
+                let relative = match include {
+                    Node::String(x) => x,
+                    other => return Err(IncludeError::Invalid(current, format!("expected a path, found {:?}", other)))
+                };
+
+                let target = match current.parent() {
+                    Some(dir) => dir.join(relative),
+                    None => PathBuf::from(relative)
+                };
+
+                let included = self.load(&target)?;
+
+                if map.is_empty() {
+                    return Ok(included);
+                }
+
+                // Any keys next to the `$include` are overlaid on top of the included object.
+                match included {
+                    Node::Object(mut base) => {
+                        base.extend(map);
+                        Node::Object(base)
+                    },
+                    _ => return Err(IncludeError::Invalid(
+                        current,
+                        format!("{} must contain an object to have keys overlaid on it", target.display())
+                    ))
+                }
+            },
+
+            other => other
+        };
+
+        Ok(spliced)
+    }
+}
+
+/// Load a document, replacing any `{"$include": "relative/path.json"}` with the
+/// contents of that file, relative to the file that includes it.
+///
+/// Keys next to an `$include` are overlaid on the included object, replacing
+/// any keys it already has.
+pub fn load_with_includes(path: impl AsRef<Path>) -> Result<Node, IncludeError> {
+    load_with_includes_options(path, &IncludeOptions::default())
+}
+
+pub fn load_with_includes_options(path: impl AsRef<Path>, options: &IncludeOptions) -> Result<Node, IncludeError> {
+    let mut loader = Loader {
+        options,
+        stack: Vec::new()
+    };
+
+    loader.load(path.as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{create_dir_all, remove_dir_all, write};
+
+    use crate::compare::Tolerance;
+    use crate::parser::parse_str;
+
+    /// Write `files` into a fresh directory for one test, giving back its path.
+    fn files(test: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("json-rs-include-{}-{}", std::process::id(), test));
+        let _ = remove_dir_all(&dir);
+
+        for (name, text) in files {
+            let path = dir.join(name);
+            create_dir_all(path.parent().unwrap()).unwrap();
+            write(path, text).unwrap();
+        }
+
+        dir
+    }
+
+    #[test]
+    fn includes_are_spliced_relative_to_their_file() {
+        let dir = files("splice", &[
+            ("main.json", r#"{"db": {"$include": "conf/db.json", "port": 5433}, "list": [{"$include": "conf/db.json"}]}"#),
+            ("conf/db.json", r#"{"host": "localhost", "port": 5432, "user": {"$include": "user.json"}}"#),
+            ("conf/user.json", r#""admin""#)
+        ]);
+
+        let node = load_with_includes(dir.join("main.json")).unwrap();
+        let db = parse_str(r#"{"host": "localhost", "port": 5432, "user": "admin"}"#).unwrap();
+
+        assert!(matches!(node.pointer("/db/port"), Some(Node::Integer(5433))));
+        assert!(matches!(node.pointer("/db/user"), Some(Node::String(s)) if s == "admin"));
+        assert!(node.pointer("/list/0").unwrap().approx_eq(&db, &Tolerance::exact()));
+
+        remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn cycles_are_errors() {
+        let dir = files("cycle", &[
+            ("a.json", r#"{"$include": "b.json"}"#),
+            ("b.json", r#"[{"$include": "a.json"}]"#)
+        ]);
+
+        let error = load_with_includes(dir.join("a.json")).unwrap_err();
+        assert!(matches!(&error, IncludeError::Cycle(path) if path.ends_with("a.json")), "{}", error);
+
+        remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn nesting_is_limited() {
+        let dir = files("depth", &[
+            ("0.json", r#"{"$include": "1.json"}"#),
+            ("1.json", r#"{"$include": "2.json"}"#),
+            ("2.json", "true")
+        ]);
+
+        let options = IncludeOptions { max_depth: 2 };
+        assert!(matches!(load_with_includes_options(dir.join("0.json"), &options), Err(IncludeError::TooDeep(_))));
+
+        let options = IncludeOptions { max_depth: 3 };
+        assert!(matches!(load_with_includes_options(dir.join("0.json"), &options), Ok(Node::Bool(true))));
+
+        remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn bad_includes_are_errors() {
+        let dir = files("bad", &[
+            ("number.json", r#"{"$include": 1}"#),
+            ("overlay.json", r#"{"$include": "list.json", "a": 1}"#),
+            ("list.json", "[]"),
+            ("missing.json", r#"{"$include": "nowhere.json"}"#),
+            ("broken.json", r#"{"$include": "list.json""#)
+        ]);
+
+        let load = |name: &str| load_with_includes(dir.join(name)).unwrap_err();
+
+        assert!(matches!(load("number.json"), IncludeError::Invalid(..)));
+        assert!(matches!(load("overlay.json"), IncludeError::Invalid(..)));
+        assert!(matches!(load("missing.json"), IncludeError::Io(..)));
+        assert!(matches!(load("broken.json"), IncludeError::Parse(..)));
+
+        remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,100 @@
+use std::fmt::Display;
+
+use crate::error::JsonError;
+use crate::lexer::tokenise;
+use crate::parser::{parse, Node};
+use crate::utils::base64_decode;
+
+/// Why a token couldn't be decoded.
+#[derive(Debug)]
+pub enum JwtError {
+    /// The token isn't made of three dot-separated parts.
+    Malformed,
+
+    /// A part isn't valid base64url, or doesn't decode to UTF-8 text.
+    Encoding(&'static str),
+
+    /// A part decoded fine but isn't valid JSON.
+    Json(&'static str, Box<JsonError>)
+}
+
+impl Display for JwtError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Malformed => write!(f, "A JWT must have three parts separated by dots"),
+            Self::Encoding(part) => write!(f, "The JWT {} is not valid base64url-encoded UTF-8", part),
+            Self::Json(part, e) => write!(f, "The JWT {} is not valid JSON: {}", part, e)
+        }
+    }
+}
+
+impl std::error::Error for JwtError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Json(_, e) => Some(e.as_ref()),
+            _ => None
+        }
+    }
+}
+
+fn decode_part(text: &str, part: &'static str) -> Result<Node, JwtError> {
+    let bytes = base64_decode(text).ok_or(JwtError::Encoding(part))?;
+    let json = String::from_utf8(bytes).map_err(|_| JwtError::Encoding(part))?;
+
+    tokenise(&json).and_then(|tokens| parse(&tokens)).map_err(|e| JwtError::Json(part, Box::new(e)))
+}
+
+/// Decode the header and claims of a JWT **without verifying its signature**.
+///
+/// This is only meant for debugging and logging. Never trust the claims
+/// it gives back for anything like authentication.
+pub fn decode_unverified(token: &str) -> Result<(Node, Node), JwtError> {
+    let parts: Vec<&str> = token.trim().split('.').collect();
+
+    let [header, claims, _signature] = parts[..] else {
+        return Err(JwtError::Malformed);
+    };
+
+    Ok((decode_part(header, "header")?, decode_part(claims, "claims")?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::base64_encode;
+
+    fn segment(json: &str) -> String {
+        base64_encode(json.as_bytes(), true)
+    }
+
+    #[test]
+    fn header_and_claims_are_decoded() {
+        let token = "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9.eyJzdWIiOiIxMjM0NTY3ODkwIiwiaWF0IjoxNTE2MjM5MDIyfQ.sig";
+        let (header, claims) = decode_unverified(token).unwrap();
+
+        assert!(matches!(header.get("alg"), Some(Node::String(s)) if s == "HS256"));
+        assert!(matches!(claims.get("iat"), Some(Node::Integer(1516239022))));
+    }
+
+    #[test]
+    fn segments_are_base64url_without_padding() {
+        let claims = segment(r#"{"q":"???>>>"}"#);
+        assert!(claims.contains(['-', '_']) && !claims.ends_with('='));
+
+        let token = format!("{}.{}.", segment("{}"), claims);
+        let (_, claims) = decode_unverified(&format!("  {}\n", token)).unwrap();
+
+        assert!(matches!(claims.get("q"), Some(Node::String(s)) if s == "???>>>"));
+    }
+
+    #[test]
+    fn bad_tokens_are_errors() {
+        let header = segment("{}");
+
+        assert!(matches!(decode_unverified(&format!("{}.{}", header, header)), Err(JwtError::Malformed)));
+        assert!(matches!(decode_unverified(&format!("{0}.{0}.{0}.{0}", header)), Err(JwtError::Malformed)));
+        assert!(matches!(decode_unverified(&format!("{}.!!.", header)), Err(JwtError::Encoding("claims"))));
+        assert!(matches!(decode_unverified(&format!("{}.{}.", base64_encode(&[0xff], true), header)), Err(JwtError::Encoding("header"))));
+        assert!(matches!(decode_unverified(&format!("{}.{}.", header, segment("{"))), Err(JwtError::Json("claims", _))));
+    }
+}
@@ -1,12 +1,190 @@
-use crate::token::{Token, TokenType};
+use crate::error::{ErrorCode, JsonError, Result};
+use crate::lines::LineIndex;
+use crate::token::{Payload, Token, TokenType};
 use crate::utils::{CharIter, Pos};
 
-fn try_convert_escape_sequence<'a>(chars: &mut CharIter, pos: &'a mut Pos) -> char {
+/// What to do with escape sequences that aren't valid, like `\q`,
+/// and with `\u` escapes for surrogates that aren't part of a pair.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EscapePolicy {
+    /// Fail to tokenise.
+    Error,
+
+    /// Put a U+FFFD replacement character in their place.
+    Replace,
+
+    /// Keep the escape sequence in the string as it was written, backslash and all.
+    PassThrough
+}
+
+/// What to do with NUL characters in strings, whether written as `\u0000` or as they are,
+/// for strings that end up somewhere NUL ends a string, like C code.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NulPolicy {
+    /// Keep them in the string.
+    Allow,
+
+    /// Fail to tokenise.
+    Error,
+
+    /// Put a U+FFFD replacement character in their place.
+    Replace
+}
+
+/// Options for how text is tokenised.
+#[derive(Clone, Copy, Debug)]
+pub struct LexOptions {
+    pub escape_policy: EscapePolicy,
+    pub nul_policy: NulPolicy,
+
+    /// Skip `// line` and `/* block */` comments wherever whitespace can go.
+    pub allow_comments: bool,
+
+    /// Allow strings in single quotes, like `'text'`, where `"` needs no escape
+    /// and `\'` is a single quote.
+    pub allow_single_quotes: bool,
+
+    /// Allow the numbers JSON5 has on top of JSON's: hex like `0x1F`, a `+` in front,
+    /// and a decimal point with no digits on one side, like `.5` or `5.`.
+    pub allow_json5_numbers: bool,
+
+    /// Fail on numbers with a zero in front of their other digits, like `01`,
+    /// which JSON doesn't allow.
+    pub reject_leading_zeros: bool,
+
+    /// Fail on control characters below U+0020 written into strings as they are,
+    /// like a raw tab, which JSON only allows escaped. Newlines always fail.
+    pub reject_control_characters: bool,
+
+    /// The most digits a number can have, not counting its exponent.
+    /// Longer numbers are rejected as soon as the limit is passed.
+    pub max_number_digits: usize,
+
+    /// The largest exponent a number can have, either way, like the 400 in `1e400` or `1e-400`.
+    pub max_exponent: u64
+}
+
+impl Default for LexOptions {
+    fn default() -> LexOptions {
+        LexOptions {
+            escape_policy: EscapePolicy::Error,
+            nul_policy: NulPolicy::Allow,
+            allow_comments: false,
+            allow_single_quotes: false,
+            allow_json5_numbers: false,
+            reject_leading_zeros: false,
+            reject_control_characters: false,
+
+            // Far more than an f64 can tell apart, which is about 17 digits
+            // and exponents of about 300, so nothing sensible gets turned away.
+            max_number_digits: 1000,
+            max_exponent: 10_000
+        }
+    }
+}
+
+/// Handle an escape sequence that isn't valid, where `raw` is what came after the backslash.
+#[cold]
+fn invalid_escape(raw: &str, message: String, policy: EscapePolicy, pos: &Pos, out: &mut String) -> Result<()> {
+    match policy {
+        EscapePolicy::Error => return Err(JsonError::at_pos(message, pos).with_code(ErrorCode::InvalidEscape)),
+        EscapePolicy::Replace => out.push('\u{fffd}'),
+        EscapePolicy::PassThrough => {
+            out.push('\\');
+            out.push_str(raw);
+        }
+    }
+
+    Ok(())
+}
+
+/// Read the 4 hex digits of a `\u` escape.
+///
+/// If a character that isn't a hex digit turns up, it's left unread
+/// and the digits read so far are given back as the error.
+fn try_read_hex(chars: &mut CharIter, pos: &mut Pos) -> Result<std::result::Result<u32, String>> {
+    let mut hex = String::new();
+
+    for _ in 0..4 {
+        match chars.peek() {
+            Some(ch @ ('0'..='9' | 'a'..='f' | 'A'..='F')) => {
+                hex.push(ch);
+                chars.next();
+
+                pos.column = pos.column.saturating_add(1);
+            },
+            Some(_) => return Ok(Err(hex)),
+            None => return Err(JsonError::at_pos("Found EOF when trying to convert escape sequence.", pos).with_code(ErrorCode::UnterminatedString))
+        }
+    }
+
+    // We've already verified the hex digits, so this can't fail.
+    Ok(Ok(u32::from_str_radix(hex.as_str(), 16).unwrap()))
+}
+
+/// Push the character for a `\u` escape, pairing up surrogates
+/// with the escape after them when there is one.
+fn try_push_unicode(code: u32, chars: &mut CharIter, pos: &mut Pos, policy: EscapePolicy, out: &mut String) -> Result<()> {
+    let lone = |out: &mut String, pos: &Pos| invalid_escape(
+        &format!("u{:04x}", code),
+        format!("Found a lone surrogate in escape sequence \\u{:04x}", code),
+        policy,
+        pos,
+        out
+    );
+
+    // Anything outside of the surrogate range is a character on its own.
+    if let Some(ch) = char::from_u32(code) {
+        out.push(ch);
+        return Ok(());
+    }
+
+    // A low surrogate has to come after a high one.
+    if code >= 0xdc00 || chars.peek() != Some('\\') {
+        return lone(out, pos);
+    }
+
+    chars.next();
+    pos.column = pos.column.saturating_add(1);
+
+    if chars.peek() != Some('u') {
+        lone(out, pos)?;
+        return try_convert_escape_sequence(chars, pos, policy, out);
+    }
+
+    chars.next();
+    pos.column = pos.column.saturating_add(1);
+
+    let low = match try_read_hex(chars, pos)? {
+        Ok(x) => x,
+        Err(raw) => {
+            lone(out, pos)?;
+            return invalid_escape(&format!("u{}", raw), "Invalid character for unicode codepoint".to_string(), policy, pos, out);
+        }
+    };
+
+    if !(0xdc00..0xe000).contains(&low) {
+        lone(out, pos)?;
+        return try_push_unicode(low, chars, pos, policy, out);
+    }
+
+    let combined = 0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00);
+
+    // Any high and low surrogate pair makes a valid character.
+    out.push(char::from_u32(combined).unwrap());
+
+    Ok(())
+}
+
+#[inline(never)]
+fn try_convert_escape_sequence(chars: &mut CharIter, pos: &mut Pos, policy: EscapePolicy, out: &mut String) -> Result<()> {
     let ch = match chars.next() {
         Some(x) => x,
-        None => panic!("Found EOF when trying to parse escape sequence. {}", pos)
+        None => return Err(JsonError::at_pos("Found EOF when trying to parse escape sequence.", pos).with_code(ErrorCode::UnterminatedString))
     };
 
+    pos.column = pos.column.saturating_add(1);
+
     let converted = match ch {
         // Literal characters we want to escape.
         '"' | '\\' | '/' => ch,
@@ -21,199 +199,344 @@ fn try_convert_escape_sequence<'a>(chars: &mut CharIter, pos: &'a mut Pos) -> ch
         't' => '\t',
 
         // Unicode escape sequences
-        'u' => {
-            let mut hex = String::new();
-
-            for _ in 0..4 {
-                match chars.next() {
-                    Some(ch) => match ch {
-                        '0'..='9' | 'a'..='f' | 'A'..='F' => hex.push(ch),
-                        _ => panic!("Invalid character for unicode codepoint: {:?} {}", ch, pos)
-                    },
-                    None => panic!("Found EOF when trying to convert escape sequence. {}", pos)
-                };
+        'u' => return match try_read_hex(chars, pos)? {
+            Ok(code) => try_push_unicode(code, chars, pos, policy, out),
+            Err(raw) => invalid_escape(
+                &format!("u{}", raw),
+                format!("Invalid character for unicode codepoint: {:?}", chars.peek().unwrap()),
+                policy,
+                pos,
+                out
+            )
+        },
 
-                pos.column += 3;
-            };
+        _ => return invalid_escape(&ch.to_string(), format!("Invalid escape sequence {:?}", ch), policy, pos, out)
+    };
 
-            // We've already verified the hex digits with the match statement,
-            // so we can safely unwrap on both cases.
-            char::from_u32(u32::from_str_radix(hex.as_str(), 16).unwrap()).unwrap()
-        }
+    out.push(converted);
 
-        _ => panic!("Invalid escape sequence {:?} {}", ch, pos)
-    };
+    Ok(())
+}
 
-    pos.column += 1;
-    
-    converted
+/// Deal with any NUL characters pushed onto `out` since `start`.
+fn check_nul(out: &mut String, start: usize, policy: NulPolicy, pos: &Pos) -> Result<()> {
+    if policy == NulPolicy::Allow || !out[start..].contains('\0') {
+        return Ok(());
+    }
+
+    match policy {
+        NulPolicy::Error => Err(JsonError::at_pos("Found a NUL character in string.", pos).with_code(ErrorCode::NulInString)),
+        _ => {
+            let replaced = out[start..].replace('\0', "\u{fffd}");
+
+            out.truncate(start);
+            out.push_str(&replaced);
+
+            Ok(())
+        }
+    }
 }
 
-fn try_get_string<'a>(chars: &mut CharIter, pos: &'a mut Pos) -> Token {
-    // We know for sure that the first character is a double quote.
-    let mut result = String::from(chars.next().unwrap());
+fn try_get_string(chars: &mut CharIter, pos: &mut Pos, options: &LexOptions) -> Result<Token> {
+    // We know for sure that the first character is a quote,
+    // and we don't keep quotes in the token value.
+    let quote = chars.next().unwrap();
+
+    let mut result = String::new();
 
     let (line_no, col_no) = (pos.line, pos.column);
-    
+
     loop {
         // Since an EOF results in an unterminated string literal,
         // this is a fatal error and we cannot tokenise the object.
         let ch = match chars.peek() {
             Some(x) => x,
-            None => panic!("Found EOF when trying to parse string. {}", pos)
+            None => return Err(JsonError::at_pos("Found EOF when trying to parse string.", pos).with_code(ErrorCode::UnterminatedString).with_hint("add a closing '\"'"))
         };
 
         match ch {
-            '\n' => panic!("Found newline when trying to parse string. {}", pos),
-            
+            '\n' => return Err(JsonError::at_pos("Found newline when trying to parse string.", pos).with_code(ErrorCode::NewlineInString).with_hint("use \\n for a newline inside a string")),
+
             // Escape whatever character is after.
-            // TODO: Add 'try_convert_escape_sequence()'
             '\\' => {
                 chars.next();
 
-                pos.column += 1;
-                
-                result.push(try_convert_escape_sequence(chars, pos));
+                pos.column = pos.column.saturating_add(1);
+
+                // Single-quoted strings need a way to have a single quote in them.
+                if quote == '\'' && chars.peek() == Some('\'') {
+                    result.push(chars.next().unwrap());
+                    pos.column = pos.column.saturating_add(1);
+
+                    continue;
+                }
+
+                let start = result.len();
+
+                try_convert_escape_sequence(chars, pos, options.escape_policy, &mut result)?;
+                check_nul(&mut result, start, options.nul_policy, pos)?;
             },
 
             // The string is completed.
-            '"' => {
-                result.push(chars.next().unwrap());
-
-                return Token::new(
-                    TokenType::String,
-                    result,
-                    line_no,
-                    col_no
-                );
+            c if c == quote => {
+                chars.next();
+
+                return Ok(Token::string(result).at(line_no, col_no));
             }
-            
-            // Anything else just goes in the string.
-            c => {
+
+            c if c < ' ' && options.reject_control_characters => return Err(JsonError::at_pos(
+                format!("Found control character {:?} in string.", c),
+                pos
+            ).with_code(ErrorCode::UnexpectedCharacter).with_hint(format!("write it as \\u{:04x}", c as u32))),
+
+            // Otherwise, control characters other than newlines are let through,
+            // but they end a run, so push them one at a time.
+            c if c < ' ' => {
+                let start = result.len();
+
                 result.push(c);
+                check_nul(&mut result, start, options.nul_policy, pos)?;
                 chars.next();
 
-                pos.column += 1;
+                pos.column = pos.column.saturating_add(1);
+            },
+
+            // Anything else goes in the string, along with everything up to
+            // the next character that needs a closer look.
+            _ => {
+                let run = chars.take_until(|b| b == quote as u8 || matches!(b, b'\\' | 0x00..=0x1f));
+
+                result.push_str(run);
+
+                pos.column = pos.column.saturating_add(u32::try_from(run.chars().count()).unwrap_or(u32::MAX));
             }
         }
     }
 }
 
-fn try_grab_integer(chars: &mut CharIter, pos: &mut Pos) -> String {
-    let first = chars.next().unwrap();
-    let mut result = String::from(first);
+#[cold]
+fn unrecognised_character(ch: char, pos: &Pos) -> JsonError {
+    JsonError::at_pos(format!("Unrecognised character: {:?}", ch), pos).with_code(ErrorCode::UnexpectedCharacter)
+}
+
+/// Read a run of digits, with an optional minus sign in front.
+///
+/// `digits` is how many digits the number has so far, which carries on
+/// across the parts of a number so the limit covers all of them.
+fn try_grab_integer(chars: &mut CharIter, pos: &mut Pos, digits: &mut usize, max_digits: usize) -> Result<String> {
+    let mut result = String::new();
 
     // If we have a negative sign and there is no number after it,
     // this is a fatal EOF error which we need to check for.
-    if first == '-' {
+    if chars.peek() == Some('-') {
+        result.push(chars.next().unwrap());
+
         match chars.peek() {
             Some(x) => match x {
-                '0'..='9' => result.push(x),
-                _ => panic!("Found non-digit after minus sign when trying to parse number. {}", pos)
+                '0'..='9' => {},
+                _ => return Err(JsonError::at_pos("Found non-digit after minus sign when trying to parse number.", pos).with_code(ErrorCode::InvalidNumber))
             },
-            None => panic!("Encountered an EOF when trying to parse number. {}", pos)
+            None => return Err(JsonError::at_pos("Encountered an EOF when trying to parse number.", pos).with_code(ErrorCode::InvalidNumber))
         };
     }
-    
-    loop {
-        // Since an EOF when parsing an integer isn't fatal,
-        // we can let any EOFs we encounter pass silently
-        // by breaking.
-        match chars.peek() {
-            Some(ch) => match ch {
-                '0'..='9' => {
-                    result.push(ch);
-                    chars.next();
-                },
-                _ => break
+
+    // Since an EOF when parsing an integer isn't fatal,
+    // we can let any EOFs we encounter pass silently
+    // by stopping.
+    while let Some(ch) = chars.peek() {
+        match ch {
+            '0'..='9' => {
+                *digits += 1;
+
+                // Stop straight away, rather than reading the rest of a huge number.
+                if *digits > max_digits {
+                    return Err(JsonError::at_pos(
+                        format!("Number has more than {} digits.", max_digits),
+                        pos
+                    ).with_code(ErrorCode::LimitExceeded));
+                }
+
+                result.push(ch);
+                chars.next();
             },
-            None => break
+            _ => break
         }
     }
 
-    result
+    Ok(result)
 }
 
-fn try_grab_exponent(chars: &mut CharIter, pos: &mut Pos) -> String {
+fn try_grab_exponent(chars: &mut CharIter, pos: &mut Pos, max_exponent: u64) -> Result<String> {
     chars.next();
-    
+
     let mut result = String::from('e');
 
+    // A plus sign is allowed but means nothing, so we keep it and move on to the digits.
+    if chars.peek() == Some('+') {
+        result.push(chars.next().unwrap());
+
+        if !matches!(chars.peek(), Some('0'..='9')) {
+            return Err(JsonError::at_pos("Found non-digit after plus sign when trying to parse exponent.", pos).with_code(ErrorCode::InvalidNumber));
+        }
+    }
+
     match chars.peek() {
         Some(ch) => match ch {
             '0'..='9' | '-' => {
-                result.push_str(try_grab_integer(chars, pos).as_str());
+                // Leading zeros don't change the size, so only count the digits after them.
+                let exponent = try_grab_integer(chars, pos, &mut 0, usize::MAX)?;
+
+                let too_big = match exponent.trim_start_matches('-').trim_start_matches('0') {
+                    "" => false,
+                    significant => significant.parse::<u64>().map_or(true, |n| n > max_exponent)
+                };
+
+                if too_big {
+                    return Err(JsonError::at_pos(
+                        format!("Number has an exponent bigger than {}.", max_exponent),
+                        pos
+                    ).with_code(ErrorCode::LimitExceeded));
+                }
+
+                result.push_str(&exponent);
             },
-            _ => panic!("Found non-digit after minus sign when trying to parse exponent. {}", pos)
+            _ => return Err(JsonError::at_pos("Found non-digit after minus sign when trying to parse exponent.", pos).with_code(ErrorCode::InvalidNumber))
         },
-        None => panic!("Encountered EOF when trying to parse exponent of number. {}", pos)
+        None => return Err(JsonError::at_pos("Encountered EOF when trying to parse exponent of number.", pos).with_code(ErrorCode::InvalidNumber))
+    }
+
+    Ok(result)
+}
+
+/// Read the hex digits of a number like `0x1F`, after any sign in `result`.
+fn try_get_hex(chars: &mut CharIter, pos: &mut Pos, mut result: String, max_digits: usize) -> Result<Token> {
+    // The `0x` is safe, since it's how we got here.
+    result.push(chars.next().unwrap());
+    result.push(chars.next().unwrap());
+
+    let mut digits = 0usize;
+
+    while let Some(ch @ ('0'..='9' | 'a'..='f' | 'A'..='F')) = chars.peek() {
+        digits += 1;
+
+        if digits > max_digits {
+            return Err(JsonError::at_pos(
+                format!("Number has more than {} digits.", max_digits),
+                pos
+            ).with_code(ErrorCode::LimitExceeded));
+        }
+
+        result.push(ch);
+        chars.next();
     }
 
-    result
+    if digits == 0 {
+        return Err(JsonError::at_pos("Found no hex digits after 0x when trying to parse number.", pos).with_code(ErrorCode::InvalidNumber));
+    }
+
+    Ok(Token::integer(result).at(pos.line, pos.column))
 }
 
-fn try_get_number(chars: &mut CharIter, pos: &mut Pos) -> Token {
-    // Get the integer body of the number.
-    let mut result = try_grab_integer(chars, pos);
+fn try_get_number(chars: &mut CharIter, pos: &mut Pos, options: &LexOptions) -> Result<Token> {
+    let json5 = options.allow_json5_numbers;
+    let mut digits = 0usize;
+    let mut result = String::new();
+
+    // A plus sign only gets this far in JSON5, where it's allowed but means nothing.
+    let sign = match chars.peek() {
+        Some(ch @ ('+' | '-')) => {
+            result.push(ch);
+            chars.next();
+
+            if ch == '+' { "plus" } else { "minus" }
+        },
+        _ => "minus"
+    };
+
+    if chars.peek() == Some('0') && matches!(chars.peek_n(1), Some('x' | 'X')) {
+        if !json5 {
+            return Err(JsonError::at_pos("Found a hex number, which JSON doesn't allow.", pos)
+                .with_code(ErrorCode::InvalidNumber)
+                .with_hint("write it in decimal, or parse it with the json5 or lenient profile"));
+        }
+
+        return try_get_hex(chars, pos, result, options.max_number_digits);
+    }
+
+    // Get the integer body of the number, which JSON5 lets numbers like `.5` go without.
+    match chars.peek() {
+        Some('0'..='9') => {
+            let integer = try_grab_integer(chars, pos, &mut digits, options.max_number_digits)?;
+
+            if options.reject_leading_zeros && integer.len() > 1 && integer.starts_with('0') {
+                return Err(JsonError::at_pos("Found a number with a leading zero, which JSON doesn't allow.", pos)
+                    .with_code(ErrorCode::InvalidNumber)
+                    .with_hint("take out the leading zero"));
+            }
+
+            result.push_str(&integer);
+        },
+        Some('.') if json5 => {},
+        Some(_) => return Err(JsonError::at_pos(format!("Found non-digit after {} sign when trying to parse number.", sign), pos).with_code(ErrorCode::InvalidNumber)),
+        None => return Err(JsonError::at_pos("Encountered an EOF when trying to parse number.", pos).with_code(ErrorCode::InvalidNumber))
+    }
 
     let next = chars.peek();
-    
+
     // If we've encountered an EOF, that's the full number.
     if next.is_none() {
-        return Token::new(
-            TokenType::Int,
-            result,
-            pos.line,
-            pos.column
-        );
+        return Ok(Token::integer(result).at(pos.line, pos.column));
     }
 
     match next.unwrap() {
         // If we have an integer and exponent like '1e5',
         // we need to verify and append the exponent.
+        // Numbers with exponents are floats, like in JavaScript.
         'e' | 'E' => {
-            result.push_str(try_grab_exponent(chars, pos).as_str());
+            result.push_str(try_grab_exponent(chars, pos, options.max_exponent)?.as_str());
+
+            return Ok(Token::float(result).at(pos.line, pos.column));
         },
 
         // If we have a decimal like '5.6',
         // we need to verify and append the decimal part.
         '.' => {
             result.push(chars.next().unwrap());
-            
-            match chars.peek() {
-                Some(ch) => match ch {
-                    '0'..='9' => result.push_str(try_grab_integer(chars, pos).as_str()),
-                    _ => panic!("Found non-digit after decimal point when trying to parse exponent. {}", pos)
+
+            // JSON5 allows a decimal point with no digits after it, like `5.`, if there were some before it.
+            let bare = json5 && digits > 0;
+
+            let error = match chars.peek() {
+                Some('0'..='9') => {
+                    result.push_str(try_grab_integer(chars, pos, &mut digits, options.max_number_digits)?.as_str());
+                    None
                 },
-                None => panic!("Encountered EOF when trying to parse decimal part of a number. {}", pos)
+                _ if bare => None,
+                Some(_) => Some("Found non-digit after decimal point when trying to parse exponent."),
+                None => Some("Encountered EOF when trying to parse decimal part of a number.")
+            };
+
+            if let Some(message) = error {
+                let e = JsonError::at_pos(message, pos).with_code(ErrorCode::InvalidNumber);
+
+                return Err(match json5 {
+                    true => e,
+                    false => e.with_hint("put a digit after the decimal point, or parse it with the json5 or lenient profile")
+                });
             }
 
             // If there's an exponent part, we need that as well.
             // If nothing's there, we can just pass quietly.
-            match chars.peek() {
-                Some(ch) => match ch {
-                    'e' | 'E' => result.push_str(try_grab_exponent(chars, pos).as_str()),
-                    _ => {}
-                },
-                None => {}
+            if let Some('e' | 'E') = chars.peek() {
+                result.push_str(try_grab_exponent(chars, pos, options.max_exponent)?.as_str());
             }
 
-            return Token::new(
-                TokenType::Float,
-                result,
-                pos.line,
-                pos.column
-            );
+            return Ok(Token::float(result).at(pos.line, pos.column));
         },
         _ => {}
     }
 
-    Token::new(
-        TokenType::Int,
-        result,
-        pos.line,
-        pos.column
-    )
+    Ok(Token::integer(result).at(pos.line, pos.column))
 }
 
 fn try_get_name(chars: &mut CharIter, pos: &mut Pos) -> Token {
@@ -221,94 +544,458 @@ fn try_get_name(chars: &mut CharIter, pos: &mut Pos) -> Token {
     let mut result = String::from(chars.next().unwrap());
 
     // Grab any valid variable name characters.
-    loop {
-        match chars.peek() {
-            Some(ch) => match ch {
-                'a'..='z' | 'A'..='Z' | '0'..='9' | '_' => {
-                    result.push(ch);
-                    chars.next();
+    while let Some(ch) = chars.peek() {
+        match ch {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' => {
+                result.push(ch);
+                chars.next();
 
-                    pos.column += 1;
-                },
-                _ => break
+                pos.column = pos.column.saturating_add(1);
             },
-            None => break
+            _ => break
         }
     }
 
-    Token::new(
-        TokenType::Name,
-        result,
-        pos.line,
-        pos.column
-    )
+    Token::name(result).at(pos.line, pos.column)
 }
 
-pub fn tokenise(text: &str) -> Vec<Token> {
-    let mut chars = CharIter::new(text);
+/// Skip a comment starting at the `/` that's next, keeping the position up to date.
+fn skip_comment(chars: &mut CharIter, pos: &mut Pos) -> Result<()> {
+    let start = *pos;
 
-    let mut tokens: Vec<Token> = vec![];
-    
-    let mut pos = Pos {
-        line: 1,
-        column: 1
-    };
+    chars.next();
+    pos.column = pos.column.saturating_add(1);
 
-    loop {
-        let ch = match chars.peek() {
-            Some(x) => x,
-            None => break
-        };
+    match chars.peek() {
+        // Line comments end at the newline, which is left for the caller.
+        Some('/') => while let Some(ch) = chars.peek() && ch != '\n' {
+            chars.next();
+            pos.column = pos.column.saturating_add(1);
+        },
 
-        let token: Token = match ch {
-            // Newlines are special whitespace because they indicate
-            // we need to go to the next line.
-            '\n' => {
-                chars.next();
+        Some('*') => {
+            chars.next();
+            pos.column = pos.column.saturating_add(1);
 
-                pos.line += 1;
-                pos.column = 0;
+            let mut star = false;
 
-                continue;
-            },
+            loop {
+                let ch = match chars.next() {
+                    Some(x) => x,
+                    None => return Err(JsonError::at_pos("Found EOF when trying to parse comment.", &start).with_code(ErrorCode::UnexpectedEof).with_hint("add a closing '*/'"))
+                };
 
-            // All other whitespace is irrelevant, so we can skip it.
-            ' ' | '\t' | '\r' => {
-                chars.next();
+                if ch == '\n' {
+                    pos.line = pos.line.saturating_add(1);
+                    pos.column = 1;
+                }
+                else {
+                    pos.column = pos.column.saturating_add(1);
+                }
+
+                if star && ch == '/' {
+                    break;
+                }
+
+                star = ch == '*';
+            }
+        },
+
+        _ => return Err(unrecognised_character('/', &start))
+    }
+
+    Ok(())
+}
+
+/// Produces tokens one at a time from the source text,
+/// so callers don't need to hold the whole token stream in memory.
+pub struct Lexer<'a> {
+    chars: CharIter<'a>,
+    pos: Pos,
+    options: LexOptions,
+    text: &'a str,
 
-                pos.column += 1;
-                continue;
+    /// Where the comments skipped so far were, if they're being kept.
+    comments: Option<Vec<Span>>
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(text: &'a str) -> Lexer<'a> {
+        Lexer::with_options(text, LexOptions::default())
+    }
+
+    pub fn with_options(text: &'a str, options: LexOptions) -> Lexer<'a> {
+        Lexer {
+            chars: CharIter::new(text),
+            pos: Pos {
+                line: 1,
+                column: 1
             },
+            options,
+            text,
+            comments: None
+        }
+    }
+
+    /// Keep where each comment the lexer skips was, so they can be
+    /// taken with `take_comments`.
+    pub fn keep_comments(mut self) -> Lexer<'a> {
+        self.comments = Some(Vec::new());
+        self
+    }
 
-            '"'                         => try_get_string(&mut chars, &mut pos),
-            '0'..='9' | '-'             => try_get_number(&mut chars, &mut pos),
-            'a'..='z' | 'A'..='Z' | '_' => try_get_name(&mut chars, &mut pos),
+    /// The spans of the comments skipped since this was last called, in order,
+    /// or none if they aren't being kept.
+    pub fn take_comments(&mut self) -> Vec<Span> {
+        self.comments.as_mut().map(std::mem::take).unwrap_or_default()
+    }
 
-            '{' | '}' | '[' | ']' | ',' | ':' => {
-                chars.next();
-                pos.column += 1;
-
-                Token::new(
-                    match ch {
-                        '{' => TokenType::LBrace,
-                        '}' => TokenType::RBrace,
-                        '[' => TokenType::LSqBrac,
-                        ']' => TokenType::RSqBrac,
-                        ',' => TokenType::Comma,
-                        ':' => TokenType::Colon,
-                        _ => todo!()
-                    },
-                    ch.to_string(),
-                    pos.line,
-                    pos.column
-                )
-            }
-            
-            c => panic!("Unrecognised character: {:?}", c)
+    pub fn pos(&self) -> Pos {
+        self.pos
+    }
+
+    /// The byte offset into the text of the next character to be read.
+    pub fn offset(&self) -> usize {
+        self.text.len() - self.chars.remaining_len()
+    }
+
+    /// Get the next token in the text, or `None` if we've reached the end.
+    pub fn next_token(&mut self) -> Result<Option<Token>> {
+        while let Some(ch) = self.chars.peek() {
+            let start = self.pos;
+            let start_offset = self.offset();
+
+            // Whitespace and punctuation make up most of a document,
+            // so they're dealt with here and everything else is handed off.
+            let tok_type = match ch {
+                // Newlines are special whitespace because they indicate
+                // we need to go to the next line.
+                '\n' => {
+                    self.chars.next();
+
+                    self.pos.line = self.pos.line.saturating_add(1);
+                    self.pos.column = 1;
+
+                    continue;
+                },
+
+                // All other whitespace is irrelevant, so we can skip it.
+                ' ' | '\t' | '\r' => {
+                    self.chars.next();
+
+                    self.pos.column = self.pos.column.saturating_add(1);
+                    continue;
+                },
+
+                '/' if self.options.allow_comments => {
+                    skip_comment(&mut self.chars, &mut self.pos).map_err(|e| e.with_offset(start_offset))?;
+
+                    let end = self.offset();
+
+                    if let Some(comments) = &mut self.comments {
+                        comments.push(Span { start: start_offset, end });
+                    }
+
+                    continue;
+                },
+
+                _ => match TokenType::from_punctuation(ch) {
+                    Some(tok_type) => tok_type,
+                    None => return self.scan_value(ch, start, start_offset).map(Some)
+                }
+            };
+
+            self.chars.next();
+            self.pos.column = self.pos.column.saturating_add(1);
+
+            // Punctuation has no text to keep, so an empty string saves allocating one.
+            return Ok(Some(
+                Token::new(tok_type, String::new(), start.line, start.column).with_offset(start_offset)
+            ));
+        }
+
+        Ok(None)
+    }
+
+    /// Read a string, number or name starting with `ch`.
+    fn scan_value(&mut self, ch: char, start: Pos, start_offset: usize) -> Result<Token> {
+        let chars = &mut self.chars;
+        let pos = &mut self.pos;
+
+        let token = match ch {
+            '"'                         => try_get_string(chars, pos, &self.options),
+            '\'' if self.options.allow_single_quotes => try_get_string(chars, pos, &self.options),
+            '0'..='9' | '-'             => try_get_number(chars, pos, &self.options),
+            '+' | '.' if self.options.allow_json5_numbers => try_get_number(chars, pos, &self.options),
+            '+' | '.' => Err(unrecognised_character(ch, pos).with_hint(format!(
+                "JSON numbers can't start with '{}', but the json5 and lenient profiles allow it", ch
+            ))),
+            'a'..='z' | 'A'..='Z' | '_' => Ok(try_get_name(chars, pos)),
+
+            c => Err(unrecognised_character(c, pos))
         };
 
+        let end_offset = self.text.len() - chars.remaining_len();
+
+        // Errors happen wherever the scanner stopped.
+        let mut token = match token {
+            Ok(x) => x,
+            Err(e) => return Err(e.with_offset(end_offset))
+        };
+
+        // The scanners don't all keep the column in step, so work it out from how much
+        // text the token took up. Numbers and names are only ever ASCII, so only
+        // strings need their characters counting.
+        let width = match token.tok_type {
+            TokenType::String => self.text[start_offset..end_offset].chars().count(),
+            _ => end_offset - start_offset
+        };
+
+        pos.column = start.column.saturating_add(u32::try_from(width).unwrap_or(u32::MAX));
+
+        token.set_pos(start.line, start.column, start_offset);
+
+        Ok(token)
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = Result<Token>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_token().transpose()
+    }
+}
+
+/// Where a token or comment is in the text it was scanned from, as byte offsets,
+/// so `&text[span.start..span.end]` is the token as it was written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize
+}
+
+/// Put an error from a scanner at `offset` in the whole text. The scanners count lines
+/// and columns from wherever they're started, so these are worked out again from the offset.
+fn locate(mut e: JsonError, text: &str, offset: usize) -> JsonError {
+    let (line, column) = LineIndex::new(text).offset_to_line_col(offset);
+
+    e.line = u32::try_from(line).unwrap_or(u32::MAX);
+    e.column = u32::try_from(column).unwrap_or(u32::MAX);
+    e.with_offset(offset)
+}
+
+/// Run one of the scanners on the token at `start` in `text`, where `starts` says
+/// which characters the token can begin with.
+fn scan_at<T>(text: &str, start: usize, starts: impl Fn(char) -> bool, scan: impl FnOnce(&mut CharIter, &mut Pos) -> Result<T>) -> Result<(T, Span)> {
+    let mut pos = Pos { line: 1, column: 1 };
+
+    let Some(rest) = text.get(start..) else {
+        let message = format!("Byte {} isn't the start of a character in the text.", start);
+        return Err(locate(JsonError::at_pos(message, &pos), text, start.min(text.len())));
+    };
+
+    let mut chars = CharIter::new(rest);
+
+    let result = match chars.peek() {
+        Some(ch) if starts(ch) => scan(&mut chars, &mut pos),
+        Some(ch) => Err(unrecognised_character(ch, &pos)),
+        None => Err(JsonError::at_pos("Found the end of the text where a token was needed.", &pos).with_code(ErrorCode::UnexpectedEof))
+    };
+
+    let end = text.len() - chars.remaining_len();
+
+    result.map(|value| (value, Span { start, end })).map_err(|e| locate(e, text, end))
+}
+
+/// Scan the string starting with the quote at byte `start` of `text`, giving back
+/// its contents with escape sequences decoded, and where it was including its quotes.
+///
+/// This is the scanner the lexer uses, so `options` work the same way, like
+/// `allow_single_quotes` letting the string start with `'`. Errors are located
+/// in the whole of `text`, not from `start`.
+pub fn scan_string(text: &str, start: usize, options: &LexOptions) -> Result<(String, Span)> {
+    let starts = |ch| ch == '"' || (ch == '\'' && options.allow_single_quotes);
+
+    scan_at(text, start, starts, |chars, pos| try_get_string(chars, pos, options)).map(|(token, span)| match token.payload {
+        Payload::String { decoded } => (decoded, span),
+        _ => unreachable!("the string scanner only makes strings")
+    })
+}
+
+/// Scan the number starting at byte `start` of `text`, giving back the number as it
+/// was written, like `-1.5e3`, and where it was.
+///
+/// The number is checked the same way the lexer checks it, along with the limits in `options`,
+/// but it's left as text, so the caller can decide what to turn it into. It's a float
+/// when it has a fraction or an exponent.
+pub fn scan_number<'a>(text: &'a str, start: usize, options: &LexOptions) -> Result<(&'a str, Span)> {
+    let starts = |ch: char| ch.is_ascii_digit() || ch == '-' || (matches!(ch, '+' | '.') && options.allow_json5_numbers);
+
+    scan_at(text, start, starts, |chars, pos| try_get_number(chars, pos, options)).map(|(_, span)| (&text[span.start..span.end], span))
+}
+
+/// Scan the bare word starting at byte `start` of `text`, like `true`, `false` or `null`,
+/// giving back the word and where it was.
+///
+/// Any word made of letters, digits and underscores is scanned, the same as in the lexer,
+/// so checking which word it is is left to the caller, like the parser does.
+pub fn scan_literal(text: &str, start: usize) -> Result<(&str, Span)> {
+    let starts = |ch: char| ch.is_ascii_alphabetic() || ch == '_';
+
+    scan_at(text, start, starts, |chars, pos| Ok(try_get_name(chars, pos))).map(|(_, span)| (&text[span.start..span.end], span))
+}
+
+/// A guess at how many tokens `text_len` bytes of JSON will turn into.
+///
+/// Typical documents have a token every 4 to 8 bytes, counting whitespace,
+/// so this guesses low and lets the vector grow once at most, rather than
+/// reserving far more than minified input needs.
+pub fn estimate_token_count(text_len: usize) -> usize {
+    text_len / 8
+}
+
+pub fn tokenise(text: &str) -> Result<Vec<Token>> {
+    tokenise_with_capacity(text, estimate_token_count(text.len()))
+}
+
+pub fn tokenise_with_options(text: &str, options: LexOptions) -> Result<Vec<Token>> {
+    collect_tokens(Lexer::with_options(text, options), estimate_token_count(text.len()))
+}
+
+/// Tokenise text into a vector that starts with room for `capacity` tokens,
+/// for when the caller has a better idea of how many there will be.
+pub fn tokenise_with_capacity(text: &str, capacity: usize) -> Result<Vec<Token>> {
+    collect_tokens(Lexer::new(text), capacity)
+}
+
+fn collect_tokens(mut lexer: Lexer, capacity: usize) -> Result<Vec<Token>> {
+    let mut tokens = Vec::with_capacity(capacity);
+
+    while let Some(token) = lexer.next_token()? {
         tokens.push(token);
     }
 
-    tokens
-}
\ No newline at end of file
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lex(text: &str, options: LexOptions) -> Result<Vec<Token>> {
+        tokenise_with_options(text, options)
+    }
+
+    fn string(text: &str, options: LexOptions) -> Result<String> {
+        scan_string(text, 0, &options).map(|(decoded, _)| decoded)
+    }
+
+    #[test]
+    fn numbers_with_exponents_are_floats() {
+        let tokens = tokenise("[1, 1.0, 1e3, -2E-2]").unwrap();
+        let types: Vec<_> = tokens.iter().filter(|t| t.tok_type != TokenType::Comma).map(|t| t.tok_type).collect();
+
+        assert_eq!(types[1..5], [TokenType::Int, TokenType::Float, TokenType::Float, TokenType::Float]);
+        assert_eq!(tokens[3].text(), "1.0");
+    }
+
+    #[test]
+    fn escape_policy_decides_on_bad_escapes() {
+        let error = string(r#""a\qb""#, LexOptions::default()).unwrap_err();
+        assert_eq!(error.code, ErrorCode::InvalidEscape);
+
+        let replace = LexOptions { escape_policy: EscapePolicy::Replace, ..LexOptions::default() };
+        assert_eq!(string(r#""a\qb""#, replace).unwrap(), "a\u{fffd}b");
+        assert_eq!(string(r#""\ud800x""#, replace).unwrap(), "\u{fffd}x");
+
+        let pass = LexOptions { escape_policy: EscapePolicy::PassThrough, ..LexOptions::default() };
+        assert_eq!(string(r#""a\qb""#, pass).unwrap(), r"a\qb");
+    }
+
+    #[test]
+    fn surrogate_escapes_are_paired_up() {
+        assert_eq!(string(r#""\ud83d\ude00""#, LexOptions::default()).unwrap(), "😀");
+        assert!(string(r#""\ude00""#, LexOptions::default()).is_err());
+    }
+
+    #[test]
+    fn nul_policy_decides_on_nuls() {
+        assert_eq!(string(r#""a\u0000""#, LexOptions::default()).unwrap(), "a\0");
+
+        let error = LexOptions { nul_policy: NulPolicy::Error, ..LexOptions::default() };
+        assert_eq!(string(r#""a\u0000""#, error).unwrap_err().code, ErrorCode::NulInString);
+
+        let replace = LexOptions { nul_policy: NulPolicy::Replace, ..LexOptions::default() };
+        assert_eq!(string("\"a\0\"", replace).unwrap(), "a\u{fffd}");
+    }
+
+    #[test]
+    fn long_strings_are_copied_whole() {
+        let text = format!("\"{}\\n{}\"", "x".repeat(100), "é".repeat(100));
+        assert_eq!(string(&text, LexOptions::default()).unwrap(), format!("{}\n{}", "x".repeat(100), "é".repeat(100)));
+    }
+
+    #[test]
+    fn comments_and_single_quotes_need_their_options() {
+        let text = "// note\n['a', /* b */ 1]";
+        assert!(tokenise(text).is_err());
+
+        let options = LexOptions { allow_comments: true, allow_single_quotes: true, ..LexOptions::default() };
+        let tokens = lex(text, options).unwrap();
+
+        assert_eq!(tokens.len(), 5);
+        assert_eq!(tokens[1].text(), "a");
+    }
+
+    #[test]
+    fn json5_numbers_need_their_option() {
+        for text in ["0x1F", "+1", ".5", "5."] {
+            assert!(tokenise(text).is_err(), "{} should be rejected", text);
+
+            let options = LexOptions { allow_json5_numbers: true, ..LexOptions::default() };
+            assert_eq!(lex(text, options).unwrap().len(), 1, "{} should be accepted", text);
+        }
+    }
+
+    #[test]
+    fn number_limits_are_enforced() {
+        let digits = LexOptions { max_number_digits: 5, ..LexOptions::default() };
+        assert!(lex("12345", digits).is_ok());
+        assert_eq!(lex("123456", digits).unwrap_err().code, ErrorCode::LimitExceeded);
+
+        let exponent = LexOptions { max_exponent: 10, ..LexOptions::default() };
+        assert!(lex("1e-10", exponent).is_ok());
+        assert_eq!(lex("1e11", exponent).unwrap_err().code, ErrorCode::LimitExceeded);
+    }
+
+    #[test]
+    fn tokens_and_errors_know_where_they_are() {
+        let tokens = tokenise("{\n  \"a\": 1}").unwrap();
+
+        assert_eq!(tokens[1].offset(), 4);
+        assert_eq!((tokens[1].line(), tokens[1].column()), (2, 3));
+
+        let error = tokenise("[1,\n  @]").unwrap_err();
+        assert_eq!((error.line, error.column), (2, 3));
+        assert_eq!(error.offset, Some(6));
+    }
+
+    #[test]
+    fn scanners_give_back_spans() {
+        let text = r#"[ "a\"b", -1.5e3, true ]"#;
+
+        assert_eq!(scan_string(text, 2, &LexOptions::default()).unwrap(), ("a\"b".to_string(), Span { start: 2, end: 8 }));
+        assert_eq!(scan_number(text, 10, &LexOptions::default()).unwrap(), ("-1.5e3", Span { start: 10, end: 16 }));
+        assert_eq!(scan_literal(text, 18).unwrap(), ("true", Span { start: 18, end: 22 }));
+
+        let error = scan_number(text, 2, &LexOptions::default()).unwrap_err();
+        assert_eq!(error.offset, Some(2));
+    }
+
+    #[test]
+    fn capacity_doesnt_change_the_tokens() {
+        let text = r#"{"a": [1, 2, {"b": null}]}"#;
+        assert_eq!(tokenise_with_capacity(text, 0).unwrap().len(), tokenise(text).unwrap().len());
+    }
+}
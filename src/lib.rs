@@ -0,0 +1,57 @@
+#[macro_use]
+mod macros;
+
+pub mod append;
+pub mod cache;
+pub mod codegen;
+pub mod coerce;
+pub mod comments;
+pub mod compare;
+pub mod config;
+pub mod convert;
+pub mod edit;
+pub mod error;
+pub mod filter;
+#[cfg(feature = "test_fixtures")]
+pub mod fixtures;
+pub mod framing;
+#[cfg(feature = "geojson")]
+pub mod geojson;
+pub mod generate;
+pub mod glob;
+pub mod hash;
+pub mod include;
+pub mod jwt;
+pub mod lexer;
+pub mod lines;
+pub mod lint;
+pub mod literal;
+pub mod memory;
+pub mod merge;
+pub mod metrics;
+pub mod migrate;
+#[cfg(feature = "normalize")]
+pub mod normalize;
+pub mod parser;
+pub mod patch;
+pub mod pointer;
+pub mod pool;
+pub mod profile;
+pub mod refs;
+pub mod render;
+pub mod repair;
+pub mod rewrite;
+pub mod schema;
+pub mod search;
+pub mod serializer;
+pub mod shard;
+pub mod shared;
+pub mod snapshot;
+pub mod stats;
+pub mod template;
+pub mod token;
+pub mod truncate;
+pub mod utils;
+pub mod validate;
+#[cfg(feature = "watch")]
+pub mod watch;
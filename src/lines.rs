@@ -0,0 +1,95 @@
+/// The byte offset of the start of every line in a text, for converting
+/// between byte offsets and 1-based lines and columns.
+///
+/// Columns count characters, the same as in `JsonError`, and lines are split on `\n`.
+/// Building the index is one pass over the text, and each conversion after that is a binary search.
+pub struct LineIndex<'a> {
+    text: &'a str,
+    starts: Vec<usize>
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(text: &'a str) -> LineIndex<'a> {
+        let mut starts = vec![0];
+
+        starts.extend(text.bytes().enumerate().filter(|(_, b)| *b == b'\n').map(|(i, _)| i + 1));
+
+        LineIndex {
+            text,
+            starts
+        }
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.starts.len()
+    }
+
+    /// The text of a line, without its line ending.
+    pub fn line(&self, line: usize) -> Option<&'a str> {
+        let start = *self.starts.get(line.checked_sub(1)?)?;
+
+        let end = match self.starts.get(line) {
+            Some(next) => next - 1,
+            None => self.text.len()
+        };
+
+        let text = &self.text[start..end];
+
+        Some(text.strip_suffix('\r').unwrap_or(text))
+    }
+
+    /// The line and column of a byte offset.
+    ///
+    /// Offsets past the end of the text are treated as the end, and offsets
+    /// in the middle of a character as the start of it.
+    pub fn offset_to_line_col(&self, offset: usize) -> (usize, usize) {
+        let mut offset = offset.min(self.text.len());
+
+        while !self.text.is_char_boundary(offset) {
+            offset -= 1;
+        }
+
+        // The last line starting at or before the offset.
+        let line = self.starts.partition_point(|&start| start <= offset);
+        let start = self.starts[line - 1];
+
+        (line, self.text[start..offset].chars().count() + 1)
+    }
+
+    /// The byte offset of a line and column, or `None` if the line doesn't exist
+    /// or the column is past the end of it.
+    ///
+    /// The column just after the last character of a line is allowed,
+    /// since that's where text would be added to the end of it.
+    pub fn line_col_to_offset(&self, line: usize, column: usize) -> Option<usize> {
+        let text = self.line(line)?;
+        let start = self.starts[line - 1];
+
+        match column.checked_sub(1)? {
+            0 => Some(start),
+            n => match text.char_indices().nth(n) {
+                Some((i, _)) => Some(start + i),
+                None if n == text.chars().count() => Some(start + text.len()),
+                None => None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn offsets_and_lines_convert_both_ways() {
+        let text = "ab\néc\r\n\nd";
+        let lines = LineIndex::new(text);
+
+        assert_eq!(lines.line_count(), 4);
+        assert_eq!(lines.line(2), Some("éc"));
+        assert_eq!(lines.offset_to_line_col(5), (2, 2));
+        assert_eq!(lines.line_col_to_offset(2, 2), Some(5));
+        assert_eq!(lines.offset_to_line_col(text.len() + 10), lines.offset_to_line_col(text.len()));
+        assert_eq!(lines.line_col_to_offset(9, 1), None);
+    }
+}
@@ -0,0 +1,265 @@
+use std::fmt::Display;
+
+use crate::error::Result;
+use crate::lexer::tokenise;
+use crate::parser::parse;
+use crate::token::{Token, TokenType as TT};
+use crate::utils::{index_path, key_path, TokenIter};
+
+/// A naming convention for object keys.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum KeyCase {
+    /// `like_this`
+    Snake,
+
+    /// `likeThis`
+    Camel,
+
+    /// `LikeThis`
+    Pascal,
+
+    /// `like-this`
+    Kebab
+}
+
+impl KeyCase {
+    pub fn matches(&self, key: &str) -> bool {
+        let words_joined_by = |sep: char| !key.is_empty() && key.split(sep).all(
+            |word| !word.is_empty() && word.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+        );
+
+        let alphanumeric = key.chars().all(|c| c.is_ascii_alphanumeric());
+
+        match self {
+            KeyCase::Snake => words_joined_by('_'),
+            KeyCase::Kebab => words_joined_by('-'),
+            KeyCase::Camel => alphanumeric && key.starts_with(|c: char| c.is_ascii_lowercase()),
+            KeyCase::Pascal => alphanumeric && key.starts_with(|c: char| c.is_ascii_uppercase())
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            KeyCase::Snake => "snake_case",
+            KeyCase::Camel => "camelCase",
+            KeyCase::Pascal => "PascalCase",
+            KeyCase::Kebab => "kebab-case"
+        }
+    }
+}
+
+/// Which lint rules to check. Every rule but `no_duplicate_keys` is off by default.
+pub struct LintConfig {
+    /// The naming convention every key should follow.
+    pub key_case: Option<KeyCase>,
+
+    pub no_duplicate_keys: bool,
+
+    /// How deeply arrays and objects can be nested.
+    pub max_depth: Option<usize>,
+
+    /// How many significant digits a number can have,
+    /// like 15 to catch numbers a double can't hold exactly.
+    pub max_significant_digits: Option<usize>,
+
+    pub forbid_nulls: bool,
+
+    /// Whether the keys of every object should be in sorted order.
+    pub sorted_keys: bool
+}
+
+impl Default for LintConfig {
+    fn default() -> LintConfig {
+        LintConfig {
+            key_case: None,
+            no_duplicate_keys: true,
+            max_depth: None,
+            max_significant_digits: None,
+            forbid_nulls: false,
+            sorted_keys: false
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Rule {
+    KeyCase,
+    DuplicateKey,
+    MaxDepth,
+    NumberPrecision,
+    ForbiddenNull,
+    UnsortedKeys
+}
+
+impl Rule {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rule::KeyCase => "key-case",
+            Rule::DuplicateKey => "duplicate-key",
+            Rule::MaxDepth => "max-depth",
+            Rule::NumberPrecision => "number-precision",
+            Rule::ForbiddenNull => "forbidden-null",
+            Rule::UnsortedKeys => "sorted-keys"
+        }
+    }
+}
+
+/// A place where the text breaks one of the lint rules.
+#[derive(Debug)]
+pub struct LintWarning {
+    pub rule: Rule,
+
+    /// Where the problem is, like `$.users[2].first_name`.
+    pub path: String,
+
+    pub line: u32,
+    pub column: u32,
+    pub message: String
+}
+
+impl Display for LintWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: {} at {} [{}]", self.line, self.column, self.message, self.path, self.rule.name())
+    }
+}
+
+/// Count the significant digits of a number token, like 3 for `-0.00120e5`.
+fn significant_digits(number: &str) -> usize {
+    let mantissa = number.split(['e', 'E']).next().unwrap_or(number);
+    let digits: String = mantissa.chars().filter(|c| c.is_ascii_digit()).collect();
+
+    // Leading zeros never count, and neither do trailing zeros of a whole number.
+    let trimmed = digits.trim_start_matches('0');
+
+    if mantissa.contains('.') {
+        trimmed.len()
+    }
+    else {
+        trimmed.trim_end_matches('0').len()
+    }
+}
+
+struct Linter<'a> {
+    config: &'a LintConfig,
+    warnings: Vec<LintWarning>
+}
+
+impl<'a> Linter<'a> {
+    fn warn(&mut self, rule: Rule, path: &str, token: &Token, message: String) {
+        self.warnings.push(LintWarning {
+            rule,
+            path: path.to_string(),
+            line: token.line(),
+            column: token.column(),
+            message
+        });
+    }
+
+    /// Check the depth of an array or object that starts with `token`.
+    fn check_depth(&mut self, token: &Token, path: &str, depth: usize) {
+        // Only the outermost container that is too deep is reported.
+        if let Some(max) = self.config.max_depth && depth == max + 1 {
+            self.warn(Rule::MaxDepth, path, token, format!("Nested more than {} levels deep", max));
+        }
+    }
+
+    // The text has already been parsed, so its structure can be trusted here.
+    fn walk(&mut self, tokens: &mut TokenIter, path: &str, depth: usize) {
+        let token = tokens.next().unwrap();
+
+        match token.tok_type {
+            TT::LSqBrac => {
+                self.check_depth(token, path, depth + 1);
+
+                let mut index = 0;
+
+                while tokens.peek().unwrap().tok_type != TT::RSqBrac {
+                    self.walk(tokens, &index_path(path, index), depth + 1);
+
+                    if tokens.peek().unwrap().tok_type == TT::Comma {
+                        tokens.next();
+                    }
+
+                    index += 1;
+                }
+
+                tokens.next();
+            },
+
+            TT::LBrace => {
+                self.check_depth(token, path, depth + 1);
+
+                let mut seen: Vec<&str> = Vec::new();
+
+                while tokens.peek().unwrap().tok_type != TT::RBrace {
+                    let key = tokens.next().unwrap();
+                    let value_path = key_path(path, key.text());
+
+                    if let Some(case) = self.config.key_case && !case.matches(key.text()) {
+                        self.warn(Rule::KeyCase, &value_path, key, format!("Key {:?} is not {}", key.text(), case.name()));
+                    }
+
+                    if self.config.no_duplicate_keys && seen.contains(&key.text()) {
+                        self.warn(Rule::DuplicateKey, &value_path, key, format!("Key {:?} appears more than once", key.text()));
+                    }
+
+                    if self.config.sorted_keys && let Some(previous) = seen.last() && key.text() < *previous {
+                        self.warn(Rule::UnsortedKeys, &value_path, key, format!("Key {:?} should come before {:?}", key.text(), previous));
+                    }
+
+                    seen.push(key.text());
+
+                    // Skip the colon.
+                    tokens.next();
+
+                    self.walk(tokens, &value_path, depth + 1);
+
+                    if tokens.peek().unwrap().tok_type == TT::Comma {
+                        tokens.next();
+                    }
+                }
+
+                tokens.next();
+            },
+
+            TT::Int | TT::Float => {
+                if let Some(max) = self.config.max_significant_digits {
+                    let digits = significant_digits(token.text());
+
+                    if digits > max {
+                        self.warn(Rule::NumberPrecision, path, token, format!("Number {} has {} significant digits, more than {}", token.text(), digits, max));
+                    }
+                }
+            },
+
+            TT::Name if self.config.forbid_nulls && token.text() == "null" => {
+                self.warn(Rule::ForbiddenNull, path, token, "Found null".to_string());
+            },
+
+            _ => {}
+        }
+    }
+}
+
+/// Check JSON text against the lint rules, giving back every warning in the order found.
+///
+/// Rules like duplicate and sorted keys depend on how the text was written,
+/// so linting works on the text rather than on a parsed `Node`.
+/// Text that isn't valid JSON is an error.
+pub fn lint(text: &str, config: &LintConfig) -> Result<Vec<LintWarning>> {
+    let tokens = tokenise(text)?;
+
+    // Make sure the structure is valid before trusting it.
+    parse(&tokens)?;
+
+    let mut linter = Linter {
+        config,
+        warnings: Vec::new()
+    };
+
+    if !tokens.is_empty() {
+        linter.walk(&mut TokenIter::new(&tokens), "$", 0);
+    }
+
+    Ok(linter.warnings)
+}
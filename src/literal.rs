@@ -0,0 +1,497 @@
+/// Where and why `validate` rejected some text.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LiteralError {
+    pub message: &'static str,
+    pub line: u32,
+    pub column: u32
+}
+
+/// The text of a `LiteralError`, built without allocating so it can be made at compile time.
+pub struct LiteralMessage {
+    bytes: [u8; 128],
+    len: usize
+}
+
+impl LiteralMessage {
+    const fn push(&mut self, s: &str) {
+        let s = s.as_bytes();
+        let mut i = 0;
+
+        while i < s.len() && self.len < self.bytes.len() {
+            self.bytes[self.len] = s[i];
+            self.len += 1;
+            i += 1;
+        }
+    }
+
+    const fn push_number(&mut self, mut n: u32) {
+        let mut digits = [0u8; 10];
+        let mut count = 0;
+
+        loop {
+            digits[count] = b'0' + (n % 10) as u8;
+            count += 1;
+            n /= 10;
+
+            if n == 0 {
+                break;
+            }
+        }
+
+        while count > 0 && self.len < self.bytes.len() {
+            count -= 1;
+            self.bytes[self.len] = digits[count];
+            self.len += 1;
+        }
+    }
+
+    pub const fn as_str(&self) -> &str {
+        // Everything pushed was ASCII, so this can't fail.
+        match std::str::from_utf8(self.bytes.split_at(self.len).0) {
+            Ok(s) => s,
+            Err(_) => ""
+        }
+    }
+}
+
+impl LiteralError {
+    /// Write out the error the same way a `JsonError` would be,
+    /// like `Invalid JSON literal: expected ':' [Line: 1, Column: 6]`.
+    pub const fn render(&self) -> LiteralMessage {
+        let mut out = LiteralMessage {
+            bytes: [0; 128],
+            len: 0
+        };
+
+        out.push("Invalid JSON literal: ");
+        out.push(self.message);
+        out.push(" [Line: ");
+        out.push_number(self.line);
+        out.push(", Column: ");
+        out.push_number(self.column);
+        out.push("]");
+
+        out
+    }
+}
+
+/// The most digits a number can have, and the biggest exponent it can have,
+/// the same as the limits `LexOptions` has by default.
+const MAX_DIGITS: usize = 1000;
+const MAX_EXPONENT: u64 = 10_000;
+
+// `?` can't be used in a const fn, so this does the same thing by hand.
+macro_rules! attempt {
+    ($e:expr) => {
+        match $e {
+            Ok(x) => x,
+            Err(e) => return Err(e)
+        }
+    };
+}
+
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    index: usize,
+    line: u32,
+    column: u32
+}
+
+impl<'a> Cursor<'a> {
+    const fn peek(&self) -> Option<u8> {
+        match self.index < self.bytes.len() {
+            true => Some(self.bytes[self.index]),
+            false => None
+        }
+    }
+
+    const fn advance(&mut self) {
+        let b = self.bytes[self.index];
+        self.index += 1;
+
+        if b == b'\n' {
+            self.line += 1;
+            self.column = 1;
+        }
+        // Continuation bytes are part of the character before them.
+        else if b & 0xc0 != 0x80 {
+            self.column += 1;
+        }
+    }
+
+    const fn error(&self, message: &'static str) -> LiteralError {
+        LiteralError {
+            message,
+            line: self.line,
+            column: self.column
+        }
+    }
+
+    const fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\r' | b'\n') = self.peek() {
+            self.advance();
+        }
+    }
+
+    const fn expect(&mut self, b: u8, message: &'static str) -> Result<(), LiteralError> {
+        match self.peek() {
+            Some(x) if x == b => {
+                self.advance();
+                Ok(())
+            },
+            _ => Err(self.error(message))
+        }
+    }
+
+    /// Read a run of digits, adding how many there were to `count`.
+    const fn digits(&mut self, count: &mut usize) -> Result<(), LiteralError> {
+        match self.peek() {
+            Some(b'0'..=b'9') => {},
+            _ => return Err(self.error("expected a digit"))
+        }
+
+        while let Some(b'0'..=b'9') = self.peek() {
+            *count += 1;
+
+            if *count > MAX_DIGITS {
+                return Err(self.error("number has more than 1000 digits"));
+            }
+
+            self.advance();
+        }
+
+        Ok(())
+    }
+
+    const fn exponent(&mut self) -> Result<(), LiteralError> {
+        match self.peek() {
+            Some(b'0'..=b'9') => {},
+            _ => return Err(self.error("expected a digit"))
+        }
+
+        // Leading zeros don't change the size, and anything past the limit is too big anyway.
+        let mut value: u64 = 0;
+
+        while let Some(b @ b'0'..=b'9') = self.peek() {
+            value = value * 10 + (b - b'0') as u64;
+
+            if value > MAX_EXPONENT {
+                return Err(self.error("number has an exponent bigger than 10000"));
+            }
+
+            self.advance();
+        }
+
+        Ok(())
+    }
+
+    /// Check the integer in `start..self.index` fits in an `i64`, or a `u64` if it isn't negative.
+    const fn integer_fits(&self, start: usize, negative: bool) -> Result<(), LiteralError> {
+        let mut value: u128 = 0;
+        let mut i = start;
+
+        while i < self.index {
+            value = value * 10 + (self.bytes[i] - b'0') as u128;
+
+            // Past this, the value is too big whatever its sign is.
+            if value > u64::MAX as u128 {
+                break;
+            }
+
+            i += 1;
+        }
+
+        let limit = match negative {
+            true => 1 << 63,
+            false => u64::MAX as u128
+        };
+
+        match value > limit {
+            true => Err(self.error("integer is out of range")),
+            false => Ok(())
+        }
+    }
+
+    const fn number(&mut self) -> Result<(), LiteralError> {
+        // Digits are counted across the integer and fraction, like the lexer does.
+        let mut count = 0;
+        let negative = matches!(self.peek(), Some(b'-'));
+
+        if negative {
+            self.advance();
+        }
+
+        let start = self.index;
+
+        match self.peek() {
+            // A leading zero can't have more digits after it.
+            Some(b'0') => {
+                count += 1;
+                self.advance();
+            },
+            _ => attempt!(self.digits(&mut count))
+        }
+
+        // Without a fraction or exponent it's an integer, which has to fit in 64 bits.
+        if !matches!(self.peek(), Some(b'.' | b'e' | b'E')) {
+            return self.integer_fits(start, negative);
+        }
+
+        if let Some(b'.') = self.peek() {
+            self.advance();
+            attempt!(self.digits(&mut count));
+        }
+
+        if let Some(b'e' | b'E') = self.peek() {
+            self.advance();
+
+            if let Some(b'+' | b'-') = self.peek() {
+                self.advance();
+            }
+
+            attempt!(self.exponent());
+        }
+
+        Ok(())
+    }
+
+    /// Read the 4 hex digits after a `\u`.
+    const fn hex(&mut self) -> Result<u32, LiteralError> {
+        let mut code = 0;
+        let mut i = 0;
+
+        while i < 4 {
+            let digit = match self.peek() {
+                Some(b @ b'0'..=b'9') => b - b'0',
+                Some(b @ b'a'..=b'f') => b - b'a' + 10,
+                Some(b @ b'A'..=b'F') => b - b'A' + 10,
+                _ => return Err(self.error("expected 4 hex digits after \\u"))
+            };
+
+            code = code * 16 + digit as u32;
+            self.advance();
+            i += 1;
+        }
+
+        Ok(code)
+    }
+
+    /// Read a `\u` escape, after the `u`, along with the low surrogate after it if it's a high one.
+    ///
+    /// `lone` is the error to give back if a surrogate isn't part of a pair.
+    const fn unicode(&mut self, lone: LiteralError) -> Result<(), LiteralError> {
+        match attempt!(self.hex()) {
+            0xd800..=0xdbff => {},
+            0xdc00..=0xdfff => return Err(lone),
+            _ => return Ok(())
+        }
+
+        // A high surrogate has to have a low one straight after it.
+        let has_low = self.index + 1 < self.bytes.len() && self.bytes[self.index] == b'\\' && self.bytes[self.index + 1] == b'u';
+
+        if !has_low {
+            return Err(lone);
+        }
+
+        self.advance();
+        self.advance();
+
+        match attempt!(self.hex()) {
+            0xdc00..=0xdfff => Ok(()),
+            _ => Err(lone)
+        }
+    }
+
+    const fn string(&mut self) -> Result<(), LiteralError> {
+        // Skip the opening quote.
+        self.advance();
+
+        loop {
+            match self.peek() {
+                None => return Err(self.error("unterminated string")),
+                Some(b'"') => {
+                    self.advance();
+                    return Ok(());
+                },
+                Some(b'\\') => {
+                    // Surrogate errors point at the start of the escape, not past its digits.
+                    let escape = self.error("lone surrogate in \\u escape");
+                    self.advance();
+
+                    match self.peek() {
+                        Some(b'"' | b'\\' | b'/' | b'b' | b'f' | b'n' | b'r' | b't') => self.advance(),
+                        Some(b'u') => {
+                            self.advance();
+                            attempt!(self.unicode(escape));
+                        },
+                        _ => return Err(self.error("invalid escape sequence"))
+                    }
+                },
+                Some(b) if b < 0x20 => return Err(self.error("control character in string")),
+                Some(_) => self.advance()
+            }
+        }
+    }
+
+    const fn word(&mut self, word: &str) -> Result<(), LiteralError> {
+        let word = word.as_bytes();
+        let mut i = 0;
+
+        while i < word.len() {
+            attempt!(self.expect(word[i], "expected true, false or null"));
+            i += 1;
+        }
+
+        Ok(())
+    }
+
+    const fn array(&mut self) -> Result<(), LiteralError> {
+        // Skip the '['.
+        self.advance();
+        self.skip_whitespace();
+
+        if let Some(b']') = self.peek() {
+            self.advance();
+            return Ok(());
+        }
+
+        loop {
+            attempt!(self.value());
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some(b',') => self.advance(),
+                Some(b']') => {
+                    self.advance();
+                    return Ok(());
+                },
+                _ => return Err(self.error("expected ',' or ']'"))
+            }
+        }
+    }
+
+    const fn object(&mut self) -> Result<(), LiteralError> {
+        // Skip the '{'.
+        self.advance();
+        self.skip_whitespace();
+
+        if let Some(b'}') = self.peek() {
+            self.advance();
+            return Ok(());
+        }
+
+        loop {
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some(b'"') => attempt!(self.string()),
+                _ => return Err(self.error("expected a string key"))
+            }
+
+            self.skip_whitespace();
+            attempt!(self.expect(b':', "expected ':'"));
+            attempt!(self.value());
+            self.skip_whitespace();
+
+            match self.peek() {
+                Some(b',') => self.advance(),
+                Some(b'}') => {
+                    self.advance();
+                    return Ok(());
+                },
+                _ => return Err(self.error("expected ',' or '}'"))
+            }
+        }
+    }
+
+    const fn value(&mut self) -> Result<(), LiteralError> {
+        self.skip_whitespace();
+
+        match self.peek() {
+            Some(b'{') => self.object(),
+            Some(b'[') => self.array(),
+            Some(b'"') => self.string(),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(b't') => self.word("true"),
+            Some(b'f') => self.word("false"),
+            Some(b'n') => self.word("null"),
+            _ => Err(self.error("expected a value"))
+        }
+    }
+}
+
+/// Check that text is a single, strictly valid JSON value.
+///
+/// This follows the same rules as `parse_str` with the default options, so surrogate
+/// escapes have to be paired up, integers have to fit in 64 bits, and numbers can't have
+/// more than 1000 digits or an exponent bigger than 10000.
+///
+/// This is a `const fn`, so it can run at compile time, which is what `json_literal!` uses it for.
+/// It only checks the text, so use `parse` to get a `Node` from it.
+pub const fn validate(text: &str) -> Result<(), LiteralError> {
+    let mut cursor = Cursor {
+        bytes: text.as_bytes(),
+        index: 0,
+        line: 1,
+        column: 1
+    };
+
+    attempt!(cursor.value());
+    cursor.skip_whitespace();
+
+    match cursor.peek() {
+        Some(_) => Err(cursor.error("unexpected text after the value")),
+        None => Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{parse_str, parse_with_options};
+    use crate::profile::Profile;
+
+    // Checked at compile time, so this only has to build.
+    const EMOJI: &str = crate::json_literal!(r#"{"face": "\ud83d\ude00", "n": -1.5e-10000}"#);
+
+    #[test]
+    fn validate_agrees_with_parse_str() {
+        let long = format!("1{}.0", "0".repeat(998));
+        let too_long = format!("1{}.0", "0".repeat(999));
+        let long_fraction = format!("0.{}", "5".repeat(999));
+        let too_long_fraction = format!("0.{}", "5".repeat(1000));
+
+        let texts = [
+            EMOJI, "[]", "{}", " 0 ", "-0.5e+3", r#""\u00e9""#, "[1, [2, {\"a\": null}]]",
+            "1.", ".5", "+1", "1e", "[1,]", "{\"a\" 1}", "tru", r#""\q""#, "1 2",
+            r#""\ud83d""#, r#""\ude00""#, r#""\ud83dx""#, r#""\ud83d\n""#, r#""\ud83d\u0041""#, r#""\ud83d\ud83d""#,
+            &long, &too_long, &long_fraction, &too_long_fraction, "1e999",
+            "18446744073709551615", "18446744073709551616", "-9223372036854775808", "-9223372036854775809",
+            "1e10000", "1e10001", "1E-0010000", "1e-10001", "1e99999999999999999999"
+        ];
+
+        for text in texts {
+            assert_eq!(validate(text).is_ok(), parse_str(text).is_ok(), "{:?}", text);
+        }
+
+        // Leading zeros and raw control characters are only let through by the default options,
+        // and are rejected like in the rfc8259 profile.
+        for text in ["01", "\"\t\""] {
+            assert!(validate(text).is_err(), "{:?}", text);
+            assert!(parse_with_options(text, &Profile::Rfc8259.options()).is_err(), "{:?}", text);
+        }
+
+        // `parse_str` gives back `Node::Empty` for empty text, but a literal has to have a value.
+        assert!(validate("").is_err());
+        assert!(validate(" \n").is_err());
+    }
+
+    #[test]
+    fn errors_say_where_they_are() {
+        let error = validate("{\n  \"a\": \"\\udc00\"}").unwrap_err();
+
+        assert_eq!(error.message, "lone surrogate in \\u escape");
+        assert_eq!((error.line, error.column), (2, 9));
+        assert_eq!(error.render().as_str(), "Invalid JSON literal: lone surrogate in \\u escape [Line: 2, Column: 9]");
+    }
+}
@@ -0,0 +1,125 @@
+/// Build a `Node` using JSON syntax.
+///
+/// Any value that isn't an array, object or `null`/`true`/`false`
+/// is treated as a Rust expression and converted with `Node::from`.
+#[macro_export]
+macro_rules! json {
+    (null)  => { $crate::parser::Node::Null };
+    (true)  => { $crate::parser::Node::Bool(true) };
+    (false) => { $crate::parser::Node::Bool(false) };
+
+    ([]) => { $crate::parser::Node::Array(::std::vec::Vec::new()) };
+    ([ $($tt:tt)+ ]) => {
+        $crate::parser::Node::Array($crate::json_internal!(@array [] () $($tt)+))
+    };
+
+    ({}) => { $crate::parser::Node::Object($crate::parser::ObjectMap::default()) };
+    ({ $($tt:tt)+ }) => {
+        $crate::parser::Node::Object({
+            let mut map = $crate::parser::ObjectMap::default();
+            $crate::json_internal!(@object map $($tt)+);
+            map
+        })
+    };
+
+    ($other:expr) => { $crate::parser::Node::from($other) };
+}
+
+/// Splits the contents of arrays and objects on top-level commas for `json!`.
+#[macro_export]
+#[doc(hidden)]
+macro_rules! json_internal {
+    // Arrays: collect tokens for the current item until we reach a comma.
+    (@array [$($items:expr,)*] ()) => { ::std::vec![$($items,)*] };
+    (@array [$($items:expr,)*] ($($cur:tt)+)) => {
+        ::std::vec![$($items,)* $crate::json!($($cur)+)]
+    };
+    (@array [$($items:expr,)*] ($($cur:tt)+) , $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($items,)* $crate::json!($($cur)+),] () $($rest)*)
+    };
+    (@array [$($items:expr,)*] ($($cur:tt)*) $next:tt $($rest:tt)*) => {
+        $crate::json_internal!(@array [$($items,)*] ($($cur)* $next) $($rest)*)
+    };
+
+    // Objects: read a key and colon, then collect the value the same way.
+    (@object $map:ident) => {};
+    (@object $map:ident $key:literal : $($rest:tt)+) => {
+        $crate::json_internal!(@value $map [$key] () $($rest)+)
+    };
+    (@value $map:ident [$key:expr] ($($cur:tt)+)) => {
+        $map.insert(::std::string::String::from($key), $crate::json!($($cur)+));
+    };
+    (@value $map:ident [$key:expr] ($($cur:tt)+) , $($rest:tt)*) => {
+        $map.insert(::std::string::String::from($key), $crate::json!($($cur)+));
+        $crate::json_internal!(@object $map $($rest)*);
+    };
+    (@value $map:ident [$key:expr] ($($cur:tt)*) $next:tt $($rest:tt)*) => {
+        $crate::json_internal!(@value $map [$key] ($($cur)* $next) $($rest)*)
+    };
+}
+
+/// Assert that two `Node`s are equal, reporting the path of the first difference.
+///
+/// An optional third argument gives the `Tolerance` to compare with,
+/// otherwise values must match exactly.
+#[macro_export]
+macro_rules! assert_json_eq {
+    ($actual:expr, $expected:expr $(,)?) => {
+        $crate::assert_json_eq!($actual, $expected, $crate::compare::Tolerance::exact())
+    };
+    ($actual:expr, $expected:expr, $tolerance:expr $(,)?) => {
+        match (&$actual, &$expected) {
+            (actual, expected) => {
+                if let Some(mismatch) = expected.first_mismatch(actual, &$tolerance) {
+                    panic!(
+                        "assertion `actual == expected` failed {}\n  actual: {:?}\nexpected: {:?}",
+                        mismatch,
+                        actual,
+                        expected
+                    );
+                }
+            }
+        }
+    };
+}
+
+/// Assert that a `Node` matches a pattern, where strings like `"<ANY>"` are wildcards.
+///
+/// See `Node::first_pattern_mismatch` for the supported wildcards.
+#[macro_export]
+macro_rules! assert_json_matches {
+    ($actual:expr, $pattern:expr $(,)?) => {
+        $crate::assert_json_matches!($actual, $pattern, $crate::compare::Tolerance::exact())
+    };
+    ($actual:expr, $pattern:expr, $tolerance:expr $(,)?) => {
+        match (&$actual, &$pattern) {
+            (actual, pattern) => {
+                if let Some(mismatch) = pattern.first_pattern_mismatch(actual, &$tolerance) {
+                    panic!(
+                        "assertion `actual matches pattern` failed {}\n  actual: {:?}\n pattern: {:?}",
+                        mismatch,
+                        actual,
+                        pattern
+                    );
+                }
+            }
+        }
+    };
+}
+
+/// Check a JSON string literal at compile time, giving it back as a `&'static str`.
+///
+/// Invalid JSON fails the build, with the line and column of the problem
+/// in the error. The text still needs parsing to get a `Node` from it.
+#[macro_export]
+macro_rules! json_literal {
+    ($text:expr $(,)?) => {{
+        const TEXT: &str = $text;
+
+        const _: () = if let ::std::result::Result::Err(error) = $crate::literal::validate(TEXT) {
+            ::std::panic!("{}", error.render().as_str());
+        };
+
+        TEXT
+    }};
+}
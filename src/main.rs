@@ -1,31 +1,13 @@
-mod token;
-mod lexer;
-mod utils;
-mod parser;
+mod cli;
 
-use std::fs::read_to_string;
-use std::time::Instant;
-
-use crate::lexer::tokenise;
-use crate::parser::parse;
+use std::env::args;
+use std::process::exit;
 
 fn main() {
-    let input = match read_to_string("massive-test.json") {
-        Ok(x) => x,
-        Err(e) => panic!("Could not read file: {}", e)
-    };
-
-    let start = Instant::now();
-
-    let tokens = tokenise(input.as_str());
-
-    let after_tokens = start.elapsed();
-
-    println!("Time taken to tokenise: {:?}", after_tokens);
-
-    let _result = parse(&tokens);
-
-    let duration = start.elapsed() - after_tokens;
+    let args: Vec<String> = args().skip(1).collect();
 
-    println!("Time taken to parse tokens: {:?}", duration);
-}
\ No newline at end of file
+    if let Err(e) = cli::run(&args) {
+        eprintln!("{}", e);
+        exit(1);
+    }
+}
@@ -0,0 +1,54 @@
+use std::mem::size_of;
+
+use crate::parser::{Node, ObjectMap};
+
+/// How many buckets a `HashMap` has room for to hold `capacity` entries,
+/// following the growth rules of the standard library's hash table.
+fn buckets(capacity: usize) -> usize {
+    match capacity {
+        0 => 0,
+        1..=3 => 4,
+        4..=7 => 8,
+        _ => (capacity * 8 / 7).next_power_of_two()
+    }
+}
+
+/// The heap memory of a `Vec<Node>` with room for `capacity` items, not counting what they own.
+pub(crate) fn array_bytes(capacity: usize) -> usize {
+    capacity * size_of::<Node>()
+}
+
+/// The heap memory of an object's table with room for `capacity` entries,
+/// not counting what the keys and values own.
+pub(crate) fn object_bytes(capacity: usize) -> usize {
+    match buckets(capacity) {
+        0 => 0,
+
+        // Each bucket has an entry and a control byte, and there's a group of control bytes spare at the end.
+        n => n * (size_of::<(String, Node)>() + 1) + 16
+    }
+}
+
+fn heap_bytes(node: &Node) -> usize {
+    match node {
+        Node::String(s) => s.capacity(),
+        Node::Array(arr) => array_bytes(arr.capacity()) + arr.iter().map(heap_bytes).sum::<usize>(),
+        Node::Object(map) => object_heap_bytes(map),
+        _ => 0
+    }
+}
+
+fn object_heap_bytes(map: &ObjectMap) -> usize {
+    object_bytes(map.capacity()) + map.iter().map(|(key, value)| key.capacity() + heap_bytes(value)).sum::<usize>()
+}
+
+impl Node {
+    /// About how many bytes of memory this tree takes up: the node itself, plus
+    /// everything its strings, arrays and objects have allocated, spare capacity included.
+    ///
+    /// Object sizes are worked out from how the standard library lays out hash tables,
+    /// and don't count the allocator's own overhead, so treat this as an estimate.
+    pub fn deep_size_of(&self) -> usize {
+        size_of::<Node>() + heap_bytes(self)
+    }
+}
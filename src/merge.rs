@@ -0,0 +1,320 @@
+use std::collections::HashMap;
+
+use crate::parser::Node;
+use crate::pointer::{parse_index, split_pointer};
+
+/// What `deep_merge_with` does when both sides have an array in the same place.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ArrayMerge {
+    /// The overlay's array replaces the one underneath it.
+    #[default]
+    Replace,
+
+    /// The overlay's items are added after the ones underneath them.
+    Concat
+}
+
+/// What `deep_merge_with` does with a `null` in an overlay object.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum NullMerge {
+    /// The `null` replaces the value underneath it, like any other value.
+    #[default]
+    Keep,
+
+    /// The key is taken out of the merged object, as in a JSON Merge Patch (RFC 7396),
+    /// so a layer can unset something an earlier one set.
+    Remove
+}
+
+/// How `deep_merge_with` merges values that aren't both objects.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MergeOptions {
+    pub arrays: ArrayMerge,
+    pub nulls: NullMerge
+}
+
+/// Which layer each value of a merged document came from, kept up by `deep_merge_traced`,
+/// for working out why a layered config ended up the way it did.
+#[derive(Clone, Debug)]
+pub struct Provenance {
+    layers: Vec<String>,
+    root: Trace
+}
+
+/// The layer a value came from, along with the layers of its items or entries.
+#[derive(Clone, Debug)]
+enum Trace {
+    Value(usize),
+    Array(usize, Vec<Trace>),
+    Object(usize, HashMap<String, Trace>)
+}
+
+impl Trace {
+    /// The trace of a value that all came from one layer.
+    fn of(node: &Node, layer: usize) -> Trace {
+        match node {
+            Node::Array(items) => Trace::Array(layer, items.iter().map(|item| Trace::of(item, layer)).collect()),
+            Node::Object(map) => Trace::Object(layer, map.iter().map(|(key, value)| (key.clone(), Trace::of(value, layer))).collect()),
+            _ => Trace::Value(layer)
+        }
+    }
+
+    fn layer(&self) -> usize {
+        match self {
+            Trace::Value(layer) | Trace::Array(layer, _) | Trace::Object(layer, _) => *layer
+        }
+    }
+}
+
+impl Provenance {
+    /// Start keeping track of a document from its first layer, called `name`,
+    /// like the path of the base file.
+    pub fn new(base: &Node, name: impl Into<String>) -> Provenance {
+        Provenance {
+            layers: vec![name.into()],
+            root: Trace::of(base, 0)
+        }
+    }
+
+    /// The names of the layers, in the order they were merged.
+    pub fn layers(&self) -> &[String] {
+        &self.layers
+    }
+
+    /// The name of the layer that supplied the value at a JSON Pointer, like `/server/port`,
+    /// or `None` if there's no value there.
+    ///
+    /// An array or object that several layers were merged into is put down to
+    /// the first layer it was in, so look at its items or keys for the rest.
+    pub fn provenance(&self, pointer: &str) -> Option<&str> {
+        let mut trace = &self.root;
+
+        for token in split_pointer(pointer)? {
+            trace = match trace {
+                Trace::Array(_, items) => items.get(parse_index(&token)?)?,
+                Trace::Object(_, entries) => entries.get(&token)?,
+                Trace::Value(_) => return None
+            };
+        }
+
+        Some(&self.layers[trace.layer()])
+    }
+}
+
+/// Merge `overlay` into `base` the same way as `deep_merge_with`,
+/// putting anything taken from it down to `layer` in `trace`.
+fn merge_traced(base: &mut Node, trace: &mut Trace, overlay: Node, options: &MergeOptions, layer: usize) {
+    match (base, trace, overlay) {
+        (Node::Object(base), Trace::Object(_, traces), Node::Object(top)) => {
+            for (key, value) in top {
+                if options.nulls == NullMerge::Remove && matches!(value, Node::Null) {
+                    base.remove(&key);
+                    traces.remove(&key);
+                    continue;
+                }
+
+                match (base.get_mut(&key), traces.get_mut(&key)) {
+                    (Some(existing), Some(trace)) => merge_traced(existing, trace, value, options, layer),
+
+                    // New values go through the same rules, so any `null`s in a new object are taken out too.
+                    _ => {
+                        let mut node = Node::Empty;
+                        let mut trace = Trace::Value(layer);
+
+                        merge_traced(&mut node, &mut trace, value, options, layer);
+
+                        base.insert(key.clone(), node);
+                        traces.insert(key, trace);
+                    }
+                }
+            }
+        },
+
+        (Node::Array(base), Trace::Array(_, traces), Node::Array(top)) if options.arrays == ArrayMerge::Concat => {
+            traces.extend(top.iter().map(|item| Trace::of(item, layer)));
+            base.extend(top);
+        },
+
+        // An object replacing anything else is merged into an empty one, so its `null`s are taken out.
+        (base, trace, top @ Node::Object(_)) if options.nulls == NullMerge::Remove => {
+            *base = Node::Object(Default::default());
+            *trace = Trace::Object(layer, HashMap::new());
+
+            merge_traced(base, trace, top, options, layer);
+        },
+
+        (base, trace, top) => {
+            *trace = Trace::of(&top, layer);
+            *base = top;
+        }
+    }
+}
+
+impl Node {
+    /// Merge `overlay` on top of this node.
+    ///
+    /// Objects are merged key by key, recursively. Anything else in the
+    /// overlay, including arrays, replaces the value underneath it.
+    pub fn deep_merge(&mut self, overlay: Node) {
+        self.deep_merge_with(overlay, &MergeOptions::default());
+    }
+
+    /// Merge `overlay` on top of this node like `deep_merge`, choosing
+    /// what happens to arrays and `null`s, like for layered config files.
+    pub fn deep_merge_with(&mut self, overlay: Node, options: &MergeOptions) {
+        match (self, overlay) {
+            (Node::Object(base), Node::Object(top)) => {
+                for (key, value) in top {
+                    if options.nulls == NullMerge::Remove && matches!(value, Node::Null) {
+                        base.remove(&key);
+                        continue;
+                    }
+
+                    match base.get_mut(&key) {
+                        Some(existing) => existing.deep_merge_with(value, options),
+
+                        // New values go through the same rules, so any `null`s in a new object are taken out too.
+                        None => {
+                            let mut node = Node::Empty;
+                            node.deep_merge_with(value, options);
+
+                            base.insert(key, node);
+                        }
+                    }
+                }
+            },
+
+            (Node::Array(base), Node::Array(top)) if options.arrays == ArrayMerge::Concat => base.extend(top),
+
+            // An object replacing anything else is merged into an empty one, so its `null`s are taken out.
+            (base, top @ Node::Object(_)) if options.nulls == NullMerge::Remove => {
+                *base = Node::Object(Default::default());
+                base.deep_merge_with(top, options);
+            },
+
+            (base, top) => *base = top
+        }
+    }
+
+    /// Merge `overlay` on top of this node like `deep_merge_with`, recording in
+    /// `provenance` that whatever's taken from it came from the layer `name`.
+    ///
+    /// `provenance` has to have been started from this node, with `Provenance::new`,
+    /// and kept up by every merge since.
+    pub fn deep_merge_traced(&mut self, overlay: Node, options: &MergeOptions, name: impl Into<String>, provenance: &mut Provenance) {
+        provenance.layers.push(name.into());
+        merge_traced(self, &mut provenance.root, overlay, options, provenance.layers.len() - 1);
+    }
+
+    /// Fill in any keys missing from this node with the ones in `defaults`, recursively.
+    ///
+    /// Values that are already here are never overwritten, including `null`s,
+    /// since a key set to `null` is there on purpose. Only objects are filled in,
+    /// so arrays are left alone even if `defaults` has a longer one.
+    pub fn apply_defaults(&mut self, defaults: &Node) {
+        match (self, defaults) {
+            (Node::Object(base), Node::Object(defaults)) => {
+                for (key, value) in defaults {
+                    match base.get_mut(key) {
+                        Some(existing) => existing.apply_defaults(value),
+                        None => {
+                            base.insert(key.clone(), value.clone());
+                        }
+                    }
+                }
+            },
+
+            // An empty document has nothing to keep.
+            (base @ Node::Empty, defaults) => *base = defaults.clone(),
+
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compare::Tolerance;
+    use crate::parser::parse_str;
+
+    fn doc(text: &str) -> Node {
+        parse_str(text).unwrap()
+    }
+
+    fn merged(base: &str, overlay: &str, options: MergeOptions) -> Node {
+        let mut node = doc(base);
+        node.deep_merge_with(doc(overlay), &options);
+        node
+    }
+
+    fn assert_same(left: &Node, right: &str) {
+        assert!(left.approx_eq(&doc(right), &Tolerance::exact()), "{:?} is not {}", left, right);
+    }
+
+    #[test]
+    fn objects_merge_key_by_key() {
+        let mut node = doc(r#"{"a": {"b": 1, "c": [1]}, "d": 2}"#);
+        node.deep_merge(doc(r#"{"a": {"c": [2], "e": null}, "d": {"f": 3}}"#));
+
+        assert_same(&node, r#"{"a": {"b": 1, "c": [2], "e": null}, "d": {"f": 3}}"#);
+    }
+
+    #[test]
+    fn arrays_can_be_concatenated() {
+        let options = MergeOptions { arrays: ArrayMerge::Concat, ..MergeOptions::default() };
+
+        assert_same(&merged(r#"{"a": [1, 2]}"#, r#"{"a": [3]}"#, options), r#"{"a": [1, 2, 3]}"#);
+        assert_same(&merged(r#"{"a": 1}"#, r#"{"a": [3]}"#, options), r#"{"a": [3]}"#);
+    }
+
+    #[test]
+    fn remove_takes_out_nulls_everywhere() {
+        let options = MergeOptions { nulls: NullMerge::Remove, ..MergeOptions::default() };
+
+        assert_same(&merged(r#"{"a": 1, "b": 2}"#, r#"{"a": null}"#, options), r#"{"b": 2}"#);
+        assert_same(&merged(r#"{"a": 1}"#, r#"{"n": {"x": null, "y": 1}}"#, options), r#"{"a": 1, "n": {"y": 1}}"#);
+
+        // An object replacing something that isn't one is merged into an empty object.
+        assert_same(&merged(r#"{"a": 1}"#, r#"{"a": {"x": null, "y": {"z": null}}}"#, options), r#"{"a": {"y": {}}}"#);
+        assert_same(&merged(r#"[1]"#, r#"{"x": null, "y": 2}"#, options), r#"{"y": 2}"#);
+    }
+
+    #[test]
+    fn keep_leaves_nulls_in() {
+        assert_same(&merged(r#"{"a": 1}"#, r#"{"a": {"x": null}}"#, MergeOptions::default()), r#"{"a": {"x": null}}"#);
+    }
+
+    #[test]
+    fn traced_merges_match_and_know_their_layers() {
+        let options = MergeOptions { arrays: ArrayMerge::Concat, nulls: NullMerge::Remove };
+        let base = doc(r#"{"server": {"port": 80, "host": "a"}, "tags": ["x"], "mode": 1}"#);
+        let overlay = r#"{"server": {"port": 8080, "tls": null}, "tags": ["y"], "mode": {"fast": true, "slow": null}}"#;
+
+        let mut traced = base.clone();
+        let mut provenance = Provenance::new(&base, "base.json");
+        traced.deep_merge_traced(doc(overlay), &options, "local.json", &mut provenance);
+
+        let mut plain = base.clone();
+        plain.deep_merge_with(doc(overlay), &options);
+
+        assert!(traced.approx_eq(&plain, &Tolerance::exact()));
+        assert_same(&traced, r#"{"server": {"port": 8080, "host": "a"}, "tags": ["x", "y"], "mode": {"fast": true}}"#);
+
+        assert_eq!(provenance.layers(), ["base.json", "local.json"]);
+        assert_eq!(provenance.provenance("/server/port"), Some("local.json"));
+        assert_eq!(provenance.provenance("/server/host"), Some("base.json"));
+        assert_eq!(provenance.provenance("/tags/0"), Some("base.json"));
+        assert_eq!(provenance.provenance("/tags/1"), Some("local.json"));
+        assert_eq!(provenance.provenance("/mode/fast"), Some("local.json"));
+        assert_eq!(provenance.provenance("/mode/slow"), None);
+    }
+
+    #[test]
+    fn defaults_fill_in_missing_keys() {
+        let mut node = doc(r#"{"a": null, "b": {"c": 1}, "list": [1]}"#);
+        node.apply_defaults(&doc(r#"{"a": 5, "b": {"c": 2, "d": 3}, "list": [1, 2], "e": true}"#));
+
+        assert_same(&node, r#"{"a": null, "b": {"c": 1, "d": 3}, "list": [1], "e": true}"#);
+    }
+}
@@ -0,0 +1,90 @@
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+use crate::error::{ErrorCode, Result};
+
+/// Somewhere to send numbers about parsing, like a Prometheus registry.
+///
+/// Once set with `set_sink`, every call to `parse_with_options`, and so `parse_str`,
+/// reports to it. `bytes` is the length of the text that was given, and `duration`
+/// covers tokenising and parsing it. Calls can come from many threads at once.
+pub trait MetricsSink: Send + Sync {
+    /// A document was parsed.
+    fn parse_succeeded(&self, bytes: usize, duration: Duration);
+
+    /// A document failed to parse, with the code of the error.
+    fn parse_failed(&self, code: ErrorCode, bytes: usize, duration: Duration);
+}
+
+static SINK: OnceLock<Box<dyn MetricsSink>> = OnceLock::new();
+
+/// Send metrics to `sink` from now on, giving back `false` without changing anything
+/// if a sink has already been set, since a sink lasts for the rest of the program.
+pub fn set_sink(sink: impl MetricsSink + 'static) -> bool {
+    SINK.set(Box::new(sink)).is_ok()
+}
+
+/// Run a parse of `bytes` bytes of text, reporting how it went to the sink if there is one.
+pub(crate) fn record<T>(bytes: usize, parse: impl FnOnce() -> Result<T>) -> Result<T> {
+    let Some(sink) = SINK.get() else {
+        return parse();
+    };
+
+    let start = Instant::now();
+    let result = parse();
+
+    match &result {
+        Ok(_) => sink.parse_succeeded(bytes, start.elapsed()),
+        Err(e) => sink.parse_failed(e.code, bytes, start.elapsed())
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    use crate::parser::parse_str;
+
+    /// Every report, as the error code if there was one and the length of the text.
+    static REPORTS: Mutex<Vec<(Option<ErrorCode>, usize)>> = Mutex::new(Vec::new());
+
+    struct Recorder;
+
+    impl MetricsSink for Recorder {
+        fn parse_succeeded(&self, bytes: usize, _: Duration) {
+            REPORTS.lock().unwrap().push((None, bytes));
+        }
+
+        fn parse_failed(&self, code: ErrorCode, bytes: usize, _: Duration) {
+            REPORTS.lock().unwrap().push((Some(code), bytes));
+        }
+    }
+
+    struct Other;
+
+    impl MetricsSink for Other {
+        fn parse_succeeded(&self, _: usize, _: Duration) {}
+        fn parse_failed(&self, _: ErrorCode, _: usize, _: Duration) {}
+    }
+
+    // The sink is global, so this is the only test that sets one. Other tests parse
+    // at the same time, so the texts here have lengths that nothing else uses.
+    #[test]
+    fn parses_are_reported_to_the_sink() {
+        assert!(set_sink(Recorder));
+        assert!(!set_sink(Other));
+
+        let good = format!("{}1", " ".repeat(4321));
+        let bad = format!("{}[1", " ".repeat(4321));
+
+        parse_str(&good).unwrap();
+        let code = parse_str(&bad).unwrap_err().code;
+
+        let reports = REPORTS.lock().unwrap();
+        assert!(reports.contains(&(None, 4322)));
+        assert!(reports.contains(&(Some(code), 4323)));
+    }
+}
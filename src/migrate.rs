@@ -0,0 +1,487 @@
+use std::fmt::Display;
+
+use crate::coerce::ValueType;
+use crate::framing::FramingError;
+use crate::lexer::tokenise;
+use crate::parser::{parse, Node};
+use crate::pointer::{escape_token, parse_index, split_pointer};
+
+/// A step of a migration that couldn't be applied.
+#[derive(Debug)]
+pub struct MigrateError {
+    /// The JSON Pointer of the value being migrated, like `/orders/17/price`.
+    pub path: String,
+    pub message: String
+}
+
+impl Display for MigrateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "at {}: {}", self.path, self.message)
+    }
+}
+
+impl std::error::Error for MigrateError {}
+
+fn error(path: &str, message: impl Into<String>) -> MigrateError {
+    MigrateError {
+        path: path.to_string(),
+        message: message.into()
+    }
+}
+
+/// One change made by a `Migration`.
+///
+/// Paths are JSON Pointers, where a `*` token stands for every item of an array
+/// or every value of an object, like `/orders/*/price`.
+#[derive(Clone, Debug)]
+pub enum Step {
+    /// Rename the key at `path` to `to`, keeping it in the same object.
+    Rename {
+        path: String,
+        to: String
+    },
+
+    /// Move the value at `from` to `to`, making any objects on the way that aren't there.
+    /// Each `*` in `to` is filled in with what the `*` in the same place in `from` matched.
+    Move {
+        from: String,
+        to: String
+    },
+
+    /// Convert the value at `path` to another type, like `"12"` to `12`, as `Node::coerce_to` does.
+    /// Nulls are left as they are.
+    Coerce {
+        path: String,
+        to: ValueType
+    },
+
+    /// Set the value at `path` if it isn't there yet.
+    Default {
+        path: String,
+        value: Node
+    }
+}
+
+/// Walk `tokens` down from `node`, giving back the concrete tokens of every value they match.
+fn expand(node: &Node, tokens: &[String], prefix: &mut Vec<String>, found: &mut Vec<Vec<String>>) {
+    let Some((first, rest)) = tokens.split_first() else {
+        found.push(prefix.clone());
+        return;
+    };
+
+    let mut visit = |token: String, child: &Node| {
+        prefix.push(token);
+        expand(child, rest, prefix, found);
+        prefix.pop();
+    };
+
+    match node {
+        Node::Array(arr) if first == "*" => {
+            arr.iter().enumerate().for_each(|(i, child)| visit(i.to_string(), child));
+        },
+        Node::Object(map) if first == "*" => {
+            // Sort the keys so errors are found in the same order every time.
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+
+            keys.into_iter().for_each(|key| visit(key.clone(), &map[key]));
+        },
+        Node::Array(arr) => {
+            if let Some(child) = parse_index(first).and_then(|i| arr.get(i)) {
+                visit(first.clone(), child);
+            }
+        },
+        Node::Object(map) => {
+            if let Some(child) = map.get(first) {
+                visit(first.clone(), child);
+            }
+        },
+        _ => {}
+    }
+}
+
+fn to_pointer(tokens: &[String]) -> String {
+    tokens.iter().map(|token| format!("/{}", escape_token(token))).collect()
+}
+
+fn parse_path(path: &str) -> Result<Vec<String>, MigrateError> {
+    split_pointer(path).ok_or_else(|| error(path, "Paths must be JSON Pointers starting with a `/`"))
+}
+
+fn get_mut<'a>(node: &'a mut Node, tokens: &[String]) -> Option<&'a mut Node> {
+    let mut current = node;
+
+    for token in tokens {
+        current = match current {
+            Node::Object(map) => map.get_mut(token)?,
+            Node::Array(arr) => arr.get_mut(parse_index(token)?)?,
+            _ => return None
+        };
+    }
+
+    Some(current)
+}
+
+fn remove(node: &mut Node, tokens: &[String]) -> Option<Node> {
+    let (last, parent) = tokens.split_last()?;
+
+    match get_mut(node, parent)? {
+        Node::Object(map) => map.remove(last),
+        Node::Array(arr) => {
+            let i = parse_index(last).filter(|&i| i < arr.len())?;
+            Some(arr.remove(i))
+        },
+        _ => None
+    }
+}
+
+/// Put a value at `tokens`, making objects for any keys on the way that aren't there.
+/// An index one past the end of an array appends to it.
+fn put(node: &mut Node, tokens: &[String], value: Node) -> Result<(), MigrateError> {
+    let Some((last, parent)) = tokens.split_last() else {
+        *node = value;
+        return Ok(());
+    };
+
+    let mut current = node;
+
+    for (depth, token) in parent.iter().enumerate() {
+        let here = to_pointer(&tokens[..=depth]);
+
+        current = match current {
+            Node::Object(map) => map.entry(token.clone()).or_insert_with(|| Node::Object(Default::default())),
+            Node::Array(arr) => match parse_index(token) {
+                Some(i) if i < arr.len() => &mut arr[i],
+                _ => return Err(error(&here, "There's no item here to move into"))
+            },
+            _ => return Err(error(&here, "There's a value here that isn't an array or object"))
+        };
+    }
+
+    match current {
+        Node::Object(map) => {
+            map.insert(last.clone(), value);
+        },
+        Node::Array(arr) => match parse_index(last) {
+            Some(i) if i < arr.len() => arr[i] = value,
+            Some(i) if i == arr.len() => arr.push(value),
+            _ => return Err(error(&to_pointer(tokens), "There's no item here to move into"))
+        },
+        _ => return Err(error(&to_pointer(parent), "There's a value here that isn't an array or object"))
+    }
+
+    Ok(())
+}
+
+/// Fill in each `*` of `template` with the token that `pattern` matched there.
+fn substitute(pattern: &[String], matched: &[String], template: &[String]) -> Vec<String> {
+    let mut wildcards = pattern.iter().zip(matched).filter(|(p, _)| *p == "*").map(|(_, m)| m);
+
+    template.iter().map(|token| match token.as_str() {
+        "*" => wildcards.next().cloned().unwrap_or_else(|| token.clone()),
+        _ => token.clone()
+    }).collect()
+}
+
+impl Step {
+    fn apply(&self, doc: &mut Node) -> Result<(), MigrateError> {
+        let mut found = Vec::new();
+
+        match self {
+            Step::Rename { path, to } => {
+                let from = parse_path(path)?;
+
+                if from.is_empty() {
+                    return Err(error(path, "The whole document can't be renamed"));
+                }
+
+                expand(doc, &from, &mut Vec::new(), &mut found);
+
+                for matched in found {
+                    let mut renamed = matched.clone();
+                    *renamed.last_mut().unwrap() = to.clone();
+
+                    // Only keys can be renamed, not array items.
+                    if !matches!(get_mut(doc, &matched[..matched.len() - 1]), Some(Node::Object(_))) {
+                        return Err(error(&to_pointer(&matched), "Only object keys can be renamed"));
+                    }
+
+                    if let Some(value) = remove(doc, &matched) {
+                        put(doc, &renamed, value)?;
+                    }
+                }
+            },
+
+            Step::Move { from, to } => {
+                let from_tokens = parse_path(from)?;
+                let to_tokens = parse_path(to)?;
+
+                expand(doc, &from_tokens, &mut Vec::new(), &mut found);
+
+                // Take everything out first, from the end, so removing
+                // an item doesn't move the ones still to be removed.
+                let mut taken: Vec<(Vec<String>, Node)> = found.iter().rev().filter_map(
+                    |matched| Some((substitute(&from_tokens, matched, &to_tokens), remove(doc, matched)?))
+                ).collect();
+
+                taken.reverse();
+
+                for (target, value) in taken {
+                    put(doc, &target, value)?;
+                }
+            },
+
+            Step::Coerce { path, to } => {
+                expand(doc, &parse_path(path)?, &mut Vec::new(), &mut found);
+
+                for matched in found {
+                    let Some(value) = get_mut(doc, &matched).filter(|value| !matches!(value, Node::Null)) else {
+                        continue;
+                    };
+
+                    *value = value.coerce_to(*to).ok_or_else(|| error(
+                        &to_pointer(&matched),
+                        format!("Could not convert {:?} to a {}", value, to.name())
+                    ))?;
+                }
+            },
+
+            Step::Default { path, value } => {
+                let tokens = parse_path(path)?;
+
+                let Some((last, parent)) = tokens.split_last() else {
+                    return Ok(());
+                };
+
+                expand(doc, parent, &mut Vec::new(), &mut found);
+
+                for matched in found {
+                    if let Some(Node::Object(map)) = get_mut(doc, &matched) {
+                        map.entry(last.clone()).or_insert_with(|| value.clone());
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Read a step from its data form, like `{"op": "rename", "path": "/a", "to": "b"}`.
+    fn from_node(node: &Node, index: usize) -> Result<Step, MigrateError> {
+        let here = format!("/{}", index);
+
+        let field = |name: &str| match node.get(name) {
+            Some(Node::String(s)) => Ok(s.clone()),
+            _ => Err(error(&here, format!("The step needs a string {:?}", name)))
+        };
+
+        let step = match field("op")?.as_str() {
+            "rename" => Step::Rename { path: field("path")?, to: field("to")? },
+            "move" => Step::Move { from: field("from")?, to: field("path")? },
+            "coerce" => Step::Coerce {
+                path: field("path")?,
+                to: ValueType::from_name(&field("type")?).ok_or_else(
+                    || error(&here, "The type must be one of string, integer, float or bool")
+                )?
+            },
+            "default" => Step::Default {
+                path: field("path")?,
+                value: node.get("value").cloned().ok_or_else(|| error(&here, "The step needs a \"value\""))?
+            },
+            op => return Err(error(&here, format!("Unknown op {:?}", op)))
+        };
+
+        Ok(step)
+    }
+}
+
+/// A list of changes to make to documents of the same shape, like renaming keys,
+/// moving values, coercing types and filling in defaults.
+///
+/// Migrations can be built up in code:
+///
+/// ```text
+/// Migration::new()
+///     .rename("/users/*/fullName", "name")
+///     .coerce("/users/*/age", ValueType::Integer)
+///     .fill_default("/users/*/active", true)
+/// ```
+///
+/// or read from data with `from_node`. Steps are applied in order, and paths that
+/// aren't in a document are skipped, so optional fields don't need special handling.
+#[derive(Clone, Debug, Default)]
+pub struct Migration {
+    pub steps: Vec<Step>
+}
+
+impl Migration {
+    pub fn new() -> Migration {
+        Migration::default()
+    }
+
+    pub fn rename(mut self, path: impl Into<String>, to: impl Into<String>) -> Migration {
+        self.steps.push(Step::Rename { path: path.into(), to: to.into() });
+        self
+    }
+
+    pub fn move_value(mut self, from: impl Into<String>, to: impl Into<String>) -> Migration {
+        self.steps.push(Step::Move { from: from.into(), to: to.into() });
+        self
+    }
+
+    pub fn coerce(mut self, path: impl Into<String>, to: ValueType) -> Migration {
+        self.steps.push(Step::Coerce { path: path.into(), to });
+        self
+    }
+
+    pub fn fill_default(mut self, path: impl Into<String>, value: impl Into<Node>) -> Migration {
+        self.steps.push(Step::Default { path: path.into(), value: value.into() });
+        self
+    }
+
+    /// Read a migration from an array of steps written like JSON Patch operations:
+    ///
+    /// ```text
+    /// [
+    ///     {"op": "rename", "path": "/users/*/fullName", "to": "name"},
+    ///     {"op": "move", "from": "/meta/created", "path": "/createdAt"},
+    ///     {"op": "coerce", "path": "/users/*/age", "type": "integer"},
+    ///     {"op": "default", "path": "/users/*/active", "value": true}
+    /// ]
+    /// ```
+    pub fn from_node(node: &Node) -> Result<Migration, MigrateError> {
+        let Node::Array(steps) = node else {
+            return Err(error("", "A migration must be an array of steps"));
+        };
+
+        let steps = steps.iter().enumerate().map(|(i, step)| Step::from_node(step, i)).collect::<Result<_, _>>()?;
+
+        Ok(Migration { steps })
+    }
+
+    /// Apply every step to a document, in order.
+    pub fn apply(&self, doc: &mut Node) -> Result<(), MigrateError> {
+        self.steps.iter().try_for_each(|step| step.apply(doc))
+    }
+
+    /// Apply the migration to many documents, stopping at the first that fails.
+    /// Errors have the document's index at the start of their path, like `/3/price`.
+    pub fn apply_all(&self, docs: &mut [Node]) -> Result<(), MigrateError> {
+        for (i, doc) in docs.iter_mut().enumerate() {
+            self.apply(doc).map_err(|e| error(&format!("/{}{}", i, e.path), e.message))?;
+        }
+
+        Ok(())
+    }
+
+    /// Parse and migrate items one at a time as they're read, like from an
+    /// `ArrayItemReader` or `NdjsonReader`, so a whole dataset never has to be in memory.
+    pub fn apply_iter<'a, I>(&'a self, items: I) -> impl Iterator<Item = Result<Node, MigrateError>> + 'a
+    where
+        I: IntoIterator<Item = Result<String, FramingError>>,
+        I::IntoIter: 'a
+    {
+        items.into_iter().enumerate().map(move |(i, item)| {
+            let here = format!("/{}", i);
+
+            let text = item.map_err(|e| error(&here, e.to_string()))?;
+            let mut doc = tokenise(&text).and_then(|tokens| parse(&tokens)).map_err(|e| error(&here, e.to_string()))?;
+
+            self.apply(&mut doc).map_err(|e| error(&format!("{}{}", here, e.path), e.message))?;
+
+            Ok(doc)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compare::Tolerance;
+    use crate::parser::parse_str;
+
+    fn doc(text: &str) -> Node {
+        parse_str(text).unwrap()
+    }
+
+    fn migrated(migration: &Migration, text: &str) -> Node {
+        let mut node = doc(text);
+        migration.apply(&mut node).unwrap();
+
+        node
+    }
+
+    const USERS: &str = r#"{"users": [{"fullName": "Ada", "age": "36"}, {"fullName": "Bob", "age": null, "active": false}], "meta": {"created": 1}}"#;
+    const MIGRATED: &str = r#"{"users": [{"name": "Ada", "age": 36, "active": true}, {"name": "Bob", "age": null, "active": false}], "createdAt": 1, "meta": {}}"#;
+
+    #[test]
+    fn steps_are_applied_in_order() {
+        let migration = Migration::new()
+            .rename("/users/*/fullName", "name")
+            .move_value("/meta/created", "/createdAt")
+            .coerce("/users/*/age", ValueType::Integer)
+            .fill_default("/users/*/active", true);
+
+        assert!(migrated(&migration, USERS).approx_eq(&doc(MIGRATED), &Tolerance::exact()));
+    }
+
+    #[test]
+    fn migrations_can_be_read_from_data() {
+        let migration = Migration::from_node(&doc(r#"[
+            {"op": "rename", "path": "/users/*/fullName", "to": "name"},
+            {"op": "move", "from": "/meta/created", "path": "/createdAt"},
+            {"op": "coerce", "path": "/users/*/age", "type": "integer"},
+            {"op": "default", "path": "/users/*/active", "value": true}
+        ]"#)).unwrap();
+
+        assert!(migrated(&migration, USERS).approx_eq(&doc(MIGRATED), &Tolerance::exact()));
+
+        for steps in [r#"{}"#, r#"[{"op": "drop"}]"#, r#"[{"op": "rename", "path": "/a"}]"#, r#"[{"op": "coerce", "path": "/a", "type": "date"}]"#, r#"[{"op": "default", "path": "/a"}]"#] {
+            assert!(Migration::from_node(&doc(steps)).is_err(), "{}", steps);
+        }
+    }
+
+    #[test]
+    fn moves_fill_in_wildcards() {
+        let migration = Migration::new().move_value("/items/*/price/*", "/prices/*/*");
+        let node = migrated(&migration, r#"{"items": [{"price": {"usd": 1}}, {"price": {"eur": 2, "usd": 3}}]}"#);
+
+        assert!(node.approx_eq(&doc(r#"{"items": [{"price": {}}, {"price": {}}], "prices": {"0": {"usd": 1}, "1": {"eur": 2, "usd": 3}}}"#), &Tolerance::exact()));
+
+        let migration = Migration::new().move_value("/a/*", "/b/*");
+        assert!(migrated(&migration, r#"{"a": [1, 2, 3], "b": []}"#).approx_eq(&doc(r#"{"a": [], "b": [1, 2, 3]}"#), &Tolerance::exact()));
+    }
+
+    #[test]
+    fn missing_paths_are_skipped() {
+        let migration = Migration::new().rename("/x/y", "z").move_value("/nowhere", "/a").coerce("/b/*", ValueType::Float);
+
+        assert!(migrated(&migration, r#"{"b": [1, null]}"#).approx_eq(&doc(r#"{"b": [1.0, null]}"#), &Tolerance::exact()));
+    }
+
+    #[test]
+    fn errors_say_where_they_are() {
+        let migration = Migration::new().coerce("/*/price", ValueType::Integer);
+        let mut docs = [doc(r#"[{"price": "1"}]"#), doc(r#"[{"price": "1"}, {"price": "a lot"}]"#)];
+
+        let error = migration.apply_all(&mut docs).unwrap_err();
+        assert_eq!(error.path, "/1/1/price");
+        assert!(error.message.contains(r#""a lot""#));
+
+        let mut node = doc("[1]");
+        assert!(Migration::new().rename("/0", "a").apply(&mut node).is_err());
+        assert!(Migration::new().rename("a", "b").apply(&mut node).is_err());
+    }
+
+    #[test]
+    fn apply_iter_migrates_each_item() {
+        let migration = Migration::new().coerce("/n", ValueType::Integer);
+        let items = [r#"{"n": "1"}"#, "[", r#"{"n": "x"}"#].map(|text| Ok(text.to_string()));
+
+        let results: Vec<_> = migration.apply_iter(items).collect();
+
+        assert!(results[0].as_ref().unwrap().approx_eq(&doc(r#"{"n": 1}"#), &Tolerance::exact()));
+        assert_eq!(results[1].as_ref().unwrap_err().path, "/1");
+        assert_eq!(results[2].as_ref().unwrap_err().path, "/2/n");
+    }
+}
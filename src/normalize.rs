@@ -0,0 +1,52 @@
+use unicode_normalization::{is_nfc_quick, IsNormalized, UnicodeNormalization};
+
+use crate::parser::{Node, ObjectMap};
+
+/// Check whether text is already in Normalization Form C, which most text is,
+/// without having to normalize it.
+pub(crate) fn is_nfc(text: &str) -> bool {
+    match is_nfc_quick(text.chars()) {
+        IsNormalized::Yes => true,
+        IsNormalized::No => false,
+        IsNormalized::Maybe => text.chars().eq(text.nfc())
+    }
+}
+
+pub(crate) fn to_nfc(text: &str) -> String {
+    text.nfc().collect()
+}
+
+fn normalize_keys(map: &mut ObjectMap) {
+    if map.keys().all(|key| is_nfc(key)) {
+        return;
+    }
+
+    let mut entries: Vec<(String, Node)> = map.drain().collect();
+
+    // Keys that were already normalized go first, so they win any clash.
+    entries.sort_by_cached_key(|(key, _)| (!is_nfc(key), key.clone()));
+
+    for (key, value) in entries {
+        let key = if is_nfc(&key) { key } else { to_nfc(&key) };
+        map.entry(key).or_insert(value);
+    }
+}
+
+impl Node {
+    /// Put every string and object key in the tree into Unicode Normalization Form C,
+    /// in place, so text that looks the same compares the same.
+    ///
+    /// If keys clash once normalized, like `"é"` and `"e\u{301}"`, only one value is kept:
+    /// the one whose key was already normalized, or else the first key in sorted order.
+    pub fn normalize_unicode(&mut self) {
+        match self {
+            Node::String(s) if !is_nfc(s) => *s = to_nfc(s),
+            Node::Array(arr) => arr.iter_mut().for_each(Node::normalize_unicode),
+            Node::Object(map) => {
+                normalize_keys(map);
+                map.values_mut().for_each(Node::normalize_unicode);
+            },
+            _ => {}
+        }
+    }
+}
@@ -1,18 +1,46 @@
-use std::collections::HashMap;
+use std::borrow::Borrow;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
 use std::fmt::Debug;
+use std::hash::Hash;
 
-use crate::token::{Token, TokenType as TT};
+use crate::error::{ErrorCode, JsonError, Result};
+use crate::lexer::{LexOptions, Lexer};
+use crate::metrics;
+use crate::pool::NodePool;
+use crate::token::{Payload, Token, TokenType as TT};
 use crate::utils::TokenIter;
 
+/// The map inside `Node::Object`.
+///
+/// Its hasher is seeded randomly on every run, unless the `deterministic_hash` feature is on.
+/// Then objects built up the same way iterate in the same order on every run,
+/// for output that can be checked against snapshots and golden files. That also lets
+/// anyone who knows the hasher pick keys that all collide, so keep it to trusted input.
+pub type ObjectMap = HashMap<String, Node, ObjectHasher>;
+
+/// The hasher for `ObjectMap`. See there for when it's deterministic.
+#[cfg(not(feature = "deterministic_hash"))]
+pub type ObjectHasher = std::collections::hash_map::RandomState;
+
+/// The hasher for `ObjectMap`. See there for when it's deterministic.
+#[cfg(feature = "deterministic_hash")]
+pub type ObjectHasher = std::hash::BuildHasherDefault<crate::hash::FnvHasher>;
+
+#[derive(Clone)]
 pub enum Node {
     Integer(i64),
+
+    /// A whole number above `i64::MAX`, up to `u64::MAX`, like a 64-bit ID.
+    /// Numbers that fit in an `i64` are always `Integer`, so each number has one form.
+    Unsigned(u64),
     String(String),
     Float(f64),
     Bool(bool),
     Null,
 
     Array(Vec<Node>),
-    Object(HashMap<String, Node>),
+    Object(ObjectMap),
 
     Empty
 }
@@ -21,213 +49,971 @@ impl Debug for Node {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let s = match self {
             Self::Integer(n) => n.to_string(),
-            Self::String(s) => s.to_string(),
-            Self::Float(f) => f.to_string(),
+            Self::Unsigned(n) => n.to_string(),
+            Self::String(s) => format!("{:?}", s),
+            // Debug formatting keeps the `.0` on whole numbers, so `2.0` isn't shown like `2`.
+            Self::Float(f) => format!("{:?}", f),
             Self::Bool(b) => b.to_string(),
             Self::Null => "null".to_string(),
             Self::Array(arr) => format!("{:?}", arr),
             Self::Object(map) => {
                 let parts: Vec<String> = map.iter().map(
-                    |(name, value)| format!("{}: {:?}", name, value)
+                    |(name, value)| format!("{:?}: {:?}", name, value)
                 ).collect();
 
                 format!("{{{}}}", parts.join(", "))
             },
             Self::Empty => "EMPTY".to_string()
         };
-        
+
         write!(f, "{}", s).unwrap();
 
         Ok(())
     }
 }
 
-fn parse_simple(token: &Token) -> Node {
-    match token.tok_type {
-        TT::Int => {
-            let result = match str::parse::<i64>(&token.value) {
-                Ok(x) => x,
-                Err(_) => panic!("Failed to parse integer token's internal value: {}", token)
-            };
+impl From<i64> for Node {
+    fn from(n: i64) -> Node {
+        Node::Integer(n)
+    }
+}
+
+impl From<i32> for Node {
+    fn from(n: i32) -> Node {
+        Node::Integer(n as i64)
+    }
+}
+
+impl From<u32> for Node {
+    fn from(n: u32) -> Node {
+        Node::Integer(n as i64)
+    }
+}
+
+impl From<u64> for Node {
+    fn from(n: u64) -> Node {
+        match i64::try_from(n) {
+            Ok(n) => Node::Integer(n),
+            Err(_) => Node::Unsigned(n)
+        }
+    }
+}
+
+impl From<f64> for Node {
+    fn from(f: f64) -> Node {
+        Node::Float(f)
+    }
+}
+
+impl From<bool> for Node {
+    fn from(b: bool) -> Node {
+        Node::Bool(b)
+    }
+}
+
+impl From<&str> for Node {
+    fn from(s: &str) -> Node {
+        Node::String(s.to_string())
+    }
+}
+
+impl From<String> for Node {
+    fn from(s: String) -> Node {
+        Node::String(s)
+    }
+}
+
+impl From<Vec<Node>> for Node {
+    fn from(arr: Vec<Node>) -> Node {
+        Node::Array(arr)
+    }
+}
+
+impl From<ObjectMap> for Node {
+    fn from(map: ObjectMap) -> Node {
+        Node::Object(map)
+    }
+}
+
+impl Node {
+    pub fn as_object(&self) -> Option<&ObjectMap> {
+        match self {
+            Node::Object(map) => Some(map),
+            _ => None
+        }
+    }
+
+    pub fn as_object_mut(&mut self) -> Option<&mut ObjectMap> {
+        match self {
+            Node::Object(map) => Some(map),
+            _ => None
+        }
+    }
+
+    /// Look up a key of an object, which can be any borrowed form of `String`,
+    /// like a `&str`, so no key has to be allocated.
+    pub fn get<Q>(&self, key: &Q) -> Option<&Node>
+    where
+        String: Borrow<Q>,
+        Q: Hash + Eq + ?Sized
+    {
+        self.as_object()?.get(key)
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut Node>
+    where
+        String: Borrow<Q>,
+        Q: Hash + Eq + ?Sized
+    {
+        self.as_object_mut()?.get_mut(key)
+    }
+
+    /// Look up a key of an object, telling a key that isn't there (`None`)
+    /// apart from one that's set to `null` (`Some(None)`).
+    ///
+    /// This is the difference between leaving a field alone and clearing it in a PATCH request.
+    pub fn get_optional<Q>(&self, key: &Q) -> Option<Option<&Node>>
+    where
+        String: Borrow<Q>,
+        Q: Hash + Eq + ?Sized
+    {
+        match self.get(key)? {
+            Node::Null => Some(None),
+            value => Some(Some(value))
+        }
+    }
+
+    /// Check whether an object has a key that's set to `null`, rather than the key not being there.
+    pub fn is_explicit_null<Q>(&self, key: &Q) -> bool
+    where
+        String: Borrow<Q>,
+        Q: Hash + Eq + ?Sized
+    {
+        matches!(self.get(key), Some(Node::Null))
+    }
+
+    /// Look up a key of an object ignoring its case, like `content-type` for `Content-Type`.
+    ///
+    /// A key that matches exactly is always picked first. Otherwise, if more than one key
+    /// matches, the first in sorted order is, so the result doesn't depend on hashing order.
+    pub fn get_ignore_case(&self, key: &str) -> Option<&Node> {
+        let map = self.as_object()?;
+
+        if let Some(value) = map.get(key) {
+            return Some(value);
+        }
+
+        let lowered = || key.chars().flat_map(char::to_lowercase);
+
+        map.iter()
+            .filter(|(k, _)| k.chars().flat_map(char::to_lowercase).eq(lowered()))
+            .min_by(|a, b| a.0.cmp(b.0))
+            .map(|(_, value)| value)
+    }
+
+    /// Make every object key in the tree lowercase, in place.
+    ///
+    /// If keys clash once lowercased, like `Id` and `id`, only one value is kept:
+    /// the one whose key was already lowercase, or else the first key in sorted order.
+    pub fn lowercase_keys_recursive(&mut self) {
+        match self {
+            Node::Array(arr) => arr.iter_mut().for_each(Node::lowercase_keys_recursive),
 
-            Node::Integer(result)
+            Node::Object(map) => {
+                let mut entries: Vec<(String, Node)> = map.drain().collect();
+
+                // Already lowercase keys go first, so they win any clash.
+                entries.sort_by_cached_key(|(key, _)| (key.to_lowercase() != *key, key.clone()));
+
+                for (key, mut value) in entries {
+                    value.lowercase_keys_recursive();
+                    map.entry(key.to_lowercase()).or_insert(value);
+                }
+            },
+
+            _ => {}
+        }
+    }
+
+    /// Get the entry for a key of an object, to insert or update it in place.
+    ///
+    /// `null` is turned into an empty object first, so trees can be built up
+    /// from nothing, like `node.entry("a").unwrap().or_insert_with(|| Node::Null)`.
+    /// Any other value that isn't an object gives back `None`.
+    pub fn entry(&mut self, key: impl Into<String>) -> Option<Entry<'_, String, Node>> {
+        if matches!(self, Node::Null | Node::Empty) {
+            *self = Node::Object(ObjectMap::default());
+        }
+
+        Some(self.as_object_mut()?.entry(key.into()))
+    }
+}
+
+/// What's kept track of while building a tree: how much of each limit
+/// in `ParseOptions` has been used up, and where allocations come from.
+struct Builder<'a> {
+    options: &'a ParseOptions,
+    depth: usize,
+    nodes: usize,
+    string_bytes: usize,
+    pool: Option<&'a mut NodePool>
+}
+
+impl<'a> Builder<'a> {
+    fn new(options: &'a ParseOptions, pool: Option<&'a mut NodePool>) -> Builder<'a> {
+        Builder {
+            options,
+            depth: 0,
+            nodes: 0,
+            string_bytes: 0,
+            pool
+        }
+    }
+
+    fn string(&mut self, text: &str) -> String {
+        match &mut self.pool {
+            Some(pool) => pool.string(text),
+            None => text.to_string()
+        }
+    }
+
+    /// Make a string value, normalized if the options ask for it.
+    fn value_string(&mut self, text: &str) -> String {
+        #[cfg(feature = "normalize")]
+        if self.options.nfc_strings && !crate::normalize::is_nfc(text) {
+            return crate::normalize::to_nfc(text);
+        }
+
+        self.string(text)
+    }
+
+    /// Make an object key, normalized if the options ask for it.
+    fn key(&mut self, text: &str) -> String {
+        #[cfg(feature = "normalize")]
+        if self.options.nfc_keys && !crate::normalize::is_nfc(text) {
+            return crate::normalize::to_nfc(text);
+        }
+
+        self.string(text)
+    }
+
+    /// Whether a token can be an object key.
+    fn is_key(&self, token: &Token) -> bool {
+        token.tok_type == TT::String || (token.tok_type == TT::Name && self.options.allow_unquoted_keys)
+    }
+
+    fn array(&mut self) -> Vec<Node> {
+        match &mut self.pool {
+            Some(pool) => pool.array(),
+            None => Vec::new()
+        }
+    }
+
+    fn object(&mut self) -> ObjectMap {
+        match &mut self.pool {
+            Some(pool) => pool.object(),
+            None => ObjectMap::default()
+        }
+    }
+
+    fn exceeded(message: String, token: &Token) -> JsonError {
+        JsonError::at_token(message, token).with_code(ErrorCode::LimitExceeded)
+    }
+
+    /// Count a value about to be made from `token`.
+    fn add_node(&mut self, token: &Token) -> Result<()> {
+        self.nodes += 1;
+
+        match self.options.max_nodes {
+            Some(max) if self.nodes > max => Err(Builder::exceeded(format!("Document has more than {} values.", max), token)),
+            _ => Ok(())
+        }
+    }
+
+    /// Count the bytes of a string, whether it's a value or a key.
+    fn add_string(&mut self, token: &Token) -> Result<()> {
+        self.string_bytes += token.text().len();
+
+        match self.options.max_total_string_bytes {
+            Some(max) if self.string_bytes > max => Err(Builder::exceeded(format!("Document has more than {} bytes of strings.", max), token)),
+            _ => Ok(())
+        }
+    }
+
+    /// Go into an array or object, which also counts as a value.
+    fn enter(&mut self, token: &Token) -> Result<()> {
+        self.add_node(token)?;
+        self.depth += 1;
+
+        match self.options.max_depth {
+            Some(max) if self.depth > max => Err(Builder::exceeded(format!("Document is nested more than {} levels deep.", max), token)),
+            _ => Ok(())
+        }
+    }
+
+    fn leave(&mut self) {
+        self.depth -= 1;
+    }
+}
+
+/// The value of an integer token, or `None` if it's too big for an `i64` or a `u64`.
+///
+/// With `allow_json5_numbers`, the token can have a `+` in front or be in hex, like `0x1F`.
+fn integer_value(raw: &str) -> Option<Node> {
+    let (negative, body) = match raw.strip_prefix('-') {
+        Some(body) => (true, body),
+        None => (false, raw.strip_prefix('+').unwrap_or(raw))
+    };
+
+    let magnitude = match body.strip_prefix("0x").or_else(|| body.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok()?,
+        None => body.parse::<u64>().ok()?
+    };
+
+    match (negative, i64::try_from(magnitude)) {
+        // An integer has no negative zero, so `-0` is a float to keep its sign, like in JavaScript.
+        (true, _) if magnitude == 0 => Some(Node::Float(-0.0)),
+        (true, _) => i64::try_from(-i128::from(magnitude)).ok().map(Node::Integer),
+        (false, Ok(n)) => Some(Node::Integer(n)),
+
+        // Only IDs and the like are too big for an `i64`.
+        (false, Err(_)) => Some(Node::Unsigned(magnitude))
+    }
+}
+
+fn parse_simple(token: &Token, builder: &mut Builder) -> Result<Node> {
+    builder.add_node(token)?;
+
+    // The payload already says what kind of value this is, so there's no need to look at the type.
+    let node = match &token.payload {
+        Payload::Number { raw, is_float: false } => match integer_value(raw) {
+            Some(node) => node,
+            None => return Err(JsonError::at_token(
+                format!("Failed to parse integer token's internal value: {}", token),
+                token
+            ).with_code(ErrorCode::NumberOutOfRange))
         },
 
-        TT::Float => {
-            let result = match str::parse::<f64>(&token.value) {
+        Payload::Number { raw, is_float: true } => {
+            let result = match str::parse::<f64>(raw) {
                 Ok(x) => x,
-                Err(_) => panic!("Failed to parse float token's internal value: {}", token)
+                Err(_) => return Err(JsonError::at_token(
+                    format!("Failed to parse float token's internal value: {}", token),
+                    token
+                ).with_code(ErrorCode::InvalidNumber))
             };
 
             Node::Float(result)
         },
-        
-        TT::String => Node::String(token.value.clone()),
 
-        TT::Name => match token.value.as_str() {
+        Payload::String { decoded } => {
+            builder.add_string(token)?;
+            Node::String(builder.value_string(decoded))
+        },
+
+        Payload::Name(name) => match name.as_str() {
             "true"  => Node::Bool(true),
             "false" => Node::Bool(false),
             "null"  => Node::Null,
 
-            _ => panic!("Failed to parse undefined name: {:?} ({})", token.value, token)
+            _ => return Err(JsonError::at_token(
+                format!("Failed to parse undefined name: {:?}", name),
+                token
+            ).with_code(ErrorCode::InvalidLiteral))
         },
 
-        _ => panic!("Cannot parse token with invalid type: {}", token)
+        Payload::None => return Err(JsonError::unexpected(TT::VALUE_START, token))
+    };
+
+    Ok(node)
+}
+
+/// Where the grammar gets its tokens from: a slice that was tokenised up front,
+/// or a lexer that's read as the grammar goes, so no vector of tokens is built.
+trait TokenSource {
+    /// A token taken from the source, borrowed from the slice or owned.
+    type Item: Borrow<Token>;
+
+    /// Look `n` tokens past the next one, so `peek_n(0)` is the next token.
+    fn peek_n(&mut self, n: usize) -> Result<Option<&Token>>;
+    fn next(&mut self) -> Result<Option<Self::Item>>;
+
+    fn peek(&mut self) -> Result<Option<&Token>> {
+        self.peek_n(0)
+    }
+
+    /// The type of the token `n` past the next one, for looking ahead without holding onto the source.
+    fn peek_type(&mut self, n: usize) -> Result<Option<TT>> {
+        Ok(self.peek_n(n)?.map(|t| t.tok_type))
+    }
+}
+
+impl<'a> TokenSource for TokenIter<'a> {
+    type Item = &'a Token;
+
+    fn peek_n(&mut self, n: usize) -> Result<Option<&Token>> {
+        Ok(TokenIter::peek_n(self, n))
+    }
+
+    fn next(&mut self) -> Result<Option<&'a Token>> {
+        Ok(Iterator::next(self))
     }
 }
 
-fn parse_array(tokens: &mut TokenIter) -> Node {
-    let mut body: Vec<Node> = Vec::new();
-    
+/// Reads tokens from a lexer as they're needed, holding onto only the ones that have been peeked at.
+struct LexerSource<'a> {
+    lexer: Lexer<'a>,
+    peeked: VecDeque<Token>
+}
+
+impl<'a> TokenSource for LexerSource<'a> {
+    type Item = Token;
+
+    fn peek_n(&mut self, n: usize) -> Result<Option<&Token>> {
+        while self.peeked.len() <= n {
+            match self.lexer.next_token()? {
+                Some(t) => self.peeked.push_back(t),
+                None => break
+            }
+        }
+
+        Ok(self.peeked.get(n))
+    }
+
+    fn next(&mut self) -> Result<Option<Token>> {
+        match self.peeked.pop_front() {
+            Some(t) => Ok(Some(t)),
+            None => self.lexer.next_token()
+        }
+    }
+}
+
+/// Parse the value starting with the next token, which has already been
+/// checked to be one of `TT::VALUE_START`.
+fn parse_value<S: TokenSource>(tokens: &mut S, kind: TT, builder: &mut Builder) -> Result<Node> {
+    match kind {
+        TT::LSqBrac => parse_array(tokens, builder),
+        TT::LBrace => parse_object(tokens, builder),
+        _ => parse_simple(tokens.next()?.unwrap().borrow(), builder)
+    }
+}
+
+fn parse_array<S: TokenSource>(tokens: &mut S, builder: &mut Builder) -> Result<Node> {
     // This is safe.
-    let start = tokens.next().unwrap();
+    let start = tokens.next()?.unwrap();
+    let start = start.borrow();
+
+    builder.enter(start)?;
+
+    let mut body = builder.array();
 
     loop {
-        let token = match tokens.peek() {
-            Some(x) => x,
-            None => panic!("Encountered an EOF while trying to build array. {}", start.pos())
+        // The first item could also be the end of an empty array.
+        let expected: &[TT] = match body.is_empty() {
+            true => &[TT::LBrace, TT::LSqBrac, TT::String, TT::Int, TT::Float, TT::Name, TT::RSqBrac],
+            false => TT::VALUE_START
         };
 
-        let node: Node = match token.tok_type {
-            TT::LSqBrac => parse_array(tokens),
-            TT::LBrace  => parse_object(tokens),
+        let kind = match tokens.peek()? {
+            Some(x) => x.tok_type,
+            None => return Err(JsonError::unexpected_eof(expected, start))
+        };
 
-            TT::RSqBrac => break,
-            
-            TT::Int | TT::String | TT::Float | TT::Name => parse_simple(tokens.next().unwrap()),
+        let node: Node = match kind {
+            // An empty array, or the end after a trailing comma when they're allowed.
+            TT::RSqBrac if body.is_empty() || builder.options.allow_trailing_commas => {
+                tokens.next()?;
+                break;
+            },
+
+            _ if TT::VALUE_START.contains(&kind) => parse_value(tokens, kind, builder).map_err(|e| e.in_index(body.len()))?,
 
-            _ => panic!("Invalid token for an array: {}", token)
+            _ => return Err(JsonError::unexpected(expected, tokens.peek()?.unwrap()).in_index(body.len()))
         };
 
         body.push(node);
 
-        let next = match tokens.peek() {
+        let next = match tokens.next()? {
             Some(t) => t,
-            None => panic!("Encountered an EOF while trying to build array. {}", start.pos())
+            None => return Err(JsonError::unexpected_eof(&[TT::Comma, TT::RSqBrac], start))
         };
 
-        match next.tok_type {
-            TT::Comma => {
-                tokens.next();
-            }
-
-            TT::RSqBrac => {
-                tokens.next();
-                break;
-            }
+        match next.borrow().tok_type {
+            TT::Comma => {},
+            TT::RSqBrac => break,
 
-            _ => panic!("Unrecognised token after parsing array item: {} {}", token, token.pos())
+            _ => return Err(JsonError::unexpected(&[TT::Comma, TT::RSqBrac], next.borrow()))
         }
     }
 
-    Node::Array(body)
-}
+    builder.leave();
 
-fn parse_pair(tokens: &mut TokenIter, start: &Token) -> (String, Node) {
-    // Get the string key
-    let name = match tokens.next() {
-        Some(t) => {
-            if t.tok_type != TT::String {
-                panic!("Expected a property name (string), got back the token {} {}", t, start.pos())
-            }
+    Ok(Node::Array(body))
+}
 
-            t.value.clone()
-        }
-        None => panic!("Encountered an EOF while trying to build object property. {}", start.pos())
+/// Check that the next token is a key, where `after` is the `{` or `,` before it.
+///
+/// Looking two tokens ahead tells a key without quotes, like `{a: 1}`, from any other
+/// misplaced token, so the error can say how to fix it.
+fn expect_key<S: TokenSource>(tokens: &mut S, after: &Token, expected: &[TT], builder: &Builder) -> Result<()> {
+    let is_key = match tokens.peek()? {
+        Some(t) => builder.is_key(t),
+        None => return Err(JsonError::unexpected_eof(expected, after))
     };
 
+    if is_key {
+        return Ok(());
+    }
+
+    let unquoted = tokens.peek_type(0)? == Some(TT::Name) && tokens.peek_type(1)? == Some(TT::Colon);
+    let error = JsonError::unexpected(expected, tokens.peek()?.unwrap());
+
+    match unquoted {
+        true => Err(error.with_hint("put the key in double quotes")),
+        false => Err(error)
+    }
+}
+
+/// Parse a key and its value, adding them to `body`. The key has already been
+/// checked by `expect_key`.
+fn parse_pair<S: TokenSource>(tokens: &mut S, builder: &mut Builder, body: &mut ObjectMap) -> Result<()> {
+    let key = tokens.next()?.unwrap();
+    let key = key.borrow();
+
+    builder.add_string(key)?;
+
+    let name = builder.key(key.text());
+
     // Check for a colon
-    match tokens.next() {
+    match tokens.next()? {
         Some(t) => {
-            if t.tok_type != TT::Colon {
-                panic!("Expected a colon, got back the token {} {}", t, start.pos())
+            if t.borrow().tok_type != TT::Colon {
+                return Err(JsonError::unexpected(&[TT::Colon], t.borrow()).in_key(&name));
             }
         },
-        None => panic!("Encountered an EOF while trying to build object property. {}", start.pos())
+        None => return Err(JsonError::unexpected_eof(&[TT::Colon], key).in_key(&name))
     };
 
-    let peeked = match tokens.peek() {
-        Some(t) => t,
-        None => panic!("Encountered an EOF while trying to build object property. {}", start.pos())
+    let kind = match tokens.peek()? {
+        Some(t) => t.tok_type,
+        None => return Err(JsonError::unexpected_eof(TT::VALUE_START, key).in_key(&name))
     };
 
-    let value = match peeked.tok_type {
-        TT::LBrace  => parse_object(tokens),
-        TT::LSqBrac => parse_array(tokens),
-        TT::Int | TT::String | TT::Float | TT::Name => parse_simple(tokens.next().unwrap()),
+    if !TT::VALUE_START.contains(&kind) {
+        return Err(JsonError::unexpected(TT::VALUE_START, tokens.peek()?.unwrap()).in_key(&name));
+    }
 
-        _ => panic!("Invalid token for an object property: {}", peeked)
-    };
+    let value = parse_value(tokens, kind, builder).map_err(|e| e.in_key(&name))?;
 
-    (name, value)
-}
+    if builder.options.reject_duplicate_keys && body.contains_key(&name) {
+        return Err(JsonError::at_token(format!("Found the key {:?} more than once.", name), key).with_code(ErrorCode::DuplicateKey).in_key(&name));
+    }
 
-fn parse_object(tokens: &mut TokenIter) -> Node {
-    let mut body: HashMap<String, Node> = HashMap::new();
+    body.insert(name, value);
+
+    Ok(())
+}
 
+fn parse_object<S: TokenSource>(tokens: &mut S, builder: &mut Builder) -> Result<Node> {
     // This will always be a '{'
-    let mut start = tokens.next().unwrap();
+    let open = tokens.next()?.unwrap();
 
-    // This is the end of the object
-    start = match tokens.peek() {
-        Some(t) => {
-            if t.tok_type == TT::RBrace {
-                return Node::Object(body);
-            }
+    builder.enter(open.borrow())?;
 
-            t
-        },
-        None => panic!("Encountered an EOF when trying to parse object. {}", start.pos())
-    };
+    let mut body = builder.object();
 
-    let (name, value) = parse_pair(tokens, start);
+    // The first key could also have been the end of an empty object.
+    if tokens.peek_type(0)? == Some(TT::RBrace) {
+        tokens.next()?;
+        builder.leave();
 
-    body.insert(name, value);
+        return Ok(Node::Object(body));
+    }
+
+    expect_key(tokens, open.borrow(), &[TT::String, TT::RBrace], builder)?;
+    parse_pair(tokens, builder, &mut body)?;
+
+    // The last `{` or `,` read here, for where to say the input ended.
+    let mut last = open;
 
     loop {
-        start = match tokens.next() {
-            Some(t) => t,
-            None => panic!("Encountered an EOF when trying to parse object pair. {}", start.pos())
-        };
+        match tokens.peek_type(0)? {
+            Some(TT::RBrace) => {
+                tokens.next()?;
+                break;
+            },
+
+            // Only look past the comma here, since looking past the `}` would read
+            // beyond the end of the value.
+            Some(TT::Comma) => {
+                if tokens.peek_type(1)? == Some(TT::RBrace) && builder.options.allow_trailing_commas {
+                    tokens.next()?;
+                    tokens.next()?;
+                    break;
+                }
+
+                let comma = tokens.next()?.unwrap();
 
-        match start.tok_type {
-            TT::RBrace => break,
-            TT::Comma  => {
-                let (name, value) = parse_pair(tokens, start);
+                expect_key(tokens, comma.borrow(), &[TT::String], builder)?;
+                parse_pair(tokens, builder, &mut body)?;
 
-                body.insert(name, value);
+                last = comma;
             },
 
-            _ => panic!("Encountered invalid token when trying to parse object. {} {}", start, start.pos())
+            // A key followed by a colon after a value is most likely a forgotten comma.
+            Some(TT::String) if tokens.peek_type(1)? == Some(TT::Colon) => {
+                let error = JsonError::unexpected(&[TT::Comma, TT::RBrace], tokens.peek()?.unwrap());
+                return Err(error.with_hint("add a comma before this key"));
+            },
+
+            Some(_) => return Err(JsonError::unexpected(&[TT::Comma, TT::RBrace], tokens.peek()?.unwrap())),
+            None => return Err(JsonError::unexpected_eof(&[TT::Comma, TT::RBrace], last.borrow()))
         }
     }
 
-    Node::Object(body)
+    builder.leave();
+
+    Ok(Node::Object(body))
+}
+
+/// Options for parsing JSON text with `parse_with_options`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ParseOptions {
+    pub lex: LexOptions,
+
+    /// Stop once the value is read instead of failing when anything comes after it,
+    /// like in protocols that send `JSON\n<binary>` frames.
+    pub allow_trailing_data: bool,
+
+    /// Allow a comma after the last item of an array or object, like `[1, 2,]`.
+    pub allow_trailing_commas: bool,
+
+    /// Allow object keys without quotes when they're plain names, like `{key: 1}`.
+    pub allow_unquoted_keys: bool,
+
+    /// Fail when an object has the same key twice, instead of keeping the last value.
+    pub reject_duplicate_keys: bool,
+
+    /// How deeply arrays and objects can be nested.
+    pub max_depth: Option<usize>,
+
+    /// How many values the tree can have, counting every array, object and value
+    /// inside them, so small input like `[[],[],[],...]` can't build a huge tree.
+    pub max_nodes: Option<usize>,
+
+    /// How many bytes all of the strings can add up to, keys included.
+    pub max_total_string_bytes: Option<usize>,
+
+    /// Put string values into Unicode Normalization Form C, so the same text
+    /// always has the same bytes however it was composed.
+    #[cfg(feature = "normalize")]
+    pub nfc_strings: bool,
+
+    /// Put object keys into Unicode Normalization Form C, so keys that only differ
+    /// in how they were composed, like `"é"` and `"e\u0301"`, count as the same key.
+    #[cfg(feature = "normalize")]
+    pub nfc_keys: bool
 }
 
-pub fn parse(token_vec: &Vec<Token>) -> Node {
-    let mut tokens = TokenIter::new(&token_vec);
+/// Parse the JSON value at the start of `text`, giving it back along with
+/// the byte offset just after it.
+///
+/// Text is only tokenised up to the end of the value, so whatever comes after it
+/// is never looked at when `allow_trailing_data` is set.
+pub fn parse_with_options(text: &str, options: &ParseOptions) -> Result<(Node, usize)> {
+    metrics::record(text.len(), || parse_text(text, options))
+}
 
-    let first = match tokens.peek() {
-        Some(t) => t,
-        None => return Node::Empty
+fn parse_text(text: &str, options: &ParseOptions) -> Result<(Node, usize)> {
+    let mut tokens = LexerSource {
+        lexer: Lexer::with_options(text, options.lex),
+        peeked: VecDeque::with_capacity(2)
     };
 
-    let out = match first.tok_type {
-        TT::Int | TT::Float | TT::String | TT::Name => {
-            tokens.next();
-            parse_simple(first)
-        }
-        TT::LBrace => parse_object(&mut tokens),
-        TT::LSqBrac => parse_array(&mut tokens),
-        
-        _ => panic!("Invalid starting token: {}", first)
+    let node = build_value(&mut tokens, &mut Builder::new(options, None))?;
+
+    // Nothing past the value has been read yet, so this is where it ends.
+    let end = tokens.lexer.offset();
+
+    if !options.allow_trailing_data && let Some(t) = tokens.next()? {
+        return Err(JsonError::unexpected(&[], &t));
+    }
+
+    Ok((node, end))
+}
+
+/// Parse JSON text in one pass, reading each token as the grammar needs it
+/// rather than tokenising the whole text first, so there's no vector of tokens
+/// taking up memory alongside the tree.
+///
+/// Use `tokenise` and `parse` for tooling that needs the tokens themselves.
+pub fn parse_str(text: &str) -> Result<Node> {
+    parse_with_options(text, &ParseOptions::default()).map(|(node, _)| node)
+}
+
+pub fn parse(token_vec: &[Token]) -> Result<Node> {
+    build(token_vec, &mut Builder::new(&ParseOptions::default(), None))
+}
+
+/// Parse tokens, taking strings, arrays and objects from `pool` where it has
+/// spares rather than allocating new ones. See `NodePool`.
+pub fn parse_with_pool(token_vec: &[Token], pool: &mut NodePool) -> Result<Node> {
+    build(token_vec, &mut Builder::new(&ParseOptions::default(), Some(pool)))
+}
+
+fn build(token_vec: &[Token], builder: &mut Builder) -> Result<Node> {
+    let mut tokens = TokenIter::new(token_vec);
+    let out = build_value(&mut tokens, builder)?;
+
+    if let Some(next) = tokens.peek() {
+        return Err(JsonError::unexpected(&[], next));
+    }
+
+    Ok(out)
+}
+
+/// Parse the value at the start of the tokens, leaving anything after it unread.
+fn build_value<S: TokenSource>(tokens: &mut S, builder: &mut Builder) -> Result<Node> {
+    let kind = match tokens.peek()? {
+        Some(t) => t.tok_type,
+        None => return Ok(Node::Empty)
     };
 
-    if !tokens.peek().is_none() {
-        let remaining: Vec<&Token> = tokens.collect();
-        panic!("Tokens iterator was not entirely consumed!\nLeftover tokens: {:?}", remaining)
+    if !TT::VALUE_START.contains(&kind) {
+        return Err(JsonError::unexpected(TT::VALUE_START, tokens.peek()?.unwrap()));
     }
 
-    out
-}
\ No newline at end of file
+    parse_value(tokens, kind, builder)
+}
+
+/// Iterates over the elements of a top-level array one at a time.
+///
+/// Only the tokens of the element currently being parsed are held in memory,
+/// so huge arrays can be processed without building the whole tree.
+pub struct ArrayIter<'a> {
+    lexer: Lexer<'a>,
+    started: bool,
+
+    /// The index of the element being read, for the path in errors.
+    index: usize,
+    done: bool
+}
+
+impl<'a> ArrayIter<'a> {
+    fn next_token(&mut self) -> Result<Token> {
+        match self.lexer.next_token()? {
+            Some(t) => Ok(t),
+            None => Err(JsonError::at_pos("Encountered an EOF while trying to iterate over array.", &self.lexer.pos())
+                .with_code(ErrorCode::UnexpectedEof)
+                .with_offset(self.lexer.offset()))
+        }
+    }
+
+    fn next_element(&mut self) -> Result<Option<Node>> {
+        let mut first = self.next_token()?;
+
+        if !self.started {
+            if first.tok_type != TT::LSqBrac {
+                return Err(JsonError::at_token(format!("Expected a top-level array, got back the token {}", first), &first).with_code(ErrorCode::UnexpectedToken));
+            }
+
+            self.started = true;
+            first = self.next_token()?;
+
+            // This is an empty array.
+            if first.tok_type == TT::RSqBrac {
+                self.finish()?;
+                return Ok(None);
+            }
+        }
+
+        // Collect every token belonging to this element, keeping track
+        // of nesting so we know where the element ends.
+        let mut depth = 0usize;
+        let mut element: Vec<Token> = Vec::new();
+        let mut token = first;
+
+        loop {
+            match token.tok_type {
+                TT::LSqBrac | TT::LBrace => depth += 1,
+                TT::RSqBrac | TT::RBrace => {
+                    if depth == 0 {
+                        return Err(JsonError::unexpected(TT::VALUE_START, &token));
+                    }
+
+                    depth -= 1;
+                },
+                TT::Comma if depth == 0 => {
+                    return Err(JsonError::unexpected(TT::VALUE_START, &token));
+                },
+                _ => {}
+            }
+
+            element.push(token);
+
+            if depth == 0 {
+                break;
+            }
+
+            token = self.next_token()?;
+        }
+
+        let node = parse(&element).map_err(|e| e.in_index(self.index))?;
+
+        // Afterwards, we should either have another element or the end of the array.
+        let next = self.next_token()?;
+
+        match next.tok_type {
+            TT::Comma => {},
+            TT::RSqBrac => self.finish()?,
+
+            _ => return Err(JsonError::unexpected(&[TT::Comma, TT::RSqBrac], &next))
+        }
+
+        self.index += 1;
+
+        Ok(Some(node))
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.done = true;
+
+        match self.lexer.next_token()? {
+            Some(t) => Err(JsonError::unexpected(&[], &t)),
+            None => Ok(())
+        }
+    }
+}
+
+impl<'a> Iterator for ArrayIter<'a> {
+    type Item = Result<Node>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let result = self.next_element();
+
+        // Any error is fatal, so there's nothing left to yield after one.
+        if result.is_err() {
+            self.done = true;
+        }
+
+        result.transpose()
+    }
+}
+
+/// Parse the elements of a top-level array lazily, one element at a time.
+pub fn parse_array_iter(input: &str) -> ArrayIter<'_> {
+    ArrayIter {
+        lexer: Lexer::new(input),
+        started: false,
+        index: 0,
+        done: false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compare::Tolerance;
+    use crate::error::{ErrorCode, PathSegment};
+    use crate::lexer::tokenise;
+
+    fn parse_options(text: &str, options: ParseOptions) -> Result<Node> {
+        parse_with_options(text, &options).map(|(node, _)| node)
+    }
+
+    #[test]
+    fn parse_str_matches_parse() {
+        let text = r#"{"a": [1, -2.5, "x", {"b": null}], "c": true}"#;
+        assert!(parse_str(text).unwrap().approx_eq(&parse(&tokenise(text).unwrap()).unwrap(), &Tolerance::exact()));
+    }
+
+    #[test]
+    fn numbers_keep_their_kind() {
+        assert!(matches!(parse_str("1.0").unwrap(), Node::Float(f) if f == 1.0));
+        assert!(matches!(parse_str("1e2").unwrap(), Node::Float(f) if f == 100.0));
+        assert!(matches!(parse_str("9223372036854775807").unwrap(), Node::Integer(i64::MAX)));
+        assert!(matches!(parse_str("18446744073709551615").unwrap(), Node::Unsigned(u64::MAX)));
+        assert!(matches!(parse_str("-0").unwrap(), Node::Float(f) if f == 0.0 && f.is_sign_negative()));
+        assert!(matches!(parse_str("0").unwrap(), Node::Integer(0)));
+    }
+
+    #[test]
+    fn json5_numbers_have_their_values() {
+        let options = ParseOptions { lex: LexOptions { allow_json5_numbers: true, ..LexOptions::default() }, ..ParseOptions::default() };
+
+        assert!(matches!(parse_options("0x1F", options).unwrap(), Node::Integer(31)));
+        assert!(matches!(parse_options("-0x10", options).unwrap(), Node::Integer(-16)));
+        assert!(matches!(parse_options("+7", options).unwrap(), Node::Integer(7)));
+        assert!(matches!(parse_options(".5", options).unwrap(), Node::Float(f) if f == 0.5));
+        assert!(matches!(parse_options("5.", options).unwrap(), Node::Float(f) if f == 5.0));
+    }
+
+    #[test]
+    fn trailing_data_gives_back_the_end() {
+        let text = "{\"a\": 1}\nrest";
+        assert!(parse_str(text).is_err());
+
+        let options = ParseOptions { allow_trailing_data: true, ..ParseOptions::default() };
+        assert_eq!(parse_with_options(text, &options).unwrap().1, 8);
+    }
+
+    #[test]
+    fn grammar_options_are_off_by_default() {
+        assert!(parse_str("[1, 2,]").is_err());
+        assert!(parse_str("{a: 1}").is_err());
+        assert!(parse_str(r#"{"a": 1, "a": 2}"#).is_ok());
+
+        let options = ParseOptions { allow_trailing_commas: true, allow_unquoted_keys: true, ..ParseOptions::default() };
+        assert!(parse_options("[1, 2,]", options).is_ok());
+        assert!(parse_options("{a: 1,}", options).unwrap().get("a").is_some());
+
+        let options = ParseOptions { reject_duplicate_keys: true, ..ParseOptions::default() };
+        assert_eq!(parse_options(r#"{"a": 1, "a": 2}"#, options).unwrap_err().code, ErrorCode::DuplicateKey);
+    }
+
+    #[test]
+    fn errors_say_what_was_expected() {
+        let error = parse_str("[1 2]").unwrap_err();
+
+        assert_eq!(error.code, ErrorCode::UnexpectedToken);
+        assert_eq!(error.expected_descriptions(), ["','", "']'"]);
+    }
+
+    #[test]
+    fn errors_know_their_path() {
+        let error = parse_str(r#"{"a": [1, {"b": tru}]}"#).unwrap_err();
+        assert_eq!(*error.path, [PathSegment::Key("a".to_string()), PathSegment::Index(1), PathSegment::Key("b".to_string())]);
+
+        let error = parse(&tokenise(r#"{"a": [1, {"b": tru}]}"#).unwrap()).unwrap_err();
+        assert_eq!(error.path.len(), 3);
+    }
+
+    #[test]
+    fn limits_are_enforced() {
+        let depth = ParseOptions { max_depth: Some(2), ..ParseOptions::default() };
+        assert!(parse_options("[[1]]", depth).is_ok());
+        assert_eq!(parse_options("[[[1]]]", depth).unwrap_err().code, ErrorCode::LimitExceeded);
+
+        let nodes = ParseOptions { max_nodes: Some(3), ..ParseOptions::default() };
+        assert!(parse_options("[1, 2]", nodes).is_ok());
+        assert_eq!(parse_options("[1, 2, 3]", nodes).unwrap_err().code, ErrorCode::LimitExceeded);
+
+        let strings = ParseOptions { max_total_string_bytes: Some(4), ..ParseOptions::default() };
+        assert!(parse_options(r#"{"ab": "cd"}"#, strings).is_ok());
+        assert_eq!(parse_options(r#"{"ab": "cde"}"#, strings).unwrap_err().code, ErrorCode::LimitExceeded);
+    }
+
+    #[test]
+    fn array_iter_parses_one_item_at_a_time() {
+        let items: Vec<_> = parse_array_iter("[1, {\"a\": 2}, [3]]").collect::<Result<_>>().unwrap();
+        assert_eq!(items.len(), 3);
+
+        let mut bad = parse_array_iter("[1, @]");
+        assert!(bad.next().unwrap().is_ok());
+        assert!(bad.next().unwrap().is_err());
+    }
+}
@@ -0,0 +1,346 @@
+use std::fmt::Display;
+
+use crate::compare::Tolerance;
+use crate::parser::{Node, ObjectMap};
+use crate::pointer::{parse_index, split_pointer};
+
+/// An operation of a patch that couldn't be applied.
+#[derive(Debug)]
+pub struct PatchError {
+    /// The JSON Pointer of the operation in the patch, like `/2`.
+    pub path: String,
+    pub message: String
+}
+
+impl Display for PatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "at {}: {}", self.path, self.message)
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+fn error(path: &str, message: impl Into<String>) -> PatchError {
+    PatchError {
+        path: path.to_string(),
+        message: message.into()
+    }
+}
+
+/// One operation of a JSON Patch (RFC 6902). Paths are JSON Pointers.
+#[derive(Clone, Debug)]
+pub enum PatchOp {
+    /// Put a value at `path`, inserting it before the item there if it's in an array,
+    /// or at the end if the last token is `-`.
+    Add {
+        path: String,
+        value: Node
+    },
+
+    /// Take out the value at `path`, which has to be there.
+    Remove {
+        path: String
+    },
+
+    /// Swap the value at `path`, which has to be there, for another.
+    Replace {
+        path: String,
+        value: Node
+    },
+
+    /// Take out the value at `from` and add it at `path`.
+    Move {
+        from: String,
+        path: String
+    },
+
+    /// Add a copy of the value at `from` at `path`.
+    Copy {
+        from: String,
+        path: String
+    },
+
+    /// Fail the patch unless the value at `path` is equal to `value`. Numbers are
+    /// compared by value, as the RFC says, so `1` and `1.0` are equal.
+    Test {
+        path: String,
+        value: Node
+    }
+}
+
+fn split(pointer: &str) -> Result<Vec<String>, String> {
+    split_pointer(pointer).ok_or_else(|| format!("{:?} isn't a JSON Pointer, since it doesn't start with a `/`", pointer))
+}
+
+fn get_mut<'a>(node: &'a mut Node, tokens: &[String]) -> Option<&'a mut Node> {
+    let mut current = node;
+
+    for token in tokens {
+        current = match current {
+            Node::Object(map) => map.get_mut(token)?,
+            Node::Array(arr) => arr.get_mut(parse_index(token)?)?,
+            _ => return None
+        };
+    }
+
+    Some(current)
+}
+
+fn add(doc: &mut Node, pointer: &str, value: Node) -> Result<(), String> {
+    let tokens = split(pointer)?;
+
+    let Some((last, parent)) = tokens.split_last() else {
+        *doc = value;
+        return Ok(());
+    };
+
+    match get_mut(doc, parent) {
+        Some(Node::Object(map)) => {
+            map.insert(last.clone(), value);
+        },
+        Some(Node::Array(arr)) => match last.as_str() {
+            "-" => arr.push(value),
+            _ => match parse_index(last) {
+                Some(i) if i <= arr.len() => arr.insert(i, value),
+                _ => return Err(format!("{:?} is past the end of the array", pointer))
+            }
+        },
+        Some(_) => return Err(format!("{:?} is inside a value that isn't an array or object", pointer)),
+        None => return Err(format!("{:?} is inside a value that isn't there", pointer))
+    }
+
+    Ok(())
+}
+
+fn remove(doc: &mut Node, pointer: &str) -> Result<Node, String> {
+    let tokens = split(pointer)?;
+    let missing = || format!("{:?} isn't there to remove", pointer);
+
+    let Some((last, parent)) = tokens.split_last() else {
+        return Err("The whole document can't be removed".to_string());
+    };
+
+    match get_mut(doc, parent) {
+        Some(Node::Object(map)) => map.remove(last).ok_or_else(missing),
+        Some(Node::Array(arr)) => match parse_index(last) {
+            Some(i) if i < arr.len() => Ok(arr.remove(i)),
+            _ => Err(missing())
+        },
+        _ => Err(missing())
+    }
+}
+
+fn get(doc: &Node, pointer: &str) -> Result<Node, String> {
+    split(pointer)?;
+    doc.pointer(pointer).cloned().ok_or_else(|| format!("{:?} isn't there", pointer))
+}
+
+impl PatchOp {
+    fn apply(&self, doc: &mut Node) -> Result<(), String> {
+        match self {
+            PatchOp::Add { path, value } => add(doc, path, value.clone()),
+            PatchOp::Remove { path } => remove(doc, path).map(|_| ()),
+            PatchOp::Replace { path, value } => {
+                let target = get_mut(doc, &split(path)?).ok_or_else(|| format!("{:?} isn't there to replace", path))?;
+                *target = value.clone();
+
+                Ok(())
+            },
+            PatchOp::Move { from, path } => {
+                // A value can't be moved inside itself, like `/a` to `/a/b`.
+                if path.starts_with(&format!("{}/", from)) {
+                    return Err(format!("{:?} can't be moved inside itself", from));
+                }
+
+                let value = remove(doc, from)?;
+                add(doc, path, value)
+            },
+            PatchOp::Copy { from, path } => {
+                let value = get(doc, from)?;
+                add(doc, path, value)
+            },
+            PatchOp::Test { path, value } => {
+                let found = get(doc, path)?;
+
+                let numeric = Tolerance {
+                    int_float_equal: true,
+                    ..Tolerance::exact()
+                };
+
+                match value.first_mismatch(&found, &numeric) {
+                    None => Ok(()),
+                    Some(mismatch) if mismatch.path == "$" => Err(format!("The test of {:?} failed: {}", path, mismatch.message)),
+                    Some(mismatch) => Err(format!("The test of {:?} failed at {} inside it: {}", path, mismatch.path, mismatch.message))
+                }
+            }
+        }
+    }
+
+    /// The JSON Pointer the operation changes or tests.
+    pub fn path(&self) -> &str {
+        match self {
+            PatchOp::Add { path, .. }
+            | PatchOp::Remove { path }
+            | PatchOp::Replace { path, .. }
+            | PatchOp::Move { path, .. }
+            | PatchOp::Copy { path, .. }
+            | PatchOp::Test { path, .. } => path
+        }
+    }
+
+    /// The operation in its data form, like `{"op": "add", "path": "/a", "value": 1}`.
+    pub fn to_node(&self) -> Node {
+        let (op, from, value) = match self {
+            PatchOp::Add { value, .. } => ("add", None, Some(value)),
+            PatchOp::Remove { .. } => ("remove", None, None),
+            PatchOp::Replace { value, .. } => ("replace", None, Some(value)),
+            PatchOp::Move { from, .. } => ("move", Some(from), None),
+            PatchOp::Copy { from, .. } => ("copy", Some(from), None),
+            PatchOp::Test { value, .. } => ("test", None, Some(value))
+        };
+
+        let mut map = ObjectMap::from_iter([
+            ("op".to_string(), Node::from(op)),
+            ("path".to_string(), Node::from(self.path()))
+        ]);
+
+        if let Some(from) = from {
+            map.insert("from".to_string(), Node::from(from.as_str()));
+        }
+
+        if let Some(value) = value {
+            map.insert("value".to_string(), value.clone());
+        }
+
+        Node::Object(map)
+    }
+
+    /// Read an operation from its data form, like `{"op": "add", "path": "/a", "value": 1}`.
+    fn from_node(node: &Node, index: usize) -> Result<PatchOp, PatchError> {
+        let here = format!("/{}", index);
+
+        let field = |name: &str| match node.get(name) {
+            Some(Node::String(s)) => Ok(s.clone()),
+            _ => Err(error(&here, format!("The operation needs a string {:?}", name)))
+        };
+
+        let value = || node.get("value").cloned().ok_or_else(|| error(&here, "The operation needs a \"value\""));
+
+        let op = match field("op")?.as_str() {
+            "add" => PatchOp::Add { path: field("path")?, value: value()? },
+            "remove" => PatchOp::Remove { path: field("path")? },
+            "replace" => PatchOp::Replace { path: field("path")?, value: value()? },
+            "move" => PatchOp::Move { from: field("from")?, path: field("path")? },
+            "copy" => PatchOp::Copy { from: field("from")?, path: field("path")? },
+            "test" => PatchOp::Test { path: field("path")?, value: value()? },
+            op => return Err(error(&here, format!("Unknown op {:?}", op)))
+        };
+
+        Ok(op)
+    }
+}
+
+/// A list of operations to make to a document, as a JSON Patch (RFC 6902) like:
+///
+/// ```text
+/// [
+///     {"op": "test", "path": "/version", "value": 2},
+///     {"op": "replace", "path": "/name", "value": "Ada"},
+///     {"op": "add", "path": "/tags/-", "value": "new"}
+/// ]
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Patch {
+    pub ops: Vec<PatchOp>
+}
+
+impl Patch {
+    /// Read a patch from its data form, an array of operation objects.
+    pub fn from_node(node: &Node) -> Result<Patch, PatchError> {
+        let Node::Array(ops) = node else {
+            return Err(error("", "A patch must be an array of operations"));
+        };
+
+        let ops = ops.iter().enumerate().map(|(i, op)| PatchOp::from_node(op, i)).collect::<Result<_, _>>()?;
+
+        Ok(Patch { ops })
+    }
+
+    /// The patch in its data form, an array of operation objects.
+    pub fn to_node(&self) -> Node {
+        Node::Array(self.ops.iter().map(PatchOp::to_node).collect())
+    }
+
+    /// Apply every operation to a document, in order.
+    ///
+    /// If one fails, like a `test` not matching, the document is left as it was,
+    /// and the error's path is the index of the operation, like `/3`.
+    pub fn apply(&self, doc: &mut Node) -> Result<(), PatchError> {
+        let mut patched = doc.clone();
+
+        for (i, op) in self.ops.iter().enumerate() {
+            op.apply(&mut patched).map_err(|message| error(&format!("/{}", i), message))?;
+        }
+
+        *doc = patched;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_str;
+
+    fn doc(text: &str) -> Node {
+        parse_str(text).unwrap()
+    }
+
+    fn apply(patch: &str, text: &str) -> Result<Node, PatchError> {
+        let mut node = doc(text);
+        Patch::from_node(&doc(patch))?.apply(&mut node).map(|_| node)
+    }
+
+    #[test]
+    fn test_compares_numbers_by_value() {
+        assert!(apply(r#"[{"op": "test", "path": "/a", "value": 1.0}]"#, r#"{"a": 1}"#).is_ok());
+        assert!(apply(r#"[{"op": "test", "path": "/a", "value": [1, {"b": 2.0}]}]"#, r#"{"a": [1.0, {"b": 2}]}"#).is_ok());
+        assert!(apply(r#"[{"op": "test", "path": "/a", "value": 1.5}]"#, r#"{"a": 1}"#).is_err());
+        assert!(apply(r#"[{"op": "test", "path": "/a", "value": "1"}]"#, r#"{"a": 1}"#).is_err());
+    }
+
+    #[test]
+    fn failed_patch_leaves_the_document_alone() {
+        let mut node = doc(r#"{"a": 1}"#);
+        let patch = Patch::from_node(&doc(r#"[{"op": "add", "path": "/b", "value": 2}, {"op": "remove", "path": "/c"}]"#)).unwrap();
+
+        assert_eq!(patch.apply(&mut node).unwrap_err().path, "/1");
+        assert!(node.approx_eq(&doc(r#"{"a": 1}"#), &Tolerance::exact()));
+    }
+
+    #[test]
+    fn ops_follow_the_rfc() {
+        let patched = apply(r#"[
+            {"op": "add", "path": "/list/1", "value": "x"},
+            {"op": "add", "path": "/list/-", "value": "end"},
+            {"op": "move", "from": "/a", "path": "/moved"},
+            {"op": "copy", "from": "/moved", "path": "/copied"},
+            {"op": "replace", "path": "/b", "value": null}
+        ]"#, r#"{"list": [1, 2], "a": {"k": 1}, "b": true}"#).unwrap();
+
+        let expected = doc(r#"{"list": [1, "x", 2, "end"], "moved": {"k": 1}, "copied": {"k": 1}, "b": null}"#);
+        assert!(patched.approx_eq(&expected, &Tolerance::exact()));
+
+        assert!(apply(r#"[{"op": "move", "from": "/a", "path": "/a/b"}]"#, r#"{"a": {}}"#).is_err());
+        assert!(apply(r#"[{"op": "add", "path": "/list/5", "value": 1}]"#, r#"{"list": []}"#).is_err());
+    }
+
+    #[test]
+    fn ops_round_trip_through_their_data_form() {
+        let text = r#"[{"op": "move", "from": "/a", "path": "/b"}, {"op": "test", "path": "/b", "value": [1]}]"#;
+        let patch = Patch::from_node(&doc(text)).unwrap();
+
+        assert!(patch.to_node().approx_eq(&doc(text), &Tolerance::exact()));
+    }
+}
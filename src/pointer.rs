@@ -0,0 +1,322 @@
+use crate::error::{ErrorCode, JsonError, Result};
+use crate::lexer::Lexer;
+use crate::parser::{parse, Node, ObjectMap};
+use crate::token::{Token, TokenType as TT};
+
+/// Split a JSON Pointer (RFC 6901) like `/a/b~1c/0` into its unescaped reference tokens.
+///
+/// Returns `None` if the pointer is neither empty nor starts with a `/`.
+pub fn split_pointer(pointer: &str) -> Option<Vec<String>> {
+    if pointer.is_empty() {
+        return Some(Vec::new());
+    }
+
+    let rest = pointer.strip_prefix('/')?;
+
+    Some(rest.split('/').map(
+        |token| token.replace("~1", "/").replace("~0", "~")
+    ).collect())
+}
+
+/// Escape a single reference token so it can be joined into a JSON Pointer.
+pub fn escape_token(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+/// Parse an array index from a reference token, rejecting leading zeros like `01`.
+pub(crate) fn parse_index(token: &str) -> Option<usize> {
+    if token.len() > 1 && token.starts_with('0') {
+        return None;
+    }
+
+    if !token.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    token.parse().ok()
+}
+
+/// Follow already split reference tokens down from `node`.
+fn lookup<'a>(node: &'a Node, tokens: &[String]) -> Option<&'a Node> {
+    let mut current = node;
+
+    for token in tokens {
+        current = match current {
+            Node::Object(map) => map.get(token)?,
+            Node::Array(arr) => arr.get(parse_index(token)?)?,
+            _ => return None
+        };
+    }
+
+    Some(current)
+}
+
+impl Node {
+    /// Look up a value by JSON Pointer, like `/orders/17/price`.
+    pub fn pointer(&self, pointer: &str) -> Option<&Node> {
+        lookup(self, &split_pointer(pointer)?)
+    }
+
+    /// Look up a value by JSON Pointer for modification.
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Node> {
+        let mut current = self;
+
+        for token in split_pointer(pointer)? {
+            current = match current {
+                Node::Object(map) => map.get_mut(&token)?,
+                Node::Array(arr) => arr.get_mut(parse_index(&token)?)?,
+                _ => return None
+            };
+        }
+
+        Some(current)
+    }
+}
+
+/// Walks the token stream once, parsing only the values that were asked for.
+struct Extractor<'a> {
+    lexer: Lexer<'a>,
+    targets: Vec<(String, Vec<String>)>,
+    found: ObjectMap
+}
+
+impl<'a> Extractor<'a> {
+    fn next_token(&mut self) -> Result<Token> {
+        match self.lexer.next_token()? {
+            Some(t) => Ok(t),
+            None => Err(JsonError::at_pos("Encountered an EOF while extracting values.", &self.lexer.pos())
+                .with_code(ErrorCode::UnexpectedEof)
+                .with_offset(self.lexer.offset()))
+        }
+    }
+
+    /// Read the rest of a value starting at `first`, keeping its tokens only if `keep` is set.
+    fn take_value(&mut self, first: Token, keep: bool) -> Result<Vec<Token>> {
+        let mut depth = 0usize;
+        let mut tokens = Vec::new();
+        let mut token = first;
+
+        loop {
+            match token.tok_type {
+                TT::LSqBrac | TT::LBrace => depth += 1,
+                TT::RSqBrac | TT::RBrace if depth == 0 => {
+                    return Err(JsonError::unexpected(TT::VALUE_START, &token));
+                },
+                TT::RSqBrac | TT::RBrace => depth -= 1,
+                _ => {}
+            }
+
+            if keep {
+                tokens.push(token);
+            }
+
+            if depth == 0 {
+                return Ok(tokens);
+            }
+
+            token = self.next_token()?;
+        }
+    }
+
+    /// Visit the value starting at `first`, found at `path`.
+    ///
+    /// Returns `true` once every target has been found, so the caller can stop.
+    fn visit(&mut self, first: Token, path: &mut Vec<String>) -> Result<bool> {
+        let wanted = self.targets.iter().any(|(_, target)| target == path);
+
+        if wanted {
+            let node = parse(&self.take_value(first, true)?)?;
+
+            // Other targets might be inside this value too, so look them up in it.
+            for (pointer, target) in &self.targets {
+                if let Some(rest) = target.strip_prefix(path.as_slice())
+                    && let Some(value) = lookup(&node, rest)
+                {
+                    self.found.insert(pointer.clone(), value.clone());
+                }
+            }
+
+            return Ok(self.found.len() == self.targets.len());
+        }
+
+        // Nothing we want lives inside this value, so skip over it.
+        if !self.targets.iter().any(|(_, target)| target.starts_with(path)) {
+            self.take_value(first, false)?;
+            return Ok(false);
+        }
+
+        match first.tok_type {
+            TT::LSqBrac => {
+                let mut token = self.next_token()?;
+
+                if token.tok_type == TT::RSqBrac {
+                    return Ok(false);
+                }
+
+                let mut index = 0usize;
+
+                loop {
+                    path.push(index.to_string());
+                    let done = self.visit(token, path)?;
+                    path.pop();
+
+                    if done {
+                        return Ok(true);
+                    }
+
+                    let next = self.next_token()?;
+
+                    match next.tok_type {
+                        TT::Comma => {},
+                        TT::RSqBrac => return Ok(false),
+                        _ => return Err(JsonError::unexpected(&[TT::Comma, TT::RSqBrac], &next))
+                    }
+
+                    index += 1;
+                    token = self.next_token()?;
+                }
+            },
+
+            TT::LBrace => {
+                let mut key = self.next_token()?;
+
+                if key.tok_type == TT::RBrace {
+                    return Ok(false);
+                }
+
+                loop {
+                    if key.tok_type != TT::String {
+                        return Err(JsonError::unexpected(&[TT::String], &key));
+                    }
+
+                    let colon = self.next_token()?;
+
+                    if colon.tok_type != TT::Colon {
+                        return Err(JsonError::unexpected(&[TT::Colon], &colon));
+                    }
+
+                    let value = self.next_token()?;
+
+                    path.push(key.text().to_string());
+                    let done = self.visit(value, path)?;
+                    path.pop();
+
+                    if done {
+                        return Ok(true);
+                    }
+
+                    let next = self.next_token()?;
+
+                    match next.tok_type {
+                        TT::Comma => {},
+                        TT::RBrace => return Ok(false),
+                        _ => return Err(JsonError::unexpected(&[TT::Comma, TT::RBrace], &next))
+                    }
+
+                    key = self.next_token()?;
+                }
+            },
+
+            // A scalar has nothing inside it to look for.
+            _ => Ok(false)
+        }
+    }
+}
+
+/// Find the values at several JSON Pointers in a single pass over `input`.
+///
+/// Only the requested values are parsed into nodes, and reading stops as soon
+/// as all of them have been found. Pointers that are invalid or don't match
+/// anything are left out of the result.
+pub fn extract(input: &str, pointers: &[&str]) -> Result<ObjectMap> {
+    let mut targets = Vec::with_capacity(pointers.len());
+
+    for pointer in pointers {
+        if let Some(tokens) = split_pointer(pointer) {
+            targets.push((pointer.to_string(), tokens));
+        }
+    }
+
+    targets.sort();
+    targets.dedup();
+
+    let mut extractor = Extractor {
+        lexer: Lexer::new(input),
+        targets,
+        found: ObjectMap::default()
+    };
+
+    if extractor.targets.is_empty() {
+        return Ok(extractor.found);
+    }
+
+    let first = extractor.next_token()?;
+    extractor.visit(first, &mut Vec::new())?;
+
+    Ok(extractor.found)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compare::Tolerance;
+    use crate::parser::parse_str;
+
+    fn doc(text: &str) -> Node {
+        parse_str(text).unwrap()
+    }
+
+    #[test]
+    fn pointers_are_split_and_unescaped() {
+        assert_eq!(split_pointer(""), Some(Vec::new()));
+        assert_eq!(split_pointer("/a~1b/~01/"), Some(vec!["a/b".to_string(), "~1".to_string(), String::new()]));
+        assert_eq!(split_pointer("a"), None);
+
+        assert_eq!(escape_token("a/b~c"), "a~1b~0c");
+    }
+
+    #[test]
+    fn pointers_find_values() {
+        let node = doc(r#"{"a": [10, {"b/c": true, "": 1}], "m~n": null}"#);
+
+        assert!(matches!(node.pointer("/a/0"), Some(Node::Integer(10))));
+        assert!(matches!(node.pointer("/a/1/b~1c"), Some(Node::Bool(true))));
+        assert!(matches!(node.pointer("/a/1/"), Some(Node::Integer(1))));
+        assert!(matches!(node.pointer("/m~0n"), Some(Node::Null)));
+        assert!(matches!(node.pointer(""), Some(Node::Object(_))));
+
+        for pointer in ["/a/01", "/a/-", "/a/2", "/a/0/x", "/b", "a"] {
+            assert!(node.pointer(pointer).is_none(), "{}", pointer);
+        }
+    }
+
+    #[test]
+    fn pointer_mut_changes_values() {
+        let mut node = doc(r#"{"a": [1, 2]}"#);
+
+        *node.pointer_mut("/a/1").unwrap() = Node::Bool(false);
+        assert!(matches!(node.pointer("/a/1"), Some(Node::Bool(false))));
+        assert!(node.pointer_mut("/a/2").is_none());
+    }
+
+    #[test]
+    fn extract_finds_several_values_at_once() {
+        let found = extract(r#"{"a": {"b": [1, {"c": "x"}]}, "d": [true], "e~f": null}"#, &["/a/b/1/c", "/a/b", "/d/0", "/e~0f", "/a/z", "/d/5", "bad"]).unwrap();
+
+        assert_eq!(found.len(), 4);
+        assert!(found["/a/b"].approx_eq(&doc(r#"[1, {"c": "x"}]"#), &Tolerance::exact()));
+        assert!(matches!(&found["/a/b/1/c"], Node::String(s) if s == "x"));
+        assert!(matches!(found["/d/0"], Node::Bool(true)));
+        assert!(matches!(found["/e~0f"], Node::Null));
+    }
+
+    #[test]
+    fn extract_stops_once_everything_is_found() {
+        let found = extract(r#"{"a": 1, "b": [tru"#, &["/a"]).unwrap();
+        assert!(matches!(found["/a"], Node::Integer(1)));
+
+        assert!(extract(r#"{"a": 1, "b": [tru"#, &["/b"]).is_err());
+        assert!(extract(r#"{"a" 1}"#, &["/a"]).is_err());
+        assert!(extract("[1, 2", &["/5"]).is_err());
+    }
+}
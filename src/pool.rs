@@ -0,0 +1,114 @@
+use crate::parser::{Node, ObjectMap};
+
+/// Allocations taken from trees that are finished with, to be handed out again
+/// by `parse_with_pool` instead of going back to the allocator.
+///
+/// Programs that parse lots of similar documents, like requests to a server,
+/// can recycle each tree once they're done with it, and after the first few
+/// documents parsing allocates hardly anything.
+///
+/// Rust's standard collections can't be given an allocator on stable, so memory
+/// still comes from the global allocator the first time. An embedder that wants it
+/// placed somewhere in particular should set `#[global_allocator]` as well.
+pub struct NodePool {
+    strings: Vec<String>,
+    arrays: Vec<Vec<Node>>,
+    objects: Vec<ObjectMap>,
+    max_kept: usize
+}
+
+impl Default for NodePool {
+    fn default() -> NodePool {
+        NodePool::new()
+    }
+}
+
+impl NodePool {
+    /// A pool that keeps up to 4096 spare allocations of each kind.
+    pub fn new() -> NodePool {
+        NodePool::with_limit(4096)
+    }
+
+    /// A pool that keeps up to `max_kept` spare strings, arrays and objects each.
+    /// Anything recycled past that is freed as usual.
+    pub fn with_limit(max_kept: usize) -> NodePool {
+        NodePool {
+            strings: Vec::new(),
+            arrays: Vec::new(),
+            objects: Vec::new(),
+            max_kept
+        }
+    }
+
+    /// How many spare allocations the pool is holding on to.
+    pub fn len(&self) -> usize {
+        self.strings.len() + self.arrays.len() + self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Free everything the pool is holding on to.
+    pub fn clear(&mut self) {
+        self.strings.clear();
+        self.arrays.clear();
+        self.objects.clear();
+    }
+
+    /// Take apart a tree that's no longer needed, keeping its strings, arrays and objects for later.
+    pub fn recycle(&mut self, node: Node) {
+        match node {
+            Node::String(s) => self.keep_string(s),
+
+            Node::Array(mut arr) => {
+                for item in arr.drain(..) {
+                    self.recycle(item);
+                }
+
+                if self.arrays.len() < self.max_kept {
+                    self.arrays.push(arr);
+                }
+            },
+
+            Node::Object(mut map) => {
+                for (key, value) in map.drain() {
+                    self.keep_string(key);
+                    self.recycle(value);
+                }
+
+                if self.objects.len() < self.max_kept {
+                    self.objects.push(map);
+                }
+            },
+
+            _ => {}
+        }
+    }
+
+    fn keep_string(&mut self, mut s: String) {
+        // Strings with no capacity didn't allocate, so there's nothing to save.
+        if s.capacity() > 0 && self.strings.len() < self.max_kept {
+            s.clear();
+            self.strings.push(s);
+        }
+    }
+
+    pub(crate) fn string(&mut self, text: &str) -> String {
+        match self.strings.pop() {
+            Some(mut s) => {
+                s.push_str(text);
+                s
+            },
+            None => text.to_string()
+        }
+    }
+
+    pub(crate) fn array(&mut self) -> Vec<Node> {
+        self.arrays.pop().unwrap_or_default()
+    }
+
+    pub(crate) fn object(&mut self) -> ObjectMap {
+        self.objects.pop().unwrap_or_default()
+    }
+}
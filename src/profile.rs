@@ -0,0 +1,301 @@
+use crate::lexer::{EscapePolicy, LexOptions, NulPolicy};
+use crate::parser::{Node, ObjectMap, ParseOptions};
+
+/// A named dialect of JSON, bundling up the options that pick what text is accepted,
+/// so `parse_with_options(text, &Profile::Json5.options())` reads as what it means.
+///
+/// Limits like `max_depth` aren't part of any profile, and can be set on the options after.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Profile {
+    /// JSON as RFC 8259 has it. Object keys have to be unique, since the RFC
+    /// says they should be and parsers disagree on what duplicates mean.
+    Rfc8259,
+
+    /// JSON as ECMA-404 has it: the same grammar as RFC 8259, but duplicate keys
+    /// are allowed, with the last value kept.
+    Ecma404,
+
+    /// JSON as it's often written by hand: comments, trailing commas and JSON5's numbers,
+    /// like `0x1F`, are allowed, and bad escape sequences become U+FFFD instead of failing.
+    Lenient,
+
+    /// The parts of JSON5 this parser handles: comments, single-quoted strings,
+    /// unquoted keys, trailing commas, and numbers like `0x1F`, `+1`, `.5` and `5.`.
+    Json5
+}
+
+impl Profile {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Rfc8259 => "rfc8259",
+            Self::Ecma404 => "ecma404",
+            Self::Lenient => "lenient",
+            Self::Json5 => "json5"
+        }
+    }
+
+    /// The profile with the given name, as given by `name`.
+    pub fn from_name(name: &str) -> Option<Profile> {
+        match name {
+            "rfc8259" => Some(Self::Rfc8259),
+            "ecma404" => Some(Self::Ecma404),
+            "lenient" => Some(Self::Lenient),
+            "json5" => Some(Self::Json5),
+            _ => None
+        }
+    }
+
+    /// The parse options for this profile, with everything else left as the default.
+    pub fn options(&self) -> ParseOptions {
+        let default = ParseOptions::default();
+
+        // The defaults let leading zeros and raw control characters through,
+        // which neither standard does.
+        let strict = ParseOptions {
+            lex: LexOptions {
+                reject_leading_zeros: true,
+                reject_control_characters: true,
+                ..default.lex
+            },
+            ..default
+        };
+
+        match self {
+            Self::Rfc8259 => ParseOptions {
+                reject_duplicate_keys: true,
+                ..strict
+            },
+
+            Self::Ecma404 => strict,
+
+            Self::Lenient => ParseOptions {
+                lex: LexOptions {
+                    escape_policy: EscapePolicy::Replace,
+                    allow_comments: true,
+                    allow_json5_numbers: true,
+                    ..default.lex
+                },
+                allow_trailing_commas: true,
+                ..default
+            },
+
+            Self::Json5 => ParseOptions {
+                lex: LexOptions {
+                    allow_comments: true,
+                    allow_single_quotes: true,
+                    allow_json5_numbers: true,
+                    ..default.lex
+                },
+                allow_trailing_commas: true,
+                allow_unquoted_keys: true,
+                ..default
+            }
+        }
+    }
+}
+
+impl From<Profile> for ParseOptions {
+    fn from(profile: Profile) -> ParseOptions {
+        profile.options()
+    }
+}
+
+/// Exactly what JSON a set of `ParseOptions` accepts, so services can advertise
+/// the dialect they take and check it against what others take.
+///
+/// Made with `ParseOptions::capabilities`, and turned into JSON with `to_node`:
+///
+/// ```text
+/// {
+///     "profile": "json5",
+///     "extensions": [
+///         "comments", "control_characters", "json5_numbers", "leading_zeros",
+///         "single_quotes", "trailing_commas", "unquoted_keys"
+///     ],
+///     "duplicate_keys": "last_wins",
+///     "invalid_escapes": "error",
+///     "nul_characters": "allow",
+///     "limits": {"max_depth": null, "max_exponent": 10000, ...}
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Capabilities {
+    /// The profile the options are the same as, ignoring limits, if there is one.
+    pub profile: Option<Profile>,
+
+    /// Everything allowed on top of standard JSON, by name, in sorted order.
+    pub extensions: Vec<&'static str>,
+
+    /// `"error"` or `"last_wins"`.
+    pub duplicate_keys: &'static str,
+
+    /// `"error"`, `"replace"` or `"pass_through"`.
+    pub invalid_escapes: &'static str,
+
+    /// `"allow"`, `"error"` or `"replace"`.
+    pub nul_characters: &'static str,
+
+    /// Each limit by name, in sorted order, with `None` for no limit.
+    pub limits: Vec<(&'static str, Option<u64>)>
+}
+
+impl Capabilities {
+    /// The capabilities as a JSON object, laid out as shown on `Capabilities`.
+    pub fn to_node(&self) -> Node {
+        let limits = self.limits.iter().map(
+            |(name, limit)| (name.to_string(), limit.map_or(Node::Null, |n| Node::Integer(i64::try_from(n).unwrap_or(i64::MAX))))
+        ).collect();
+
+        ObjectMap::from_iter([
+            ("profile".to_string(), self.profile.map_or(Node::Null, |p| Node::from(p.name()))),
+            ("extensions".to_string(), Node::Array(self.extensions.iter().map(|&e| Node::from(e)).collect())),
+            ("duplicate_keys".to_string(), Node::from(self.duplicate_keys)),
+            ("invalid_escapes".to_string(), Node::from(self.invalid_escapes)),
+            ("nul_characters".to_string(), Node::from(self.nul_characters)),
+            ("limits".to_string(), Node::Object(limits))
+        ]).into()
+    }
+
+    /// Whether everything these capabilities accept is accepted by `other` too,
+    /// ignoring limits and how escapes and NULs are handled.
+    pub fn is_subset_of(&self, other: &Capabilities) -> bool {
+        self.extensions.iter().all(|e| other.extensions.contains(e))
+            && (self.duplicate_keys == "error" || other.duplicate_keys == "last_wins")
+    }
+}
+
+impl ParseOptions {
+    /// Describe exactly what these options accept. See `Capabilities`.
+    pub fn capabilities(&self) -> Capabilities {
+        let mut capabilities = self.grammar();
+
+        capabilities.profile = [Profile::Rfc8259, Profile::Ecma404, Profile::Lenient, Profile::Json5]
+            .into_iter()
+            .find(|profile| profile.options().grammar() == capabilities);
+
+        capabilities.limits = vec![
+            ("max_depth", self.max_depth.map(|n| n as u64)),
+            ("max_exponent", Some(self.lex.max_exponent)),
+            ("max_nodes", self.max_nodes.map(|n| n as u64)),
+            ("max_number_digits", Some(self.lex.max_number_digits as u64)),
+            ("max_total_string_bytes", self.max_total_string_bytes.map(|n| n as u64))
+        ];
+
+        capabilities
+    }
+
+    /// Everything in the capabilities but the profile and the limits.
+    fn grammar(&self) -> Capabilities {
+        let flags = [
+            ("comments", self.lex.allow_comments),
+            ("control_characters", !self.lex.reject_control_characters),
+            ("json5_numbers", self.lex.allow_json5_numbers),
+            ("leading_zeros", !self.lex.reject_leading_zeros),
+            #[cfg(feature = "normalize")]
+            ("nfc_keys", self.nfc_keys),
+            #[cfg(feature = "normalize")]
+            ("nfc_strings", self.nfc_strings),
+            ("single_quotes", self.lex.allow_single_quotes),
+            ("trailing_commas", self.allow_trailing_commas),
+            ("trailing_data", self.allow_trailing_data),
+            ("unquoted_keys", self.allow_unquoted_keys)
+        ];
+
+        Capabilities {
+            profile: None,
+            extensions: flags.into_iter().filter(|(_, on)| *on).map(|(name, _)| name).collect(),
+            duplicate_keys: if self.reject_duplicate_keys { "error" } else { "last_wins" },
+            invalid_escapes: match self.lex.escape_policy {
+                EscapePolicy::Error => "error",
+                EscapePolicy::Replace => "replace",
+                EscapePolicy::PassThrough => "pass_through"
+            },
+            nul_characters: match self.lex.nul_policy {
+                NulPolicy::Allow => "allow",
+                NulPolicy::Error => "error",
+                NulPolicy::Replace => "replace"
+            },
+            limits: Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ErrorCode;
+    use crate::parser::parse_with_options;
+
+    fn parse(text: &str, profile: Profile) -> crate::error::Result<Node> {
+        parse_with_options(text, &profile.options()).map(|(node, _)| node)
+    }
+
+    #[test]
+    fn strict_profiles_reject_leading_zeros() {
+        for profile in [Profile::Rfc8259, Profile::Ecma404] {
+            assert_eq!(parse("01", profile).unwrap_err().code, ErrorCode::InvalidNumber);
+            assert_eq!(parse("-01.5", profile).unwrap_err().code, ErrorCode::InvalidNumber);
+            assert!(parse("0.5", profile).is_ok());
+            assert!(parse("-0", profile).is_ok());
+        }
+
+        assert!(parse("01", Profile::Lenient).is_ok());
+    }
+
+    #[test]
+    fn strict_profiles_reject_raw_control_characters() {
+        for profile in [Profile::Rfc8259, Profile::Ecma404] {
+            assert_eq!(parse("\"a\tb\"", profile).unwrap_err().code, ErrorCode::UnexpectedCharacter);
+            assert_eq!(parse("\"\u{1}\"", profile).unwrap_err().code, ErrorCode::UnexpectedCharacter);
+            assert!(parse("\"a\\tb\"", profile).is_ok());
+        }
+
+        assert!(parse("\"a\tb\"", Profile::Lenient).is_ok());
+    }
+
+    #[test]
+    fn each_profile_accepts_its_extensions() {
+        let text = "{key: 'a', /* note */ n: 0x10, list: [+1, .5,],}";
+
+        assert!(parse(text, Profile::Json5).is_ok());
+        assert!(parse(text, Profile::Lenient).is_err());
+        assert!(parse("[0x10, .5, 1,] // note", Profile::Lenient).is_ok());
+        assert!(parse("[0x10]", Profile::Ecma404).is_err());
+        assert!(parse(r#""\q""#, Profile::Lenient).is_ok());
+        assert!(parse(r#""\q""#, Profile::Json5).is_err());
+    }
+
+    #[test]
+    fn rfc8259_rejects_duplicate_keys() {
+        assert_eq!(parse(r#"{"a": 1, "a": 2}"#, Profile::Rfc8259).unwrap_err().code, ErrorCode::DuplicateKey);
+        assert!(parse(r#"{"a": 1, "a": 2}"#, Profile::Ecma404).is_ok());
+    }
+
+    #[test]
+    fn capabilities_report_the_strict_rules() {
+        assert!(Profile::Rfc8259.options().capabilities().extensions.is_empty());
+        assert!(Profile::Ecma404.options().capabilities().extensions.is_empty());
+
+        let defaults = ParseOptions::default().capabilities();
+
+        assert_eq!(defaults.profile, None);
+        assert_eq!(defaults.extensions, ["control_characters", "leading_zeros"]);
+    }
+
+    #[test]
+    fn capabilities_find_each_profile() {
+        for profile in [Profile::Rfc8259, Profile::Ecma404, Profile::Lenient, Profile::Json5] {
+            assert_eq!(profile.options().capabilities().profile, Some(profile));
+            assert_eq!(Profile::from_name(profile.name()), Some(profile));
+        }
+    }
+
+    #[test]
+    fn strict_json_is_a_subset_of_json5() {
+        let strict = Profile::Rfc8259.options().capabilities();
+        let json5 = Profile::Json5.options().capabilities();
+
+        assert!(strict.is_subset_of(&json5));
+        assert!(!json5.is_subset_of(&strict));
+    }
+}
@@ -0,0 +1,249 @@
+use std::fmt::Display;
+
+use crate::parser::{Node, ObjectMap};
+use crate::utils::{index_path, key_path};
+
+/// A `$ref` that couldn't be followed.
+#[derive(Debug)]
+pub struct RefError {
+    /// Where the reference is in the document, like `$.paths.users.get`.
+    pub path: String,
+    pub message: String
+}
+
+impl Display for RefError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "at {}: {}", self.path, self.message)
+    }
+}
+
+impl std::error::Error for RefError {}
+
+/// Decode `%XX` escapes, since pointers in URI fragments are percent-encoded.
+fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = s.get(i + 1..i + 3).filter(|h| h.bytes().all(|b| b.is_ascii_hexdigit()))?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        }
+        else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8(out).ok()
+}
+
+/// Get the JSON Pointer of an internal reference like `{"$ref": "#/components/schemas/User"}`.
+///
+/// References to other documents are not internal, so they give back `Ok(None)`.
+/// A fragment with a bad `%XX` escape is an error, given back as a message.
+pub fn internal_ref(node: &Node) -> Result<Option<String>, String> {
+    let Node::Object(map) = node else {
+        return Ok(None);
+    };
+
+    let Some(Node::String(target)) = map.get("$ref") else {
+        return Ok(None);
+    };
+
+    match target.strip_prefix('#') {
+        Some(fragment) => match percent_decode(fragment) {
+            Some(pointer) => Ok(Some(pointer)),
+            None => Err(format!("reference {:?} has a bad percent escape", target))
+        },
+        None => Ok(None)
+    }
+}
+
+fn inline(root: &Node, node: &Node, path: &str, stack: &mut Vec<String>) -> Result<Node, RefError> {
+    let pointer = internal_ref(node).map_err(|message| RefError {
+        path: path.to_string(),
+        message
+    })?;
+
+    if let Some(pointer) = pointer {
+        if stack.contains(&pointer) {
+            return Err(RefError {
+                path: path.to_string(),
+                message: format!("reference to {:?} is circular", pointer)
+            });
+        }
+
+        let target = match root.pointer(&pointer) {
+            Some(x) => x,
+            None => return Err(RefError {
+                path: path.to_string(),
+                message: format!("reference {:?} does not point to a value", pointer)
+            })
+        };
+
+        stack.push(pointer);
+        let resolved = inline(root, target, path, stack)?;
+        stack.pop();
+
+        return Ok(resolved);
+    }
+
+    let resolved = match node {
+        Node::Array(arr) => {
+            let mut body = Vec::with_capacity(arr.len());
+
+            for (i, item) in arr.iter().enumerate() {
+                body.push(inline(root, item, &index_path(path, i), stack)?);
+            }
+
+            Node::Array(body)
+        },
+
+        Node::Object(map) => {
+            let mut body = ObjectMap::with_capacity_and_hasher(map.len(), Default::default());
+
+            for (key, value) in map {
+                body.insert(key.clone(), inline(root, value, &key_path(path, key), stack)?);
+            }
+
+            Node::Object(body)
+        },
+
+        other => other.clone()
+    };
+
+    Ok(resolved)
+}
+
+impl Node {
+    /// Make a copy of this document with every internal `$ref` replaced by its target.
+    ///
+    /// References to other documents are left as they are,
+    /// and circular references are an error since they can't be inlined.
+    ///
+    /// As in the JSON Reference spec, any other keys next to a `$ref`
+    /// (like a `description`) are dropped along with it.
+    pub fn resolve_refs(&self) -> Result<Node, RefError> {
+        inline(self, self, "$", &mut Vec::new())
+    }
+
+    /// Follow `node` through any chain of internal references in this document,
+    /// giving back the value it finally refers to without copying anything.
+    pub fn deref<'a>(&'a self, node: &'a Node) -> Result<&'a Node, RefError> {
+        let mut current = node;
+        let mut seen: Vec<String> = Vec::new();
+
+        loop {
+            // We don't know where `node` is, so errors are located
+            // at the last reference we followed instead.
+            let path = match seen.last() {
+                Some(x) => format!("#{}", x),
+                None => "$".to_string()
+            };
+
+            let pointer = match internal_ref(current) {
+                Ok(Some(x)) => x,
+                Ok(None) => break,
+                Err(message) => return Err(RefError { path, message })
+            };
+
+            if seen.contains(&pointer) {
+                return Err(RefError {
+                    path,
+                    message: format!("reference to {:?} is circular", pointer)
+                });
+            }
+
+            current = match self.pointer(&pointer) {
+                Some(x) => x,
+                None => return Err(RefError {
+                    path,
+                    message: format!("reference {:?} does not point to a value", pointer)
+                })
+            };
+
+            seen.push(pointer);
+        }
+
+        Ok(current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compare::Tolerance;
+    use crate::parser::parse_str;
+
+    fn doc(text: &str) -> Node {
+        parse_str(text).unwrap()
+    }
+
+    #[test]
+    fn refs_are_inlined() {
+        let root = doc(r##"{"defs": {"id": {"type": "integer"}}, "user": {"id": {"$ref": "#/defs/id"}}, "other": {"$ref": "other.json#/x"}}"##);
+        let resolved = root.resolve_refs().unwrap();
+
+        assert!(resolved.pointer("/user/id").unwrap().approx_eq(&doc(r#"{"type": "integer"}"#), &Tolerance::exact()));
+        assert!(resolved.pointer("/other").unwrap().approx_eq(root.pointer("/other").unwrap(), &Tolerance::exact()));
+    }
+
+    #[test]
+    fn keys_next_to_a_ref_are_dropped() {
+        let root = doc(r##"{"defs": {"a": 1}, "b": {"$ref": "#/defs/a", "description": "ignored"}}"##);
+
+        assert!(matches!(root.resolve_refs().unwrap().pointer("/b"), Some(Node::Integer(1))));
+    }
+
+    #[test]
+    fn escapes_in_refs_are_decoded() {
+        let root = doc(r##"{"defs": {"a/b": 1, "c d": 2, "e~f": 3}, "x": [{"$ref": "#/defs/a~1b"}, {"$ref": "#/defs/c%20d"}, {"$ref": "#/defs/e~0f"}]}"##);
+        let resolved = root.resolve_refs().unwrap();
+
+        assert!(resolved.pointer("/x").unwrap().approx_eq(&doc("[1, 2, 3]"), &Tolerance::exact()));
+    }
+
+    #[test]
+    fn bad_escapes_are_errors() {
+        for target in ["#/a%2", "#/a%zz", "#/a%+1", "#/a%ff"] {
+            let root = doc(&format!(r#"{{"a": 1, "b": {{"$ref": {:?}}}}}"#, target));
+
+            let error = root.resolve_refs().unwrap_err();
+            assert_eq!(error.path, "$.b", "{}", target);
+            assert!(error.message.contains("bad percent escape"), "{}", target);
+
+            assert!(root.deref(root.pointer("/b").unwrap()).is_err(), "{}", target);
+        }
+    }
+
+    #[test]
+    fn missing_targets_are_errors() {
+        let root = doc(r##"{"a": [{"$ref": "#/nowhere"}]}"##);
+        let error = root.resolve_refs().unwrap_err();
+
+        assert_eq!(error.to_string(), r#"at $.a[0]: reference "/nowhere" does not point to a value"#);
+        assert!(root.deref(root.pointer("/a/0").unwrap()).is_err());
+    }
+
+    #[test]
+    fn cycles_are_errors() {
+        let root = doc(r##"{"a": {"$ref": "#/b"}, "b": {"$ref": "#/a"}, "c": {"d": {"$ref": "#/c"}}}"##);
+
+        assert!(root.resolve_refs().unwrap_err().message.contains("circular"));
+
+        let error = root.deref(root.pointer("/a").unwrap()).unwrap_err();
+        assert_eq!(error.path, "#/a");
+        assert!(error.message.contains("circular"));
+    }
+
+    #[test]
+    fn deref_follows_chains() {
+        let root = doc(r##"{"a": {"$ref": "#/b"}, "b": {"$ref": "#/c"}, "c": 5}"##);
+
+        assert!(matches!(root.deref(root.pointer("/a").unwrap()), Ok(Node::Integer(5))));
+        assert!(matches!(root.deref(root.pointer("/c").unwrap()), Ok(Node::Integer(5))));
+    }
+}
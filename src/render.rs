@@ -0,0 +1,493 @@
+use std::env;
+use std::fmt::{self, Write};
+use std::io::{stdout, IsTerminal};
+
+use crate::parser::{Node, ObjectMap};
+use crate::serializer::{colon, shown_entries, to_string, write_escaped_to, write_newline, write_scalar, FormatOptions, SerializeError};
+
+// Colors for each part of the text, close to what jq uses.
+const KEY: &str = "\x1b[34;1m";
+const STRING: &str = "\x1b[32m";
+const NUMBER: &str = "\x1b[36m";
+const LITERAL: &str = "\x1b[35m";
+const PUNCTUATION: &str = "\x1b[1;39m";
+const RESET: &str = "\x1b[0m";
+
+/// Whether color should be used for output going to stdout: only when it's a terminal,
+/// and `NO_COLOR` isn't set (see <https://no-color.org>).
+pub fn color_enabled() -> bool {
+    match env::var_os("NO_COLOR") {
+        Some(value) if !value.is_empty() => false,
+        _ => stdout().is_terminal()
+    }
+}
+
+fn write_colored<W: Write>(color: &str, text: &str, out: &mut W) -> fmt::Result {
+    write!(out, "{}{}{}", color, text, RESET)
+}
+
+fn write_colored_node<W: Write>(node: &Node, options: &FormatOptions, depth: usize, out: &mut W) -> fmt::Result {
+    match node {
+        Node::Array(arr) if !arr.is_empty() => {
+            write_colored(PUNCTUATION, "[", out)?;
+
+            for (i, item) in arr.iter().enumerate() {
+                if i > 0 {
+                    write_colored(PUNCTUATION, ",", out)?;
+                }
+
+                write_newline(options, depth + 1, out)?;
+                write_colored_node(item, options, depth + 1, out)?;
+            }
+
+            write_newline(options, depth, out)?;
+            write_colored(PUNCTUATION, "]", out)
+        },
+
+        Node::Object(map) if shown_entries(map, options).next().is_some() => {
+            write_colored(PUNCTUATION, "{", out)?;
+
+            for (i, (key, value)) in shown_entries(map, options).enumerate() {
+                if i > 0 {
+                    write_colored(PUNCTUATION, ",", out)?;
+                }
+
+                write_newline(options, depth + 1, out)?;
+
+                out.write_str(KEY)?;
+                write_escaped_to(key, out)?;
+                out.write_str(RESET)?;
+
+                write_colored(PUNCTUATION, colon(options), out)?;
+                write_colored_node(value, options, depth + 1, out)?;
+            }
+
+            write_newline(options, depth, out)?;
+            write_colored(PUNCTUATION, "}", out)
+        },
+
+        Node::Array(_) | Node::Object(_) => {
+            out.write_str(PUNCTUATION)?;
+            write_scalar(node, options, out)?;
+            out.write_str(RESET)
+        },
+
+        _ => {
+            let color = match node {
+                Node::String(_) => STRING,
+                Node::Integer(_) | Node::Unsigned(_) | Node::Float(_) => NUMBER,
+                _ => LITERAL
+            };
+
+            out.write_str(color)?;
+            write_scalar(node, options, out)?;
+            out.write_str(RESET)
+        }
+    }
+}
+
+/// Serialize a node to compact JSON text colored with ANSI escape codes,
+/// for showing in a terminal. See `color_enabled` for when to use it.
+pub fn to_string_colored(node: &Node) -> String {
+    let mut out = String::new();
+
+    // The default options never fail.
+    write_colored_node(node, &FormatOptions::compact(), 0, &mut out).unwrap();
+
+    out
+}
+
+/// Serialize a node to colored JSON text laid out with the given options.
+pub fn to_string_colored_with(node: &Node, options: &FormatOptions) -> Result<String, SerializeError> {
+    let mut out = String::new();
+
+    match write_colored_node(node, options, 0, &mut out) {
+        Ok(()) => Ok(out),
+        Err(_) => Err(SerializeError::non_finite())
+    }
+}
+
+/// Options for `to_html_with`.
+pub struct HtmlOptions {
+    /// Put a `<style>` block in front of the tree, so it looks right on a page with no CSS of its own.
+    pub inline_css: bool,
+
+    /// How many levels of arrays and objects start out opened.
+    pub open_depth: usize
+}
+
+impl Default for HtmlOptions {
+    fn default() -> HtmlOptions {
+        HtmlOptions {
+            inline_css: false,
+            open_depth: 1
+        }
+    }
+}
+
+/// Styles for the classes `to_html` uses, for `inline_css` or to copy into a stylesheet.
+pub const HTML_CSS: &str = "\
+.json-tree { font-family: monospace; line-height: 1.5; }
+.json-tree ul { list-style: none; margin: 0; padding-left: 1.5em; }
+.json-tree summary { cursor: pointer; }
+.json-tree .json-key { color: #1f4fa3; font-weight: bold; }
+.json-tree .json-string { color: #22863a; }
+.json-tree .json-number { color: #005cc5; }
+.json-tree .json-literal { color: #6f42c1; }
+.json-tree .json-summary { color: #6a737d; }
+";
+
+fn escape_html(text: &str, out: &mut String) {
+    for ch in text.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(ch)
+        }
+    }
+}
+
+fn write_html_label(label: Option<&str>, out: &mut String) {
+    if let Some(label) = label {
+        out.push_str("<span class=\"json-key\">");
+        escape_html(label, out);
+        out.push_str("</span>: ");
+    }
+}
+
+fn write_html_node(node: &Node, label: Option<&str>, options: &HtmlOptions, depth: usize, out: &mut String) {
+    let children: Vec<(String, &Node)> = match node {
+        Node::Array(arr) => arr.iter().enumerate().map(|(i, item)| (i.to_string(), item)).collect(),
+        Node::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+
+            keys.into_iter().map(|key| (key.clone(), &map[key])).collect()
+        },
+        _ => {
+            let class = match node {
+                Node::String(_) => "json-string",
+                Node::Integer(_) | Node::Unsigned(_) | Node::Float(_) => "json-number",
+                _ => "json-literal"
+            };
+
+            write_html_label(label, out);
+            out.push_str(&format!("<span class=\"{}\">", class));
+            escape_html(&to_string(node), out);
+            out.push_str("</span>");
+
+            return;
+        }
+    };
+
+    let summary = match node {
+        Node::Array(_) if children.len() == 1 => "[…] 1 item".to_string(),
+        Node::Array(_) => format!("[…] {} items", children.len()),
+        _ if children.len() == 1 => "{…} 1 key".to_string(),
+        _ => format!("{{…}} {} keys", children.len())
+    };
+
+    match depth < options.open_depth {
+        true => out.push_str("<details open><summary>"),
+        false => out.push_str("<details><summary>")
+    }
+
+    write_html_label(label, out);
+    out.push_str(&format!("<span class=\"json-summary\">{}</span></summary><ul>", summary));
+
+    for (label, child) in children {
+        out.push_str("<li>");
+        write_html_node(child, Some(&label), options, depth + 1, out);
+        out.push_str("</li>");
+    }
+
+    out.push_str("</ul></details>");
+}
+
+/// Render a node as HTML, with each array and object in a `<details>` element
+/// that can be opened and closed without any JavaScript. Keys are sorted,
+/// and all text is escaped so it's safe to put straight into a page.
+pub fn to_html(node: &Node) -> String {
+    to_html_with(node, &HtmlOptions::default())
+}
+
+pub fn to_html_with(node: &Node, options: &HtmlOptions) -> String {
+    let mut out = String::new();
+
+    if options.inline_css {
+        out.push_str("<style>\n");
+        out.push_str(HTML_CSS);
+        out.push_str("</style>\n");
+    }
+
+    out.push_str("<div class=\"json-tree\">");
+    write_html_node(node, None, options, 0, &mut out);
+    out.push_str("</div>\n");
+
+    out
+}
+
+/// Options for `to_dot_with`.
+pub struct DotOptions {
+    /// How many levels of arrays and objects to draw the insides of.
+    /// Anything deeper is drawn as a single box.
+    pub max_depth: Option<usize>,
+
+    /// How many characters of a value to show before cutting it off with `…`.
+    pub max_value_len: usize
+}
+
+impl Default for DotOptions {
+    fn default() -> DotOptions {
+        DotOptions {
+            max_depth: None,
+            max_value_len: 24
+        }
+    }
+}
+
+/// Quote text for a Graphviz label.
+fn dot_quoted(text: &str) -> String {
+    let mut out = String::from("\"");
+
+    for ch in text.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            _ => out.push(ch)
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+struct DotWriter<'a> {
+    options: &'a DotOptions,
+    out: String,
+    next_id: usize
+}
+
+impl<'a> DotWriter<'a> {
+    /// Write a node and everything under it, giving back its id.
+    fn write(&mut self, node: &Node, depth: usize) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let children: Vec<(String, &Node)> = match node {
+            Node::Array(arr) => arr.iter().enumerate().map(|(i, item)| (i.to_string(), item)).collect(),
+            Node::Object(map) => {
+                let mut keys: Vec<&String> = map.keys().collect();
+                keys.sort();
+
+                keys.into_iter().map(|key| (key.clone(), &map[key])).collect()
+            },
+            _ => {
+                let mut text = to_string(node);
+
+                if text.chars().count() > self.options.max_value_len {
+                    text = text.chars().take(self.options.max_value_len).collect::<String>() + "…";
+                }
+
+                self.out.push_str(&format!("    n{} [label={}, shape=plaintext];\n", id, dot_quoted(&text)));
+
+                return id;
+            }
+        };
+
+        let label = match node {
+            Node::Array(_) => format!("array ({})", children.len()),
+            _ => format!("object ({})", children.len())
+        };
+
+        let cut_off = self.options.max_depth.is_some_and(|max| depth >= max) && !children.is_empty();
+
+        match cut_off {
+            true => self.out.push_str(&format!("    n{} [label={}, style=dashed];\n", id, dot_quoted(&format!("{} …", label)))),
+            false => self.out.push_str(&format!("    n{} [label={}];\n", id, dot_quoted(&label)))
+        }
+
+        if !cut_off {
+            for (key, child) in children {
+                let child_id = self.write(child, depth + 1);
+
+                self.out.push_str(&format!("    n{} -> n{} [label={}];\n", id, child_id, dot_quoted(&key)));
+            }
+        }
+
+        id
+    }
+}
+
+/// Render the structure of a document as a Graphviz graph, with a box for each
+/// array and object, their keys and indexes on the edges, and values at the leaves.
+///
+/// Turn it into a picture with something like `dot -Tsvg`.
+pub fn to_dot(node: &Node) -> String {
+    to_dot_with(node, &DotOptions::default())
+}
+
+pub fn to_dot_with(node: &Node, options: &DotOptions) -> String {
+    let mut writer = DotWriter {
+        options,
+        out: String::from("digraph json {\n    node [shape=box, fontname=\"monospace\"];\n    edge [fontname=\"monospace\"];\n"),
+        next_id: 0
+    };
+
+    writer.write(node, 0);
+    writer.out.push_str("}\n");
+
+    writer.out
+}
+
+/// Options for `to_table_with`.
+#[derive(Default)]
+pub struct TableOptions {
+    /// The keys to show, in order. By default it's every key any row has, sorted.
+    pub columns: Option<Vec<String>>,
+
+    /// How many characters a cell can show before it's cut off with `…`.
+    pub max_cell_width: Option<usize>,
+
+    /// Add a row at the bottom adding up each column that only has numbers in it.
+    pub totals: bool
+}
+
+/// The text of a cell, with strings shown without their quotes.
+fn cell_text(value: Option<&Node>) -> String {
+    match value {
+        None => String::new(),
+        Some(Node::String(s)) => s.clone(),
+        Some(node) => to_string(node)
+    }
+}
+
+/// Add up a column, if everything in it is a number, keeping whole numbers whole.
+fn column_total(rows: &[&ObjectMap], column: &str) -> Option<String> {
+    // Wide enough for any integer, so unsigned ones can be added to the rest.
+    let mut int_total: i128 = 0;
+    let mut float_total = 0.0;
+    let mut any_float = false;
+
+    for row in rows {
+        match row.get(column) {
+            Some(Node::Integer(n)) => {
+                int_total = int_total.checked_add(*n as i128)?;
+                float_total += *n as f64;
+            },
+            Some(Node::Unsigned(n)) => {
+                int_total = int_total.checked_add(*n as i128)?;
+                float_total += *n as f64;
+            },
+            Some(Node::Float(f)) => {
+                float_total += f;
+                any_float = true;
+            },
+            None => {},
+            Some(_) => return None
+        }
+    }
+
+    match any_float {
+        true => Some(to_string(&Node::Float(float_total))),
+        false => Some(int_total.to_string())
+    }
+}
+
+/// Render an array of flat objects as a table with a column for each key,
+/// like `[{"name": "a", "size": 1}, {"name": "b", "size": 20}]` as
+///
+/// ```text
+/// name  size
+/// ----  ----
+/// a        1
+/// b       20
+/// ```
+///
+/// Gives back `None` if the node isn't an array of objects whose values are
+/// all strings, numbers, booleans or null.
+pub fn to_table(node: &Node) -> Option<String> {
+    to_table_with(node, &TableOptions::default())
+}
+
+pub fn to_table_with(node: &Node, options: &TableOptions) -> Option<String> {
+    let Node::Array(items) = node else {
+        return None;
+    };
+
+    let mut rows = Vec::with_capacity(items.len());
+
+    for item in items {
+        match item {
+            Node::Object(map) if !map.values().any(|v| matches!(v, Node::Array(_) | Node::Object(_))) => rows.push(map),
+            _ => return None
+        }
+    }
+
+    let columns = match &options.columns {
+        Some(columns) => columns.clone(),
+        None => {
+            let mut keys: Vec<String> = rows.iter().flat_map(|row| row.keys().cloned()).collect();
+            keys.sort();
+            keys.dedup();
+
+            keys
+        }
+    };
+
+    let truncate = |text: String| match options.max_cell_width {
+        Some(max) if text.chars().count() > max => text.chars().take(max.saturating_sub(1)).collect::<String>() + "…",
+        _ => text
+    };
+
+    let mut lines: Vec<Vec<String>> = vec![columns.iter().map(|c| truncate(c.clone())).collect()];
+
+    for row in &rows {
+        lines.push(columns.iter().map(|c| truncate(cell_text(row.get(c)))).collect());
+    }
+
+    if options.totals {
+        lines.push(columns.iter().map(|c| column_total(&rows, c).unwrap_or_default()).collect());
+    }
+
+    // Numbers line up on the right, like in a spreadsheet.
+    let numeric: Vec<bool> = columns.iter().map(|c| {
+        rows.iter().any(|row| row.contains_key(c))
+            && rows.iter().all(|row| matches!(row.get(c), None | Some(Node::Integer(_) | Node::Unsigned(_) | Node::Float(_))))
+    }).collect();
+
+    let widths: Vec<usize> = (0..columns.len()).map(
+        |i| lines.iter().map(|line| line[i].chars().count()).max().unwrap_or(0)
+    ).collect();
+
+    let rule: Vec<String> = widths.iter().map(|&w| "-".repeat(w)).collect();
+    let body_end = 1 + rows.len();
+
+    let mut layout: Vec<&Vec<String>> = Vec::new();
+    layout.push(&lines[0]);
+    layout.push(&rule);
+    layout.extend(&lines[1..body_end]);
+
+    if options.totals {
+        layout.push(&rule);
+        layout.push(&lines[body_end]);
+    }
+
+    let mut out = String::new();
+
+    for line in layout {
+        let cells: Vec<String> = line.iter().enumerate().map(|(i, cell)| match numeric[i] {
+            true => format!("{:>1$}", cell, widths[i]),
+            false => format!("{:<1$}", cell, widths[i])
+        }).collect();
+
+        out.push_str(cells.join("  ").trim_end());
+        out.push('\n');
+    }
+
+    Some(out)
+}
@@ -0,0 +1,568 @@
+use crate::error::{ErrorCode, JsonError, Result};
+use crate::lexer::tokenise;
+use crate::parser::{parse, Node};
+use crate::serializer::write_escaped;
+
+/// What `parse_partial` had to do to make the text complete.
+#[derive(Debug, Default, PartialEq)]
+pub struct Completion {
+    /// Everything added onto the end of the text, in order, like `"]}` for
+    /// an unterminated string inside an array inside an object.
+    ///
+    /// Also holds the rest of a cut-off `true`, `false` or `null`.
+    pub closers: String,
+
+    /// How many bytes of incomplete text were cut off the end first,
+    /// like a trailing comma, an object key with no value, or half a number.
+    pub dropped: usize
+}
+
+impl Completion {
+    /// Whether the text was already complete JSON.
+    pub fn is_complete(&self) -> bool {
+        self.closers.is_empty() && self.dropped == 0
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Expect {
+    Value,
+    Key,
+    Colon,
+    CommaOrEnd
+}
+
+/// Find where a string starting just after its opening quote ends,
+/// giving back the byte offset after the closing quote.
+pub(crate) fn string_end(text: &str, start: usize) -> Option<usize> {
+    let mut escaped = false;
+
+    for (i, ch) in text[start..].char_indices() {
+        match ch {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => return Some(start + i + 1),
+            _ => escaped = false
+        }
+    }
+
+    None
+}
+
+/// Cut any escape sequence that was cut off from the end of a string's contents,
+/// including the first half of a surrogate pair.
+fn trim_partial_escape(contents: &str) -> &str {
+    let trailing = contents.len() - contents.trim_end_matches('\\').len();
+
+    if trailing % 2 == 1 {
+        return &contents[..contents.len() - 1];
+    }
+
+    // Look for a `\u` with up to 4 hex digits at the very end.
+    for digits in 0..=4 {
+        let Some(start) = contents.len().checked_sub(digits + 2) else {
+            break;
+        };
+
+        let Some(tail) = contents.get(start..) else {
+            continue;
+        };
+
+        let Some(hex) = tail.strip_prefix("\\u") else {
+            continue;
+        };
+
+        // Make sure the backslash isn't itself escaped.
+        let before = &contents[..start];
+        let escaped = (before.len() - before.trim_end_matches('\\').len()) % 2 == 1;
+
+        if escaped || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            continue;
+        }
+
+        let high_surrogate = digits == 4 && matches!(u32::from_str_radix(hex, 16), Ok(0xd800..=0xdbff));
+
+        if digits < 4 || high_surrogate {
+            return &contents[..start];
+        }
+    }
+
+    contents
+}
+
+fn closers_for(stack: &[char]) -> String {
+    stack.iter().rev().map(|c| if *c == '[' { ']' } else { '}' }).collect()
+}
+
+/// Close off JSON text that was cut off partway through, like the output of a
+/// language model that is still being streamed in, then parse it.
+///
+/// Unterminated strings, arrays and objects are closed, and anything that
+/// can't be finished sensibly, like an object key with no value, is dropped.
+/// Text that is broken in other ways than being cut short still fails to parse.
+pub fn parse_partial(text: &str) -> Result<(Node, Completion)> {
+    let mut stack: Vec<char> = Vec::new();
+    let mut expect = Expect::Value;
+
+    // The last place the text could be cut and still be valid once closed.
+    let mut safe_len = 0;
+    let mut safe_stack: Vec<char> = Vec::new();
+
+    let mut chars = text.char_indices().peekable();
+
+    let mut completion = Completion::default();
+    let mut fixed: Option<String> = None;
+
+    while let Some((i, ch)) = chars.next() {
+        match ch {
+            c if c.is_whitespace() => continue,
+
+            '[' | '{' => {
+                stack.push(ch);
+                expect = if ch == '[' { Expect::Value } else { Expect::Key };
+
+                safe_len = i + 1;
+                safe_stack = stack.clone();
+            },
+
+            ']' | '}' => {
+                stack.pop();
+                expect = Expect::CommaOrEnd;
+
+                safe_len = i + 1;
+                safe_stack = stack.clone();
+            },
+
+            ',' => expect = if stack.last() == Some(&'{') { Expect::Key } else { Expect::Value },
+            ':' => expect = Expect::Value,
+
+            '"' => match string_end(text, i + 1) {
+                Some(end) => {
+                    while chars.peek().is_some_and(|(j, _)| *j < end) {
+                        chars.next();
+                    }
+
+                    if expect == Expect::Key {
+                        expect = Expect::Colon;
+                    }
+                    else {
+                        expect = Expect::CommaOrEnd;
+
+                        safe_len = end;
+                        safe_stack = stack.clone();
+                    }
+                },
+
+                // A string value that was cut off is kept, so it can be shown as it streams in.
+                None if expect != Expect::Key => {
+                    let kept = trim_partial_escape(&text[i + 1..]);
+
+                    completion.dropped = text.len() - (i + 1 + kept.len());
+                    completion.closers = format!("\"{}", closers_for(&stack));
+
+                    fixed = Some(format!("{}{}", &text[..i + 1 + kept.len()], completion.closers));
+                    break;
+                },
+
+                None => break
+            },
+
+            // A number or a name, which runs until the next delimiter.
+            _ => {
+                let mut end = text.len();
+
+                while let Some((j, c)) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, ',' | ']' | '}' | ':') {
+                        end = *j;
+                        break;
+                    }
+
+                    chars.next();
+                }
+
+                let token = &text[i..end];
+
+                // Anything followed by a delimiter was written out in full.
+                if end < text.len() {
+                    expect = Expect::CommaOrEnd;
+
+                    safe_len = end;
+                    safe_stack = stack.clone();
+                    continue;
+                }
+
+                if let Some(name) = ["true", "false", "null"].iter().find(|name| name.starts_with(token)) {
+                    completion.closers = format!("{}{}", &name[token.len()..], closers_for(&stack));
+
+                    fixed = Some(format!("{}{}", text, completion.closers));
+                    break;
+                }
+
+                // Cut a number back to the digits that make it valid, like `12.` to `12`.
+                let kept = token.trim_end_matches(|c: char| !c.is_ascii_digit());
+
+                if kept.is_empty() || kept == "-" {
+                    break;
+                }
+
+                completion.dropped = token.len() - kept.len();
+                completion.closers = closers_for(&stack);
+
+                fixed = Some(format!("{}{}", &text[..i + kept.len()], completion.closers));
+                break;
+            }
+        }
+    }
+
+    let fixed = match fixed {
+        Some(x) => x,
+        None => {
+            completion.dropped = text.trim_end().len() - safe_len;
+            completion.closers = closers_for(&safe_stack);
+
+            format!("{}{}", &text[..safe_len], completion.closers)
+        }
+    };
+
+    let node = parse(&tokenise(&fixed)?)?;
+
+    Ok((node, completion))
+}
+
+/// A kind of breakage that `repair` fixed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FixKind {
+    /// A string in single quotes was changed to double quotes.
+    SingleQuotes,
+
+    /// An object key without quotes was quoted.
+    UnquotedKey,
+
+    /// A comma before a closing bracket was removed.
+    TrailingComma,
+
+    /// A comma was added between two values next to each other.
+    MissingComma,
+
+    /// Python's `True`, `False` or `None` was changed to `true`, `false` or `null`.
+    PythonLiteral
+}
+
+/// A fix made by `repair`, at a byte offset in the original text.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fix {
+    pub kind: FixKind,
+    pub offset: usize
+}
+
+/// The result of `repair`: valid JSON text, and the fixes it took to get there.
+#[derive(Debug)]
+pub struct Repaired {
+    pub text: String,
+    pub fixes: Vec<Fix>
+}
+
+enum Loose<'a> {
+    Punct(char),
+
+    /// A string, already written out as valid JSON with double quotes.
+    Str(String),
+
+    /// Anything unquoted, like a number, a literal or a bare key.
+    Word(&'a str)
+}
+
+/// Split messy text into tokens, being lenient about quoting.
+fn loose_tokens<'a>(text: &'a str, fixes: &mut Vec<Fix>) -> Result<Vec<(usize, Loose<'a>)>> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((i, ch)) = chars.next() {
+        let token = match ch {
+            c if c.is_whitespace() => continue,
+
+            '[' | ']' | '{' | '}' | ',' | ':' => Loose::Punct(ch),
+
+            '"' | '\'' => {
+                let mut out = String::from('"');
+                let mut closed = false;
+
+                while let Some((_, c)) = chars.next() {
+                    match c {
+                        '\\' => match chars.next() {
+                            // An escaped single quote doesn't need escaping in double quotes.
+                            Some((_, '\'')) => out.push('\''),
+                            Some((_, escaped)) => {
+                                out.push('\\');
+                                out.push(escaped);
+                            },
+                            None => break
+                        },
+                        _ if c == ch => {
+                            closed = true;
+                            break;
+                        },
+                        '"' => out.push_str("\\\""),
+                        _ => out.push(c)
+                    }
+                }
+
+                if !closed {
+                    let line = text[..i].matches('\n').count() + 1;
+                    let column = i - text[..i].rfind('\n').map_or(0, |n| n + 1) + 1;
+
+                    return Err(JsonError::new("Found EOF when trying to parse string.", line as u32, column as u32)
+                        .with_code(ErrorCode::UnterminatedString)
+                        .with_offset(i));
+                }
+
+                if ch == '\'' {
+                    fixes.push(Fix {
+                        kind: FixKind::SingleQuotes,
+                        offset: i
+                    });
+                }
+
+                out.push('"');
+                Loose::Str(out)
+            },
+
+            _ => {
+                let mut end = text.len();
+
+                while let Some((j, c)) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '[' | ']' | '{' | '}' | ',' | ':' | '"' | '\'') {
+                        end = *j;
+                        break;
+                    }
+
+                    chars.next();
+                }
+
+                Loose::Word(&text[i..end])
+            }
+        };
+
+        tokens.push((i, token));
+    }
+
+    Ok(tokens)
+}
+
+/// Fix common ways JSON gets broken by hand or by other tools, giving back
+/// valid JSON text and a list of what was fixed.
+///
+/// This handles single-quoted strings, unquoted object keys, trailing commas,
+/// missing commas between values, and Python's `True`, `False` and `None`.
+/// The output is written compactly, and anything it still can't make sense of
+/// is an error.
+pub fn repair(text: &str) -> Result<Repaired> {
+    let mut fixes = Vec::new();
+    let tokens = loose_tokens(text, &mut fixes)?;
+
+    let mut out = String::with_capacity(text.len());
+    let mut stack: Vec<char> = Vec::new();
+    let mut expect = Expect::Value;
+
+    // Commas are held back until we know they aren't trailing.
+    let mut pending_comma: Option<usize> = None;
+
+    for (offset, token) in tokens {
+        let closes = matches!(token, Loose::Punct(']' | '}'));
+
+        if let Some(comma) = pending_comma.take() {
+            if closes {
+                fixes.push(Fix {
+                    kind: FixKind::TrailingComma,
+                    offset: comma
+                });
+            }
+            else {
+                out.push(',');
+            }
+        }
+
+        let starts_value = !matches!(token, Loose::Punct(',' | ':' | ']' | '}'));
+
+        if expect == Expect::CommaOrEnd && starts_value && !stack.is_empty() {
+            fixes.push(Fix {
+                kind: FixKind::MissingComma,
+                offset
+            });
+
+            out.push(',');
+            expect = if stack.last() == Some(&'{') { Expect::Key } else { Expect::Value };
+        }
+
+        match token {
+            Loose::Punct(',') => {
+                pending_comma = Some(offset);
+                expect = if stack.last() == Some(&'{') { Expect::Key } else { Expect::Value };
+                continue;
+            },
+            Loose::Punct(ch @ ('[' | '{')) => {
+                stack.push(ch);
+                expect = if ch == '[' { Expect::Value } else { Expect::Key };
+                out.push(ch);
+                continue;
+            },
+            Loose::Punct(ch @ (']' | '}')) => {
+                stack.pop();
+                expect = Expect::CommaOrEnd;
+                out.push(ch);
+                continue;
+            },
+            Loose::Punct(ch) => {
+                expect = Expect::Value;
+                out.push(ch);
+                continue;
+            },
+
+            Loose::Str(s) => out.push_str(&s),
+
+            Loose::Word(word) if expect == Expect::Key => {
+                fixes.push(Fix {
+                    kind: FixKind::UnquotedKey,
+                    offset
+                });
+
+                write_escaped(word, &mut out);
+            },
+
+            Loose::Word(word) => {
+                let fixed = match word {
+                    "True" => Some("true"),
+                    "False" => Some("false"),
+                    "None" => Some("null"),
+                    _ => None
+                };
+
+                match fixed {
+                    Some(literal) => {
+                        fixes.push(Fix {
+                            kind: FixKind::PythonLiteral,
+                            offset
+                        });
+
+                        out.push_str(literal);
+                    },
+                    None => out.push_str(word)
+                }
+            }
+        }
+
+        expect = if expect == Expect::Key { Expect::Colon } else { Expect::CommaOrEnd };
+    }
+
+    // Make sure what we ended up with really is valid.
+    parse(&tokenise(&out)?)?;
+
+    fixes.sort_by_key(|fix| fix.offset);
+
+    Ok(Repaired {
+        text: out,
+        fixes
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compare::Tolerance;
+    use crate::parser::parse_str;
+
+    fn doc(text: &str) -> Node {
+        parse_str(text).unwrap()
+    }
+
+    fn completion(closers: &str, dropped: usize) -> Completion {
+        Completion {
+            closers: closers.to_string(),
+            dropped
+        }
+    }
+
+    #[test]
+    fn cut_off_text_is_closed() {
+        let cases = [
+            (r#"{"a": [1, 2"#, r#"{"a": [1, 2]}"#, completion("]}", 0)),
+            (r#"{"a": "hel"#, r#"{"a": "hel"}"#, completion("\"}", 0)),
+            ("[tr", "[true]", completion("ue]", 0)),
+            ("[1.", "[1]", completion("]", 1)),
+            (r#"["a\u12"#, r#"["a"]"#, completion("\"]", 4)),
+            (r#"["a\ud83d"#, r#"["a"]"#, completion("\"]", 6)),
+            (r#"{"a": 1, "b"#, r#"{"a": 1}"#, completion("}", 4)),
+            (r#"{"a": 1, "b": "#, r#"{"a": 1}"#, completion("}", 6)),
+            ("[nope", "[]", completion("]", 4)),
+            (r#"[{"a": 1},"#, r#"[{"a": 1}]"#, completion("]", 1))
+        ];
+
+        for (text, expected, expected_completion) in cases {
+            let (node, completion) = parse_partial(text).unwrap();
+
+            assert!(node.approx_eq(&doc(expected), &Tolerance::exact()), "{}", text);
+            assert_eq!(completion, expected_completion, "{}", text);
+        }
+    }
+
+    #[test]
+    fn complete_text_is_left_alone() {
+        let (node, completion) = parse_partial(r#"{"a": [1, "x"]} "#).unwrap();
+
+        assert!(completion.is_complete());
+        assert!(node.approx_eq(&doc(r#"{"a": [1, "x"]}"#), &Tolerance::exact()));
+    }
+
+    #[test]
+    fn otherwise_broken_text_still_fails() {
+        for text in [r#"{"a" 1"#, "[1 2", "[1}"] {
+            assert!(parse_partial(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn repair_fixes_common_breakage() {
+        let repaired = repair(r#"{name: 'Ada', 'tags': ['a', 'b',], ok: True "x": None}"#).unwrap();
+
+        assert_eq!(repaired.text, r#"{"name":"Ada","tags":["a","b"],"ok":true,"x":null}"#);
+
+        let fixes: Vec<(FixKind, usize)> = repaired.fixes.iter().map(|fix| (fix.kind, fix.offset)).collect();
+        assert_eq!(fixes, [
+            (FixKind::UnquotedKey, 1),
+            (FixKind::SingleQuotes, 7),
+            (FixKind::SingleQuotes, 14),
+            (FixKind::SingleQuotes, 23),
+            (FixKind::SingleQuotes, 28),
+            (FixKind::TrailingComma, 31),
+            (FixKind::UnquotedKey, 35),
+            (FixKind::PythonLiteral, 39),
+            (FixKind::MissingComma, 44),
+            (FixKind::PythonLiteral, 49)
+        ]);
+    }
+
+    #[test]
+    fn repair_rewrites_quotes_inside_strings() {
+        let repaired = repair(r#"['it\'s', 'say "hi"', "a\nb"]"#).unwrap();
+
+        assert_eq!(repaired.text, r#"["it's","say \"hi\"","a\nb"]"#);
+        assert!(doc(&repaired.text).approx_eq(&doc(r#"["it's", "say \"hi\"", "a\nb"]"#), &Tolerance::exact()));
+    }
+
+    #[test]
+    fn valid_json_needs_no_fixes() {
+        let repaired = repair(r#"{"a": [1, 2.5, true, null], "b": {}}"#).unwrap();
+
+        assert!(repaired.fixes.is_empty());
+        assert_eq!(repaired.text, r#"{"a":[1,2.5,true,null],"b":{}}"#);
+    }
+
+    #[test]
+    fn repair_gives_up_on_what_it_cant_fix() {
+        let error = repair("[1, 'two").unwrap_err();
+        assert_eq!((error.code, error.line, error.column), (ErrorCode::UnterminatedString, 1, 5));
+
+        for text in ["[1, nope]", "{a: }", "[1, 2"] {
+            assert!(repair(text).is_err(), "{}", text);
+        }
+    }
+}
@@ -0,0 +1,272 @@
+use std::collections::HashSet;
+use std::fmt::Display;
+
+use crate::compare::Tolerance;
+use crate::error::JsonError;
+use crate::lexer::tokenise;
+use crate::parser::{parse, Node};
+use crate::repair::string_end;
+use crate::serializer::{to_string_with, write_escaped, FormatOptions, SerializeError};
+use crate::token::{Token, TokenType};
+
+/// Why a document couldn't be rewritten.
+#[derive(Debug)]
+pub enum RewriteError {
+    /// The old text isn't valid JSON, so there's no layout to follow.
+    Parse(Box<JsonError>),
+
+    /// Part of the new document can't be written, like a NaN.
+    Serialize(SerializeError)
+}
+
+impl Display for RewriteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Parse(e) => write!(f, "Could not parse the old document: {}", e),
+            Self::Serialize(e) => write!(f, "Could not write the new document: {}", e)
+        }
+    }
+}
+
+impl std::error::Error for RewriteError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Parse(e) => Some(e.as_ref()),
+            Self::Serialize(e) => Some(e)
+        }
+    }
+}
+
+/// Where a value was in the old text, and where its items or entries were.
+struct Layout {
+    start: usize,
+    end: usize,
+    kind: LayoutKind
+}
+
+enum LayoutKind {
+    Scalar,
+    Array(Vec<Layout>),
+    Object(Vec<Entry>)
+}
+
+/// An entry of an object, with where its key was written.
+struct Entry {
+    key: String,
+    key_start: usize,
+    key_end: usize,
+    value: Layout
+}
+
+impl Layout {
+    /// The span of each item or entry of a container, so the text between them
+    /// is the spacing to copy, like `,\n    `.
+    fn parts(&self) -> Vec<(usize, usize)> {
+        match &self.kind {
+            LayoutKind::Scalar => Vec::new(),
+            LayoutKind::Array(items) => items.iter().map(|item| (item.start, item.end)).collect(),
+            LayoutKind::Object(entries) => entries.iter().map(|entry| (entry.key_start, entry.value.end)).collect()
+        }
+    }
+}
+
+/// Find the layout of the value starting at `tokens[*i]`, leaving `i` just after it.
+///
+/// The tokens have already been parsed, so they're known to make up a whole value.
+fn layout(tokens: &[Token], i: &mut usize, text: &str) -> Layout {
+    let token = &tokens[*i];
+    let start = token.offset();
+    *i += 1;
+
+    let (closer, is_object) = match token.tok_type {
+        TokenType::LSqBrac => (TokenType::RSqBrac, false),
+        TokenType::LBrace => (TokenType::RBrace, true),
+        TokenType::String => {
+            let end = string_end(text, start + 1).unwrap_or(text.len());
+            return Layout { start, end, kind: LayoutKind::Scalar };
+        },
+        _ => return Layout { start, end: start + token.text().len(), kind: LayoutKind::Scalar }
+    };
+
+    let mut items = Vec::new();
+    let mut entries = Vec::new();
+
+    loop {
+        let token = &tokens[*i];
+
+        match token.tok_type {
+            kind if kind == closer => break,
+            TokenType::Comma => *i += 1,
+            TokenType::String if is_object => {
+                let key = token.text().to_string();
+                let key_start = token.offset();
+                let key_end = string_end(text, key_start + 1).unwrap_or(text.len());
+
+                // Skip the key and the colon after it.
+                *i += 2;
+
+                entries.push(Entry { key, key_start, key_end, value: layout(tokens, i, text) });
+            },
+            _ => items.push(layout(tokens, i, text))
+        }
+    }
+
+    let end = tokens[*i].offset() + 1;
+    *i += 1;
+
+    let kind = match is_object {
+        true => LayoutKind::Object(entries),
+        false => LayoutKind::Array(items)
+    };
+
+    Layout { start, end, kind }
+}
+
+struct Rewriter<'a> {
+    text: &'a str,
+    options: &'a FormatOptions
+}
+
+impl Rewriter<'_> {
+    /// Write a value that has nothing in the old text to follow, indented to fit at `depth`.
+    fn write_fresh(&self, node: &Node, depth: usize, out: &mut String) -> Result<(), SerializeError> {
+        let written = to_string_with(node, self.options)?;
+        let indent = " ".repeat(self.options.indent.unwrap_or(0) * depth);
+
+        // Strings can't hold a raw newline, so every newline is between lines of layout.
+        out.push_str(&written.replace('\n', &format!("\n{}", indent)));
+
+        Ok(())
+    }
+
+    fn write(&self, new: &Node, old: Option<(&Node, &Layout)>, depth: usize, out: &mut String) -> Result<(), SerializeError> {
+        let Some((old, layout)) = old else {
+            return self.write_fresh(new, depth, out);
+        };
+
+        if old.approx_eq(new, &Tolerance::exact()) {
+            out.push_str(&self.text[layout.start..layout.end]);
+            return Ok(());
+        }
+
+        let parts = layout.parts();
+
+        // With nothing inside the old container, there's no spacing to copy.
+        let (Some(first), Some(last)) = (parts.first(), parts.last()) else {
+            return self.write_fresh(new, depth, out);
+        };
+
+        let open = &self.text[layout.start + 1..first.0];
+        let close = &self.text[last.1..layout.end - 1];
+        let separator = match parts.get(1) {
+            Some(second) => self.text[first.1..second.0].to_string(),
+
+            // With only one item, put the next on a line of its own if that one was.
+            None => format!(",{}", open)
+        };
+
+        match (new, old, &layout.kind) {
+            (Node::Array(items), Node::Array(old_items), LayoutKind::Array(old_layouts)) => {
+                if items.is_empty() {
+                    out.push_str("[]");
+                    return Ok(());
+                }
+
+                let mut used = vec![false; old_items.len()];
+
+                out.push('[');
+                out.push_str(open);
+
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(&separator);
+                    }
+
+                    // Follow an old item that's the same as this one, wherever it was,
+                    // so moving items around doesn't rewrite them. Otherwise, follow
+                    // the one that was in the same place.
+                    let same = (0..old_items.len()).find(|&j| !used[j] && old_items[j].approx_eq(item, &Tolerance::exact()));
+                    let counterpart = same.or((i < old_items.len()).then_some(i));
+
+                    if let Some(j) = same {
+                        used[j] = true;
+                    }
+
+                    self.write(item, counterpart.map(|j| (&old_items[j], &old_layouts[j])), depth + 1, out)?;
+                }
+
+                out.push_str(close);
+                out.push(']');
+            },
+
+            (Node::Object(map), Node::Object(old_map), LayoutKind::Object(old_entries)) => {
+                if map.is_empty() {
+                    out.push_str("{}");
+                    return Ok(());
+                }
+
+                let colon = &self.text[old_entries[0].key_end..old_entries[0].value.start];
+
+                // Keys keep their old order, with any new ones after them.
+                // Sort the new keys so they come out in the same order every time.
+                let mut seen = HashSet::new();
+                let mut keys: Vec<(&String, Option<&Entry>)> = old_entries.iter()
+                    .filter(|entry| map.contains_key(&entry.key) && seen.insert(&entry.key))
+                    .map(|entry| (&entry.key, Some(entry)))
+                    .collect();
+
+                let mut added: Vec<&String> = map.keys().filter(|key| !old_map.contains_key(*key)).collect();
+                added.sort();
+                keys.extend(added.into_iter().map(|key| (key, None)));
+
+                out.push('{');
+                out.push_str(open);
+
+                for (i, (key, old_entry)) in keys.into_iter().enumerate() {
+                    if i > 0 {
+                        out.push_str(&separator);
+                    }
+
+                    match old_entry {
+                        Some(entry) => out.push_str(&self.text[entry.key_start..entry.key_end]),
+                        None => write_escaped(key, out)
+                    }
+
+                    out.push_str(colon);
+
+                    let counterpart = old_entry.map(|entry| (&old_map[key], &entry.value));
+                    self.write(&map[key], counterpart, depth + 1, out)?;
+                }
+
+                out.push_str(close);
+                out.push('}');
+            },
+
+            _ => self.write_fresh(new, depth, out)?
+        }
+
+        Ok(())
+    }
+}
+
+/// Write `new` laid out like `old`, so regenerating a file only changes the lines
+/// that hold different values, keeping diffs in version control small.
+///
+/// Values that haven't changed are copied from the old text as they were written,
+/// objects keep their old key order with any new keys after it, and changed arrays
+/// and objects keep the old spacing around their items. Anything with nothing to
+/// follow in the old text, like a new key's value, is written with `options`.
+pub fn rewrite(old: &str, new: &Node, options: &FormatOptions) -> Result<String, RewriteError> {
+    let tokens = tokenise(old).map_err(|e| RewriteError::Parse(Box::new(e)))?;
+    let old_doc = parse(&tokens).map_err(|e| RewriteError::Parse(Box::new(e)))?;
+
+    let layout = layout(&tokens, &mut 0, old);
+    let rewriter = Rewriter { text: old, options };
+
+    // Keep whatever was around the document too, like a newline at the end.
+    let mut out = old[..layout.start].to_string();
+    rewriter.write(new, Some((&old_doc, &layout)), 0, &mut out).map_err(RewriteError::Serialize)?;
+    out.push_str(&old[layout.end..]);
+
+    Ok(out)
+}
@@ -0,0 +1,492 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::ops::Bound;
+
+use crate::comments::{Attachment, Comment, CommentKind};
+use crate::parser::{Node, ObjectMap};
+use crate::utils::{index_path, key_path};
+use crate::validate::{any, array, boolean, integer, null, nullable, number, object, string, Validator};
+
+/// A part of a schema that couldn't be turned into a validator.
+#[derive(Debug)]
+pub struct SchemaError {
+    /// Where the problem is in the schema, like `$.properties.age.minimum`.
+    pub path: String,
+    pub message: String
+}
+
+impl Display for SchemaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "at {}: {}", self.path, self.message)
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+fn error(path: &str, message: impl Into<String>) -> SchemaError {
+    SchemaError {
+        path: path.to_string(),
+        message: message.into()
+    }
+}
+
+/// Keywords that only describe a value, so they don't change what's valid.
+const ANNOTATIONS: [&str; 7] = ["$schema", "$id", "$comment", "title", "description", "default", "examples"];
+
+/// The keywords each type understands, on top of `type`, `enum` and the annotations.
+fn keywords(kind: &str) -> &'static [&'static str] {
+    match kind {
+        "string" => &["minLength", "maxLength"],
+        "integer" | "number" => &["minimum", "maximum", "exclusiveMinimum", "exclusiveMaximum"],
+        "array" => &["items", "minItems", "maxItems"],
+        "object" => &["properties", "required", "additionalProperties"],
+        _ => &[]
+    }
+}
+
+/// Read the schema's types, splitting out `"null"`, like `["string", "null"]`.
+fn types(schema: &ObjectMap, path: &str) -> Result<(Option<String>, bool), SchemaError> {
+    let here = key_path(path, "type");
+
+    let names = match schema.get("type") {
+        None => Vec::new(),
+        Some(Node::String(name)) => vec![name.clone()],
+        Some(Node::Array(names)) => names.iter().enumerate().map(|(i, name)| match name {
+            Node::String(name) => Ok(name.clone()),
+            _ => Err(error(&index_path(&here, i), "Types must be strings"))
+        }).collect::<Result<_, _>>()?,
+        Some(_) => return Err(error(&here, "The type must be a string or an array of strings"))
+    };
+
+    let nullable = names.iter().any(|name| name == "null");
+    let mut others: Vec<String> = names.into_iter().filter(|name| name != "null").collect();
+
+    if others.len() > 1 {
+        return Err(error(&here, "Only one type besides \"null\" is supported"));
+    }
+
+    let kind = match others.pop() {
+        Some(kind) => Some(kind),
+        None if nullable => Some("null".to_string()),
+
+        // Guess the type from its keywords, like `properties` for an object.
+        None => ["string", "number", "array", "object"].into_iter()
+            .find(|kind| keywords(kind).iter().any(|keyword| schema.contains_key(*keyword)))
+            .map(|kind| kind.to_string())
+    };
+
+    Ok((kind, nullable))
+}
+
+fn get_usize(schema: &ObjectMap, keyword: &str, path: &str) -> Result<Option<usize>, SchemaError> {
+    match schema.get(keyword) {
+        None => Ok(None),
+        Some(Node::Integer(n)) if *n >= 0 => Ok(Some(*n as usize)),
+        Some(_) => Err(error(&key_path(path, keyword), format!("{} must be a whole number of at least 0", keyword)))
+    }
+}
+
+/// Read the bounds of a number from `minimum`, `maximum` and their exclusive forms.
+fn bounds<T: Copy>(schema: &ObjectMap, path: &str, read: impl Fn(&Node) -> Option<T>) -> Result<(Bound<T>, Bound<T>), SchemaError> {
+    let get = |keyword: &str| match schema.get(keyword) {
+        None => Ok(None),
+        Some(node) => read(node).map(Some).ok_or_else(|| error(&key_path(path, keyword), format!("{} must be a number of the same type", keyword)))
+    };
+
+    let start = match (get("exclusiveMinimum")?, get("minimum")?) {
+        (Some(n), _) => Bound::Excluded(n),
+        (None, Some(n)) => Bound::Included(n),
+        (None, None) => Bound::Unbounded
+    };
+
+    let end = match (get("exclusiveMaximum")?, get("maximum")?) {
+        (Some(n), _) => Bound::Excluded(n),
+        (None, Some(n)) => Bound::Included(n),
+        (None, None) => Bound::Unbounded
+    };
+
+    Ok((start, end))
+}
+
+fn object_validator(schema: &ObjectMap, path: &str) -> Result<Box<dyn Validator>, SchemaError> {
+    let required = match schema.get("required") {
+        None => Vec::new(),
+        Some(Node::Array(keys)) => keys.iter().enumerate().map(|(i, key)| match key {
+            Node::String(key) => Ok(key.clone()),
+            _ => Err(error(&index_path(&key_path(path, "required"), i), "Required keys must be strings"))
+        }).collect::<Result<_, _>>()?,
+        Some(_) => return Err(error(&key_path(path, "required"), "required must be an array of keys"))
+    };
+
+    let empty = ObjectMap::default();
+
+    let properties = match schema.get("properties") {
+        None => &empty,
+        Some(Node::Object(properties)) => properties,
+        Some(_) => return Err(error(&key_path(path, "properties"), "properties must be an object"))
+    };
+
+    let mut validator = object();
+
+    // Sort the keys so violations come out in the same order every time.
+    let mut keys: Vec<&String> = properties.keys().chain(required.iter().filter(|key| !properties.contains_key(*key))).collect();
+    keys.sort();
+
+    for key in keys {
+        let inner = match properties.get(key) {
+            Some(property) => from_schema_at(property, &key_path(&key_path(path, "properties"), key))?,
+            None => Box::new(any())
+        };
+
+        validator = match required.contains(key) {
+            true => validator.key(key.clone(), inner),
+            false => validator.optional(key.clone(), inner)
+        };
+    }
+
+    match schema.get("additionalProperties") {
+        None | Some(Node::Bool(true)) => {},
+        Some(Node::Bool(false)) => validator = validator.deny_unknown_keys(),
+        Some(_) => return Err(error(&key_path(path, "additionalProperties"), "Only true or false are supported for additionalProperties"))
+    }
+
+    Ok(Box::new(validator))
+}
+
+fn from_schema_at(schema: &Node, path: &str) -> Result<Box<dyn Validator>, SchemaError> {
+    let map = match schema {
+        Node::Bool(true) => return Ok(Box::new(any())),
+        Node::Object(map) => map,
+        _ => return Err(error(path, "A schema must be an object, or true"))
+    };
+
+    let (kind, allows_null) = types(map, path)?;
+
+    // Anything this can't check is an error, so a schema is never quietly looser than it reads.
+    let mut unknown: Vec<&String> = map.keys().filter(|keyword| {
+        let keyword = keyword.as_str();
+
+        !ANNOTATIONS.contains(&keyword)
+            && !["type", "enum"].contains(&keyword)
+            && !kind.as_deref().is_some_and(|kind| keywords(kind).contains(&keyword))
+    }).collect();
+
+    unknown.sort();
+
+    if let Some(keyword) = unknown.first() {
+        return Err(error(&key_path(path, keyword), format!("{:?} isn't supported here", keyword)));
+    }
+
+    let allowed = match map.get("enum") {
+        None => None,
+        Some(Node::Array(values)) if values.iter().all(|value| matches!(value, Node::String(_))) => {
+            Some(values.iter().filter_map(|value| match value {
+                Node::String(s) => Some(s.clone()),
+                _ => None
+            }).collect::<Vec<_>>())
+        },
+        Some(_) => return Err(error(&key_path(path, "enum"), "Only an enum of strings is supported"))
+    };
+
+    let kind = match (kind, &allowed) {
+        (None, Some(_)) => Some("string".to_string()),
+        (Some(kind), Some(_)) if kind != "string" => return Err(error(&key_path(path, "enum"), "An enum can only be used with strings")),
+        (kind, _) => kind
+    };
+
+    let validator: Box<dyn Validator> = match kind.as_deref() {
+        None => Box::new(any()),
+        Some("null") => Box::new(null()),
+        Some("boolean") => Box::new(boolean()),
+        Some("string") => {
+            let mut validator = string();
+
+            if let Some(len) = get_usize(map, "minLength", path)? {
+                validator = validator.min_len(len);
+            }
+
+            if let Some(len) = get_usize(map, "maxLength", path)? {
+                validator = validator.max_len(len);
+            }
+
+            if let Some(allowed) = allowed {
+                validator = validator.one_of(allowed);
+            }
+
+            Box::new(validator)
+        },
+        Some("integer") => Box::new(integer().range(bounds(map, path, |node| match node {
+            Node::Integer(n) => Some(*n),
+            _ => None
+        })?)),
+        Some("number") => Box::new(number().range(bounds(map, path, |node| match node {
+            Node::Integer(n) => Some(*n as f64),
+            Node::Unsigned(n) => Some(*n as f64),
+            Node::Float(f) => Some(*f),
+            _ => None
+        })?)),
+        Some("array") => {
+            let mut validator = array();
+
+            if let Some(items) = map.get("items") {
+                validator = validator.items(from_schema_at(items, &key_path(path, "items"))?);
+            }
+
+            if let Some(len) = get_usize(map, "minItems", path)? {
+                validator = validator.min_len(len);
+            }
+
+            if let Some(len) = get_usize(map, "maxItems", path)? {
+                validator = validator.max_len(len);
+            }
+
+            Box::new(validator)
+        },
+        Some("object") => object_validator(map, path)?,
+        Some(kind) => return Err(error(&key_path(path, "type"), format!("Unknown type {:?}", kind)))
+    };
+
+    match allows_null && kind.as_deref() != Some("null") {
+        true => Ok(Box::new(nullable(validator))),
+        false => Ok(validator)
+    }
+}
+
+/// Turn a JSON Schema document into a `Validator`, for checking data against
+/// schemas kept in files rather than built in code.
+///
+/// Only the parts of JSON Schema that map onto the validators in `validate` are
+/// supported: `type` (with at most one type besides `"null"`), `enum` of strings,
+/// `minLength`, `maxLength`, `minimum`, `maximum`, `exclusiveMinimum`, `exclusiveMaximum`,
+/// `items`, `minItems`, `maxItems`, `properties`, `required` and a true or false
+/// `additionalProperties`. Any other keyword is an error, apart from ones that only
+/// describe a value, like `title` and `description`.
+pub fn from_schema(schema: &Node) -> Result<Box<dyn Validator>, SchemaError> {
+    from_schema_at(schema, "$")
+}
+
+/// The text of a `///` doc comment, without the slashes and the space after them.
+fn doc_text(comment: &Comment) -> Option<&str> {
+    let text = match (comment.kind, comment.attachment) {
+        (CommentKind::Line, Attachment::Leading | Attachment::Trailing) => comment.text.strip_prefix('/')?,
+        _ => return None
+    };
+
+    Some(text.strip_prefix(' ').unwrap_or(text).trim_end())
+}
+
+fn describe_at(node: &Node, path: &str, docs: &HashMap<&str, Vec<&str>>) -> Node {
+    let mut schema = ObjectMap::default();
+
+    let kind = match node {
+        Node::Null => Some("null"),
+        Node::Bool(_) => Some("boolean"),
+        Node::Integer(_) | Node::Unsigned(_) => Some("integer"),
+        Node::Float(_) => Some("number"),
+        Node::String(_) => Some("string"),
+        Node::Array(_) => Some("array"),
+        Node::Object(_) => Some("object"),
+        Node::Empty => None
+    };
+
+    if let Some(kind) = kind {
+        schema.insert("type".to_string(), Node::from(kind));
+    }
+
+    if let Some(lines) = docs.get(path) {
+        schema.insert("description".to_string(), Node::from(lines.join("\n")));
+    }
+
+    match node {
+        // Items are described by the first one, with its comments.
+        Node::Array(items) => if let Some(first) = items.first() {
+            schema.insert("items".to_string(), describe_at(first, &index_path(path, 0), docs));
+        },
+        Node::Object(map) => {
+            let properties = map.iter()
+                .map(|(key, value)| (key.clone(), describe_at(value, &key_path(path, key), docs)))
+                .collect();
+
+            schema.insert("properties".to_string(), Node::Object(properties));
+        },
+        Node::Empty => {},
+
+        // The value in the file is what's used when it isn't changed, so it's the default.
+        scalar => {
+            schema.insert("default".to_string(), scalar.clone());
+        }
+    }
+
+    Node::Object(schema)
+}
+
+/// Write a JSON Schema describing `doc`, taking each value's `description` from
+/// the `///` comments attached to it, so a commented config file documents itself.
+///
+/// The comments come from `comments::parse_with_comments`. Only line comments
+/// starting with `///` count, either before a value or after it on the same line,
+/// with several in a row joined by newlines. Types come from the values, scalars
+/// have their value as the `default`, and arrays are described by their first item.
+///
+/// ```text
+/// {
+///     /// Which port to listen on.
+///     "port": 8080
+/// }
+/// ```
+///
+/// gives `$.properties.port` as `{"type": "integer", "description": "Which port to listen on.", "default": 8080}`.
+pub fn describe(doc: &Node, comments: &[Comment]) -> Node {
+    let mut docs: HashMap<&str, Vec<&str>> = HashMap::new();
+
+    for comment in comments {
+        if let Some(text) = doc_text(comment) {
+            docs.entry(comment.path.as_str()).or_default().push(text);
+        }
+    }
+
+    let mut schema = describe_at(doc, "$", &docs);
+
+    if let Node::Object(map) = &mut schema {
+        map.insert("$schema".to_string(), Node::from("https://json-schema.org/draft/2020-12/schema"));
+    }
+
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::comments::parse_with_comments;
+    use crate::compare::Tolerance;
+    use crate::parser::parse_str;
+    use crate::profile::Profile;
+
+    fn doc(text: &str) -> Node {
+        parse_str(text).unwrap()
+    }
+
+    /// Where `data` breaks `schema`.
+    fn violations(schema: &str, data: &str) -> Vec<String> {
+        from_schema(&doc(schema)).unwrap().validate(&doc(data)).into_iter().map(|v| v.path).collect()
+    }
+
+    #[test]
+    fn every_keyword_is_checked() {
+        let cases = [
+            (r#"{"type": "string", "minLength": 2, "maxLength": 3}"#, r#""ab""#, r#""abcd""#),
+            (r#"{"type": "string", "minLength": 2}"#, r#""ab""#, r#""a""#),
+            (r#"{"enum": ["a", "b"]}"#, r#""b""#, r#""c""#),
+            (r#"{"type": "integer", "minimum": 1, "maximum": 3}"#, "3", "4"),
+            (r#"{"type": "integer", "exclusiveMinimum": 1}"#, "2", "1"),
+            (r#"{"type": "number", "exclusiveMaximum": 1.5}"#, "1", "1.5"),
+            (r#"{"type": "number"}"#, "1.5", r#""1.5""#),
+            (r#"{"type": "boolean"}"#, "false", "0"),
+            (r#"{"type": "null"}"#, "null", "false"),
+            (r#"{"type": ["string", "null"]}"#, "null", "1"),
+            (r#"{"type": "array", "items": {"type": "integer"}, "minItems": 1, "maxItems": 2}"#, "[1]", "[]"),
+            (r#"{"type": "array", "maxItems": 1}"#, "[1]", "[1, 2]"),
+            (r#"{"type": "object", "required": ["a"]}"#, r#"{"a": null}"#, "{}"),
+            (r#"{"type": "object", "properties": {"a": {"type": "string"}}, "additionalProperties": false}"#, r#"{"a": "x"}"#, r#"{"b": 1}"#),
+            (r#"{"title": "Anything", "description": "at all"}"#, "[1]", "")
+        ];
+
+        for (schema, valid, invalid) in cases {
+            assert!(violations(schema, valid).is_empty(), "{} with {}", schema, valid);
+
+            if !invalid.is_empty() {
+                assert!(!violations(schema, invalid).is_empty(), "{} with {}", schema, invalid);
+            }
+        }
+
+        assert!(from_schema(&Node::Bool(true)).unwrap().validate(&doc(r#"{"a": 1}"#)).is_empty());
+    }
+
+    #[test]
+    fn types_are_guessed_from_keywords() {
+        assert_eq!(violations(r#"{"properties": {"a": {"minimum": 0}}}"#, r#"{"a": -1}"#), ["$.a"]);
+        assert_eq!(violations(r#"{"maxLength": 1}"#, "1"), ["$"]);
+    }
+
+    #[test]
+    fn violations_say_where_they_are() {
+        let schema = r#"{
+            "type": "object",
+            "required": ["users"],
+            "properties": {
+                "users": {"type": "array", "items": {
+                    "type": "object",
+                    "required": ["name", "age"],
+                    "properties": {"name": {"type": "string"}, "age": {"type": "integer", "minimum": 0}}
+                }}
+            }
+        }"#;
+
+        let found = violations(schema, r#"{"users": [{"name": "Ada", "age": 36}, {"name": 1, "age": -1}, {"name": "Bob"}]}"#);
+        assert_eq!(found, ["$.users[1].age", "$.users[1].name", "$.users[2].age"]);
+    }
+
+    #[test]
+    fn unsupported_schemas_are_errors() {
+        let cases = [
+            (r#"{"type": "string", "pattern": "^a"}"#, "$.pattern"),
+            (r#"{"type": "string", "minimum": 1}"#, "$.minimum"),
+            (r#"{"type": ["string", "integer"]}"#, "$.type"),
+            (r#"{"type": "date"}"#, "$.type"),
+            (r#"{"type": [1]}"#, "$.type[0]"),
+            (r#"{"enum": [1, 2]}"#, "$.enum"),
+            (r#"{"type": "integer", "enum": ["a"]}"#, "$.enum"),
+            (r#"{"type": "integer", "minimum": 1.5}"#, "$.minimum"),
+            (r#"{"type": "string", "minLength": -1}"#, "$.minLength"),
+            (r#"{"required": "a"}"#, "$.required"),
+            (r#"{"required": [1]}"#, "$.required[0]"),
+            (r#"{"properties": []}"#, "$.properties"),
+            (r#"{"properties": {"a": {"type": "array", "items": 1}}}"#, "$.properties.a.items"),
+            (r#"{"type": "object", "additionalProperties": {}}"#, "$.additionalProperties"),
+            ("false", "$")
+        ];
+
+        for (schema, path) in cases {
+            let error = from_schema(&doc(schema)).err().unwrap_or_else(|| panic!("{}", schema));
+            assert_eq!(error.path, path, "{}", schema);
+        }
+    }
+
+    #[test]
+    fn describe_takes_descriptions_from_doc_comments() {
+        let text = r#"{
+            /// Which port to listen on.
+            /// Ports below 1024 need root.
+            "port": 8080, /// Trailing ones count too.
+            // An ordinary comment.
+            "hosts": [
+                /// A host name.
+                "a", "b"
+            ],
+            "tls": {"on": false} /// Whether to use TLS.
+        }"#;
+
+        let (node, comments) = parse_with_comments(text, &Profile::Lenient.options()).unwrap();
+        let schema = describe(&node, &comments);
+
+        let expected = doc(r#"{
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "type": "object",
+            "properties": {
+                "port": {"type": "integer", "description": "Which port to listen on.\nPorts below 1024 need root.\nTrailing ones count too.", "default": 8080},
+                "hosts": {"type": "array", "items": {"type": "string", "description": "A host name.", "default": "a"}},
+                "tls": {"type": "object", "description": "Whether to use TLS.", "properties": {"on": {"type": "boolean", "default": false}}}
+            }
+        }"#);
+
+        assert!(schema.approx_eq(&expected, &Tolerance::exact()));
+    }
+
+    #[test]
+    fn described_schemas_accept_what_they_describe() {
+        let node = doc(r#"{"name": "x", "ratio": 0.5, "tags": ["a"], "extra": null}"#);
+        let schema = describe(&node, &[]);
+
+        assert!(from_schema(&schema).unwrap().validate(&node).is_empty());
+    }
+}
@@ -0,0 +1,97 @@
+use crate::parser::Node;
+use crate::pointer::escape_token;
+
+/// Limits on how much of a tree a search looks through.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SearchOptions {
+    /// Don't look at values nested deeper than this, where the top-level value is at depth 0.
+    pub max_depth: Option<usize>,
+
+    /// Stop once this many matches have been found.
+    pub max_results: Option<usize>
+}
+
+struct Search<'a, F> {
+    options: &'a SearchOptions,
+    matches: F,
+    found: Vec<String>
+}
+
+impl<'a, F: FnMut(Option<&str>, &Node) -> bool> Search<'a, F> {
+    fn full(&self) -> bool {
+        self.options.max_results.is_some_and(|max| self.found.len() >= max)
+    }
+
+    /// `key` is the object key the value is under, if it's in an object.
+    fn walk(&mut self, node: &Node, key: Option<&str>, pointer: &mut String, depth: usize) {
+        if self.full() || self.options.max_depth.is_some_and(|max| depth > max) {
+            return;
+        }
+
+        if (self.matches)(key, node) {
+            self.found.push(pointer.clone());
+        }
+
+        let len = pointer.len();
+
+        match node {
+            Node::Array(arr) => for (i, item) in arr.iter().enumerate() {
+                pointer.push('/');
+                pointer.push_str(&i.to_string());
+
+                self.walk(item, None, pointer, depth + 1);
+                pointer.truncate(len);
+            },
+
+            Node::Object(map) => {
+                // Sort the keys so matches come back in the same order every time.
+                let mut keys: Vec<&String> = map.keys().collect();
+                keys.sort();
+
+                for key in keys {
+                    pointer.push('/');
+                    pointer.push_str(&escape_token(key));
+
+                    self.walk(&map[key], Some(key), pointer, depth + 1);
+                    pointer.truncate(len);
+                }
+            },
+
+            _ => {}
+        }
+    }
+}
+
+fn search(node: &Node, options: &SearchOptions, matches: impl FnMut(Option<&str>, &Node) -> bool) -> Vec<String> {
+    let mut search = Search {
+        options,
+        matches,
+        found: Vec::new()
+    };
+
+    search.walk(node, None, &mut String::new(), 0);
+    search.found
+}
+
+impl Node {
+    /// Find every value that `matches` picks out, like every string equal to an ID,
+    /// giving back their JSON Pointers.
+    ///
+    /// Values are looked at in document order, with object keys sorted,
+    /// and arrays and objects are given to `matches` as well as what's inside them.
+    pub fn search_values(&self, mut matches: impl FnMut(&Node) -> bool, options: &SearchOptions) -> Vec<String> {
+        search(self, options, |_, node| matches(node))
+    }
+
+    /// Find every value under an object key that contains `substring`,
+    /// giving back their JSON Pointers. See `search_keys_with` for other ways of matching keys.
+    pub fn search_keys(&self, substring: &str, options: &SearchOptions) -> Vec<String> {
+        self.search_keys_with(|key| key.contains(substring), options)
+    }
+
+    /// Find every value under an object key that `matches` picks out,
+    /// like with a regex, giving back their JSON Pointers.
+    pub fn search_keys_with(&self, mut matches: impl FnMut(&str) -> bool, options: &SearchOptions) -> Vec<String> {
+        search(self, options, |key, _| key.is_some_and(&mut matches))
+    }
+}
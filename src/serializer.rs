@@ -0,0 +1,995 @@
+use std::fmt::{self, Display, Write};
+use std::io;
+
+use crate::parser::{Node, ObjectMap};
+
+/// Why a node couldn't be serialized.
+#[derive(Debug)]
+pub struct SerializeError {
+    pub message: String
+}
+
+impl Display for SerializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for SerializeError {}
+
+impl SerializeError {
+    /// Writing into memory only fails when a float isn't allowed to be written.
+    pub(crate) fn non_finite() -> SerializeError {
+        SerializeError {
+            message: "Found NaN or infinity, which JSON can't represent".to_string()
+        }
+    }
+}
+
+/// How floats are written out.
+#[derive(Clone, Copy, PartialEq)]
+pub enum FloatFormat {
+    /// The fewest digits that still read back as the same float, written the
+    /// same way JavaScript's `String(number)` would, like `0.1`, `1e+21` or `1e-7`.
+    ///
+    /// Unlike JavaScript, whole numbers keep a `.0`, like `2.0`,
+    /// so they're read back as floats instead of integers.
+    Shortest,
+
+    /// A fixed number of digits after the decimal point, like `3.14` for 2.
+    Fixed(usize),
+
+    /// The fewest digits that read back the same, always in scientific notation, like `1.5e3`.
+    Scientific
+}
+
+/// What to do with NaN and infinities, which JSON has no way to write.
+#[derive(Clone, Copy, PartialEq)]
+pub enum NonFinitePolicy {
+    /// Fail to serialize.
+    Error,
+
+    /// Write `null`, like `JSON.stringify` does.
+    Null,
+
+    /// Write them as the strings `"NaN"`, `"Infinity"` and `"-Infinity"`.
+    String
+}
+
+/// How JSON text should be laid out when serializing.
+pub struct FormatOptions {
+    /// How many spaces to indent nested values by, or `None` to write everything on one line.
+    pub indent: Option<usize>,
+
+    pub float_format: FloatFormat,
+    pub non_finite: NonFinitePolicy,
+
+    /// Leave out object keys whose value is `null`, so they read as absent.
+    /// Nulls in arrays are always kept, since leaving them out would move the items after them.
+    pub skip_null_fields: bool,
+
+    /// Write object keys in sorted order, instead of the order the map holds them in,
+    /// so the same document is always written the same way.
+    pub sort_keys: bool,
+
+    /// When indenting, write arrays and objects on one line if they fit in this many columns,
+    /// like `{ "x": 1, "y": [1, 2] }`, and only spread them over lines if they don't,
+    /// the way Prettier formats JSON. See `FormatOptions::prettier`.
+    /// Colored output from `render` doesn't use this.
+    pub max_width: Option<usize>
+}
+
+impl FormatOptions {
+    pub fn compact() -> FormatOptions {
+        FormatOptions {
+            indent: None,
+            float_format: FloatFormat::Shortest,
+            non_finite: NonFinitePolicy::Null,
+            skip_null_fields: false,
+            sort_keys: false,
+            max_width: None
+        }
+    }
+
+    pub fn pretty(indent: usize) -> FormatOptions {
+        FormatOptions {
+            indent: Some(indent),
+            ..FormatOptions::compact()
+        }
+    }
+
+    /// Lay out JSON the same way Prettier does with its defaults: indented by two spaces,
+    /// with arrays and objects kept on one line when they fit in `width` columns (80 in Prettier).
+    ///
+    /// Like Prettier, arrays of more than one object or array (each with more than one entry)
+    /// are always spread out, and long arrays of numbers are filled onto as few lines as fit.
+    /// Prettier also keeps an object spread out if it was in the original text, which
+    /// can't be known from a `Node`, so objects are only spread out when they don't fit.
+    pub fn prettier(width: usize) -> FormatOptions {
+        FormatOptions {
+            max_width: Some(width),
+            ..FormatOptions::pretty(2)
+        }
+    }
+}
+
+impl Default for FormatOptions {
+    fn default() -> FormatOptions {
+        FormatOptions::compact()
+    }
+}
+
+/// The escape sequence for a character in a string, if it needs one.
+fn escape_for(ch: char) -> Option<&'static str> {
+    match ch {
+        '"' => Some("\\\""),
+        '\\' => Some("\\\\"),
+        '\n' => Some("\\n"),
+        '\r' => Some("\\r"),
+        '\t' => Some("\\t"),
+        '\x08' => Some("\\b"),
+        '\x0c' => Some("\\f"),
+        _ => None
+    }
+}
+
+pub(crate) fn write_escaped_to<W: Write>(s: &str, out: &mut W) -> fmt::Result {
+    out.write_char('"')?;
+
+    for ch in s.chars() {
+        match escape_for(ch) {
+            Some(escape) => out.write_str(escape)?,
+            None if (ch as u32) < 0x20 => write!(out, "\\u{:04x}", ch as u32)?,
+            None => out.write_char(ch)?
+        }
+    }
+
+    out.write_char('"')
+}
+
+/// Write a string with quotes, escaping anything JSON doesn't allow as-is.
+/// Control characters without a short escape, NUL included, are written like `\u0000`.
+pub fn write_escaped(s: &str, out: &mut String) {
+    write_escaped_to(s, out).unwrap();
+}
+
+fn escaped_len(s: &str) -> usize {
+    let mut len = 2;
+
+    for ch in s.chars() {
+        len += match escape_for(ch) {
+            Some(escape) => escape.len(),
+            None if (ch as u32) < 0x20 => 6,
+            None => ch.len_utf8()
+        };
+    }
+
+    len
+}
+
+fn integer_len(n: i64) -> usize {
+    let mut len = if n < 0 { 2 } else { 1 };
+    let mut rest = n.unsigned_abs();
+
+    while rest >= 10 {
+        rest /= 10;
+        len += 1;
+    }
+
+    len
+}
+
+/// Start a new line at the given depth, if pretty printing.
+pub(crate) fn write_newline<W: Write>(options: &FormatOptions, depth: usize, out: &mut W) -> fmt::Result {
+    if let Some(indent) = options.indent {
+        out.write_char('\n')?;
+
+        for _ in 0..indent * depth {
+            out.write_char(' ')?;
+        }
+    }
+
+    Ok(())
+}
+
+fn newline_len(options: &FormatOptions, depth: usize) -> usize {
+    match options.indent {
+        Some(indent) => 1 + indent * depth,
+        None => 0
+    }
+}
+
+pub(crate) fn colon(options: &FormatOptions) -> &'static str {
+    if options.indent.is_some() { ": " } else { ":" }
+}
+
+fn write_float<W: Write>(f: f64, options: &FormatOptions, out: &mut W) -> fmt::Result {
+    if !f.is_finite() {
+        let name = if f.is_nan() { "NaN" } else if f > 0.0 { "Infinity" } else { "-Infinity" };
+
+        return match options.non_finite {
+            NonFinitePolicy::Error => Err(fmt::Error),
+            NonFinitePolicy::Null => out.write_str("null"),
+            NonFinitePolicy::String => write!(out, "\"{}\"", name)
+        };
+    }
+
+    match options.float_format {
+        // JavaScript switches to an exponent outside of this range.
+        FloatFormat::Shortest if f != 0.0 && (f.abs() >= 1e21 || f.abs() < 1e-6) => {
+            let text = format!("{:e}", f);
+
+            match text.split_once('e') {
+                Some((digits, exponent)) if !exponent.starts_with('-') => write!(out, "{}e+{}", digits, exponent),
+                _ => out.write_str(&text)
+            }
+        },
+        FloatFormat::Shortest if f.fract() == 0.0 => write!(out, "{}.0", f),
+        FloatFormat::Shortest => write!(out, "{}", f),
+        FloatFormat::Fixed(precision) => write!(out, "{:.*}", precision, f),
+        FloatFormat::Scientific => write!(out, "{:e}", f)
+    }
+}
+
+/// Write anything that isn't a non-empty array or object.
+pub(crate) fn write_scalar<W: Write>(node: &Node, options: &FormatOptions, out: &mut W) -> fmt::Result {
+    match node {
+        Node::Integer(n) => write!(out, "{}", n),
+        Node::Unsigned(n) => write!(out, "{}", n),
+        Node::Float(f) => write_float(*f, options, out),
+
+        Node::String(s) => write_escaped_to(s, out),
+        Node::Bool(b) => out.write_str(if *b { "true" } else { "false" }),
+        Node::Null => out.write_str("null"),
+        Node::Array(_) => out.write_str("[]"),
+        Node::Object(_) => out.write_str("{}"),
+
+        // An empty document has nothing to write.
+        Node::Empty => Ok(())
+    }
+}
+
+/// The entries of an object that get written with the given options.
+pub(crate) fn shown_entries<'a>(map: &'a ObjectMap, options: &FormatOptions) -> impl Iterator<Item = (&'a String, &'a Node)> {
+    let skip_nulls = options.skip_null_fields;
+    let entries = map.iter().filter(move |(_, value)| !(skip_nulls && matches!(value, Node::Null)));
+
+    // Only collect the entries when they need sorting, so the usual case doesn't allocate.
+    let sorted = options.sort_keys.then(|| {
+        let mut sorted: Vec<_> = entries.clone().collect();
+        sorted.sort_unstable_by_key(|(key, _)| *key);
+        sorted
+    });
+
+    let unsorted = (!options.sort_keys).then_some(entries);
+
+    sorted.into_iter().flatten().chain(unsorted.into_iter().flatten())
+}
+
+/// How long a value is on one line, the way `write_flat` writes it,
+/// or `None` as soon as it's found to be longer than `limit`.
+fn flat_len(node: &Node, options: &FormatOptions, limit: usize) -> Option<usize> {
+    let len = match node {
+        Node::Array(arr) if !arr.is_empty() => {
+            let mut len = 2 + 2 * (arr.len() - 1);
+
+            for item in arr {
+                len += flat_len(item, options, limit.checked_sub(len)?)?;
+            }
+
+            len
+        },
+
+        Node::Object(map) if shown_entries(map, options).next().is_some() => {
+            // `{ ` and ` }`, with `, ` between entries.
+            let mut len = 2;
+
+            for (key, value) in shown_entries(map, options) {
+                len += 2 + escaped_len(key) + 2;
+                len += flat_len(value, options, limit.checked_sub(len)?)?;
+            }
+
+            len
+        },
+
+        _ => node_len(node, options, 0)
+    };
+
+    (len <= limit).then_some(len)
+}
+
+fn write_flat<W: Write>(node: &Node, options: &FormatOptions, out: &mut W) -> fmt::Result {
+    match node {
+        Node::Array(arr) if !arr.is_empty() => {
+            out.write_char('[')?;
+
+            for (i, item) in arr.iter().enumerate() {
+                if i > 0 {
+                    out.write_str(", ")?;
+                }
+
+                write_flat(item, options, out)?;
+            }
+
+            out.write_char(']')
+        },
+
+        Node::Object(map) if shown_entries(map, options).next().is_some() => {
+            out.write_char('{')?;
+
+            for (i, (key, value)) in shown_entries(map, options).enumerate() {
+                out.write_str(if i > 0 { ", " } else { " " })?;
+                write_escaped_to(key, out)?;
+                out.write_str(": ")?;
+                write_flat(value, options, out)?;
+            }
+
+            out.write_str(" }")
+        },
+
+        _ => write_scalar(node, options, out)
+    }
+}
+
+/// Whether Prettier would always spread an array out, which it does for arrays
+/// of more than one object, or more than one array, each with more than one entry.
+fn always_breaks(arr: &[Node]) -> bool {
+    arr.len() > 1 && (
+        arr.iter().all(|item| matches!(item, Node::Object(map) if map.len() > 1))
+        || arr.iter().all(|item| matches!(item, Node::Array(inner) if inner.len() > 1))
+    )
+}
+
+/// Whether a value fits on the rest of its line for `max_width`, where `used` is how many
+/// columns the rest of the line takes up, like the indent and key before it and the comma after it.
+fn fits_flat(node: &Node, options: &FormatOptions, width: usize, used: usize) -> bool {
+    let forced = matches!(node, Node::Array(arr) if always_breaks(arr));
+
+    !forced && flat_len(node, options, width.saturating_sub(used)).is_some()
+}
+
+/// Whether an array that doesn't fit on one line has its items packed onto lines,
+/// which it does when they're all numbers.
+fn packs_numbers(arr: &[Node]) -> bool {
+    arr.len() > 1 && arr.iter().all(|item| matches!(item, Node::Integer(_) | Node::Unsigned(_) | Node::Float(_)))
+}
+
+/// Write an array of numbers with as many on each line as fit in `width`.
+fn write_packed<W: Write>(arr: &[Node], options: &FormatOptions, width: usize, depth: usize, out: &mut W) -> fmt::Result {
+    let inner = options.indent.unwrap_or(0) * (depth + 1);
+
+    out.write_char('[')?;
+    write_newline(options, depth + 1, out)?;
+
+    let mut column = inner;
+
+    for (i, item) in arr.iter().enumerate() {
+        let len = node_len(item, options, 0) + 1;
+
+        if i > 0 {
+            out.write_char(',')?;
+
+            match column + 1 + len > width {
+                true => {
+                    write_newline(options, depth + 1, out)?;
+                    column = inner;
+                },
+                false => {
+                    out.write_char(' ')?;
+                    column += 1;
+                }
+            }
+        }
+
+        write_scalar(item, options, out)?;
+        column += len;
+    }
+
+    write_newline(options, depth, out)?;
+    out.write_char(']')
+}
+
+/// Write a value for `max_width`, where `used` is how many columns the rest of its line
+/// takes up, like the indent and key before it and the comma after it.
+fn write_wrapped<W: Write>(node: &Node, options: &FormatOptions, width: usize, depth: usize, used: usize, out: &mut W) -> fmt::Result {
+    let inner = options.indent.unwrap_or(0) * (depth + 1);
+
+    if fits_flat(node, options, width, used) {
+        return write_flat(node, options, out);
+    }
+
+    match node {
+        Node::Array(arr) if packs_numbers(arr) => write_packed(arr, options, width, depth, out),
+
+        Node::Array(arr) if !arr.is_empty() => {
+            out.write_char('[')?;
+
+            for (i, item) in arr.iter().enumerate() {
+                if i > 0 {
+                    out.write_char(',')?;
+                }
+
+                let comma = if i + 1 < arr.len() { 1 } else { 0 };
+
+                write_newline(options, depth + 1, out)?;
+                write_wrapped(item, options, width, depth + 1, inner + comma, out)?;
+            }
+
+            write_newline(options, depth, out)?;
+            out.write_char(']')
+        },
+
+        Node::Object(map) if shown_entries(map, options).next().is_some() => {
+            out.write_char('{')?;
+
+            let count = shown_entries(map, options).count();
+
+            for (i, (key, value)) in shown_entries(map, options).enumerate() {
+                if i > 0 {
+                    out.write_char(',')?;
+                }
+
+                let comma = if i + 1 < count { 1 } else { 0 };
+
+                write_newline(options, depth + 1, out)?;
+                write_escaped_to(key, out)?;
+                out.write_str(": ")?;
+                write_wrapped(value, options, width, depth + 1, inner + escaped_len(key) + 2 + comma, out)?;
+            }
+
+            write_newline(options, depth, out)?;
+            out.write_char('}')
+        },
+
+        _ => write_scalar(node, options, out)
+    }
+}
+
+/// Counts the bytes written to it, for measuring text without keeping it.
+struct Counter(usize);
+
+impl Write for Counter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+fn write_node<W: Write>(node: &Node, options: &FormatOptions, depth: usize, out: &mut W) -> fmt::Result {
+    if options.indent.is_some() && let Some(width) = options.max_width {
+        return write_wrapped(node, options, width, depth, options.indent.unwrap_or(0) * depth, out);
+    }
+
+    match node {
+        Node::Array(arr) if !arr.is_empty() => {
+            out.write_char('[')?;
+
+            for (i, item) in arr.iter().enumerate() {
+                if i > 0 {
+                    out.write_char(',')?;
+                }
+
+                write_newline(options, depth + 1, out)?;
+                write_node(item, options, depth + 1, out)?;
+            }
+
+            write_newline(options, depth, out)?;
+            out.write_char(']')
+        },
+
+        Node::Object(map) if shown_entries(map, options).next().is_some() => {
+            out.write_char('{')?;
+
+            for (i, (key, value)) in shown_entries(map, options).enumerate() {
+                if i > 0 {
+                    out.write_char(',')?;
+                }
+
+                write_newline(options, depth + 1, out)?;
+                write_escaped_to(key, out)?;
+                out.write_str(colon(options))?;
+                write_node(value, options, depth + 1, out)?;
+            }
+
+            write_newline(options, depth, out)?;
+            out.write_char('}')
+        },
+
+        _ => write_scalar(node, options, out)
+    }
+}
+
+fn node_len(node: &Node, options: &FormatOptions, depth: usize) -> usize {
+    // Where `max_width` breaks lines depends on how long everything on them is,
+    // so the simplest exact answer is to write the text and count it.
+    if let (Some(indent), Some(width), Node::Array(_) | Node::Object(_)) = (options.indent, options.max_width, node) {
+        let mut counter = Counter(0);
+        let _ = write_wrapped(node, options, width, depth, indent * depth, &mut counter);
+
+        return counter.0;
+    }
+
+    match node {
+        Node::Integer(n) => integer_len(*n),
+        Node::Unsigned(n) => n.checked_ilog10().map_or(1, |digits| digits as usize + 1),
+        Node::Float(f) => {
+            let mut text = String::new();
+
+            match write_float(*f, options, &mut text) {
+                Ok(()) => text.len(),
+                Err(_) => 0
+            }
+        },
+        Node::String(s) => escaped_len(s),
+        Node::Bool(true) => 4,
+        Node::Bool(false) => 5,
+        Node::Null => 4,
+
+        Node::Array(arr) if arr.is_empty() => 2,
+        Node::Array(arr) => {
+            let items: usize = arr.iter().map(
+                |item| newline_len(options, depth + 1) + node_len(item, options, depth + 1)
+            ).sum();
+
+            // Brackets, commas between items, and the line the closing bracket goes on.
+            2 + (arr.len() - 1) + items + newline_len(options, depth)
+        },
+
+        Node::Object(map) => {
+            let (count, entries) = shown_entries(map, options).fold((0, 0), |(count, len), (key, value)| (
+                count + 1,
+                len + newline_len(options, depth + 1) + escaped_len(key) + colon(options).len() + node_len(value, options, depth + 1)
+            ));
+
+            match count {
+                0 => 2,
+                _ => 2 + (count - 1) + entries + newline_len(options, depth)
+            }
+        },
+
+        Node::Empty => 0
+    }
+}
+
+/// Serialize a node to compact JSON text.
+///
+/// NaN and infinities are written as `null`.
+pub fn to_string(node: &Node) -> String {
+    let mut out = String::new();
+
+    // The default options never fail.
+    write_node(node, &FormatOptions::compact(), 0, &mut out).unwrap();
+
+    out
+}
+
+/// Serialize a node to JSON text laid out with the given options.
+pub fn to_string_with(node: &Node, options: &FormatOptions) -> Result<String, SerializeError> {
+    let mut out = String::with_capacity(node_len(node, options, 0));
+
+    match write_node(node, options, 0, &mut out) {
+        Ok(()) => Ok(out),
+        Err(_) => Err(SerializeError::non_finite())
+    }
+}
+
+/// A buffer that serialized text can be appended to.
+pub trait SerializeTarget {
+    /// Append a node's text, or leave the buffer as it was if serializing fails.
+    fn append(&mut self, node: &Node, options: &FormatOptions) -> Result<(), SerializeError>;
+}
+
+impl SerializeTarget for String {
+    fn append(&mut self, node: &Node, options: &FormatOptions) -> Result<(), SerializeError> {
+        let start = self.len();
+
+        write_node(node, options, 0, self).map_err(|_| {
+            self.truncate(start);
+            SerializeError::non_finite()
+        })
+    }
+}
+
+/// Appends UTF-8 text onto a byte buffer.
+struct ByteWriter<'a>(&'a mut Vec<u8>);
+
+impl<'a> Write for ByteWriter<'a> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.extend_from_slice(s.as_bytes());
+        Ok(())
+    }
+}
+
+impl SerializeTarget for Vec<u8> {
+    fn append(&mut self, node: &Node, options: &FormatOptions) -> Result<(), SerializeError> {
+        let start = self.len();
+
+        write_node(node, options, 0, &mut ByteWriter(self)).map_err(|_| {
+            self.truncate(start);
+            SerializeError::non_finite()
+        })
+    }
+}
+
+/// Serializes nodes into buffers the caller owns, so they can be cleared and reused
+/// instead of allocating a new `String` for every message.
+#[derive(Default)]
+pub struct Serializer {
+    pub options: FormatOptions
+}
+
+impl Serializer {
+    pub fn new(options: FormatOptions) -> Serializer {
+        Serializer {
+            options
+        }
+    }
+
+    /// Append a node's JSON text to the end of `out`, leaving what's already there alone.
+    pub fn serialize_into<T: SerializeTarget>(&self, node: &Node, out: &mut T) -> Result<(), SerializeError> {
+        out.append(node, &self.options)
+    }
+}
+
+/// Passes text through to an `io::Write`, holding onto the real error
+/// since `fmt::Error` can't carry one.
+struct IoWriter<W: io::Write> {
+    inner: W,
+    error: Option<io::Error>
+}
+
+impl<W: io::Write> Write for IoWriter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
+}
+
+/// Serialize a node into a writer with the given options, without building the text in memory first.
+///
+/// Output is written in many small pieces, so wrap unbuffered writers like files
+/// and sockets in a `BufWriter`.
+pub fn to_writer_with<W: io::Write>(node: &Node, options: &FormatOptions, writer: W) -> io::Result<()> {
+    let mut out = IoWriter {
+        inner: writer,
+        error: None
+    };
+
+    match write_node(node, options, 0, &mut out) {
+        Ok(()) => Ok(()),
+        Err(_) => Err(out.error.unwrap_or_else(|| io::Error::new(io::ErrorKind::InvalidData, SerializeError::non_finite().message)))
+    }
+}
+
+/// Serialize a node into a writer as compact JSON.
+pub fn to_writer<W: io::Write>(node: &Node, writer: W) -> io::Result<()> {
+    to_writer_with(node, &FormatOptions::compact(), writer)
+}
+
+/// Serialize a node into a writer as JSON indented by two spaces.
+pub fn to_writer_pretty<W: io::Write>(node: &Node, writer: W) -> io::Result<()> {
+    to_writer_with(node, &FormatOptions::pretty(2), writer)
+}
+
+/// Serialize a node into a `fmt::Write`, like a `fmt::Formatter`.
+pub fn to_fmt_writer<W: Write>(node: &Node, options: &FormatOptions, writer: &mut W) -> fmt::Result {
+    write_node(node, options, 0, writer)
+}
+
+#[cfg(feature = "async")]
+mod nonblocking {
+    use std::future::Future;
+    use std::io;
+    use std::pin::Pin;
+
+    use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+    use super::{
+        colon, escaped_len, fits_flat, packs_numbers, shown_entries, write_escaped_to, write_flat, write_newline,
+        write_packed, write_scalar, FormatOptions, SerializeError
+    };
+    use crate::parser::Node;
+
+    /// How much text to gather up before handing it to the writer.
+    const CHUNK_SIZE: usize = 8 * 1024;
+
+    struct Chunked<'a, W> {
+        writer: &'a mut W,
+        buffer: String,
+        options: &'a FormatOptions
+    }
+
+    fn non_finite() -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, SerializeError::non_finite().message)
+    }
+
+    impl<'a, W: AsyncWrite + Unpin + Send> Chunked<'a, W> {
+        async fn flush_if_full(&mut self) -> io::Result<()> {
+            if self.buffer.len() >= CHUNK_SIZE {
+                self.writer.write_all(self.buffer.as_bytes()).await?;
+                self.buffer.clear();
+            }
+
+            Ok(())
+        }
+
+        fn write_node<'b>(&'b mut self, node: &'b Node, depth: usize) -> Pin<Box<dyn Future<Output = io::Result<()>> + Send + 'b>> {
+            Box::pin(async move {
+                // Writing punctuation into a `String` can't fail, so those results are unwrapped.
+                match node {
+                    Node::Array(arr) if !arr.is_empty() => {
+                        self.buffer.push('[');
+
+                        for (i, item) in arr.iter().enumerate() {
+                            if i > 0 {
+                                self.buffer.push(',');
+                            }
+
+                            write_newline(self.options, depth + 1, &mut self.buffer).unwrap();
+                            self.write_node(item, depth + 1).await?;
+                        }
+
+                        write_newline(self.options, depth, &mut self.buffer).unwrap();
+                        self.buffer.push(']');
+                    },
+
+                    Node::Object(map) if shown_entries(map, self.options).next().is_some() => {
+                        self.buffer.push('{');
+
+                        for (i, (key, value)) in shown_entries(map, self.options).enumerate() {
+                            if i > 0 {
+                                self.buffer.push(',');
+                            }
+
+                            write_newline(self.options, depth + 1, &mut self.buffer).unwrap();
+                            write_escaped_to(key, &mut self.buffer).unwrap();
+                            self.buffer.push_str(colon(self.options));
+                            self.write_node(value, depth + 1).await?;
+                        }
+
+                        write_newline(self.options, depth, &mut self.buffer).unwrap();
+                        self.buffer.push('}');
+                    },
+
+                    _ => write_scalar(node, self.options, &mut self.buffer).map_err(|_| non_finite())?
+                }
+
+                self.flush_if_full().await
+            })
+        }
+
+        /// Write a value for `max_width` the same way `super::write_wrapped` does,
+        /// going back to the writer between items.
+        fn write_wrapped<'b>(&'b mut self, node: &'b Node, width: usize, depth: usize, used: usize) -> Pin<Box<dyn Future<Output = io::Result<()>> + Send + 'b>> {
+            Box::pin(async move {
+                let options = self.options;
+                let inner = options.indent.unwrap_or(0) * (depth + 1);
+
+                match node {
+                    _ if fits_flat(node, options, width, used) => write_flat(node, options, &mut self.buffer).map_err(|_| non_finite())?,
+
+                    Node::Array(arr) if packs_numbers(arr) => write_packed(arr, options, width, depth, &mut self.buffer).map_err(|_| non_finite())?,
+
+                    Node::Array(arr) if !arr.is_empty() => {
+                        self.buffer.push('[');
+
+                        for (i, item) in arr.iter().enumerate() {
+                            if i > 0 {
+                                self.buffer.push(',');
+                            }
+
+                            let comma = if i + 1 < arr.len() { 1 } else { 0 };
+
+                            write_newline(options, depth + 1, &mut self.buffer).unwrap();
+                            self.write_wrapped(item, width, depth + 1, inner + comma).await?;
+                        }
+
+                        write_newline(options, depth, &mut self.buffer).unwrap();
+                        self.buffer.push(']');
+                    },
+
+                    Node::Object(map) if shown_entries(map, options).next().is_some() => {
+                        self.buffer.push('{');
+
+                        let count = shown_entries(map, options).count();
+
+                        for (i, (key, value)) in shown_entries(map, options).enumerate() {
+                            if i > 0 {
+                                self.buffer.push(',');
+                            }
+
+                            let comma = if i + 1 < count { 1 } else { 0 };
+
+                            write_newline(options, depth + 1, &mut self.buffer).unwrap();
+                            write_escaped_to(key, &mut self.buffer).unwrap();
+                            self.buffer.push_str(": ");
+                            self.write_wrapped(value, width, depth + 1, inner + escaped_len(key) + 2 + comma).await?;
+                        }
+
+                        write_newline(options, depth, &mut self.buffer).unwrap();
+                        self.buffer.push('}');
+                    },
+
+                    _ => write_scalar(node, options, &mut self.buffer).map_err(|_| non_finite())?
+                }
+
+                self.flush_if_full().await
+            })
+        }
+    }
+
+    /// Serialize a node into an async writer, sending it out in chunks of about 8 KiB.
+    pub async fn to_async_writer<W: AsyncWrite + Unpin + Send>(node: &Node, options: &FormatOptions, writer: &mut W) -> io::Result<()> {
+        let mut chunked = Chunked {
+            writer,
+            buffer: String::with_capacity(CHUNK_SIZE),
+            options
+        };
+
+        match (options.indent, options.max_width) {
+            (Some(_), Some(width)) => chunked.write_wrapped(node, width, 0, 0).await?,
+            _ => chunked.write_node(node, 0).await?
+        }
+
+        if !chunked.buffer.is_empty() {
+            chunked.writer.write_all(chunked.buffer.as_bytes()).await?;
+        }
+
+        chunked.writer.flush().await
+    }
+}
+
+#[cfg(feature = "async")]
+pub use nonblocking::to_async_writer;
+
+impl Node {
+    /// The exact length in bytes of this tree once serialized with the given options,
+    /// worked out without building the text.
+    ///
+    /// If the options make serializing fail, floats that couldn't be written count as 0 bytes,
+    /// and with `max_width`, so does everything after the first of them.
+    pub fn estimated_json_size(&self, options: &FormatOptions) -> usize {
+        node_len(self, options, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_str;
+
+    fn doc(text: &str) -> Node {
+        parse_str(text).unwrap()
+    }
+
+    fn write(node: &Node, options: &FormatOptions) -> String {
+        to_string_with(node, options).unwrap()
+    }
+
+    #[test]
+    fn pretty_indents_nested_values() {
+        let options = FormatOptions { sort_keys: true, ..FormatOptions::pretty(2) };
+
+        assert_eq!(write(&doc(r#"{"b":[1,{}],"a":[]}"#), &options), "{\n  \"a\": [],\n  \"b\": [\n    1,\n    {}\n  ]\n}");
+        assert_eq!(write(&doc(r#"{"b":1,"a":2}"#), &FormatOptions { sort_keys: true, ..FormatOptions::compact() }), r#"{"a":2,"b":1}"#);
+    }
+
+    #[test]
+    fn estimated_size_is_exact() {
+        let node = doc(r#"{"a":[1,-2.5,"x\ny"],"b":{"c":null,"d":true},"e":1e300}"#);
+
+        for options in [FormatOptions::compact(), FormatOptions::pretty(4)] {
+            assert_eq!(node.estimated_json_size(&options), write(&node, &options).len());
+        }
+    }
+
+    #[test]
+    fn writers_match_to_string() {
+        let node = doc(r#"{"a":[1,2.5,"é"],"b":null}"#);
+        let mut out = Vec::new();
+
+        to_writer(&node, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), to_string(&node));
+
+        let mut out = Vec::new();
+        to_writer_pretty(&node, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), write(&node, &FormatOptions::pretty(2)));
+    }
+
+    #[test]
+    fn serialize_into_appends() {
+        let serializer = Serializer::new(FormatOptions::compact());
+        let mut out = String::from("data: ");
+
+        serializer.serialize_into(&doc("[1, 2]"), &mut out).unwrap();
+        assert_eq!(out, "data: [1,2]");
+
+        // A failure leaves what was there alone.
+        let failing = Serializer::new(FormatOptions { non_finite: NonFinitePolicy::Error, ..FormatOptions::compact() });
+        assert!(failing.serialize_into(&Node::Array(vec![Node::Integer(1), Node::Float(f64::NAN)]), &mut out).is_err());
+        assert_eq!(out, "data: [1,2]");
+    }
+
+    #[test]
+    fn floats_follow_the_float_format() {
+        let format = |float_format, f: f64| write(&Node::Float(f), &FormatOptions { float_format, ..FormatOptions::compact() });
+
+        assert_eq!(format(FloatFormat::Shortest, 2.0), "2.0");
+        assert_eq!(format(FloatFormat::Shortest, 0.1), "0.1");
+        assert_eq!(format(FloatFormat::Shortest, 1e21), "1e+21");
+        assert_eq!(format(FloatFormat::Fixed(2), 1.23456), "1.23");
+        assert_eq!(format(FloatFormat::Scientific, 1500.0), "1.5e3");
+        assert_eq!(format(FloatFormat::Shortest, -0.0), "-0.0");
+    }
+
+    #[test]
+    fn non_finite_floats_follow_the_policy() {
+        let format = |non_finite| to_string_with(&Node::Float(f64::NEG_INFINITY), &FormatOptions { non_finite, ..FormatOptions::compact() });
+
+        assert_eq!(format(NonFinitePolicy::Null).unwrap(), "null");
+        assert_eq!(format(NonFinitePolicy::String).unwrap(), r#""-Infinity""#);
+        assert!(format(NonFinitePolicy::Error).is_err());
+    }
+
+    #[test]
+    fn skip_null_fields_only_skips_fields() {
+        let options = FormatOptions { skip_null_fields: true, sort_keys: true, ..FormatOptions::compact() };
+
+        assert_eq!(write(&doc(r#"{"a":null,"b":[null],"c":{"d":null}}"#), &options), r#"{"b":[null],"c":{}}"#);
+    }
+
+    #[test]
+    fn max_width_keeps_short_values_on_one_line() {
+        let options = FormatOptions { sort_keys: true, ..FormatOptions::prettier(80) };
+
+        assert_eq!(to_string_with(&doc(r#"{"b":[1,2],"a":{"c":null}}"#), &options).unwrap(), r#"{ "a": { "c": null }, "b": [1, 2] }"#);
+    }
+
+    #[test]
+    fn max_width_breaks_long_values() {
+        let options = FormatOptions { sort_keys: true, ..FormatOptions::prettier(20) };
+        let text = to_string_with(&doc(r#"{"a":[1,2,{"b":"xyz"}],"c":{"d":null,"e":1.5}}"#), &options).unwrap();
+
+        assert_eq!(text, "{\n  \"a\": [\n    1,\n    2,\n    { \"b\": \"xyz\" }\n  ],\n  \"c\": {\n    \"d\": null,\n    \"e\": 1.5\n  }\n}");
+        assert!(text.lines().all(|line| line.len() <= 20));
+    }
+
+    #[test]
+    fn max_width_packs_numbers() {
+        let options = FormatOptions::prettier(12);
+
+        assert_eq!(to_string_with(&doc("[1,2,3,4,5,6,7]"), &options).unwrap(), "[\n  1, 2, 3,\n  4, 5, 6,\n  7\n]");
+    }
+
+    #[test]
+    fn estimated_size_is_exact_with_max_width() {
+        let node = doc(r#"{"a":[1,2,{"b":"xyz"}],"c":{"d":null,"e":1.5},"f":[[1,2],[3,4]],"g":[10,20,30,40,50,60,70]}"#);
+
+        for width in [0, 10, 20, 40, 80, 200] {
+            let options = FormatOptions { sort_keys: true, ..FormatOptions::prettier(width) };
+
+            assert_eq!(node.estimated_json_size(&options), to_string_with(&node, &options).unwrap().len(), "width {}", width);
+        }
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn async_writer_matches_max_width() {
+        use std::pin::pin;
+        use std::task::{Context, Poll, Waker};
+
+        let node = doc(r#"{"a":[1,2,{"b":"xyz"}],"c":{"d":null,"e":1.5},"g":[10,20,30,40,50,60,70]}"#);
+        let options = FormatOptions { sort_keys: true, ..FormatOptions::prettier(20) };
+        let mut out = Vec::new();
+
+        // Writing into a `Vec` never has to wait, so one poll finishes it.
+        {
+            let future = pin!(to_async_writer(&node, &options, &mut out));
+            assert!(matches!(future.poll(&mut Context::from_waker(Waker::noop())), Poll::Ready(Ok(()))));
+        }
+
+        assert_eq!(String::from_utf8(out).unwrap(), to_string_with(&node, &options).unwrap());
+    }
+}
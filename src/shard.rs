@@ -0,0 +1,112 @@
+use std::io::{BufRead, Write};
+
+use crate::framing::{ArrayItemReader, FramingError};
+use crate::generate::Rng;
+
+/// Split a top-level array into smaller arrays of at most `items_per_file` items each,
+/// reading it a piece at a time so it never has to fit in memory.
+///
+/// `open_shard` is called with 0, 1, 2... to get somewhere to write each shard.
+/// Items are copied as they were written, so nothing is reformatted.
+/// An empty array makes no shards. Gives back how many shards were written.
+pub fn split_array<R, W>(reader: R, items_per_file: usize, mut open_shard: impl FnMut(usize) -> std::io::Result<W>) -> Result<usize, FramingError>
+where
+    R: BufRead,
+    W: Write
+{
+    let items_per_file = items_per_file.max(1);
+
+    let mut shards = 0;
+    let mut current: Option<(W, usize)> = None;
+
+    for item in ArrayItemReader::new(reader) {
+        let item = item?;
+
+        let (writer, count) = match &mut current {
+            Some(shard) => shard,
+            None => {
+                let writer = open_shard(shards).map_err(FramingError::Io)?;
+                shards += 1;
+
+                current.insert((writer, 0))
+            }
+        };
+
+        let separator = if *count == 0 { "[\n" } else { ",\n" };
+
+        write!(writer, "{}{}", separator, item).map_err(FramingError::Io)?;
+        *count += 1;
+
+        if *count == items_per_file {
+            close_shard(current.take())?;
+        }
+    }
+
+    close_shard(current)?;
+
+    Ok(shards)
+}
+
+fn close_shard<W: Write>(shard: Option<(W, usize)>) -> Result<(), FramingError> {
+    if let Some((mut writer, _)) = shard {
+        writer.write_all(b"\n]\n").and_then(|_| writer.flush()).map_err(FramingError::Io)?;
+    }
+
+    Ok(())
+}
+
+/// Join top-level arrays back into one, in order, reading each a piece at a time.
+/// This is the inverse of `split_array`. Gives back how many items were written.
+pub fn join_arrays<R, W>(readers: impl IntoIterator<Item = R>, mut writer: W) -> Result<usize, FramingError>
+where
+    R: BufRead,
+    W: Write
+{
+    let mut count = 0;
+
+    for reader in readers {
+        for item in ArrayItemReader::new(reader) {
+            let separator = if count == 0 { "[\n" } else { ",\n" };
+
+            write!(writer, "{}{}", separator, item?).map_err(FramingError::Io)?;
+            count += 1;
+        }
+    }
+
+    let end = if count == 0 { "[]\n" } else { "\n]\n" };
+
+    writer.write_all(end.as_bytes()).and_then(|_| writer.flush()).map_err(FramingError::Io)?;
+
+    Ok(count)
+}
+
+/// Pick `n` items at random from a stream of them, giving every item the same chance
+/// (reservoir sampling), so only the items picked are ever kept in memory.
+///
+/// Items can come from an `ArrayItemReader` or `NdjsonReader`, or anything else.
+/// The picked items are given back in the order they came in, and
+/// the same items and seed always give the same sample. The first error stops sampling.
+pub fn sample<T, E>(items: impl IntoIterator<Item = Result<T, E>>, n: usize, seed: u64) -> Result<Vec<T>, E> {
+    let mut rng = Rng(seed);
+    let mut picked: Vec<(usize, T)> = Vec::with_capacity(n.min(4096));
+
+    for (i, item) in items.into_iter().enumerate() {
+        let item = item?;
+
+        if i < n {
+            picked.push((i, item));
+            continue;
+        }
+
+        // Keep the new item with a chance of n in (i + 1), in place of a random earlier one.
+        let j = rng.up_to(i);
+
+        if j < n {
+            picked[j] = (i, item);
+        }
+    }
+
+    picked.sort_by_key(|(i, _)| *i);
+
+    Ok(picked.into_iter().map(|(_, item)| item).collect())
+}
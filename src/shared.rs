@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::error::Result;
+use crate::parser::{parse_with_options, Node, ParseOptions};
+use crate::pointer::{parse_index, split_pointer};
+
+/// A JSON document that can be shared between threads and cloned cheaply.
+///
+/// Strings, arrays and objects are behind `Arc`s, so a clone only copies a pointer.
+/// Changing a clone with `set` or `remove` copies just the arrays and objects on the
+/// way down to the change, and everything else stays shared with the original.
+#[derive(Clone, Debug)]
+pub enum SharedNode {
+    Integer(i64),
+    Unsigned(u64),
+    String(Arc<str>),
+    Float(f64),
+    Bool(bool),
+    Null,
+
+    Array(Arc<Vec<SharedNode>>),
+    Object(Arc<HashMap<String, SharedNode>>)
+}
+
+impl From<Node> for SharedNode {
+    fn from(node: Node) -> SharedNode {
+        match node {
+            Node::Integer(n) => SharedNode::Integer(n),
+            Node::Unsigned(n) => SharedNode::Unsigned(n),
+            Node::String(s) => SharedNode::String(s.into()),
+            Node::Float(f) => SharedNode::Float(f),
+            Node::Bool(b) => SharedNode::Bool(b),
+            Node::Null | Node::Empty => SharedNode::Null,
+            Node::Array(arr) => SharedNode::Array(Arc::new(
+                arr.into_iter().map(SharedNode::from).collect()
+            )),
+            Node::Object(map) => SharedNode::Object(Arc::new(
+                map.into_iter().map(|(key, value)| (key, SharedNode::from(value))).collect()
+            ))
+        }
+    }
+}
+
+impl From<&SharedNode> for Node {
+    fn from(node: &SharedNode) -> Node {
+        match node {
+            SharedNode::Integer(n) => Node::Integer(*n),
+            SharedNode::Unsigned(n) => Node::Unsigned(*n),
+            SharedNode::String(s) => Node::String(s.to_string()),
+            SharedNode::Float(f) => Node::Float(*f),
+            SharedNode::Bool(b) => Node::Bool(*b),
+            SharedNode::Null => Node::Null,
+            SharedNode::Array(arr) => Node::Array(arr.iter().map(Node::from).collect()),
+            SharedNode::Object(map) => Node::Object(
+                map.iter().map(|(key, value)| (key.clone(), Node::from(value))).collect()
+            )
+        }
+    }
+}
+
+impl SharedNode {
+    pub fn get(&self, key: &str) -> Option<&SharedNode> {
+        match self {
+            SharedNode::Object(map) => map.get(key),
+            _ => None
+        }
+    }
+
+    /// Look up a value by JSON Pointer, like `/orders/17/price`.
+    pub fn pointer(&self, pointer: &str) -> Option<&SharedNode> {
+        self.pointer_to(&split_pointer(pointer)?)
+    }
+
+    /// Whether two documents are the same one in memory, rather than just equal,
+    /// which is how to tell that part of a document was shared and not copied.
+    pub fn ptr_eq(&self, other: &SharedNode) -> bool {
+        match (self, other) {
+            (SharedNode::String(a), SharedNode::String(b)) => Arc::ptr_eq(a, b),
+            (SharedNode::Array(a), SharedNode::Array(b)) => Arc::ptr_eq(a, b),
+            (SharedNode::Object(a), SharedNode::Object(b)) => Arc::ptr_eq(a, b),
+            _ => false
+        }
+    }
+
+    /// Follow reference tokens down for modification, copying each array and object
+    /// on the way that's shared with another document.
+    fn make_mut(&mut self, tokens: &[String]) -> Option<&mut SharedNode> {
+        let mut current = self;
+
+        for token in tokens {
+            current = match current {
+                SharedNode::Object(map) => Arc::make_mut(map).get_mut(token)?,
+                SharedNode::Array(arr) => Arc::make_mut(arr).get_mut(parse_index(token)?)?,
+                _ => return None
+            };
+        }
+
+        Some(current)
+    }
+
+    /// Set the value at a JSON Pointer, adding it if its object doesn't have the key yet.
+    ///
+    /// An array index one past the end, or `-`, adds to the end of the array.
+    /// Gives back `false`, and changes nothing, if there's nowhere to put the value.
+    pub fn set(&mut self, pointer: &str, value: SharedNode) -> bool {
+        self.put(pointer, value, false)
+    }
+
+    /// Insert a value at a JSON Pointer, moving the items from there on along by one
+    /// if it's in an array. In an object it's the same as `set`.
+    ///
+    /// This is what the `add` operation of JSON Patch does.
+    pub fn insert(&mut self, pointer: &str, value: SharedNode) -> bool {
+        self.put(pointer, value, true)
+    }
+
+    fn put(&mut self, pointer: &str, value: SharedNode, shift: bool) -> bool {
+        let Some(tokens) = split_pointer(pointer) else {
+            return false;
+        };
+
+        let Some((last, parents)) = tokens.split_last() else {
+            *self = value;
+            return true;
+        };
+
+        // Check the way is clear before copying anything.
+        let fits = match self.pointer_to(parents) {
+            Some(SharedNode::Object(_)) => true,
+            Some(SharedNode::Array(arr)) => last == "-" || parse_index(last).is_some_and(|i| i <= arr.len()),
+            _ => false
+        };
+
+        if !fits {
+            return false;
+        }
+
+        match self.make_mut(parents) {
+            Some(SharedNode::Object(map)) => {
+                Arc::make_mut(map).insert(last.clone(), value);
+            },
+            Some(SharedNode::Array(arr)) => {
+                let arr = Arc::make_mut(arr);
+
+                match parse_index(last) {
+                    Some(i) if shift => arr.insert(i, value),
+                    Some(i) if i < arr.len() => arr[i] = value,
+                    _ => arr.push(value)
+                }
+            },
+            _ => unreachable!()
+        }
+
+        true
+    }
+
+    /// Remove the value at a JSON Pointer, giving it back if it was there.
+    ///
+    /// Later items of an array move down to fill the gap.
+    pub fn remove(&mut self, pointer: &str) -> Option<SharedNode> {
+        let tokens = split_pointer(pointer)?;
+        let (last, parents) = tokens.split_last()?;
+
+        // Only copy anything once it's certain there's something to remove.
+        self.pointer_to(&tokens)?;
+
+        match self.make_mut(parents)? {
+            SharedNode::Object(map) => Arc::make_mut(map).remove(last),
+            SharedNode::Array(arr) => Some(Arc::make_mut(arr).remove(parse_index(last)?)),
+            _ => None
+        }
+    }
+
+    fn pointer_to(&self, tokens: &[String]) -> Option<&SharedNode> {
+        let mut current = self;
+
+        for token in tokens {
+            current = match current {
+                SharedNode::Object(map) => map.get(token)?,
+                SharedNode::Array(arr) => arr.get(parse_index(token)?)?,
+                _ => return None
+            };
+        }
+
+        Some(current)
+    }
+}
+
+/// How much `SharedNode::deduplicated` was able to share.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SharingStats {
+    /// How many strings, arrays and objects the document has, counting every copy.
+    pub total: usize,
+
+    /// How many of them were different from all the others, and so are kept in memory.
+    pub unique: usize
+}
+
+impl SharingStats {
+    /// How many strings, arrays and objects were found to be copies and shared instead.
+    pub fn shared(&self) -> usize {
+        self.total - self.unique
+    }
+}
+
+/// One child of an array or object, in a form that can be hashed and compared in one step.
+///
+/// Children are always deduplicated before their parent, so two children are equal
+/// exactly when they're the same allocation, and their addresses can stand in for them.
+#[derive(Hash, PartialEq, Eq)]
+enum Identity {
+    Integer(i64),
+    Unsigned(u64),
+    Float(u64),
+    Bool(bool),
+    Null,
+    Pointer(usize)
+}
+
+fn identity(node: &SharedNode) -> Identity {
+    match node {
+        SharedNode::Integer(n) => Identity::Integer(*n),
+        SharedNode::Unsigned(n) => Identity::Unsigned(*n),
+        SharedNode::Float(f) => Identity::Float(f.to_bits()),
+        SharedNode::Bool(b) => Identity::Bool(*b),
+        SharedNode::Null => Identity::Null,
+        SharedNode::String(s) => Identity::Pointer(Arc::as_ptr(s) as *const u8 as usize),
+        SharedNode::Array(arr) => Identity::Pointer(Arc::as_ptr(arr) as usize),
+        SharedNode::Object(map) => Identity::Pointer(Arc::as_ptr(map) as usize)
+    }
+}
+
+/// What an array or object is made of, used to look up copies of it that have been seen before.
+#[derive(Hash, PartialEq, Eq)]
+enum Shape {
+    String(Arc<str>),
+    Array(Vec<Identity>),
+
+    /// Entries sorted by key, so key order doesn't matter.
+    Object(Vec<(String, Identity)>)
+}
+
+#[derive(Default)]
+struct Interner {
+    seen: HashMap<Shape, SharedNode>,
+    stats: SharingStats
+}
+
+impl Interner {
+    fn intern(&mut self, node: SharedNode, shape: Shape) -> SharedNode {
+        self.stats.total += 1;
+
+        self.seen.entry(shape).or_insert_with(|| {
+            self.stats.unique += 1;
+            node
+        }).clone()
+    }
+
+    fn add(&mut self, node: Node) -> SharedNode {
+        match node {
+            Node::String(s) => {
+                let s: Arc<str> = s.into();
+                self.intern(SharedNode::String(s.clone()), Shape::String(s))
+            },
+            Node::Array(arr) => {
+                let arr: Vec<SharedNode> = arr.into_iter().map(|item| self.add(item)).collect();
+                let shape = Shape::Array(arr.iter().map(identity).collect());
+
+                self.intern(SharedNode::Array(Arc::new(arr)), shape)
+            },
+            Node::Object(map) => {
+                let map: HashMap<String, SharedNode> = map.into_iter().map(|(key, value)| (key, self.add(value))).collect();
+
+                let mut entries: Vec<(String, Identity)> = map.iter().map(|(key, value)| (key.clone(), identity(value))).collect();
+                entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+                self.intern(SharedNode::Object(Arc::new(map)), Shape::Object(entries))
+            },
+            scalar => SharedNode::from(scalar)
+        }
+    }
+}
+
+impl SharedNode {
+    /// Turn a tree into a `SharedNode` where every string, array and object that's
+    /// equal to another is stored once and shared, so memory use reflects only
+    /// the unique content. This suits documents made from templates, with
+    /// thousands of objects that are all the same.
+    ///
+    /// Equal floats are only shared if their bits are the same, so `0.0` and `-0.0` aren't.
+    /// Object keys are still stored in every object, as only whole values are shared.
+    pub fn deduplicated(node: Node) -> (SharedNode, SharingStats) {
+        let mut interner = Interner::default();
+        let shared = interner.add(node);
+
+        (shared, interner.stats)
+    }
+}
+
+/// Parse text into a deduplicated `SharedNode`, as with `SharedNode::deduplicated`.
+pub fn parse_deduplicated(text: &str, options: &ParseOptions) -> Result<(SharedNode, SharingStats)> {
+    let (node, _) = parse_with_options(text, options)?;
+
+    Ok(SharedNode::deduplicated(node))
+}
@@ -0,0 +1,476 @@
+use std::fmt::Display;
+
+use crate::parser::{Node, ObjectMap};
+
+/// The first bytes of every snapshot, then a version number.
+const MAGIC: &[u8; 4] = b"JSNP";
+const VERSION: u32 = 1;
+
+/// The magic, the version, and the offset of the root value.
+const HEADER_LEN: usize = 16;
+
+const TAG_NULL: u8 = 0;
+const TAG_FALSE: u8 = 1;
+const TAG_TRUE: u8 = 2;
+const TAG_INTEGER: u8 = 3;
+const TAG_FLOAT: u8 = 4;
+const TAG_STRING: u8 = 5;
+const TAG_ARRAY: u8 = 6;
+const TAG_OBJECT: u8 = 7;
+const TAG_EMPTY: u8 = 8;
+const TAG_UNSIGNED: u8 = 9;
+
+/// A snapshot that couldn't be read.
+#[derive(Debug)]
+pub struct SnapshotError {
+    /// The byte offset into the snapshot where the problem is.
+    pub offset: usize,
+    pub message: String
+}
+
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "at byte {}: {}", self.offset, self.message)
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+fn error(offset: usize, message: impl Into<String>) -> SnapshotError {
+    SnapshotError {
+        offset,
+        message: message.into()
+    }
+}
+
+fn push_u64(out: &mut Vec<u8>, n: u64) {
+    out.extend_from_slice(&n.to_le_bytes());
+}
+
+/// Write a value after everything inside it, giving back where it starts.
+fn write_value(node: &Node, out: &mut Vec<u8>) -> u64 {
+    let offset = match node {
+        Node::Array(arr) => {
+            let items: Vec<u64> = arr.iter().map(|item| write_value(item, out)).collect();
+            let offset = out.len() as u64;
+
+            out.push(TAG_ARRAY);
+            push_u64(out, items.len() as u64);
+            items.into_iter().for_each(|item| push_u64(out, item));
+
+            return offset;
+        },
+
+        Node::Object(map) => {
+            // Sort the keys so lookups can search for them.
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+
+            let entries: Vec<(u64, u64)> = keys.into_iter().map(|key| {
+                let key_offset = write_value(&Node::String(key.clone()), out);
+                (key_offset, write_value(&map[key], out))
+            }).collect();
+
+            let offset = out.len() as u64;
+
+            out.push(TAG_OBJECT);
+            push_u64(out, entries.len() as u64);
+
+            for (key, value) in entries {
+                push_u64(out, key);
+                push_u64(out, value);
+            }
+
+            return offset;
+        },
+
+        _ => out.len() as u64
+    };
+
+    match node {
+        Node::Null => out.push(TAG_NULL),
+        Node::Bool(false) => out.push(TAG_FALSE),
+        Node::Bool(true) => out.push(TAG_TRUE),
+        Node::Integer(n) => {
+            out.push(TAG_INTEGER);
+            out.extend_from_slice(&n.to_le_bytes());
+        },
+        Node::Unsigned(n) => {
+            out.push(TAG_UNSIGNED);
+            out.extend_from_slice(&n.to_le_bytes());
+        },
+        Node::Float(f) => {
+            out.push(TAG_FLOAT);
+            out.extend_from_slice(&f.to_bits().to_le_bytes());
+        },
+        Node::String(s) => {
+            out.push(TAG_STRING);
+            push_u64(out, s.len() as u64);
+            out.extend_from_slice(s.as_bytes());
+        },
+        _ => out.push(TAG_EMPTY)
+    }
+
+    offset
+}
+
+impl Node {
+    /// Write the tree into one buffer that `Snapshot` can read values out of in place,
+    /// so a parsed document can be handed to another process, like through shared memory,
+    /// without being turned back into text.
+    ///
+    /// Everything in the buffer is found by its offset from the start, so it can be
+    /// copied or mapped anywhere. Numbers are little-endian, and object keys are sorted.
+    pub fn to_snapshot(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(HEADER_LEN);
+
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+        push_u64(&mut out, 0);
+
+        let root = write_value(self, &mut out);
+        out[8..HEADER_LEN].copy_from_slice(&root.to_le_bytes());
+
+        out
+    }
+}
+
+/// A tree written by `Node::to_snapshot`, read in place without copying.
+///
+/// The whole buffer is checked when the snapshot is made, so reading values out of it
+/// afterwards can't fail, even if the bytes came from somewhere untrusted.
+#[derive(Clone, Copy, Debug)]
+pub struct Snapshot<'a> {
+    bytes: &'a [u8],
+    root: usize
+}
+
+impl<'a> Snapshot<'a> {
+    pub fn new(bytes: &'a [u8]) -> Result<Snapshot<'a>, SnapshotError> {
+        if bytes.len() < HEADER_LEN || &bytes[..4] != MAGIC {
+            return Err(error(0, "not a snapshot"));
+        }
+
+        let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+
+        if version != VERSION {
+            return Err(error(4, format!("snapshot is version {}, but only version {} can be read", version, VERSION)));
+        }
+
+        let snapshot = Snapshot {
+            bytes,
+            root: read_u64(bytes, 8).map_err(|_| error(8, "snapshot is cut off"))?
+        };
+
+        snapshot.check(snapshot.root, bytes.len())?;
+
+        Ok(snapshot)
+    }
+
+    pub fn root(&self) -> ValueRef<'a> {
+        value_at(self.bytes, self.root)
+    }
+
+    /// Check the value at `offset` and everything inside it, where each value
+    /// has to come before `before`. Values always come after what's inside them,
+    /// so this keeps bad offsets from making loops.
+    fn check(&self, offset: usize, before: usize) -> Result<(), SnapshotError> {
+        if offset < HEADER_LEN || offset >= before {
+            return Err(error(offset, "offset points outside of where the value can be"));
+        }
+
+        let bytes = &self.bytes[..before];
+        let cut_off = || error(offset, "value is cut off");
+
+        match bytes[offset] {
+            TAG_NULL | TAG_FALSE | TAG_TRUE | TAG_EMPTY => Ok(()),
+            TAG_INTEGER | TAG_UNSIGNED | TAG_FLOAT => read_u64(bytes, offset + 1).map(|_| ()).map_err(|_| cut_off()),
+            TAG_STRING => {
+                let len = read_u64(bytes, offset + 1).map_err(|_| cut_off())?;
+                let text = len.checked_add(offset + 9).and_then(|end| bytes.get(offset + 9..end)).ok_or_else(cut_off)?;
+
+                std::str::from_utf8(text).map(|_| ()).map_err(|_| error(offset, "string isn't valid UTF-8"))
+            },
+            TAG_ARRAY | TAG_OBJECT => {
+                let is_object = bytes[offset] == TAG_OBJECT;
+                let len = read_u64(bytes, offset + 1).map_err(|_| cut_off())?;
+                let slots = if is_object { len.checked_mul(2) } else { Some(len) };
+
+                let end = slots.and_then(|n| n.checked_mul(8)).and_then(|n| n.checked_add(offset + 9)).ok_or_else(cut_off)?;
+
+                if end > bytes.len() {
+                    return Err(cut_off());
+                }
+
+                let mut last_key: Option<&str> = None;
+
+                for slot in 0..slots.unwrap() {
+                    let child = read_u64(bytes, offset + 9 + slot * 8).map_err(|_| error(offset, "offset is too large"))?;
+                    self.check(child, offset)?;
+
+                    if !is_object || slot % 2 == 1 {
+                        continue;
+                    }
+
+                    if self.bytes[child] != TAG_STRING {
+                        return Err(error(child, "object key isn't a string"));
+                    }
+
+                    // Lookups search the keys, so they have to be in order.
+                    let key = string_at(self.bytes, child);
+
+                    if last_key.is_some_and(|last| last >= key) {
+                        return Err(error(child, "object keys aren't sorted"));
+                    }
+
+                    last_key = Some(key);
+                }
+
+                Ok(())
+            },
+            tag => Err(error(offset, format!("unknown value tag {}", tag)))
+        }
+    }
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Result<usize, ()> {
+    let slice = bytes.get(offset..offset.checked_add(8).ok_or(())?).ok_or(())?;
+    usize::try_from(u64::from_le_bytes(slice.try_into().unwrap())).map_err(|_| ())
+}
+
+/// Read a value that's already been checked.
+fn value_at(bytes: &[u8], offset: usize) -> ValueRef<'_> {
+    let raw = || u64::from_le_bytes(bytes[offset + 1..offset + 9].try_into().unwrap());
+
+    match bytes[offset] {
+        TAG_NULL => ValueRef::Null,
+        TAG_FALSE => ValueRef::Bool(false),
+        TAG_TRUE => ValueRef::Bool(true),
+        TAG_INTEGER => ValueRef::Integer(raw() as i64),
+        TAG_UNSIGNED => ValueRef::Unsigned(raw()),
+        TAG_FLOAT => ValueRef::Float(f64::from_bits(raw())),
+        TAG_STRING => ValueRef::String(string_at(bytes, offset)),
+        TAG_ARRAY => ValueRef::Array(ArrayRef { bytes, offset, len: raw() as usize }),
+        TAG_OBJECT => ValueRef::Object(ObjectRef { bytes, offset, len: raw() as usize }),
+        _ => ValueRef::Empty
+    }
+}
+
+fn string_at(bytes: &[u8], offset: usize) -> &str {
+    let len = read_u64(bytes, offset + 1).unwrap();
+
+    // Checked to be UTF-8 when the snapshot was made.
+    std::str::from_utf8(&bytes[offset + 9..offset + 9 + len]).unwrap()
+}
+
+/// A value in a `Snapshot`, borrowing strings straight from its bytes.
+#[derive(Clone, Copy, Debug)]
+pub enum ValueRef<'a> {
+    Integer(i64),
+    Unsigned(u64),
+    String(&'a str),
+    Float(f64),
+    Bool(bool),
+    Null,
+
+    Array(ArrayRef<'a>),
+    Object(ObjectRef<'a>),
+
+    Empty
+}
+
+impl<'a> ValueRef<'a> {
+    /// Copy the value and everything inside it out into a `Node`.
+    pub fn to_node(&self) -> Node {
+        match self {
+            ValueRef::Integer(n) => Node::Integer(*n),
+            ValueRef::Unsigned(n) => Node::Unsigned(*n),
+            ValueRef::String(s) => Node::String(s.to_string()),
+            ValueRef::Float(f) => Node::Float(*f),
+            ValueRef::Bool(b) => Node::Bool(*b),
+            ValueRef::Null => Node::Null,
+            ValueRef::Array(arr) => Node::Array(arr.iter().map(|item| item.to_node()).collect()),
+            ValueRef::Object(map) => Node::Object(
+                map.iter().map(|(key, value)| (key.to_string(), value.to_node())).collect::<ObjectMap>()
+            ),
+            ValueRef::Empty => Node::Empty
+        }
+    }
+}
+
+/// An array in a `Snapshot`.
+#[derive(Clone, Copy, Debug)]
+pub struct ArrayRef<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    len: usize
+}
+
+impl<'a> ArrayRef<'a> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, index: usize) -> Option<ValueRef<'a>> {
+        (index < self.len).then(|| value_at(self.bytes, read_u64(self.bytes, self.offset + 9 + index * 8).unwrap()))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = ValueRef<'a>> + 'a {
+        let array = *self;
+        (0..self.len).map(move |i| array.get(i).unwrap())
+    }
+}
+
+/// An object in a `Snapshot`, with its keys in sorted order.
+#[derive(Clone, Copy, Debug)]
+pub struct ObjectRef<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    len: usize
+}
+
+impl<'a> ObjectRef<'a> {
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The key and value of the `index`th entry, in sorted order.
+    fn entry(&self, index: usize) -> (&'a str, ValueRef<'a>) {
+        let slot = self.offset + 9 + index * 16;
+
+        let key = read_u64(self.bytes, slot).unwrap();
+        let value = read_u64(self.bytes, slot + 8).unwrap();
+
+        (string_at(self.bytes, key), value_at(self.bytes, value))
+    }
+
+    /// Look up a key, searching the sorted keys rather than going through them all.
+    pub fn get(&self, key: &str) -> Option<ValueRef<'a>> {
+        let (mut low, mut high) = (0, self.len);
+
+        while low < high {
+            let middle = low + (high - low) / 2;
+            let (found, value) = self.entry(middle);
+
+            match found.cmp(key) {
+                std::cmp::Ordering::Equal => return Some(value),
+                std::cmp::Ordering::Less => low = middle + 1,
+                std::cmp::Ordering::Greater => high = middle
+            }
+        }
+
+        None
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, ValueRef<'a>)> + 'a {
+        let object = *self;
+        (0..self.len).map(move |i| object.entry(i))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compare::Tolerance;
+    use crate::parser::parse_str;
+
+    const TEXT: &str = r#"{"b": [1, -2.5, "é", null, true, false, 18446744073709551615], "a": {"z": {}, "y": []}, "": "empty key"}"#;
+
+    fn doc(text: &str) -> Node {
+        parse_str(text).unwrap()
+    }
+
+    #[test]
+    fn snapshots_survive_a_round_trip() {
+        let node = doc(TEXT);
+        let bytes = node.to_snapshot();
+
+        assert!(Snapshot::new(&bytes).unwrap().root().to_node().approx_eq(&node, &Tolerance::exact()));
+        assert!(matches!(Snapshot::new(&Node::Empty.to_snapshot()).unwrap().root(), ValueRef::Empty));
+    }
+
+    #[test]
+    fn values_are_read_in_place() {
+        let bytes = doc(TEXT).to_snapshot();
+
+        // Offsets are from the start of the snapshot, so it can be copied anywhere.
+        let mut moved = vec![0; 3];
+        moved.extend_from_slice(&bytes);
+
+        let ValueRef::Object(root) = Snapshot::new(&moved[3..]).unwrap().root() else { panic!() };
+
+        assert_eq!(root.iter().map(|(key, _)| key).collect::<Vec<_>>(), ["", "a", "b"]);
+        assert!(matches!(root.get(""), Some(ValueRef::String("empty key"))));
+        assert!(root.get("c").is_none());
+
+        let Some(ValueRef::Array(b)) = root.get("b") else { panic!() };
+        assert_eq!(b.len(), 7);
+        assert!(matches!(b.get(2), Some(ValueRef::String("é"))));
+        assert!(matches!(b.get(6), Some(ValueRef::Unsigned(u64::MAX))));
+        assert!(b.get(7).is_none());
+
+        let Some(ValueRef::Object(a)) = root.get("a") else { panic!() };
+        assert!(matches!(a.get("y"), Some(ValueRef::Array(y)) if y.is_empty()));
+    }
+
+    #[test]
+    fn bad_snapshots_are_errors() {
+        let bytes = doc(TEXT).to_snapshot();
+
+        assert_eq!(Snapshot::new(b"JSON").unwrap_err().offset, 0);
+
+        let mut version = bytes.clone();
+        version[4] = 2;
+        assert_eq!(Snapshot::new(&version).unwrap_err().offset, 4);
+
+        let mut root = bytes.clone();
+        root[8..16].copy_from_slice(&(bytes.len() as u64).to_le_bytes());
+        assert!(Snapshot::new(&root).is_err());
+
+        let mut tag = bytes.clone();
+        let offset = u64::from_le_bytes(bytes[8..16].try_into().unwrap()) as usize;
+        tag[offset] = 42;
+        assert_eq!(Snapshot::new(&tag).unwrap_err().message, "unknown value tag 42");
+    }
+
+    #[test]
+    fn unsorted_keys_are_errors() {
+        let mut bytes = doc(r#"{"a": 1, "b": 2}"#).to_snapshot();
+        let root = u64::from_le_bytes(bytes[8..16].try_into().unwrap()) as usize;
+
+        // Swap the two entries around.
+        let entries = root + 9;
+        let (first, second) = bytes[entries..entries + 32].split_at_mut(16);
+        first.swap_with_slice(second);
+
+        assert_eq!(Snapshot::new(&bytes).unwrap_err().message, "object keys aren't sorted");
+    }
+
+    #[test]
+    fn damaged_snapshots_never_panic() {
+        let bytes = doc(TEXT).to_snapshot();
+
+        for len in 0..bytes.len() {
+            assert!(Snapshot::new(&bytes[..len]).is_err(), "{}", len);
+        }
+
+        for i in 0..bytes.len() {
+            for flip in [0x01, 0x80, 0xff] {
+                let mut damaged = bytes.clone();
+                damaged[i] ^= flip;
+
+                if let Ok(snapshot) = Snapshot::new(&damaged) {
+                    snapshot.root().to_node();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use std::collections::{HashMap, HashSet};
+use std::mem::size_of;
+
+use crate::error::{ErrorCode, JsonError, Result};
+use crate::lexer::Lexer;
+use crate::memory::{array_bytes, object_bytes};
+use crate::parser::Node;
+use crate::token::{Token, TokenType as TT};
+use crate::utils::{index_path, key_path};
+
+/// How many values of each type a document has.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TypeCounts {
+    pub objects: usize,
+    pub arrays: usize,
+    pub strings: usize,
+    pub integers: usize,
+    pub floats: usize,
+    pub booleans: usize,
+    pub nulls: usize
+}
+
+impl TypeCounts {
+    pub fn total(&self) -> usize {
+        self.objects + self.arrays + self.strings + self.integers + self.floats + self.booleans + self.nulls
+    }
+}
+
+/// A value found by `stats` and how big it is.
+#[derive(Clone, Debug)]
+pub struct LargeValue {
+    /// Where the value is, like `$.users[2]`.
+    pub path: String,
+
+    /// Bytes of text for values, or items for arrays.
+    pub size: usize
+}
+
+/// A key that an object has more than once.
+#[derive(Clone, Debug)]
+pub struct DuplicateKey {
+    /// Where the repeated key is, like `$.users[2].name`.
+    pub path: String,
+    pub line: u32,
+    pub column: u32
+}
+
+/// What a document is made of, from `stats`.
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
+    pub types: TypeCounts,
+
+    /// How many times each key is used, across every object.
+    pub key_counts: HashMap<String, usize>,
+
+    /// How many values there are at each depth, where the top-level value is at depth 0.
+    pub depths: Vec<usize>,
+
+    /// The values that take up the most text, biggest first, not counting the top-level value.
+    pub largest_values: Vec<LargeValue>,
+
+    /// The arrays with the most items, biggest first.
+    pub largest_arrays: Vec<LargeValue>,
+
+    pub duplicate_keys: Vec<DuplicateKey>,
+
+    /// About how many bytes parsing the document into a `Node` would take,
+    /// worked out the same way as `Node::deep_size_of`.
+    pub estimated_tree_bytes: usize
+}
+
+/// Add to a list kept biggest first, only keeping the top `top`.
+fn add_largest(list: &mut Vec<LargeValue>, top: usize, path: &str, size: usize) {
+    if list.len() == top && list.last().is_none_or(|last| last.size >= size) {
+        return;
+    }
+
+    let at = list.partition_point(|item| item.size >= size);
+    list.insert(at, LargeValue { path: path.to_string(), size });
+    list.truncate(top);
+}
+
+struct Walker<'a> {
+    lexer: Lexer<'a>,
+    top: usize,
+    stats: Stats
+}
+
+impl<'a> Walker<'a> {
+    fn next(&mut self, expected: &[TT]) -> Result<Token> {
+        match self.lexer.next_token()? {
+            Some(t) => Ok(t),
+            None => {
+                let message = format!("Found the end of the input, but expected {}", expected.iter().map(TT::describe).collect::<Vec<_>>().join(" or "));
+
+                Err(JsonError::at_pos(message, &self.lexer.pos())
+                    .with_code(ErrorCode::UnexpectedEof)
+                    .with_offset(self.lexer.offset()))
+            }
+        }
+    }
+
+    /// Take in a whole value, starting from its first token.
+    fn value(&mut self, token: Token, path: &str, depth: usize) -> Result<()> {
+        if self.stats.depths.len() <= depth {
+            self.stats.depths.push(0);
+        }
+
+        self.stats.depths[depth] += 1;
+
+        let types = &mut self.stats.types;
+
+        match token.tok_type {
+            TT::LSqBrac => {
+                types.arrays += 1;
+
+                let mut count = 0;
+                let mut next = self.next(&[TT::LBrace, TT::LSqBrac, TT::String, TT::Int, TT::Float, TT::Name, TT::RSqBrac])?;
+
+                if next.tok_type != TT::RSqBrac {
+                    loop {
+                        self.value(next, &index_path(path, count), depth + 1)?;
+                        count += 1;
+
+                        let after = self.next(&[TT::Comma, TT::RSqBrac])?;
+
+                        match after.tok_type {
+                            TT::Comma => next = self.next(TT::VALUE_START)?,
+                            TT::RSqBrac => break,
+                            _ => return Err(JsonError::unexpected(&[TT::Comma, TT::RSqBrac], &after))
+                        }
+                    }
+                }
+
+                add_largest(&mut self.stats.largest_arrays, self.top, path, count);
+                self.stats.estimated_tree_bytes += array_bytes(count);
+            },
+
+            TT::LBrace => {
+                types.objects += 1;
+
+                let mut seen = HashSet::new();
+                let mut key = self.next(&[TT::String, TT::RBrace])?;
+
+                if key.tok_type != TT::RBrace {
+                    loop {
+                        if key.tok_type != TT::String {
+                            return Err(JsonError::unexpected(&[TT::String], &key));
+                        }
+
+                        let value_path = key_path(path, key.text());
+
+                        *self.stats.key_counts.entry(key.text().to_string()).or_default() += 1;
+
+                        if seen.insert(key.text().to_string()) {
+                            self.stats.estimated_tree_bytes += key.text().len();
+                        }
+                        else {
+                            self.stats.duplicate_keys.push(DuplicateKey {
+                                path: value_path.clone(),
+                                line: key.line(),
+                                column: key.column()
+                            });
+                        }
+
+                        let colon = self.next(&[TT::Colon])?;
+
+                        if colon.tok_type != TT::Colon {
+                            return Err(JsonError::unexpected(&[TT::Colon], &colon));
+                        }
+
+                        let value = self.next(TT::VALUE_START)?;
+                        self.value(value, &value_path, depth + 1)?;
+
+                        let after = self.next(&[TT::Comma, TT::RBrace])?;
+
+                        match after.tok_type {
+                            TT::Comma => key = self.next(&[TT::String])?,
+                            TT::RBrace => break,
+                            _ => return Err(JsonError::unexpected(&[TT::Comma, TT::RBrace], &after))
+                        }
+                    }
+                }
+
+                // Repeated keys replace each other, so they only take up one entry.
+                self.stats.estimated_tree_bytes += object_bytes(seen.len());
+            },
+
+            TT::String => {
+                types.strings += 1;
+                self.stats.estimated_tree_bytes += token.text().len();
+            },
+            TT::Int => types.integers += 1,
+            TT::Float => types.floats += 1,
+
+            TT::Name => match token.text() {
+                "true" | "false" => types.booleans += 1,
+                "null" => types.nulls += 1,
+
+                name => return Err(JsonError::at_token(
+                    format!("Failed to parse undefined name: {:?}", name),
+                    &token
+                ).with_code(ErrorCode::InvalidLiteral))
+            },
+
+            _ => return Err(JsonError::unexpected(TT::VALUE_START, &token))
+        }
+
+        if depth > 0 {
+            let size = self.lexer.offset() - token.offset();
+            add_largest(&mut self.stats.largest_values, self.top, path, size);
+        }
+
+        Ok(())
+    }
+}
+
+/// Work out what a document is made of: how many of each type of value it has,
+/// how often each key is used, how deeply it's nested, its biggest values and arrays
+/// (the `top` of each), and which objects repeat a key.
+///
+/// The text is read in one pass without building a tree, so it works on documents
+/// too big to parse. Repeated keys are found in the text, since a parsed `Node` only keeps one.
+pub fn stats(text: &str, top: usize) -> Result<Stats> {
+    let mut walker = Walker {
+        lexer: Lexer::new(text),
+        top,
+        stats: Stats::default()
+    };
+
+    if let Some(first) = walker.lexer.next_token()? {
+        walker.stats.estimated_tree_bytes += size_of::<Node>();
+        walker.value(first, "$", 0)?;
+
+        if let Some(t) = walker.lexer.next_token()? {
+            return Err(JsonError::unexpected(&[], &t));
+        }
+    }
+
+    Ok(walker.stats)
+}
@@ -0,0 +1,196 @@
+use std::fmt::Display;
+
+use crate::parser::{Node, ObjectMap};
+use crate::refs::internal_ref;
+use crate::utils::{index_path, key_path};
+
+/// A placeholder in a template that couldn't be substituted.
+#[derive(Debug)]
+pub struct TemplateError {
+    /// Where the placeholder is in the template, like `$.server.home`.
+    pub path: String,
+    pub message: String
+}
+
+impl Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "at {}: {}", self.path, self.message)
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// Find a variable by a dotted path like `env.HOME` or `servers.0.name`.
+fn lookup<'a>(vars: &'a Node, name: &str) -> Option<&'a Node> {
+    let mut current = vars;
+
+    for part in name.split('.') {
+        current = match current {
+            Node::Object(map) => map.get(part)?,
+            Node::Array(arr) => arr.get(part.parse::<usize>().ok()?)?,
+            _ => return None
+        };
+    }
+
+    Some(current)
+}
+
+fn interpolate(s: &str, vars: &Node, path: &str) -> Result<Node, TemplateError> {
+    let error = |message: String| TemplateError {
+        path: path.to_string(),
+        message
+    };
+
+    // A string that is only a placeholder takes on the variable's value as-is,
+    // so `"${port}"` can become a number instead of a string.
+    if let Some(name) = s.strip_prefix("${").and_then(|x| x.strip_suffix('}'))
+        && !name.contains('}')
+    {
+        return match lookup(vars, name) {
+            Some(value) => Ok(value.clone()),
+            None => Err(error(format!("undefined variable {:?}", name)))
+        };
+    }
+
+    let mut result = String::new();
+    let mut rest = s;
+
+    while let Some(start) = rest.find('$') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        // `$${` is an escaped `${`.
+        if let Some(after) = rest.strip_prefix("$${") {
+            result.push_str("${");
+            rest = after;
+            continue;
+        }
+
+        let Some(after) = rest.strip_prefix("${") else {
+            result.push('$');
+            rest = &rest[1..];
+            continue;
+        };
+
+        let end = match after.find('}') {
+            Some(x) => x,
+            None => return Err(error("unterminated placeholder".to_string()))
+        };
+
+        let name = &after[..end];
+
+        match lookup(vars, name) {
+            Some(Node::String(x)) => result.push_str(x),
+            Some(value @ (Node::Integer(_) | Node::Unsigned(_) | Node::Float(_) | Node::Bool(_) | Node::Null)) => {
+                result.push_str(&format!("{:?}", value));
+            },
+            Some(_) => return Err(error(format!("variable {:?} cannot be inserted into a string", name))),
+            None => return Err(error(format!("undefined variable {:?}", name)))
+        }
+
+        rest = &after[end + 1..];
+    }
+
+    result.push_str(rest);
+
+    Ok(Node::String(result))
+}
+
+fn resolve_node(node: &Node, root: &Node, vars: &Node, path: &str, stack: &mut Vec<String>) -> Result<Node, TemplateError> {
+    let error = |message: String| TemplateError {
+        path: path.to_string(),
+        message
+    };
+
+    // An object like `{"$ref": "#/defs/x"}` is replaced by the value it points to
+    // in the template, with that value's own placeholders substituted.
+    if let Some(pointer) = internal_ref(node).map_err(&error)? {
+        if stack.contains(&pointer) {
+            return Err(error(format!("reference to {:?} is circular", pointer)));
+        }
+
+        let Some(target) = root.pointer(&pointer) else {
+            return Err(error(format!("reference {:?} does not point to a value", pointer)));
+        };
+
+        stack.push(pointer);
+        let resolved = resolve_node(target, root, vars, path, stack)?;
+        stack.pop();
+
+        return Ok(resolved);
+    }
+
+    let resolved = match node {
+        Node::String(s) => interpolate(s, vars, path)?,
+
+        Node::Array(arr) => {
+            let mut body = Vec::with_capacity(arr.len());
+
+            for (i, item) in arr.iter().enumerate() {
+                body.push(resolve_node(item, root, vars, &index_path(path, i), stack)?);
+            }
+
+            Node::Array(body)
+        },
+
+        Node::Object(map) => {
+            let mut body = ObjectMap::with_capacity_and_hasher(map.len(), Default::default());
+
+            for (key, value) in map {
+                body.insert(key.clone(), resolve_node(value, root, vars, &key_path(path, key), stack)?);
+            }
+
+            Node::Object(body)
+        },
+
+        other => other.clone()
+    };
+
+    Ok(resolved)
+}
+
+impl Node {
+    /// Substitute placeholders in this template with values from `vars`.
+    ///
+    /// - A string that is only `"${a.b}"` becomes the value at that dotted path.
+    /// - Placeholders inside other text are inserted as text, and `$${` gives a literal `${`.
+    /// - An object like `{"$ref": "#/defs/x"}` becomes the value at that JSON Pointer
+    ///   in this template, resolved in turn. References to other documents are left alone.
+    pub fn resolve(&self, vars: &Node) -> Result<Node, TemplateError> {
+        resolve_node(self, self, vars, "$", &mut Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compare::Tolerance;
+    use crate::parser::parse_str;
+
+    fn doc(text: &str) -> Node {
+        parse_str(text).unwrap()
+    }
+
+    #[test]
+    fn refs_point_into_the_template() {
+        let template = doc(r##"{"defs": {"a/b": {"port": "${port}"}}, "server": {"$ref": "#/defs/a~1b"}, "other": {"$ref": "#/defs/a%7E1b"}}"##);
+        let resolved = template.resolve(&doc(r#"{"port": 80, "defs": {}}"#)).unwrap();
+
+        assert!(resolved.get("server").unwrap().approx_eq(&doc(r#"{"port": 80}"#), &Tolerance::exact()));
+        assert!(resolved.get("other").unwrap().approx_eq(&doc(r#"{"port": 80}"#), &Tolerance::exact()));
+    }
+
+    #[test]
+    fn bad_refs_are_errors() {
+        assert_eq!(doc(r##"{"a": {"$ref": "#/missing"}}"##).resolve(&doc("{}")).unwrap_err().path, "$.a");
+        assert!(doc(r##"{"a": {"$ref": "#/a"}}"##).resolve(&doc("{}")).unwrap_err().message.contains("circular"));
+    }
+
+    #[test]
+    fn placeholders_come_from_vars() {
+        let resolved = doc(r#"["${a.0}", "x${b}y", "$${b}"]"#).resolve(&doc(r#"{"a": [true], "b": 1}"#)).unwrap();
+
+        assert!(resolved.approx_eq(&doc(r#"[true, "x1y", "${b}"]"#), &Tolerance::exact()));
+        assert!(doc(r#""${c}""#).resolve(&doc("{}")).is_err());
+    }
+}
@@ -1,7 +1,7 @@
 
 use std::fmt::{Debug, Display};
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum TokenType {
     Int,
     String,
@@ -15,23 +15,185 @@ pub enum TokenType {
     Colon
 }
 
+impl TokenType {
+    /// The token types a value can start with.
+    pub const VALUE_START: &[TokenType] = &[
+        TokenType::LBrace,
+        TokenType::LSqBrac,
+        TokenType::String,
+        TokenType::Int,
+        TokenType::Float,
+        TokenType::Name
+    ];
+
+    /// The token type of a punctuation character, like `LBrace` for `{`.
+    pub fn from_punctuation(ch: char) -> Option<TokenType> {
+        match ch {
+            '{' => Some(TokenType::LBrace),
+            '}' => Some(TokenType::RBrace),
+            '[' => Some(TokenType::LSqBrac),
+            ']' => Some(TokenType::RSqBrac),
+            ',' => Some(TokenType::Comma),
+            ':' => Some(TokenType::Colon),
+            _ => None
+        }
+    }
+
+    /// How punctuation is written, like `{` for `LBrace`, or `None` for token types with text of their own.
+    pub fn symbol(&self) -> Option<&'static str> {
+        match self {
+            TokenType::LBrace => Some("{"),
+            TokenType::RBrace => Some("}"),
+            TokenType::LSqBrac => Some("["),
+            TokenType::RSqBrac => Some("]"),
+            TokenType::Comma => Some(","),
+            TokenType::Colon => Some(":"),
+            _ => None
+        }
+    }
+
+    /// How the token type is written in error messages, like `','` or `a string`.
+    pub fn describe(&self) -> &'static str {
+        match self {
+            TokenType::Int | TokenType::Float => "a number",
+            TokenType::String => "a string",
+            TokenType::Name => "true/false/null",
+            TokenType::LSqBrac => "'['",
+            TokenType::RSqBrac => "']'",
+            TokenType::LBrace => "'{'",
+            TokenType::RBrace => "'}'",
+            TokenType::Comma => "','",
+            TokenType::Colon => "':'"
+        }
+    }
+}
+
+/// What a token holds beyond its type.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Payload {
+    /// Punctuation, which is nothing more than its type.
+    None,
+
+    /// A number as it was written, so no precision is lost until it's converted.
+    Number {
+        raw: String,
+        is_float: bool
+    },
+
+    /// A string, with its escape sequences already decoded.
+    String {
+        decoded: String
+    },
+
+    /// A bare word, like `true` or `null`.
+    Name(String)
+}
+
+#[derive(Clone)]
 pub struct Token {
     line_no: u32,
     col_no: u32,
+    offset: usize,
     pub tok_type: TokenType,
-    pub value: String
+    pub payload: Payload
 }
 
 impl Token {
+    /// Build a token from its type and text, where `value` is the number as written,
+    /// the decoded contents of a string, the name, or the punctuation character.
+    #[inline]
     pub fn new(tok_type: TokenType, value: String, line: u32, column: u32) -> Token {
+        let payload = match tok_type {
+            TokenType::Int | TokenType::Float => Payload::Number {
+                raw: value,
+                is_float: tok_type == TokenType::Float
+            },
+            TokenType::String => Payload::String {
+                decoded: value
+            },
+            TokenType::Name => Payload::Name(value),
+            _ => Payload::None
+        };
+
         Token {
-            tok_type: tok_type,
-            value: value,
+            tok_type,
+            payload,
             line_no: line,
-            col_no: column
+            col_no: column,
+            offset: 0
+        }
+    }
+
+    /// A punctuation token, like `{` or `,`, or `None` if `ch` isn't punctuation.
+    ///
+    /// Like the other constructors, this is at line 1, column 1, unless it's moved with `at`.
+    pub fn punctuation(ch: char) -> Option<Token> {
+        TokenType::from_punctuation(ch).map(|tok_type| Token::new(tok_type, String::new(), 1, 1))
+    }
+
+    /// A string token, holding its contents with any escape sequences already decoded.
+    pub fn string(decoded: impl Into<String>) -> Token {
+        Token::new(TokenType::String, decoded.into(), 1, 1)
+    }
+
+    /// An integer token, holding the number as it's written, like `-12`.
+    pub fn integer(raw: impl Into<String>) -> Token {
+        Token::new(TokenType::Int, raw.into(), 1, 1)
+    }
+
+    /// A float token, holding the number as it's written, like `1.5e3`.
+    pub fn float(raw: impl Into<String>) -> Token {
+        Token::new(TokenType::Float, raw.into(), 1, 1)
+    }
+
+    /// A name token, like `true`, `false` or `null`.
+    pub fn name(name: impl Into<String>) -> Token {
+        Token::new(TokenType::Name, name.into(), 1, 1)
+    }
+
+    /// Set the line and column of the token, for where errors about it are reported.
+    pub fn at(mut self, line: u32, column: u32) -> Token {
+        self.line_no = line;
+        self.col_no = column;
+        self
+    }
+
+    /// Set the byte offset of the token in its text, which `new` leaves as 0.
+    pub fn with_offset(mut self, offset: usize) -> Token {
+        self.offset = offset;
+        self
+    }
+
+    pub(crate) fn set_pos(&mut self, line: u32, column: u32, offset: usize) {
+        self.line_no = line;
+        self.col_no = column;
+        self.offset = offset;
+    }
+
+    /// The token's text: the number as written, the decoded string,
+    /// the name, or the punctuation character.
+    pub fn text(&self) -> &str {
+        match &self.payload {
+            Payload::Number { raw, .. } => raw,
+            Payload::String { decoded } => decoded,
+            Payload::Name(name) => name,
+            Payload::None => self.tok_type.symbol().unwrap_or("")
         }
     }
 
+    pub fn line(&self) -> u32 {
+        self.line_no
+    }
+
+    pub fn column(&self) -> u32 {
+        self.col_no
+    }
+
+    /// The byte offset of the start of the token in the text it came from.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
     pub fn pos(&self) -> String {
         format!("[Line: {}, Column: {}]", self.line_no, self.col_no)
     }
@@ -39,15 +201,15 @@ impl Token {
 
 impl Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if self.value.len() == 1 {
-            write!(f, "Token({:?})", self.value).unwrap();
+        if self.text().len() == 1 {
+            write!(f, "Token({:?})", self.text()).unwrap();
             return Ok(());
         }
         
         write!(f,
             "Token(type = '{:?}', value = {:?})",
             self.tok_type,
-            self.value
+            self.text()
         ).unwrap();
 
         Ok(())
@@ -59,12 +221,12 @@ impl Debug for Token {
         write!(f,
 "Token(
     type = '{:?}',
-    value = {:?},
+    payload = {:?},
     line = {},
     column = {}
 )",
             self.tok_type,
-            self.value,
+            self.payload,
             self.line_no,
             self.col_no
         ).unwrap();
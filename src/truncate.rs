@@ -0,0 +1,86 @@
+use crate::parser::{Node, ObjectMap};
+
+fn marker(more: usize) -> String {
+    format!("…(+{} more)", more)
+}
+
+fn truncate(node: &Node, max_string_len: usize, max_array_items: usize, depth_left: usize) -> Node {
+    match node {
+        Node::String(s) => {
+            let len = s.chars().count();
+
+            if len <= max_string_len {
+                return node.clone();
+            }
+
+            let mut short: String = s.chars().take(max_string_len).collect();
+            short.push_str(&marker(len - max_string_len));
+
+            Node::String(short)
+        },
+
+        // Containers past the depth limit are replaced by a marker with how many items they held.
+        Node::Array(arr) if depth_left == 0 => Node::String(marker(arr.len())),
+        Node::Object(map) if depth_left == 0 => Node::String(marker(map.len())),
+
+        Node::Array(arr) => {
+            let mut short: Vec<Node> = arr.iter().take(max_array_items).map(
+                |item| truncate(item, max_string_len, max_array_items, depth_left - 1)
+            ).collect();
+
+            if arr.len() > max_array_items {
+                short.push(Node::String(marker(arr.len() - max_array_items)));
+            }
+
+            Node::Array(short)
+        },
+
+        Node::Object(map) => {
+            let short: ObjectMap = map.iter().map(
+                |(key, value)| (key.clone(), truncate(value, max_string_len, max_array_items, depth_left - 1))
+            ).collect();
+
+            Node::Object(short)
+        },
+
+        _ => node.clone()
+    }
+}
+
+impl Node {
+    /// Make an abbreviated copy of this tree that is safe to write to logs.
+    ///
+    /// Strings longer than `max_string_len` characters and arrays with more than
+    /// `max_array_items` items are cut short, ending with a marker like `"…(+1234 more)"`.
+    /// Arrays and objects nested deeper than `max_depth` are replaced by a marker
+    /// with how many items they held.
+    pub fn truncate_for_log(&self, max_string_len: usize, max_array_items: usize, max_depth: usize) -> Node {
+        truncate(self, max_string_len, max_array_items, max_depth)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compare::Tolerance;
+    use crate::parser::parse_str;
+
+    fn doc(text: &str) -> Node {
+        parse_str(text).unwrap()
+    }
+
+    #[test]
+    fn long_strings_and_arrays_are_cut_short() {
+        let node = doc(r#"{"s": "héllo wörld", "a": [1, 2, 3, 4, 5], "short": "ok"}"#).truncate_for_log(5, 2, 10);
+
+        assert!(node.approx_eq(&doc(r#"{"s": "héllo…(+6 more)", "a": [1, 2, "…(+3 more)"], "short": "ok"}"#), &Tolerance::exact()));
+    }
+
+    #[test]
+    fn deep_containers_become_markers() {
+        let node = doc(r#"[{"a": {"b": 1, "c": 2}, "d": [[]], "e": true}]"#).truncate_for_log(100, 100, 2);
+
+        assert!(node.approx_eq(&doc(r#"[{"a": "…(+2 more)", "d": "…(+1 more)", "e": true}]"#), &Tolerance::exact()));
+        assert!(matches!(doc("[1]").truncate_for_log(100, 100, 0), Node::String(s) if s == "…(+1 more)"));
+    }
+}
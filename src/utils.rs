@@ -1,75 +1,119 @@
-use std::{fmt::{Debug, Display}, slice::Iter};
+use std::fmt::{Debug, Display};
 
 use crate::token::Token;
 
 pub struct CharIter<'a> {
-    remaining: &'a str,
+    /// The text left to read, starting with the peeked character.
+    rest: &'a str,
     next: Option<char>
 }
 
 impl<'a> CharIter<'a> {
     pub fn new(s: &'a str) -> CharIter<'a> {
-        let mut chars = s.chars();
-        let next = chars.next();
-
         CharIter {
-            remaining: chars.as_str(),
-            next: next
+            rest: s,
+            next: s.chars().next()
         }
     }
 
+    #[inline]
     pub fn peek(&mut self) -> Option<char> {
         self.next
     }
+
+    /// Look `n` characters past the next one without reading anything,
+    /// so `peek_n(0)` is the same as `peek`.
+    pub fn peek_n(&self, n: usize) -> Option<char> {
+        match n {
+            0 => self.next,
+            _ => self.rest.chars().nth(n)
+        }
+    }
+
+    /// How many bytes of text are left, including the peeked character.
+    #[inline]
+    pub fn remaining_len(&self) -> usize {
+        self.rest.len()
+    }
+
+    /// Take the text from the peeked character up to the first byte `stop` matches,
+    /// or to the end of the text, as one slice.
+    ///
+    /// This looks at bytes rather than characters, so `stop` should only match ASCII bytes,
+    /// which never turn up inside a multi-byte character. Anything else would split a character
+    /// and panic.
+    pub fn take_until(&mut self, stop: impl Fn(u8) -> bool) -> &'a str {
+        let end = self.rest.bytes().position(stop).unwrap_or(self.rest.len());
+        let (run, rest) = self.rest.split_at(end);
+
+        self.rest = rest;
+        self.next = rest.chars().next();
+
+        run
+    }
 }
 
 impl<'a> Iterator for CharIter<'a> {
     type Item = char;
 
+    #[inline]
     fn next(&mut self) -> Option<Self::Item> {
-        let mut chars = self.remaining.chars();
+        let next = self.next?;
 
-        let next = self.next;
-        self.next = chars.next();
-        self.remaining = chars.as_str();
+        self.rest = &self.rest[next.len_utf8()..];
+        self.next = self.rest.chars().next();
 
-        next
+        Some(next)
     }
 }
 
 impl<'a> Debug for CharIter<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if let Some(ch) = self.next {
-            let s = String::from(ch) + self.remaining;
-
-            write!(f, "CharIter({:?})", s).unwrap();
-        }
-        else {
-            write!(f, "CharIter(\"\")").unwrap();
-        }
-
-        Ok(())
+        write!(f, "CharIter({:?})", self.rest)
     }
 }
 
 pub struct TokenIter<'a> {
-    remaining: Iter<'a, Token>,
-    next: Option<&'a Token>
+    tokens: &'a [Token],
+
+    /// The index of the next token.
+    index: usize
 }
 
-impl<'a> TokenIter<'a> {
-    pub fn new(tokens: &'a Vec<Token>) -> TokenIter<'a> {
-        let mut iter = tokens.iter();
-        let next = iter.next();
+/// A place in a `TokenIter` to go back to with `reset`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TokenMark(usize);
 
+impl<'a> TokenIter<'a> {
+    pub fn new(tokens: &'a [Token]) -> TokenIter<'a> {
         TokenIter {
-            remaining: iter,
-            next: next
+            tokens,
+            index: 0
         }
     }
 
     pub fn peek(&self) -> Option<&'a Token> {
-        self.next
+        self.tokens.get(self.index)
+    }
+
+    /// Look `n` tokens past the next one without reading anything,
+    /// so `peek_n(0)` is the same as `peek`.
+    pub fn peek_n(&self, n: usize) -> Option<&'a Token> {
+        self.tokens.get(self.index + n)
+    }
+
+    /// Remember where the iterator is, so it can go back there with `reset`
+    /// if a guess about what comes next turns out to be wrong.
+    pub fn mark(&self) -> TokenMark {
+        TokenMark(self.index)
+    }
+
+    /// Go back to a place from `mark`, so the tokens after it are read again.
+    ///
+    /// Marks from a different `TokenIter` put this one somewhere meaningless,
+    /// and past the end of its tokens if that one had more.
+    pub fn reset(&mut self, mark: TokenMark) {
+        self.index = mark.0;
     }
 }
 
@@ -77,14 +121,14 @@ impl<'a> Iterator for TokenIter<'a> {
     type Item = &'a Token;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let next = self.next;
-        
-        self.next = self.remaining.next();
+        let next = self.tokens.get(self.index)?;
+        self.index += 1;
 
-        next
+        Some(next)
     }
 }
 
+#[derive(Clone, Copy)]
 pub struct Pos {
     pub line: u32,
     pub column: u32
@@ -100,4 +144,116 @@ impl Display for Pos {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Append an object key to a `$`-rooted path like `$.orders[17]`,
+/// quoting any key that isn't a plain identifier.
+pub fn key_path(path: &str, key: &str) -> String {
+    let is_plain = key.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    if is_plain {
+        format!("{}.{}", path, key)
+    }
+    else {
+        format!("{}[{:?}]", path, key)
+    }
+}
+
+/// Append an array index to a `$`-rooted path like `$.orders[17]`.
+pub fn index_path(path: &str, index: usize) -> String {
+    format!("{}[{}]", path, index)
+}
+
+const BASE64_STANDARD: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const BASE64_URL_SAFE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Encode bytes as padded base64, or unpadded base64url if `url_safe` is set.
+pub fn base64_encode(bytes: &[u8], url_safe: bool) -> String {
+    let alphabet = if url_safe { BASE64_URL_SAFE } else { BASE64_STANDARD };
+    let mut result = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let n = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+
+        for i in 0..=chunk.len() {
+            result.push(alphabet[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+
+        if !url_safe {
+            for _ in chunk.len()..3 {
+                result.push('=');
+            }
+        }
+    }
+
+    result
+}
+
+/// Decode base64 in either the standard or URL-safe alphabet, with or without padding.
+pub fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let text = text.trim_end_matches('=');
+    let mut result = Vec::with_capacity(text.len() * 3 / 4);
+
+    let mut buffer = 0u32;
+    let mut bits = 0;
+
+    for byte in text.bytes() {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return None
+        };
+
+        buffer = buffer << 6 | value as u32;
+        bits += 6;
+
+        if bits >= 8 {
+            bits -= 8;
+            result.push((buffer >> bits) as u8);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+
+    // A single leftover character can't make up a whole byte.
+    if bits >= 6 {
+        return None;
+    }
+
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::tokenise;
+
+    #[test]
+    fn char_iter_peeks_ahead() {
+        let mut chars = CharIter::new("aé");
+
+        assert_eq!((chars.peek_n(0), chars.peek_n(1), chars.peek_n(2)), (Some('a'), Some('é'), None));
+        chars.next();
+        assert_eq!(chars.peek_n(0), Some('é'));
+    }
+
+    #[test]
+    fn token_iter_goes_back_to_marks() {
+        let tokens = tokenise("[1, 2]").unwrap();
+        let mut iter = TokenIter::new(&tokens);
+
+        iter.next();
+        let mark = iter.mark();
+
+        assert_eq!(iter.peek_n(2).map(|t| t.text()), Some("2"));
+        assert_eq!(iter.by_ref().count(), 4);
+
+        iter.reset(mark);
+        assert_eq!(iter.peek().map(|t| t.text()), Some("1"));
+    }
+}
@@ -0,0 +1,413 @@
+use std::fmt::Display;
+use std::ops::{Bound, RangeBounds};
+
+use crate::parser::Node;
+use crate::utils::{index_path, key_path};
+
+/// A place where a value didn't pass validation.
+#[derive(Debug)]
+pub struct Violation {
+    /// Where the value is, like `$.users[2].age`.
+    pub path: String,
+    pub message: String
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "at {}: {}", self.path, self.message)
+    }
+}
+
+/// A check that can be run against a `Node`, built up from functions like
+/// `object`, `string` and `integer`:
+///
+/// ```text
+/// object()
+///     .key("name", string().non_empty())
+///     .key("age", integer().range(0..150))
+///     .optional("tags", array().items(string()))
+/// ```
+pub trait Validator {
+    /// Check `node`, which is at `path`, adding anything wrong with it to `violations`.
+    fn check(&self, node: &Node, path: &str, violations: &mut Vec<Violation>);
+
+    /// Check a whole document, giving back every violation in the order found.
+    fn validate(&self, node: &Node) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        self.check(node, "$", &mut violations);
+
+        violations
+    }
+}
+
+/// So validators can be picked at runtime, like from a schema document.
+impl<V: Validator + ?Sized> Validator for Box<V> {
+    fn check(&self, node: &Node, path: &str, violations: &mut Vec<Violation>) {
+        (**self).check(node, path, violations);
+    }
+}
+
+fn violation(violations: &mut Vec<Violation>, path: &str, message: String) {
+    violations.push(Violation {
+        path: path.to_string(),
+        message
+    });
+}
+
+/// What kind of value a node is, for messages like "expected a string, found an integer".
+fn describe(node: &Node) -> &'static str {
+    match node {
+        Node::Integer(_) | Node::Unsigned(_) => "an integer",
+        Node::String(_) => "a string",
+        Node::Float(_) => "a float",
+        Node::Bool(_) => "a bool",
+        Node::Null => "null",
+        Node::Array(_) => "an array",
+        Node::Object(_) => "an object",
+        Node::Empty => "nothing"
+    }
+}
+
+fn wrong_type(violations: &mut Vec<Violation>, path: &str, expected: &str, found: &Node) {
+    violation(violations, path, format!("expected {}, found {}", expected, describe(found)));
+}
+
+fn in_range<T: PartialOrd>(bounds: &(Bound<T>, Bound<T>), value: &T) -> bool {
+    bounds.contains(value)
+}
+
+fn describe_range<T: Display>(bounds: &(Bound<T>, Bound<T>)) -> String {
+    let start = match &bounds.0 {
+        Bound::Included(n) => format!("at least {}", n),
+        Bound::Excluded(n) => format!("more than {}", n),
+        Bound::Unbounded => String::new()
+    };
+
+    let end = match &bounds.1 {
+        Bound::Included(n) => format!("at most {}", n),
+        Bound::Excluded(n) => format!("less than {}", n),
+        Bound::Unbounded => String::new()
+    };
+
+    match (start.is_empty(), end.is_empty()) {
+        (false, false) => format!("{} and {}", start, end),
+        _ => start + &end
+    }
+}
+
+fn to_bounds<T: Copy>(range: impl RangeBounds<T>) -> (Bound<T>, Bound<T>) {
+    (range.start_bound().cloned(), range.end_bound().cloned())
+}
+
+/// Checks for a length, shared by strings and arrays.
+fn check_len(len: usize, min: Option<usize>, max: Option<usize>, unit: &str, path: &str, violations: &mut Vec<Violation>) {
+    let plural = |n: usize| if n == 1 { "" } else { "s" };
+
+    if let Some(min) = min && len < min {
+        violation(violations, path, format!("expected at least {} {}{}, found {}", min, unit, plural(min), len));
+    }
+
+    if let Some(max) = max && len > max {
+        violation(violations, path, format!("expected at most {} {}{}, found {}", max, unit, plural(max), len));
+    }
+}
+
+/// Accepts any value. Made with `any`.
+pub struct AnyValidator;
+
+impl Validator for AnyValidator {
+    fn check(&self, _: &Node, _: &str, _: &mut Vec<Violation>) {}
+}
+
+/// Checks for a string. Made with `string`.
+#[derive(Default)]
+pub struct StringValidator {
+    min_len: Option<usize>,
+    max_len: Option<usize>,
+    one_of: Option<Vec<String>>
+}
+
+impl StringValidator {
+    pub fn non_empty(self) -> StringValidator {
+        self.min_len(1)
+    }
+
+    /// The fewest characters the string can have.
+    pub fn min_len(mut self, len: usize) -> StringValidator {
+        self.min_len = Some(len);
+        self
+    }
+
+    /// The most characters the string can have.
+    pub fn max_len(mut self, len: usize) -> StringValidator {
+        self.max_len = Some(len);
+        self
+    }
+
+    /// Only allow these strings, like an enum.
+    pub fn one_of<S: Into<String>>(mut self, allowed: impl IntoIterator<Item = S>) -> StringValidator {
+        self.one_of = Some(allowed.into_iter().map(Into::into).collect());
+        self
+    }
+}
+
+impl Validator for StringValidator {
+    fn check(&self, node: &Node, path: &str, violations: &mut Vec<Violation>) {
+        let Node::String(s) = node else {
+            return wrong_type(violations, path, "a string", node);
+        };
+
+        check_len(s.chars().count(), self.min_len, self.max_len, "character", path, violations);
+
+        if let Some(allowed) = &self.one_of && !allowed.contains(s) {
+            violation(violations, path, format!("expected one of {:?}, found {:?}", allowed, s));
+        }
+    }
+}
+
+/// Checks for an integer. Made with `integer`.
+pub struct IntegerValidator {
+    range: (Bound<i64>, Bound<i64>)
+}
+
+impl IntegerValidator {
+    /// Only allow integers in a range, like `0..150` or `1..`.
+    pub fn range(mut self, range: impl RangeBounds<i64>) -> IntegerValidator {
+        self.range = to_bounds(range);
+        self
+    }
+}
+
+impl Validator for IntegerValidator {
+    fn check(&self, node: &Node, path: &str, violations: &mut Vec<Violation>) {
+        let n = match node {
+            Node::Integer(n) => n,
+
+            // These are above every `i64`, so only a range with no upper end allows them.
+            Node::Unsigned(n) => {
+                if !matches!(self.range.1, Bound::Unbounded) {
+                    violation(violations, path, format!("expected {}, found {}", describe_range(&self.range), n));
+                }
+
+                return;
+            },
+
+            _ => return wrong_type(violations, path, "an integer", node)
+        };
+
+        if !in_range(&self.range, n) {
+            violation(violations, path, format!("expected {}, found {}", describe_range(&self.range), n));
+        }
+    }
+}
+
+/// Checks for any number, integer or float. Made with `number`.
+pub struct NumberValidator {
+    range: (Bound<f64>, Bound<f64>)
+}
+
+impl NumberValidator {
+    /// Only allow numbers in a range, like `0.0..=1.0`.
+    pub fn range(mut self, range: impl RangeBounds<f64>) -> NumberValidator {
+        self.range = to_bounds(range);
+        self
+    }
+}
+
+impl Validator for NumberValidator {
+    fn check(&self, node: &Node, path: &str, violations: &mut Vec<Violation>) {
+        let n = match node {
+            Node::Integer(n) => *n as f64,
+            Node::Unsigned(n) => *n as f64,
+            Node::Float(f) => *f,
+            _ => return wrong_type(violations, path, "a number", node)
+        };
+
+        if !in_range(&self.range, &n) {
+            violation(violations, path, format!("expected {}, found {}", describe_range(&self.range), n));
+        }
+    }
+}
+
+/// Checks for `true` or `false`. Made with `boolean`.
+pub struct BoolValidator;
+
+impl Validator for BoolValidator {
+    fn check(&self, node: &Node, path: &str, violations: &mut Vec<Violation>) {
+        if !matches!(node, Node::Bool(_)) {
+            wrong_type(violations, path, "a bool", node);
+        }
+    }
+}
+
+/// Checks for an array. Made with `array`.
+#[derive(Default)]
+pub struct ArrayValidator {
+    items: Option<Box<dyn Validator>>,
+    min_len: Option<usize>,
+    max_len: Option<usize>
+}
+
+impl ArrayValidator {
+    /// Check every item of the array.
+    pub fn items(mut self, validator: impl Validator + 'static) -> ArrayValidator {
+        self.items = Some(Box::new(validator));
+        self
+    }
+
+    pub fn non_empty(self) -> ArrayValidator {
+        self.min_len(1)
+    }
+
+    /// The fewest items the array can have.
+    pub fn min_len(mut self, len: usize) -> ArrayValidator {
+        self.min_len = Some(len);
+        self
+    }
+
+    /// The most items the array can have.
+    pub fn max_len(mut self, len: usize) -> ArrayValidator {
+        self.max_len = Some(len);
+        self
+    }
+}
+
+impl Validator for ArrayValidator {
+    fn check(&self, node: &Node, path: &str, violations: &mut Vec<Violation>) {
+        let Node::Array(arr) = node else {
+            return wrong_type(violations, path, "an array", node);
+        };
+
+        check_len(arr.len(), self.min_len, self.max_len, "item", path, violations);
+
+        if let Some(items) = &self.items {
+            for (i, item) in arr.iter().enumerate() {
+                items.check(item, &index_path(path, i), violations);
+            }
+        }
+    }
+}
+
+struct Field {
+    key: String,
+    validator: Box<dyn Validator>,
+    required: bool
+}
+
+/// Checks for an object. Made with `object`.
+#[derive(Default)]
+pub struct ObjectValidator {
+    fields: Vec<Field>,
+    deny_unknown_keys: bool
+}
+
+impl ObjectValidator {
+    /// Require a key, and check its value.
+    pub fn key(mut self, key: impl Into<String>, validator: impl Validator + 'static) -> ObjectValidator {
+        self.fields.push(Field { key: key.into(), validator: Box::new(validator), required: true });
+        self
+    }
+
+    /// Check a key's value if it's there, without requiring it.
+    pub fn optional(mut self, key: impl Into<String>, validator: impl Validator + 'static) -> ObjectValidator {
+        self.fields.push(Field { key: key.into(), validator: Box::new(validator), required: false });
+        self
+    }
+
+    /// Don't allow any keys that haven't been given with `key` or `optional`.
+    pub fn deny_unknown_keys(mut self) -> ObjectValidator {
+        self.deny_unknown_keys = true;
+        self
+    }
+}
+
+impl Validator for ObjectValidator {
+    fn check(&self, node: &Node, path: &str, violations: &mut Vec<Violation>) {
+        let Node::Object(map) = node else {
+            return wrong_type(violations, path, "an object", node);
+        };
+
+        for field in &self.fields {
+            match map.get(&field.key) {
+                Some(value) => field.validator.check(value, &key_path(path, &field.key), violations),
+                None if field.required => violation(violations, &key_path(path, &field.key), "key is missing".to_string()),
+                None => {}
+            }
+        }
+
+        if self.deny_unknown_keys {
+            // Sort the keys so the violations don't depend on hashing order.
+            let mut unknown: Vec<&String> = map.keys().filter(
+                |key| !self.fields.iter().any(|field| field.key == **key)
+            ).collect();
+
+            unknown.sort();
+
+            for key in unknown {
+                violation(violations, &key_path(path, key), "key was not expected".to_string());
+            }
+        }
+    }
+}
+
+/// Checks for `null`. Made with `null`.
+pub struct NullValidator;
+
+impl Validator for NullValidator {
+    fn check(&self, node: &Node, path: &str, violations: &mut Vec<Violation>) {
+        if !matches!(node, Node::Null) {
+            wrong_type(violations, path, "null", node);
+        }
+    }
+}
+
+/// Allows `null` as well as anything the inner validator allows. Made with `nullable`.
+pub struct NullableValidator<V: Validator>(V);
+
+impl<V: Validator> Validator for NullableValidator<V> {
+    fn check(&self, node: &Node, path: &str, violations: &mut Vec<Violation>) {
+        if !matches!(node, Node::Null) {
+            self.0.check(node, path, violations);
+        }
+    }
+}
+
+pub fn any() -> AnyValidator {
+    AnyValidator
+}
+
+pub fn string() -> StringValidator {
+    StringValidator::default()
+}
+
+pub fn integer() -> IntegerValidator {
+    IntegerValidator {
+        range: (Bound::Unbounded, Bound::Unbounded)
+    }
+}
+
+pub fn number() -> NumberValidator {
+    NumberValidator {
+        range: (Bound::Unbounded, Bound::Unbounded)
+    }
+}
+
+pub fn boolean() -> BoolValidator {
+    BoolValidator
+}
+
+pub fn null() -> NullValidator {
+    NullValidator
+}
+
+pub fn array() -> ArrayValidator {
+    ArrayValidator::default()
+}
+
+pub fn object() -> ObjectValidator {
+    ObjectValidator::default()
+}
+
+pub fn nullable<V: Validator>(validator: V) -> NullableValidator<V> {
+    NullableValidator(validator)
+}
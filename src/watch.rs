@@ -0,0 +1,258 @@
+use std::collections::HashMap;
+use std::fs::read_dir;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::cache::{load, stamp, CacheError};
+use crate::glob::wildcard_match;
+use crate::parser::Node;
+
+#[derive(Clone, Copy)]
+pub struct WatchOptions {
+    /// How often to check whether the file has changed.
+    pub poll_interval: Duration,
+
+    /// How long the file has to stay the same after a change before it's read,
+    /// so a save that takes a few writes is only delivered once.
+    pub debounce: Duration
+}
+
+impl Default for WatchOptions {
+    fn default() -> WatchOptions {
+        WatchOptions {
+            poll_interval: Duration::from_millis(250),
+            debounce: Duration::from_millis(100)
+        }
+    }
+}
+
+/// A file being watched on a background thread, which stops when this is dropped.
+pub struct Watcher {
+    path: PathBuf,
+    stopped: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>
+}
+
+impl Watcher {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Stop watching, waiting for the callback to finish if it's running.
+    pub fn stop(mut self) {
+        self.shut_down();
+    }
+
+    fn shut_down(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+
+        if let Some(handle) = self.handle.take() {
+            // Wake it up so it doesn't finish sleeping first.
+            handle.thread().unpark();
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for Watcher {
+    fn drop(&mut self) {
+        self.shut_down();
+    }
+}
+
+/// Watch a JSON file with the default options. See `watch_with_options`.
+pub fn watch<F>(path: impl AsRef<Path>, callback: F) -> Watcher
+where
+    F: Fn(Result<Node, CacheError>) + Send + 'static
+{
+    watch_with_options(path, WatchOptions::default(), callback)
+}
+
+/// Watch a JSON file, calling `callback` with the parsed document each time it changes.
+///
+/// The file is read once straight away, then again whenever its modification time
+/// or size changes. Files that can't be read or parsed are passed on as the error,
+/// and a file being deleted counts as a change. Changes are found by polling,
+/// so it works the same everywhere, including on network drives.
+pub fn watch_with_options<F>(path: impl AsRef<Path>, options: WatchOptions, callback: F) -> Watcher
+where
+    F: Fn(Result<Node, CacheError>) + Send + 'static
+{
+    let path = path.as_ref().to_path_buf();
+    let stopped = Arc::new(AtomicBool::new(false));
+
+    let handle = {
+        let path = path.clone();
+        let stopped = stopped.clone();
+
+        thread::spawn(move || {
+            // Sleep, giving back whether we should carry on.
+            let wait = |duration: Duration| {
+                thread::park_timeout(duration);
+                !stopped.load(Ordering::Relaxed)
+            };
+
+            let mut last = stamp(&path).ok();
+            callback(load(&path).map(|(_, node)| node));
+
+            while wait(options.poll_interval) {
+                let mut current = stamp(&path).ok();
+
+                if current == last {
+                    continue;
+                }
+
+                // Wait for the file to settle down.
+                loop {
+                    if !wait(options.debounce) {
+                        return;
+                    }
+
+                    let again = stamp(&path).ok();
+
+                    if again == current {
+                        break;
+                    }
+
+                    current = again;
+                }
+
+                last = current;
+                callback(load(&path).map(|(_, node)| node));
+            }
+        })
+    };
+
+    Watcher {
+        path,
+        stopped,
+        handle: Some(handle)
+    }
+}
+
+/// Every file under `dir` whose name matches `pattern`, looking in subdirectories
+/// but skipping hidden ones, like `.git`.
+fn find_files(dir: &Path, pattern: &str, found: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+
+        if name.starts_with('.') {
+            continue;
+        }
+
+        match entry.file_type()?.is_dir() {
+            true => find_files(&entry.path(), pattern, found)?,
+            false if wildcard_match(pattern, &name) => found.push(entry.path()),
+            false => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// A directory being watched on a background thread, which stops when this is dropped.
+pub struct DirWatcher {
+    dir: PathBuf,
+    stopped: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>
+}
+
+impl DirWatcher {
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Stop watching every file, waiting for any callbacks that are running to finish.
+    pub fn stop(mut self) {
+        self.shut_down();
+    }
+
+    fn shut_down(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+
+        if let Some(handle) = self.handle.take() {
+            handle.thread().unpark();
+            let _ = handle.join();
+        }
+    }
+}
+
+impl Drop for DirWatcher {
+    fn drop(&mut self) {
+        self.shut_down();
+    }
+}
+
+/// Watch every file in a directory whose name matches a pattern like `*.json`,
+/// where `*` stands for any run of characters, calling `callback` with the file's path
+/// and parsed document each time one changes. Subdirectories are watched too,
+/// apart from hidden ones.
+///
+/// Each file is watched as `watch_with_options` does, so it's read once when it's
+/// first found. The directory is looked through again every `poll_interval` for files
+/// that have been added or removed. Files that are deleted stop being watched,
+/// which can happen before their deletion is passed on as an error.
+pub fn watch_dir<F>(dir: impl AsRef<Path>, pattern: &str, options: WatchOptions, callback: F) -> io::Result<DirWatcher>
+where
+    F: Fn(&Path, Result<Node, CacheError>) + Send + Sync + 'static
+{
+    let dir = dir.as_ref().to_path_buf();
+    let pattern = pattern.to_string();
+    let stopped = Arc::new(AtomicBool::new(false));
+    let callback = Arc::new(callback);
+
+    // Look once up front, so a directory that can't be read is an error straight away.
+    let mut found = Vec::new();
+    find_files(&dir, &pattern, &mut found)?;
+
+    let handle = {
+        let dir = dir.clone();
+        let stopped = stopped.clone();
+
+        thread::spawn(move || {
+            let mut watchers: HashMap<PathBuf, Watcher> = HashMap::new();
+
+            loop {
+                watchers.retain(|path, _| found.contains(path));
+
+                // Sort them so files found at the same time are first read in the same order.
+                found.sort();
+
+                for path in found.drain(..) {
+                    if watchers.contains_key(&path) {
+                        continue;
+                    }
+
+                    let callback = callback.clone();
+                    let watched = path.clone();
+
+                    let watcher = watch_with_options(&path, options, move |result| callback(&watched, result));
+                    watchers.insert(path, watcher);
+                }
+
+                thread::park_timeout(options.poll_interval);
+
+                if stopped.load(Ordering::Relaxed) {
+                    return;
+                }
+
+                // If the directory can't be read for a moment, keep watching what's there.
+                if find_files(&dir, &pattern, &mut found).is_err() {
+                    found = watchers.keys().cloned().collect();
+                }
+            }
+        })
+    };
+
+    Ok(DirWatcher {
+        dir,
+        stopped,
+        handle: Some(handle)
+    })
+}